@@ -30,6 +30,17 @@ fn main() {
     }
 
     let dest = PathBuf::from(&env::var("OUT_DIR").unwrap());
-    let mut file = File::create(&dest.join("gl_bindings.rs")).unwrap();
-    Registry::new(Api::Gles2, (3, 3), Profile::Core, Fallbacks::All, []).write_bindings(StructGenerator, &mut file).unwrap();
+    let mut file = File::create(dest.join("gl_bindings.rs")).unwrap();
+    let extensions = [
+        "GL_EXT_disjoint_timer_query",
+        "GL_EXT_multi_draw_indirect",
+        "GL_EXT_sRGB_write_control",
+        "GL_EXT_texture_compression_bptc",
+        "GL_EXT_texture_compression_rgtc",
+        "GL_EXT_texture_compression_s3tc",
+        "GL_EXT_texture_compression_s3tc_srgb",
+        "GL_EXT_texture_filter_anisotropic",
+        "GL_KHR_debug",
+    ];
+    Registry::new(Api::Gles2, (3, 3), Profile::Core, Fallbacks::All, extensions).write_bindings(StructGenerator, &mut file).unwrap();
 }
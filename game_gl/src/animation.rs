@@ -0,0 +1,261 @@
+//////////////////////////////////////////////////
+// Using
+
+use nalgebra_glm as glm;
+
+use crate::camera::{transform, Mat4};
+use crate::gl;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Shader
+
+/// Maximum joints per skin, matching `SKINNING_GLSL`.
+pub const MAX_JOINTS: usize = 64;
+
+/// GLSL helper for vertex shaders, insert after the `#version` line. Expects joints in slot 4 and weights in slot 5
+/// (as bound by `ModelPrimitive`) and the joint palette in the uniform block `Joints`.
+pub const SKINNING_GLSL: &str = "
+layout(location = 4) in vec4 a_Joints;
+layout(location = 5) in vec4 a_Weights;
+
+layout(std140) uniform Joints {
+    mat4 u_Joints[64];
+};
+
+mat4 skin_matrix() {
+    return a_Weights.x * u_Joints[int(a_Joints.x)]
+         + a_Weights.y * u_Joints[int(a_Joints.y)]
+         + a_Weights.z * u_Joints[int(a_Joints.z)]
+         + a_Weights.w * u_Joints[int(a_Joints.w)];
+}
+";
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Local transform of a node, the unit animations write to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NodeTransform {
+    pub translation: glm::Vec3,
+    pub rotation: glm::Quat,
+    pub scale: glm::Vec3,
+}
+
+/// Node of a model's scene hierarchy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelNode {
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// Rest pose.
+    pub transform: NodeTransform,
+    pub mesh: Option<usize>,
+    pub skin: Option<usize>,
+}
+
+/// Joints of a skinned mesh, given as node indices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelSkin {
+    pub name: Option<String>,
+    pub joints: Vec<usize>,
+    pub inverse_bind_matrices: Vec<glm::Mat4>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub name: Option<String>,
+    /// Time of the last keyframe in seconds.
+    pub duration: f32,
+    pub channels: Vec<AnimationChannel>,
+}
+
+/// Keyframes animating one property of one node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationChannel {
+    pub node: usize,
+    pub property: AnimationProperty,
+    pub interpolation: Interpolation,
+    pub times: Vec<f32>,
+    /// One value per keyframe, three (in tangent, value, out tangent) for cubic splines. Vectors leave w unused.
+    pub values: Vec<[f32; 4]>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AnimationProperty {
+    Translation,
+    Rotation,
+    Scale,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    CubicSpline,
+}
+
+/// Joint matrices of one skin in std140 layout.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct JointMatrices(pub [Mat4; MAX_JOINTS]);
+
+/// Uniform buffer holding the joint matrices of a skinned mesh, bound to the `Joints` block.
+#[derive(Debug, Default)]
+pub struct JointPalette {
+    buffer: GlUniformBuffer<JointMatrices>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl NodeTransform {
+    pub fn matrix(&self) -> glm::Mat4 {
+        transform(&self.translation, &self.rotation, &self.scale)
+    }
+}
+
+impl Animation {
+    /// Writes the animated properties at `time` seconds into `pose`, which holds one transform per node.
+    /// Times outside the keyframes clamp to the first or last one, wrap `time` by `duration` to loop.
+    pub fn sample(&self, time: f32, pose: &mut [NodeTransform]) {
+        self.channels.iter().for_each(|channel| {
+            if let Some(node) = pose.get_mut(channel.node) {
+                let value = channel.sample(time);
+                match channel.property {
+                    AnimationProperty::Translation => node.translation = glm::vec3(value[0], value[1], value[2]),
+                    AnimationProperty::Rotation => node.rotation = glm::quat_normalize(&glm::quat(value[0], value[1], value[2], value[3])),
+                    AnimationProperty::Scale => node.scale = glm::vec3(value[0], value[1], value[2]),
+                }
+            }
+        });
+    }
+}
+
+impl AnimationChannel {
+    pub fn sample(&self, time: f32) -> [f32; 4] {
+        let stride = if self.interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+        let value = |key: usize, offset: usize| self.values.get(key * stride + offset).copied().unwrap_or_default();
+        let center = if stride == 3 { 1 } else { 0 };
+        let (first, last) = match (self.times.first(), self.times.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Default::default(),
+        };
+        if time <= first {
+            return value(0, center);
+        }
+        if time >= last {
+            return value(self.times.len() - 1, center);
+        }
+        let next = self.times.partition_point(|&t| t <= time);
+        let previous = next - 1;
+        let dt = self.times[next] - self.times[previous];
+        let t = (time - self.times[previous]) / dt;
+        let rotation = self.property == AnimationProperty::Rotation;
+        match self.interpolation {
+            Interpolation::Step => value(previous, 0),
+            Interpolation::Linear if rotation => {
+                let (a, b) = (value(previous, 0), value(next, 0));
+                let q = glm::quat_slerp(&glm::quat(a[0], a[1], a[2], a[3]), &glm::quat(b[0], b[1], b[2], b[3]), t);
+                [q.i, q.j, q.k, q.w]
+            }
+            Interpolation::Linear => {
+                let (a, b) = (value(previous, 0), value(next, 0));
+                [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * t)
+            }
+            Interpolation::CubicSpline => {
+                // hermite spline with tangents scaled by the keyframe distance
+                let (v0, b0, a1, v1) = (value(previous, 1), value(previous, 2), value(next, 0), value(next, 1));
+                let (t2, t3) = (t * t, t * t * t);
+                let result = [0, 1, 2, 3].map(|i| (2.0 * t3 - 3.0 * t2 + 1.0) * v0[i] + (t3 - 2.0 * t2 + t) * dt * b0[i] + (-2.0 * t3 + 3.0 * t2) * v1[i] + (t3 - t2) * dt * a1[i]);
+                if rotation {
+                    let q = glm::quat_normalize(&glm::quat(result[0], result[1], result[2], result[3]));
+                    [q.i, q.j, q.k, q.w]
+                } else {
+                    result
+                }
+            }
+        }
+    }
+}
+
+/// Model space matrices of all nodes for the local transforms in `pose`.
+pub fn global_transforms(nodes: &[ModelNode], pose: &[NodeTransform]) -> Vec<glm::Mat4> {
+    fn visit(nodes: &[ModelNode], pose: &[NodeTransform], node: usize, parent: &glm::Mat4, globals: &mut [glm::Mat4]) {
+        globals[node] = parent * pose[node].matrix();
+        let global = globals[node];
+        nodes[node].children.iter().for_each(|&child| visit(nodes, pose, child, &global, globals));
+    }
+    let mut globals = vec![glm::Mat4::identity(); nodes.len()];
+    (0..nodes.len()).filter(|&node| nodes[node].parent.is_none()).for_each(|root| visit(nodes, pose, root, &glm::Mat4::identity(), &mut globals));
+    globals
+}
+
+impl ModelSkin {
+    /// Joint matrices for skinning, `globals` as returned by `global_transforms`.
+    pub fn joint_matrices(&self, globals: &[glm::Mat4]) -> Vec<glm::Mat4> {
+        self.joints.iter().zip(self.inverse_bind_matrices.iter()).map(|(&joint, inverse_bind)| globals[joint] * inverse_bind).collect()
+    }
+}
+
+impl JointPalette {
+    pub fn new(gl: &Gl) -> JointPalette {
+        JointPalette {
+            buffer: GlUniformBuffer::new(gl, gl::DYNAMIC_DRAW, &JointMatrices::default()),
+        }
+    }
+
+    /// Uploads the joint matrices of `skin`, joints beyond `MAX_JOINTS` are dropped.
+    pub fn update(&mut self, skin: &ModelSkin, globals: &[glm::Mat4]) {
+        let matrices = skin.joint_matrices(globals);
+        if matrices.len() > MAX_JOINTS {
+            log::warn!("Skin with {} joints exceeds the limit of {}", matrices.len(), MAX_JOINTS);
+        }
+        let mut palette = JointMatrices::default();
+        palette.0.iter_mut().zip(matrices.iter()).for_each(|(target, matrix)| *target = (*matrix).into());
+        self.buffer.update(&palette);
+    }
+
+    /// Binds the palette, link it to the shader's `Joints` block with `GlShader::link_uniform`.
+    pub fn bind(&mut self, unit: u32) {
+        self.buffer.bind(unit);
+    }
+
+    pub fn unbind(&mut self) {
+        self.buffer.unbind();
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl Default for NodeTransform {
+    fn default() -> NodeTransform {
+        NodeTransform {
+            translation: glm::Vec3::zeros(),
+            rotation: glm::Quat::identity(),
+            scale: glm::vec3(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Default for JointMatrices {
+    fn default() -> JointMatrices {
+        JointMatrices([glm::Mat4::identity().into(); MAX_JOINTS])
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl GlResource for JointPalette {
+    fn release(&mut self) {
+        self.buffer.release();
+    }
+}
+impl Drop for JointPalette {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
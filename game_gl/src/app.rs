@@ -1,6 +1,8 @@
 //////////////////////////////////////////////////
 // Using
 
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::error::Error;
 use std::ffi::CString;
 use std::num::NonZeroU32;
@@ -8,19 +10,22 @@ use std::rc::Rc;
 
 use glutin_winit::GlWindow;
 use raw_window_handle::HasWindowHandle;
+use serde::{Deserialize, Serialize};
 
-use winit::dpi::PhysicalSize;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 use winit::event_loop::ActiveEventLoop;
+use winit::monitor::{MonitorHandle, VideoModeHandle};
 
 use glutin::config::{Config, ConfigTemplateBuilder, GetGlConfig};
 use glutin::context::{ContextApi, ContextAttributesBuilder, NotCurrentContext, Version};
 use glutin::display::GetGlDisplay;
 use glutin::prelude::*;
-use glutin::surface::SwapInterval;
-use winit::window::{Window, WindowAttributes};
+use glutin::surface::{SurfaceAttributesBuilder, SwapInterval};
+use winit::window::{Cursor, CustomCursor, CustomCursorSource, Fullscreen, Window, WindowAttributes};
 
+use crate::file::Files;
 use crate::gl;
-use crate::opengl::GlString;
+use crate::opengl::{GlExt, GlString, GlVersion};
 
 //////////////////////////////////////////////////
 // Types
@@ -35,13 +40,84 @@ struct AppState {
     surface: glutin::surface::Surface<glutin::surface::WindowSurface>,
 }
 
+/// Surface and window options requested by the game, see `GameLoop::window_config`.
+#[derive(Debug, Clone, Default)]
+pub struct WindowConfig {
+    /// Minimum stencil bits of the default framebuffer. Falls back to less bits if unavailable.
+    pub stencil_size: u8,
+    /// Depth bits of the default framebuffer, e.g. 0, 16, 24 or 32. `Some(0)` prefers no depth buffer, `None` takes any.
+    pub depth_size: Option<u8>,
+    /// Requests an sRGB default framebuffer, writes are converted from linear to sRGB.
+    pub srgb: bool,
+    /// Smallest inner size of the window in physical pixels, e.g. the smallest size the GUI fits into.
+    pub min_size: Option<[u32; 2]>,
+    /// Largest inner size of the window in physical pixels.
+    pub max_size: Option<[u32; 2]>,
+    /// Width divided by height the window keeps when resized by the user, the dragged side is kept and the other
+    /// one follows. Orientations conflicting with the aspect ratio are rejected, see `Orientation::allows_aspect`.
+    pub aspect_ratio: Option<f32>,
+    /// Keeps the window above or below other windows, e.g. for overlay tools. See `GameContext::set_window_level`.
+    pub window_level: WindowLevel,
+}
+
+/// How the window is shown on desktop, see `GameContext::request_display_mode`. Ignored on Android.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    /// Window covering the screen at its current resolution.
+    Borderless,
+    /// Screen switched to the video mode of the window size, the largest video mode if no size was set.
+    Exclusive,
+}
+
+/// Screen orientation the game is shown in, see `GameContext::set_orientation`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Orientation {
+    /// Height at least width, upside down allowed on Android.
+    Portrait,
+    /// Width at least height, both sides allowed on Android.
+    Landscape,
+    /// Follows the device rotation on Android, any window size on desktop.
+    #[default]
+    Sensor,
+}
+
+// use winit window levels
+pub type WindowLevel = winit::window::WindowLevel;
+
+// use winit cursor icons
+pub type CursorIcon = winit::window::CursorIcon;
+
+/// Mouse pointer shown over the window, see `GameContext::set_cursor`. Ignored on Android.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CursorImage {
+    Icon(CursorIcon),
+    /// Image file of the assets, with the click point in pixels from its top left corner.
+    Custom { path: String, hotspot: [u16; 2] },
+}
+
 pub struct App {
     template: ConfigTemplateBuilder,
     window: WindowAttributes,
+    config: WindowConfig,
+    size: Option<PhysicalSize<u32>>,
+    // inner size of the last resize, tells which side the user drags
+    inner_size: Option<PhysicalSize<u32>>,
+    display_mode: DisplayMode,
+    // name of the screen for the window, the current screen if unset
+    monitor: Option<String>,
+    orientation: Orientation,
+    ime_allowed: bool,
+    cursor: Option<Cursor>,
+    // custom cursors by path, created once
+    custom_cursors: HashMap<String, CustomCursor>,
     display: GlDisplayCreationState,
     context: Option<glutin::context::PossiblyCurrentContext>,
     state: Option<AppState>,
     renderer: Option<Gl>,
+    srgb: bool,
+    vsync: bool,
     exit_state: Result<(), Box<dyn Error>>,
 }
 
@@ -55,16 +131,232 @@ enum GlDisplayCreationState {
 //////////////////////////////////////////////////
 // Implementations
 
+impl Orientation {
+    /// True if a screen of this size is shown in the orientation.
+    pub fn matches(&self, width: u32, height: u32) -> bool {
+        match self {
+            Orientation::Portrait => height >= width,
+            Orientation::Landscape => width >= height,
+            Orientation::Sensor => true,
+        }
+    }
+
+    /// True if windows of the aspect ratio (width divided by height) are shown in the orientation.
+    pub fn allows_aspect(&self, aspect_ratio: f32) -> bool {
+        match self {
+            Orientation::Portrait => aspect_ratio <= 1.0,
+            Orientation::Landscape => aspect_ratio >= 1.0,
+            Orientation::Sensor => true,
+        }
+    }
+}
+
 impl App {
-    pub fn new(template: ConfigTemplateBuilder, window: WindowAttributes) -> Self {
+    pub fn new(template: ConfigTemplateBuilder, mut window: WindowAttributes, mut config: WindowConfig) -> Self {
+        if let Some(aspect_ratio) = config.aspect_ratio.filter(|aspect_ratio| !(aspect_ratio.is_finite() && *aspect_ratio > 0.0)) {
+            log::error!("Ignoring invalid window aspect ratio {}", aspect_ratio);
+            config.aspect_ratio = None;
+        }
+        if let Some([width, height]) = config.min_size {
+            window = window.with_min_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some([width, height]) = config.max_size {
+            window = window.with_max_inner_size(PhysicalSize::new(width, height));
+        }
+        window = window.with_window_level(config.window_level);
         Self {
             template,
             window,
+            config,
+            size: None,
+            inner_size: None,
+            display_mode: DisplayMode::Windowed,
+            monitor: None,
+            orientation: Orientation::Sensor,
+            ime_allowed: false,
+            cursor: None,
+            custom_cursors: HashMap::new(),
             display: GlDisplayCreationState::Build,
             exit_state: Ok(()),
             context: None,
             state: None,
             renderer: None,
+            srgb: false,
+            vsync: true,
+        }
+    }
+
+    /// Applied when the next surface is created.
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.vsync = vsync;
+    }
+
+    /// Window size, applied to the current window and when the window is created.
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        let size = PhysicalSize::new(width, height);
+        self.size = Some(size);
+        self.window = self.window.clone().with_inner_size(size);
+        if let Some(AppState { window, .. }) = self.state.as_ref() {
+            match self.display_mode {
+                // the surface is resized by the following resize event
+                DisplayMode::Windowed => {
+                    let _ = window.request_inner_size(size);
+                }
+                // borderless keeps the resolution of the screen
+                DisplayMode::Borderless => {}
+                DisplayMode::Exclusive => self.apply_display_mode(window),
+            }
+        }
+    }
+
+    /// Position of the window's top left corner on the desktop, applied to the current window and when the window
+    /// is created. Ignored by some platforms, e.g. Wayland.
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        let position = PhysicalPosition::new(x, y);
+        self.window = self.window.clone().with_position(position);
+        if let Some(AppState { window, .. }) = self.state.as_ref() {
+            window.set_outer_position(position);
+        }
+    }
+
+    /// Window level, applied to the current window and when the window is created.
+    pub fn set_window_level(&mut self, level: WindowLevel) {
+        self.window = self.window.clone().with_window_level(level);
+        if let Some(AppState { window, .. }) = self.state.as_ref() {
+            window.set_window_level(level);
+        }
+    }
+
+    pub fn window_level(&self) -> WindowLevel {
+        self.window.window_level
+    }
+
+    /// Display mode, applied to the current window and when the window is created.
+    pub fn set_display_mode(&mut self, display_mode: DisplayMode) {
+        self.display_mode = display_mode;
+        if let Some(AppState { window, .. }) = self.state.as_ref() {
+            self.apply_display_mode(window);
+        }
+    }
+
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display_mode
+    }
+
+    /// Screen of the window by name, applied to the current window and when the window is created. Windows are
+    /// centered on the screen, fullscreen covers it. Unknown screens keep the current one.
+    pub fn set_monitor(&mut self, monitor: Option<String>) {
+        self.monitor = monitor;
+        if let Some(AppState { window, .. }) = self.state.as_ref() {
+            self.apply_monitor(window);
+        }
+    }
+
+    pub fn monitor(&self) -> Option<&str> {
+        self.monitor.as_deref()
+    }
+
+    /// Orientation of the window, applied to the current window and when the window is created. Windows not
+    /// matching the orientation are turned by swapping width and height, fullscreen windows are left as they are.
+    /// Orientations conflicting with the aspect ratio of the window config are rejected.
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        if let Some(aspect_ratio) = self.config.aspect_ratio.filter(|aspect_ratio| !orientation.allows_aspect(*aspect_ratio)) {
+            log::error!("Orientation {:?} conflicts with the window aspect ratio {}, keeping {:?}", orientation, aspect_ratio, self.orientation);
+            return;
+        }
+        self.orientation = orientation;
+        if let Some(AppState { window, .. }) = self.state.as_ref() {
+            self.apply_aspect(window, None);
+        }
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Cursor over the window, applied to the current window and when the window is created. Custom cursors are
+    /// loaded from `files` once, a cursor failing to load keeps the current one.
+    pub fn set_cursor(&mut self, event_loop: &ActiveEventLoop, cursor: &CursorImage, files: &Files) {
+        let cursor = match cursor {
+            CursorImage::Icon(icon) => Cursor::Icon(*icon),
+            CursorImage::Custom { path, hotspot } => match self.custom_cursors.get(path) {
+                Some(custom) => Cursor::Custom(custom.clone()),
+                None => match load_cursor(path, *hotspot, files) {
+                    Ok(source) => {
+                        let custom = event_loop.create_custom_cursor(source);
+                        self.custom_cursors.insert(path.clone(), custom.clone());
+                        Cursor::Custom(custom)
+                    }
+                    Err(e) => {
+                        log::error!("Failed to load cursor {}: {}", path, e);
+                        return;
+                    }
+                },
+            },
+        };
+        if let Some(AppState { window, .. }) = self.state.as_ref() {
+            window.set_cursor(cursor.clone());
+        }
+        self.cursor = Some(cursor);
+    }
+
+    /// Text input through the input method, applied to the current window and when the window is created.
+    pub fn set_ime_allowed(&mut self, allowed: bool) {
+        self.ime_allowed = allowed;
+        if let Some(AppState { window, .. }) = self.state.as_ref() {
+            window.set_ime_allowed(allowed);
+        }
+    }
+
+    pub fn is_ime_allowed(&self) -> bool {
+        self.ime_allowed
+    }
+
+    fn target_monitor(&self, window: &Window) -> Option<MonitorHandle> {
+        let name = self.monitor.as_ref();
+        window.available_monitors().find(|monitor| monitor.name().as_ref() == name).or_else(|| window.current_monitor())
+    }
+
+    fn apply_monitor(&self, window: &Window) {
+        match self.display_mode {
+            DisplayMode::Windowed => {
+                if let Some(monitor) = self.target_monitor(window).filter(|monitor| window.current_monitor().as_ref() != Some(monitor)) {
+                    let (position, screen, size) = (monitor.position(), monitor.size(), window.outer_size());
+                    let x = position.x + (screen.width as i32 - size.width as i32) / 2;
+                    let y = position.y + (screen.height as i32 - size.height as i32) / 2;
+                    window.set_outer_position(PhysicalPosition::new(x, y));
+                }
+            }
+            _ => self.apply_display_mode(window),
+        }
+    }
+
+    // winit has no aspect constraints, windows not matching are resized
+    fn apply_aspect(&self, window: &Window, previous: Option<PhysicalSize<u32>>) {
+        let size = window.inner_size();
+        let target = aspect_size(size, previous, self.orientation, self.config.aspect_ratio);
+        // rounding may keep the window a pixel off, requesting it again would resize forever
+        if self.display_mode == DisplayMode::Windowed && (target.width.abs_diff(size.width) > 1 || target.height.abs_diff(size.height) > 1) {
+            let _ = window.request_inner_size(target);
+        }
+    }
+
+    fn apply_display_mode(&self, window: &Window) {
+        let fullscreen = match self.display_mode {
+            DisplayMode::Windowed => None,
+            DisplayMode::Borderless => Some(Fullscreen::Borderless(self.target_monitor(window))),
+            DisplayMode::Exclusive => match self.target_monitor(window).and_then(|monitor| video_mode(&monitor, self.size)) {
+                Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                None => {
+                    log::warn!("No video mode for exclusive fullscreen, using borderless fullscreen");
+                    Some(Fullscreen::Borderless(self.target_monitor(window)))
+                }
+            },
+        };
+        window.set_fullscreen(fullscreen);
+        // leaving fullscreen restores the size of the window before, which may have changed since
+        if let (DisplayMode::Windowed, Some(size)) = (self.display_mode, self.size) {
+            let _ = window.request_inner_size(size);
         }
     }
 }
@@ -76,7 +368,8 @@ impl App {
             // create the context.
             GlDisplayCreationState::Build => {
                 let display_builder = glutin_winit::DisplayBuilder::new().with_window_attributes(Some(self.window.clone()));
-                let (window, gl_config) = match display_builder.build(event_loop, self.template.clone(), gl_config_picker) {
+                let config = &self.config;
+                let (window, gl_config) = match display_builder.build(event_loop, self.template.clone(), |configs| gl_config_picker(configs, config)) {
                     Ok((window, gl_config)) => (window.unwrap(), gl_config),
                     Err(err) => {
                         self.exit_state = Err(err);
@@ -85,7 +378,7 @@ impl App {
                     }
                 };
 
-                log::debug!("Picked a config with {} samples", gl_config.num_samples());
+                log::debug!("Picked a config with {} samples, {} depth bits, {} stencil bits", gl_config.num_samples(), gl_config.depth_size(), gl_config.stencil_size());
 
                 // Mark the display as initialized to not recreate it on resume, since the
                 // display is valid until we explicitly destroy it.
@@ -129,6 +422,10 @@ impl App {
             if let Some(shaders_version) = GlString::get(&gl, gl::SHADING_LANGUAGE_VERSION) {
                 log::debug!("Shaders version on {}", shaders_version);
             }
+
+            if cfg!(debug_assertions) && gl.enable_debug_output(log::max_level()) {
+                log::debug!("Enabled OpenGL debug output");
+            }
             gl
         });
     }
@@ -137,7 +434,10 @@ impl App {
         log::debug!("Window resumed");
 
         let (window, gl_config) = self.create_window(event_loop).unwrap();
-        let attrs = window.build_surface_attributes(Default::default()).expect("Failed to build surface attributes");
+        self.srgb = self.config.srgb && gl_config.srgb_capable();
+        let attrs = window
+            .build_surface_attributes(SurfaceAttributesBuilder::new().with_srgb(Some(self.srgb)))
+            .expect("Failed to build surface attributes");
         let gl_surface = unsafe { gl_config.display().create_window_surface(&gl_config, &attrs).unwrap() };
 
         // The context needs to be current for the Renderer to set up shaders and
@@ -147,12 +447,30 @@ impl App {
         gl_context.make_current(&gl_surface).unwrap();
 
         // Try setting vsync.
-        if let Err(res) = gl_surface.set_swap_interval(gl_context, SwapInterval::Wait(NonZeroU32::new(1).unwrap())) {
+        let interval = if self.vsync { SwapInterval::Wait(NonZeroU32::new(1).unwrap()) } else { SwapInterval::DontWait };
+        if let Err(res) = gl_surface.set_swap_interval(gl_context, interval) {
             log::error!("Error setting vsync: {res:?}");
         }
 
         self.create_renderer(&gl_config.display());
 
+        // desktop contexts only convert to sRGB if enabled, EGL surfaces always do
+        let gl = self.renderer();
+        if self.srgb && !GlVersion::get(gl).es {
+            unsafe { gl.Enable(gl::FRAMEBUFFER_SRGB_EXT) };
+        }
+
+        if self.display_mode != DisplayMode::Windowed || self.monitor.is_some() {
+            self.apply_monitor(&window);
+        }
+        if self.ime_allowed {
+            window.set_ime_allowed(true);
+        }
+        if let Some(cursor) = self.cursor.clone() {
+            window.set_cursor(cursor);
+        }
+        self.inner_size = Some(window.inner_size());
+        self.apply_aspect(&window, None);
         assert!(self.state.replace(AppState { surface: gl_surface, window }).is_none());
     }
 
@@ -178,13 +496,16 @@ impl App {
     }
 
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        let previous = self.inner_size.replace(size);
         // Some platforms like EGL require resizing GL surface to update the size
         // Notable platforms here are Wayland and macOS, other don't require it
         // and the function is no-op, but it's wise to resize it for portability
         // reasons.
-        if let Some(AppState { surface, window: _ }) = self.state.as_ref() {
+        if let Some(AppState { surface, window }) = self.state.as_ref() {
             let gl_context = self.context.as_ref().unwrap();
             surface.resize(gl_context, NonZeroU32::new(size.width).unwrap(), NonZeroU32::new(size.height).unwrap());
+            // keep the orientation and aspect ratio when the user resizes the window
+            self.apply_aspect(window, previous);
         }
     }
 
@@ -205,10 +526,20 @@ impl App {
         }
     }
 
+    /// Window of the current surface, `None` while suspended.
+    pub fn window(&self) -> Option<&Window> {
+        self.state.as_ref().map(|state| &state.window)
+    }
+
     pub fn has_surface_and_context(&self) -> bool {
         self.context.is_some() && self.state.is_some()
     }
 
+    /// Whether the default framebuffer converts writes to sRGB.
+    pub fn is_srgb(&self) -> bool {
+        self.srgb
+    }
+
     pub fn has_renderer(&self) -> bool {
         self.renderer.is_some()
     }
@@ -218,8 +549,44 @@ impl App {
     }
 }
 
-pub fn gl_config_picker(configs: Box<dyn Iterator<Item = Config> + '_>) -> Config {
+// size of the window in the orientation and aspect ratio, computed once from the side the user drags
+fn aspect_size(size: PhysicalSize<u32>, previous: Option<PhysicalSize<u32>>, orientation: Orientation, aspect_ratio: Option<f32>) -> PhysicalSize<u32> {
+    let Some(aspect_ratio) = aspect_ratio else {
+        return if orientation.matches(size.width, size.height) { size } else { PhysicalSize::new(size.height, size.width) };
+    };
+    // the aspect ratio implies the orientation, keep the width unless only the height was dragged
+    let height_dragged = previous.is_some_and(|previous| size.height.abs_diff(previous.height) > size.width.abs_diff(previous.width));
+    if height_dragged {
+        PhysicalSize::new((size.height as f32 * aspect_ratio).round().max(1.0) as u32, size.height)
+    } else {
+        PhysicalSize::new(size.width, (size.width as f32 / aspect_ratio).round().max(1.0) as u32)
+    }
+}
+
+// video mode of the requested size with the highest refresh rate, the largest video mode otherwise
+fn video_mode(monitor: &MonitorHandle, size: Option<PhysicalSize<u32>>) -> Option<VideoModeHandle> {
+    let video_modes: Vec<VideoModeHandle> = monitor.video_modes().collect();
+    let quality = |video_mode: &&VideoModeHandle| (video_mode.refresh_rate_millihertz(), video_mode.bit_depth());
+    video_modes
+        .iter()
+        .filter(|video_mode| Some(video_mode.size()) == size)
+        .max_by_key(quality)
+        .or_else(|| video_modes.iter().max_by_key(|video_mode| (video_mode.size().width * video_mode.size().height, quality(video_mode))))
+        .cloned()
+}
+
+pub fn gl_config_picker(configs: Box<dyn Iterator<Item = Config> + '_>, window_config: &WindowConfig) -> Config {
+    // prefer configs fulfilling the requested buffers, fall back to any config otherwise
+    let configs: Vec<Config> = configs.collect();
+    let matching: Vec<Config> = configs.iter().filter(|config| window_config.matches(config)).cloned().collect();
+    let configs = if matching.is_empty() {
+        log::warn!("No surface config matches {:?}, falling back", window_config);
+        configs
+    } else {
+        matching
+    };
     configs
+        .into_iter()
         .reduce(|accum, config| {
             let transparency_check = config.supports_transparency().unwrap_or(false) & !accum.supports_transparency().unwrap_or(false);
             if transparency_check || config.num_samples() > accum.num_samples() {
@@ -231,19 +598,41 @@ pub fn gl_config_picker(configs: Box<dyn Iterator<Item = Config> + '_>) -> Confi
         .unwrap()
 }
 
+impl WindowConfig {
+    fn matches(&self, config: &Config) -> bool {
+        let depth = match self.depth_size {
+            Some(0) => config.depth_size() == 0,
+            Some(depth_size) => config.depth_size() >= depth_size,
+            None => true,
+        };
+        depth && config.stencil_size() >= self.stencil_size && (!self.srgb || config.srgb_capable())
+    }
+}
+
+fn load_cursor(path: &str, hotspot: [u16; 2], files: &Files) -> Result<CustomCursorSource, String> {
+    let bytes = files.load_bytes(path).map_err(|e| e.to_string())?;
+    let image = image::load_from_memory(&bytes).map_err(|e| e.to_string())?.to_rgba8();
+    let too_large = |_| format!("Cursor {} is larger than {}x{}", path, u16::MAX, u16::MAX);
+    let (width, height) = (u16::try_from(image.width()).map_err(too_large)?, u16::try_from(image.height()).map_err(too_large)?);
+    if hotspot[0] >= width || hotspot[1] >= height {
+        return Err(format!("Cursor hotspot {:?} lies outside of {} ({}x{})", hotspot, path, width, height));
+    }
+    CustomCursor::from_rgba(image.into_raw(), width, height, hotspot[0], hotspot[1]).map_err(|e| e.to_string())
+}
+
 fn create_gl_context(window: &Window, gl_config: &Config) -> NotCurrentContext {
     let raw_window_handle = window.window_handle().ok().map(|wh| wh.as_raw());
 
     // The context creation part.
-    let context_attributes = ContextAttributesBuilder::new().build(raw_window_handle);
+    let context_attributes = ContextAttributesBuilder::new().with_debug(cfg!(debug_assertions)).build(raw_window_handle);
 
     // Since glutin by default tries to create OpenGL core context, which may not be
     // present we should try gles.
-    let fallback_context_attributes = ContextAttributesBuilder::new().with_context_api(ContextApi::Gles(None)).build(raw_window_handle);
+    let fallback_context_attributes = ContextAttributesBuilder::new().with_debug(cfg!(debug_assertions)).with_context_api(ContextApi::Gles(None)).build(raw_window_handle);
 
     // There are also some old devices that support neither modern OpenGL nor GLES.
     // To support these we can try and create a 2.1 context.
-    let legacy_context_attributes = ContextAttributesBuilder::new().with_context_api(ContextApi::OpenGl(Some(Version::new(2, 1)))).build(raw_window_handle);
+    let legacy_context_attributes = ContextAttributesBuilder::new().with_debug(cfg!(debug_assertions)).with_context_api(ContextApi::OpenGl(Some(Version::new(2, 1)))).build(raw_window_handle);
 
     // Reuse the uncurrented context from a suspended() call if it exists, otherwise
     // this is the first time resumed() is called, where the context still
@@ -258,3 +647,41 @@ fn create_gl_context(window: &Window, gl_config: &Config) -> NotCurrentContext {
         })
     }
 }
+
+//////////////////////////////////////////////////
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_dragged_side() {
+        let size = |width, height| PhysicalSize::new(width, height);
+        // dragging the right edge keeps the width, the bottom edge keeps the height
+        assert_eq!(aspect_size(size(900, 600), Some(size(800, 600)), Orientation::Sensor, Some(16.0 / 9.0)), size(900, 506));
+        assert_eq!(aspect_size(size(800, 700), Some(size(800, 450)), Orientation::Sensor, Some(16.0 / 9.0)), size(1244, 700));
+        // the window created without a previous size keeps its width
+        assert_eq!(aspect_size(size(800, 800), None, Orientation::Landscape, Some(2.0)), size(800, 400));
+        // the target is stable, resizing to it requests no further change
+        let target = aspect_size(size(1001, 600), Some(size(1000, 600)), Orientation::Sensor, Some(4.0 / 3.0));
+        assert_eq!(aspect_size(target, Some(size(1001, 600)), Orientation::Sensor, Some(4.0 / 3.0)), target);
+    }
+
+    #[test]
+    fn turns_windows_into_the_orientation() {
+        let size = PhysicalSize::new(800, 600);
+        assert_eq!(aspect_size(size, None, Orientation::Portrait, None), PhysicalSize::new(600, 800));
+        assert_eq!(aspect_size(size, None, Orientation::Landscape, None), size);
+        assert_eq!(aspect_size(size, None, Orientation::Sensor, None), size);
+    }
+
+    #[test]
+    fn rejects_conflicting_aspect_ratios() {
+        assert!(!Orientation::Portrait.allows_aspect(16.0 / 9.0));
+        assert!(Orientation::Portrait.allows_aspect(9.0 / 16.0));
+        assert!(!Orientation::Landscape.allows_aspect(0.5));
+        assert!(Orientation::Landscape.allows_aspect(1.0));
+        assert!(Orientation::Sensor.allows_aspect(0.5));
+    }
+}
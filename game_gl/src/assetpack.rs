@@ -0,0 +1,345 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::HashMap;
+
+use crate::events::Events;
+
+#[cfg(target_os = "android")]
+use jni::objects::{GlobalRef, JObject};
+#[cfg(target_os = "android")]
+use jni::JNIEnv;
+#[cfg(target_os = "android")]
+use winit::platform::android::activity::AndroidApp;
+
+#[cfg(target_os = "android")]
+use crate::file::Files;
+#[cfg(target_os = "android")]
+use crate::platform::with_activity;
+
+//////////////////////////////////////////////////
+// Definition
+
+// seconds between two queries of the pack states
+const POLL_INTERVAL: f32 = 0.25;
+
+#[cfg(target_os = "android")]
+const MANAGER_FACTORY: &str = "com/google/android/play/core/assetpacks/AssetPackManagerFactory";
+
+/// Download status of an asset pack, see `AssetPacks::fetch`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AssetPackStatus {
+    Unknown,
+    NotInstalled,
+    Pending,
+    Downloading,
+    /// Downloaded and being extracted to storage.
+    Transferring,
+    /// Large downloads wait for wifi until the user agrees to use mobile data, see `AssetPacks::request_confirmation`.
+    WaitingForWifi,
+    /// The user has to agree to the download, see `AssetPacks::request_confirmation`.
+    RequiresUserConfirmation,
+    /// Installed and mounted, its files are found by `Files`.
+    Completed,
+    Failed,
+    Canceled,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AssetPackState {
+    pub status: AssetPackStatus,
+    pub bytes_downloaded: u64,
+    pub total_bytes: u64,
+}
+
+/// Sent through `GameContext::events` when the state of a fetched pack changed, e.g. to show download progress.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssetPackUpdated {
+    pub name: String,
+    pub state: AssetPackState,
+}
+
+/// Asset packs of Play Asset Delivery, for games too large for the base APK. Install-time packs are part of the
+/// app's assets and need no fetching. Fast-follow and on-demand packs are downloaded by `fetch` and mounted into
+/// `Files` once installed, so their files are loaded like any other asset. Needs the Play asset delivery library
+/// in the app. On desktop all assets ship in the `assets` folder and packs complete right away.
+#[derive(Default)]
+pub struct AssetPacks {
+    #[cfg(target_os = "android")]
+    android_app: Option<AndroidApp>,
+    #[cfg(target_os = "android")]
+    files: Option<Files>,
+    #[cfg(target_os = "android")]
+    manager: Option<GlobalRef>,
+    // running query of the pack states
+    #[cfg(target_os = "android")]
+    query: Option<GlobalRef>,
+    states: HashMap<String, AssetPackState>,
+    // fetched packs, queried until finished
+    fetched: Vec<String>,
+    poll_time: f32,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl AssetPackStatus {
+    /// True once the pack won't change without another `fetch`.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, AssetPackStatus::Completed | AssetPackStatus::Failed | AssetPackStatus::Canceled)
+    }
+
+    // `AssetPackStatus` constants of the Play library
+    #[cfg(target_os = "android")]
+    fn from_java(status: i32) -> AssetPackStatus {
+        match status {
+            1 => AssetPackStatus::Pending,
+            2 => AssetPackStatus::Downloading,
+            3 => AssetPackStatus::Transferring,
+            4 => AssetPackStatus::Completed,
+            5 => AssetPackStatus::Failed,
+            6 => AssetPackStatus::Canceled,
+            7 => AssetPackStatus::WaitingForWifi,
+            8 => AssetPackStatus::NotInstalled,
+            9 => AssetPackStatus::RequiresUserConfirmation,
+            _ => AssetPackStatus::Unknown,
+        }
+    }
+}
+
+impl AssetPackState {
+    /// Downloaded part in [0, 1].
+    pub fn progress(&self) -> f32 {
+        match (self.status, self.total_bytes) {
+            (AssetPackStatus::Completed, _) => 1.0,
+            (_, 0) => 0.0,
+            (_, total) => (self.bytes_downloaded as f64 / total as f64).min(1.0) as f32,
+        }
+    }
+}
+
+impl AssetPacks {
+    /// Starts downloading a pack, or mounts it if it is already installed. Progress is sent as `AssetPackUpdated`.
+    pub fn fetch(&mut self, name: &str) {
+        if self.is_ready(name) {
+            return;
+        }
+        if !self.fetched.iter().any(|other| other == name) {
+            self.fetched.push(name.to_string());
+        }
+        self.fetch_native(name);
+        // query in the next update
+        self.poll_time = POLL_INTERVAL;
+    }
+
+    /// Last known state, `None` for packs that were never fetched.
+    pub fn state(&self, name: &str) -> Option<AssetPackState> {
+        self.states.get(name).copied()
+    }
+
+    pub fn is_ready(&self, name: &str) -> bool {
+        self.state(name).is_some_and(|state| state.status == AssetPackStatus::Completed)
+    }
+
+    /// Combined progress of all fetched packs in [0, 1], e.g. for a loading bar.
+    pub fn progress(&self) -> f32 {
+        let (downloaded, total) = self.states.values().fold((0, 0), |(downloaded, total), state| match state.status {
+            AssetPackStatus::Completed => (downloaded + state.total_bytes.max(1), total + state.total_bytes.max(1)),
+            _ => (downloaded + state.bytes_downloaded, total + state.total_bytes),
+        });
+        match total {
+            0 if self.states.is_empty() => 1.0,
+            0 => 0.0,
+            total => (downloaded as f64 / total as f64).min(1.0) as f32,
+        }
+    }
+
+    /// Queries the states of the fetched packs, mounts installed ones and sends the changes, called by the game loop.
+    pub(crate) fn update(&mut self, events: &mut Events, elapsed_time: f32) {
+        self.poll_time += elapsed_time;
+        if self.fetched.is_empty() || self.poll_time < POLL_INTERVAL {
+            return;
+        }
+        self.poll_time = 0.0;
+
+        for (name, state) in self.query_native() {
+            if self.states.get(&name) == Some(&state) {
+                continue;
+            }
+            let state = match state.status {
+                AssetPackStatus::Completed if !self.mount_native(&name) => AssetPackState {
+                    status: AssetPackStatus::Failed,
+                    ..state
+                },
+                _ => state,
+            };
+            self.states.insert(name.clone(), state);
+            events.send(AssetPackUpdated { name, state });
+        }
+        let states = &self.states;
+        self.fetched.retain(|name| !states.get(name).is_some_and(|state| state.status.is_finished()));
+    }
+}
+
+#[cfg(target_os = "android")]
+impl AssetPacks {
+    pub(crate) fn new(android_app: AndroidApp, files: Files) -> AssetPacks {
+        AssetPacks {
+            android_app: Some(android_app),
+            files: Some(files),
+            ..Default::default()
+        }
+    }
+
+    /// Asks the user to allow downloads waiting for confirmation or for wifi.
+    pub fn request_confirmation(&mut self) {
+        let Some((android_app, manager)) = self.manager() else { return };
+        with_activity(&android_app, "show asset pack confirmation", |env, activity| {
+            env.call_method(&manager, "showConfirmationDialog", "(Landroid/app/Activity;)Lcom/google/android/gms/tasks/Task;", &[activity.into()])?;
+            Ok(())
+        });
+    }
+
+    fn manager(&mut self) -> Option<(AndroidApp, GlobalRef)> {
+        let android_app = self.android_app.clone()?;
+        if self.manager.is_none() {
+            self.manager = with_activity(&android_app, "get asset pack manager", |env, activity| {
+                let manager = env
+                    .call_static_method(MANAGER_FACTORY, "getInstance", "(Landroid/content/Context;)Lcom/google/android/play/core/assetpacks/AssetPackManager;", &[activity.into()])?
+                    .l()?;
+                env.new_global_ref(manager)
+            });
+        }
+        Some((android_app, self.manager.clone()?))
+    }
+
+    fn fetch_native(&mut self, name: &str) {
+        let Some((android_app, manager)) = self.manager() else { return };
+        with_activity(&android_app, "fetch asset pack", |env, _| {
+            let names = string_list(env, &[name.to_string()])?;
+            env.call_method(&manager, "fetch", "(Ljava/util/List;)Lcom/google/android/gms/tasks/Task;", &[(&names).into()])?;
+            Ok(())
+        });
+    }
+
+    // `getPackStates` answers asynchronously, the task is checked until it completes
+    fn query_native(&mut self) -> Vec<(String, AssetPackState)> {
+        let Some((android_app, manager)) = self.manager() else { return Vec::new() };
+        let fetched = self.fetched.clone();
+        let query = self.query.take();
+        let (query, states) = with_activity(&android_app, "query asset packs", |env, _| {
+            let Some(query) = query else {
+                let names = string_list(env, &fetched)?;
+                let query = env.call_method(&manager, "getPackStates", "(Ljava/util/List;)Lcom/google/android/gms/tasks/Task;", &[(&names).into()])?.l()?;
+                return Ok((Some(env.new_global_ref(query)?), Vec::new()));
+            };
+            if !env.call_method(&query, "isComplete", "()Z", &[])?.z()? {
+                return Ok((Some(query), Vec::new()));
+            }
+            if !env.call_method(&query, "isSuccessful", "()Z", &[])?.z()? {
+                log::warn!("Failed to query asset packs");
+                return Ok((None, Vec::new()));
+            }
+            let result = env.call_method(&query, "getResult", "()Ljava/lang/Object;", &[])?.l()?;
+            let pack_states = env.call_method(&result, "packStates", "()Ljava/util/Map;", &[])?.l()?;
+            let mut states = Vec::new();
+            for name in &fetched {
+                let key = env.new_string(name)?;
+                let state = env.call_method(&pack_states, "get", "(Ljava/lang/Object;)Ljava/lang/Object;", &[(&key).into()])?.l()?;
+                if !state.is_null() {
+                    states.push((name.clone(), pack_state(env, &state)?));
+                }
+            }
+            Ok((None, states))
+        })
+        .unwrap_or_default();
+        self.query = query;
+        states
+    }
+
+    // downloaded packs are extracted to a folder, install-time packs are already part of the assets
+    fn mount_native(&mut self, name: &str) -> bool {
+        let Some((android_app, manager)) = self.manager() else { return false };
+        let path = with_activity(&android_app, "locate asset pack", |env, _| {
+            let key = env.new_string(name)?;
+            let location = env.call_method(&manager, "getPackLocation", "(Ljava/lang/String;)Lcom/google/android/play/core/assetpacks/AssetPackLocation;", &[(&key).into()])?.l()?;
+            if location.is_null() {
+                return Ok(None);
+            }
+            let path = env.call_method(&location, "assetsPath", "()Ljava/lang/String;", &[])?.l()?;
+            if path.is_null() {
+                return Ok(None);
+            }
+            let path: String = env.get_string(&jni::objects::JString::from(path))?.into();
+            Ok(Some(path))
+        });
+        match (path, self.files.as_ref()) {
+            (Some(Some(path)), Some(files)) => match files.mount_dir(&path) {
+                Ok(()) => true,
+                Err(e) => {
+                    log::error!("Failed to mount asset pack {}: {}", name, e);
+                    false
+                }
+            },
+            (Some(None), _) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl AssetPacks {
+    pub(crate) fn new() -> AssetPacks {
+        Default::default()
+    }
+
+    /// Only needed on Android.
+    pub fn request_confirmation(&mut self) {}
+
+    fn fetch_native(&mut self, _name: &str) {}
+
+    fn query_native(&mut self) -> Vec<(String, AssetPackState)> {
+        let state = AssetPackState {
+            status: AssetPackStatus::Completed,
+            bytes_downloaded: 0,
+            total_bytes: 0,
+        };
+        self.fetched.iter().map(|name| (name.clone(), state)).collect()
+    }
+
+    fn mount_native(&mut self, _name: &str) -> bool {
+        true
+    }
+}
+
+// `java.util.Arrays.asList(names)`
+#[cfg(target_os = "android")]
+fn string_list<'local>(env: &mut JNIEnv<'local>, names: &[String]) -> jni::errors::Result<JObject<'local>> {
+    let array = env.new_object_array(names.len() as i32, "java/lang/String", JObject::null())?;
+    for (index, name) in names.iter().enumerate() {
+        let name = env.new_string(name)?;
+        env.set_object_array_element(&array, index as i32, name)?;
+    }
+    env.call_static_method("java/util/Arrays", "asList", "([Ljava/lang/Object;)Ljava/util/List;", &[(&array).into()])?.l()
+}
+
+#[cfg(target_os = "android")]
+fn pack_state(env: &mut JNIEnv, state: &JObject) -> jni::errors::Result<AssetPackState> {
+    let status = env.call_method(state, "status", "()I", &[])?.i()?;
+    let bytes_downloaded = env.call_method(state, "bytesDownloaded", "()J", &[])?.j()?;
+    let total_bytes = env.call_method(state, "totalBytesToDownload", "()J", &[])?.j()?;
+    Ok(AssetPackState {
+        status: AssetPackStatus::from_java(status),
+        bytes_downloaded: bytes_downloaded.max(0) as u64,
+        total_bytes: total_bytes.max(0) as u64,
+    })
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl std::fmt::Debug for AssetPacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AssetPacks").field("states", &self.states).field("fetched", &self.fetched).finish()
+    }
+}
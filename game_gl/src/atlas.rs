@@ -0,0 +1,202 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::HashMap;
+
+use image::RgbaImage;
+
+use crate::file::Files;
+use crate::opengl::*;
+use crate::texture::TextureError;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Collects images and packs them into a single texture at runtime.
+#[derive(Debug)]
+pub struct TextureAtlasBuilder {
+    images: Vec<(String, RgbaImage)>,
+    padding: u32,
+    max_size: u32,
+}
+
+/// Texture holding many packed images, addressed by name.
+#[derive(Debug, Default)]
+pub struct TextureAtlas {
+    texture: GlTexture,
+    width: u32,
+    height: u32,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+/// Location of a packed image in pixels and normalized texture coordinates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AtlasRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl TextureAtlasBuilder {
+    /// Creates a builder for atlases up to `max_size` pixels in each dimension.
+    pub fn new(max_size: u32) -> TextureAtlasBuilder {
+        TextureAtlasBuilder {
+            images: Vec::new(),
+            padding: 1,
+            max_size,
+        }
+    }
+
+    /// Sets the border around each image, filled with its edge pixels to avoid bleeding when filtering.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn add_image(&mut self, name: &str, image: RgbaImage) {
+        self.images.push((name.to_string(), image));
+    }
+
+    /// Loads and decodes an image, using its filename as region name.
+    pub fn add_file(&mut self, files: &Files, filename: &str) -> Result<(), TextureError> {
+        let bytes = files.load_bytes(filename).map_err(TextureError::File)?;
+        let image = image::load_from_memory(&bytes).map_err(|err| TextureError::Parse(format!("{}: {}", filename, err)))?;
+        self.add_image(filename, image.to_rgba8());
+        Ok(())
+    }
+
+    /// Packs all images into the smallest power of two texture that fits them.
+    pub fn build(self, gl: &Gl) -> Result<TextureAtlas, TextureError> {
+        let padding = self.padding;
+        let sizes: Vec<(u32, u32)> = self.images.iter().map(|(_, image)| (image.width() + 2 * padding, image.height() + 2 * padding)).collect();
+
+        // start with the smallest square holding the summed area, grow until everything fits
+        let area: u64 = sizes.iter().map(|&(w, h)| w as u64 * h as u64).sum();
+        let mut width = ((area as f64).sqrt().ceil() as u32).max(1).next_power_of_two();
+        let mut height = width;
+        let positions = loop {
+            if width > self.max_size || height > self.max_size {
+                return Err(TextureError::Unsupported(format!("Atlas images do not fit into {0}x{0}", self.max_size)));
+            }
+            if let Some(positions) = pack_shelves(&sizes, width, height) {
+                break positions;
+            }
+            if width <= height {
+                width *= 2;
+            } else {
+                height *= 2;
+            }
+        };
+
+        let mut pixels = RgbaImage::new(width, height);
+        let regions = self
+            .images
+            .iter()
+            .zip(positions)
+            .map(|((name, image), (x, y))| {
+                blit_extruded(&mut pixels, image, x, y, padding);
+                let (x, y) = (x + padding, y + padding);
+                let region = AtlasRegion {
+                    x,
+                    y,
+                    width: image.width(),
+                    height: image.height(),
+                    uv_min: [x as f32 / width as f32, y as f32 / height as f32],
+                    uv_max: [(x + image.width()) as f32 / width as f32, (y + image.height()) as f32 / height as f32],
+                };
+                (name.clone(), region)
+            })
+            .collect();
+
+        log::debug!("Packed {} images into {}x{} atlas", self.images.len(), width, height);
+        Ok(TextureAtlas {
+            texture: GlTexture::try_new(gl, &[pixels]).map_err(TextureError::Gl)?,
+            width,
+            height,
+            regions,
+        })
+    }
+}
+
+impl TextureAtlas {
+    pub fn texture(&mut self) -> &mut GlTexture {
+        &mut self.texture
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = (&str, &AtlasRegion)> {
+        self.regions.iter().map(|(name, region)| (name.as_str(), region))
+    }
+}
+
+//////////////////////////////////////////////////
+// Packing
+
+// places rects row by row, tallest first. Returns positions in input order.
+fn pack_shelves(sizes: &[(u32, u32)], width: u32, height: u32) -> Option<Vec<(u32, u32)>> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for i in order {
+        let (w, h) = sizes[i];
+        if w > width {
+            return None;
+        }
+        if x + w > width {
+            x = 0;
+            y += shelf_height;
+            shelf_height = 0;
+        }
+        if y + h > height {
+            return None;
+        }
+        positions[i] = (x, y);
+        x += w;
+        shelf_height = shelf_height.max(h);
+    }
+    Some(positions)
+}
+
+// copies image into target at (x + padding, y + padding), repeating its edge pixels into the padding
+fn blit_extruded(target: &mut RgbaImage, image: &RgbaImage, x: u32, y: u32, padding: u32) {
+    if image.width() == 0 || image.height() == 0 {
+        return;
+    }
+    for ty in 0..image.height() + 2 * padding {
+        for tx in 0..image.width() + 2 * padding {
+            let sx = tx.saturating_sub(padding).min(image.width() - 1);
+            let sy = ty.saturating_sub(padding).min(image.height() - 1);
+            target.put_pixel(x + tx, y + ty, *image.get_pixel(sx, sy));
+        }
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl GlResource for TextureAtlas {
+    fn release(&mut self) {
+        self.texture.release();
+    }
+}
+impl Drop for TextureAtlas {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
@@ -0,0 +1,106 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::fmt;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hound::{SampleFormat, WavReader};
+use lewton::inside_ogg::OggStreamReader;
+
+use crate::file::FileError;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Decoded PCM samples in [-1, 1], interleaved by channel. Samples are shared, so buffers are cheap to clone
+/// and can be handed to the `Mixer` while staying cached.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SoundBuffer {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Arc<[f32]>,
+}
+
+#[derive(Debug)]
+pub enum AudioError {
+    /// The file could not be read.
+    File(FileError),
+    /// The data is neither WAV nor Ogg Vorbis.
+    Unsupported,
+    /// The file could not be decoded.
+    Decode(String),
+    /// The audio device could not be opened or started.
+    Output(String),
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl SoundBuffer {
+    /// Decodes WAV or Ogg Vorbis data, detected by its header.
+    pub fn decode(bytes: &[u8]) -> Result<SoundBuffer, AudioError> {
+        match bytes.get(0..4) {
+            Some(b"RIFF") => SoundBuffer::decode_wav(bytes),
+            Some(b"OggS") => SoundBuffer::decode_ogg(bytes),
+            _ => Err(AudioError::Unsupported),
+        }
+    }
+
+    /// Integer samples of any bit depth and float samples.
+    pub fn decode_wav(bytes: &[u8]) -> Result<SoundBuffer, AudioError> {
+        let mut reader = WavReader::new(Cursor::new(bytes)).map_err(|e| AudioError::Decode(e.to_string()))?;
+        let spec = reader.spec();
+        let samples = match spec.sample_format {
+            SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>(),
+            SampleFormat::Int => {
+                let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+                reader.samples::<i32>().map(|sample| sample.map(|sample| sample as f32 * scale)).collect()
+            }
+        };
+        Ok(SoundBuffer {
+            sample_rate: spec.sample_rate,
+            channels: spec.channels,
+            samples: samples.map_err(|e| AudioError::Decode(e.to_string()))?.into(),
+        })
+    }
+
+    pub fn decode_ogg(bytes: &[u8]) -> Result<SoundBuffer, AudioError> {
+        let mut reader = OggStreamReader::new(Cursor::new(bytes)).map_err(|e| AudioError::Decode(e.to_string()))?;
+        let mut samples = Vec::new();
+        while let Some(packet) = reader.read_dec_packet_itl().map_err(|e| AudioError::Decode(e.to_string()))? {
+            samples.extend(packet.into_iter().map(|sample| sample as f32 / 32768.0));
+        }
+        Ok(SoundBuffer {
+            sample_rate: reader.ident_hdr.audio_sample_rate,
+            channels: reader.ident_hdr.audio_channels as u16,
+            samples: samples.into(),
+        })
+    }
+
+    /// Samples per channel.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames() as f64 / self.sample_rate.max(1) as f64)
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioError::File(err) => write!(f, "Failed to read audio: {}", err),
+            AudioError::Unsupported => write!(f, "Unsupported audio format, expected WAV or Ogg Vorbis"),
+            AudioError::Decode(err) => write!(f, "Failed to decode audio: {}", err),
+            AudioError::Output(err) => write!(f, "Failed to open audio output: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
@@ -0,0 +1,1219 @@
+//////////////////////////////////////////////////
+// Using
+
+use ktx2::{ColorModel, DfdBlockBasic, SupercompressionScheme, TransferFunction};
+
+use crate::gl;
+use crate::gl::types::*;
+use crate::texture::{ktx2_levels, TextureError};
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Block format Basis Universal textures are transcoded to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TranscodeTarget {
+    /// ASTC 4x4 blocks.
+    Astc4x4,
+    /// BC7 blocks.
+    Bc7,
+    /// BC3 (DXT5) blocks.
+    Bc3,
+    /// ETC2 blocks with EAC alpha.
+    Etc2Rgba8,
+    /// Uncompressed RGBA8 pixels.
+    Rgba8,
+}
+
+/// Basis Universal encoding of a KTX2 container.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BasisFormat {
+    /// ETC1S blocks with BasisLZ supercompression.
+    Etc1s,
+    /// UASTC blocks, optionally Zstandard supercompressed.
+    Uastc,
+}
+
+// decoded 4x4 block, pixels in raster order
+type Block = [[u8; 4]; 16];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+// canonical huffman table, codes are stored most significant bit first
+struct Huffman {
+    counts: [u16; 17],
+    symbols: Vec<u16>,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct Etc1sEndpoint {
+    color: [u8; 3],
+    intensity: u8,
+}
+
+struct Etc1sCodebook {
+    endpoints: Vec<Etc1sEndpoint>,
+    // four bytes per selector, one per row with two bits per pixel
+    selectors: Vec<[u8; 4]>,
+    endpoint_pred: Huffman,
+    delta_endpoint: Huffman,
+    selector: Huffman,
+    selector_rle: Huffman,
+    history_size: usize,
+}
+
+// approximate move to front list of recently used selectors
+struct SelectorHistory {
+    values: Vec<usize>,
+    rover: usize,
+}
+
+const ETC1_INTENSITIES: [[i32; 4]; 8] = [
+    [-8, -2, 2, 8],
+    [-17, -5, 5, 17],
+    [-29, -9, 9, 29],
+    [-42, -13, 13, 42],
+    [-60, -18, 18, 60],
+    [-80, -24, 24, 80],
+    [-106, -33, 33, 106],
+    [-183, -47, 47, 183],
+];
+
+const CODE_LENGTH_ORDER: [usize; 21] = [17, 18, 19, 20, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15, 16];
+const ENDPOINT_PRED_REPEAT: u32 = 256;
+const SELECTOR_RLE_VLC: u32 = 63;
+
+// huffman coded mode index, read from the low bits of the block
+const UASTC_MODE_CODES: [(u32, u32); 19] = [
+    (0x01, 4),
+    (0x35, 6),
+    (0x1D, 5),
+    (0x03, 5),
+    (0x13, 5),
+    (0x0B, 5),
+    (0x1B, 5),
+    (0x07, 5),
+    (0x17, 5),
+    (0x0F, 5),
+    (0x02, 3),
+    (0x00, 2),
+    (0x06, 3),
+    (0x1F, 5),
+    (0x0D, 5),
+    (0x05, 7),
+    (0x15, 6),
+    (0x25, 6),
+    (0x09, 4),
+];
+const UASTC_SOLID_MODE: usize = 8;
+const UASTC_WEIGHT_BITS: [u32; 19] = [4, 2, 3, 2, 2, 3, 2, 2, 0, 2, 4, 2, 3, 1, 2, 4, 2, 2, 5];
+const UASTC_ENDPOINT_RANGES: [usize; 19] = [19, 20, 8, 7, 12, 20, 18, 12, 0, 8, 13, 13, 19, 20, 20, 20, 20, 20, 11];
+const UASTC_COMPONENTS: [usize; 19] = [3, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 2, 2, 2, 3];
+// bc1, etc1 and etc2 hints skipped when decoding to rgba
+const UASTC_HINT_BITS: [u32; 19] = [15, 15, 15, 15, 15, 15, 15, 15, 0, 23, 17, 17, 17, 23, 23, 23, 23, 23, 15];
+// astc partition seeds of the patterns shared with bc7
+const UASTC_PATTERNS_2: [u32; 30] = [
+    28, 20, 16, 29, 91, 9, 107, 72, 149, 204, 50, 114, 496, 17, 78, 39, 252, 828, 43, 156, 116, 210, 476, 273, 684, 359, 246, 195, 694, 524,
+];
+const UASTC_PATTERNS_3: [u32; 11] = [260, 74, 32, 156, 183, 15, 745, 0, 335, 902, 254];
+// astc partition seeds of mode 7
+const UASTC_PATTERNS_ASTC_2: [u32; 19] = [36, 48, 61, 137, 161, 183, 226, 281, 302, 307, 479, 495, 593, 594, 605, 799, 812, 988, 993];
+
+// bits, trits and quints of the astc integer sequence ranges
+const ISE_RANGES: [(u32, bool, bool); 21] = [
+    (1, false, false),
+    (0, true, false),
+    (2, false, false),
+    (0, false, true),
+    (1, true, false),
+    (3, false, false),
+    (1, false, true),
+    (2, true, false),
+    (4, false, false),
+    (2, false, true),
+    (3, true, false),
+    (5, false, false),
+    (3, false, true),
+    (4, true, false),
+    (6, false, false),
+    (4, false, true),
+    (5, true, false),
+    (7, false, false),
+    (5, false, true),
+    (6, true, false),
+    (8, false, false),
+];
+
+const BC7_WEIGHTS: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+const ASTC_WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const ASTC_WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+// etc1 selectors ordered by modifier: -b, -a, +a, +b
+const ETC1_SELECTORS: [u64; 4] = [3, 2, 0, 1];
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl TranscodeTarget {
+    /// Picks the best target the device can upload: ASTC, then BC7 or BC3, then ETC2, falling back to RGBA8.
+    pub fn select(formats: &[GLenum]) -> TranscodeTarget {
+        use TranscodeTarget::*;
+        [Astc4x4, Bc7, Bc3, Etc2Rgba8]
+            .iter()
+            .copied()
+            .find(|target| formats.contains(&target.internal_format(false)))
+            .unwrap_or(Rgba8)
+    }
+
+    /// Internal format of the transcoded texture.
+    pub fn internal_format(self, srgb: bool) -> GLenum {
+        match (self, srgb) {
+            (TranscodeTarget::Astc4x4, false) => gl::COMPRESSED_RGBA_ASTC_4x4,
+            (TranscodeTarget::Astc4x4, true) => gl::COMPRESSED_SRGB8_ALPHA8_ASTC_4x4,
+            (TranscodeTarget::Bc7, false) => gl::COMPRESSED_RGBA_BPTC_UNORM_EXT,
+            (TranscodeTarget::Bc7, true) => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM_EXT,
+            (TranscodeTarget::Bc3, false) => gl::COMPRESSED_RGBA_S3TC_DXT5_EXT,
+            (TranscodeTarget::Bc3, true) => gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+            (TranscodeTarget::Etc2Rgba8, false) => gl::COMPRESSED_RGBA8_ETC2_EAC,
+            (TranscodeTarget::Etc2Rgba8, true) => gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            (TranscodeTarget::Rgba8, false) => gl::RGBA8,
+            (TranscodeTarget::Rgba8, true) => gl::SRGB8_ALPHA8,
+        }
+    }
+
+    // appends the blocks of one image, uncompressed pixels are cropped to the image size
+    fn encode(self, blocks: &[Block], width: u32, height: u32, output: &mut Vec<u8>) {
+        match self {
+            TranscodeTarget::Astc4x4 => blocks.iter().for_each(|block| output.extend_from_slice(&encode_astc(block))),
+            TranscodeTarget::Bc7 => blocks.iter().for_each(|block| output.extend_from_slice(&encode_bc7(block))),
+            TranscodeTarget::Bc3 => blocks.iter().for_each(|block| output.extend_from_slice(&encode_bc3(block))),
+            TranscodeTarget::Etc2Rgba8 => blocks.iter().for_each(|block| output.extend_from_slice(&encode_etc2(block))),
+            TranscodeTarget::Rgba8 => {
+                let blocks_x = width.div_ceil(4) as usize;
+                (0..height as usize).for_each(|y| {
+                    (0..width as usize).for_each(|x| output.extend_from_slice(&blocks[(y / 4) * blocks_x + x / 4][(y % 4) * 4 + x % 4]));
+                });
+            }
+        }
+    }
+}
+
+/// Returns the Basis Universal format and the sRGB flag of a KTX2 container, `None` for regular formats.
+pub fn basis_format<Data: AsRef<[u8]>>(reader: &ktx2::Reader<Data>) -> Option<(BasisFormat, bool)> {
+    let dfd = reader.dfd_blocks().find_map(|block| DfdBlockBasic::parse(block.data).ok().map(|dfd| dfd.header))?;
+    let srgb = dfd.transfer_function == Some(TransferFunction::SRGB);
+    match (reader.header().supercompression_scheme, dfd.color_model) {
+        (Some(SupercompressionScheme::BasisLZ), _) | (_, Some(ColorModel::ETC1S)) => Some((BasisFormat::Etc1s, srgb)),
+        (_, Some(ColorModel::UASTC)) => Some((BasisFormat::Uastc, srgb)),
+        _ => None,
+    }
+}
+
+/// Transcodes all levels of a Basis Universal KTX2 container to `target`.
+/// Levels are returned level major with all layers and faces of a level consecutive, ready for upload.
+pub fn transcode<Data: AsRef<[u8]>>(reader: &ktx2::Reader<Data>, format: BasisFormat, target: TranscodeTarget) -> Result<Vec<Vec<u8>>, TextureError> {
+    let header = reader.header();
+    let images = (header.layer_count.max(1) * header.face_count.max(1)) as usize;
+    match format {
+        BasisFormat::Etc1s => transcode_etc1s(reader, images, target),
+        BasisFormat::Uastc => ktx2_levels(reader)?
+            .iter()
+            .enumerate()
+            .map(|(level, data)| {
+                let (width, height) = ((header.pixel_width >> level).max(1), (header.pixel_height >> level).max(1));
+                let image_size = (width.div_ceil(4) * height.div_ceil(4)) as usize * 16;
+                if data.len() < image_size * images {
+                    return Err(TextureError::Parse("Truncated UASTC level".to_string()));
+                }
+                let mut output = Vec::new();
+                for image in data.chunks_exact(image_size).take(images) {
+                    let blocks = image.chunks_exact(16).map(decode_uastc).collect::<Result<Vec<_>, _>>()?;
+                    target.encode(&blocks, width, height, &mut output);
+                }
+                Ok(output)
+            })
+            .collect(),
+    }
+}
+
+//////////////////////////////////////////////////
+// ETC1S
+
+fn transcode_etc1s<Data: AsRef<[u8]>>(reader: &ktx2::Reader<Data>, images: usize, target: TranscodeTarget) -> Result<Vec<Vec<u8>>, TextureError> {
+    let header = reader.header();
+    let global = reader.supercompression_global_data();
+    let levels: Vec<&[u8]> = reader.levels().map(|level| level.data).collect();
+
+    // global data header, followed by one image descriptor per level, layer and face and the codebooks
+    let u16_at = |offset: usize| u16::from_le_bytes([global[offset], global[offset + 1]]) as usize;
+    let u32_at = |offset: usize| u32::from_le_bytes([global[offset], global[offset + 1], global[offset + 2], global[offset + 3]]) as usize;
+    if global.len() < 20 {
+        return Err(TextureError::Parse("Missing BasisLZ global data".to_string()));
+    }
+    let (endpoint_count, selector_count) = (u16_at(0), u16_at(2));
+    let descriptors = 20;
+    let endpoints = descriptors + levels.len() * images * 20;
+    let selectors = endpoints + u32_at(4);
+    let tables = selectors + u32_at(8);
+    let end = tables + u32_at(12);
+    if end > global.len() {
+        return Err(TextureError::Parse("Truncated BasisLZ global data".to_string()));
+    }
+    let codebook = Etc1sCodebook::read(endpoint_count, selector_count, &global[endpoints..selectors], &global[selectors..tables], &global[tables..end])?;
+
+    levels
+        .iter()
+        .enumerate()
+        .map(|(level, data)| {
+            let (width, height) = ((header.pixel_width >> level).max(1), (header.pixel_height >> level).max(1));
+            let (blocks_x, blocks_y) = (width.div_ceil(4) as usize, height.div_ceil(4) as usize);
+            let mut output = Vec::new();
+            for image in 0..images {
+                let descriptor = descriptors + (level * images + image) * 20;
+                if u32_at(descriptor) & 0x2 != 0 {
+                    return Err(TextureError::Unsupported("ETC1S video frames".to_string()));
+                }
+                let slice = |offset: usize, length: usize| data.get(offset..offset + length).ok_or_else(|| TextureError::Parse("ETC1S slice out of bounds".to_string()));
+                let mut blocks = codebook.decode_slice(slice(u32_at(descriptor + 4), u32_at(descriptor + 8))?, blocks_x, blocks_y)?;
+                let alpha_length = u32_at(descriptor + 16);
+                if alpha_length > 0 {
+                    // alpha is stored as a second slice, its green channel holds the alpha values
+                    let alpha = codebook.decode_slice(slice(u32_at(descriptor + 12), alpha_length)?, blocks_x, blocks_y)?;
+                    blocks
+                        .iter_mut()
+                        .zip(alpha)
+                        .for_each(|(block, alpha)| block.iter_mut().zip(alpha).for_each(|(pixel, alpha)| pixel[3] = alpha[1]));
+                }
+                target.encode(&blocks, width, height, &mut output);
+            }
+            Ok(output)
+        })
+        .collect()
+}
+
+impl Etc1sCodebook {
+    fn read(endpoint_count: usize, selector_count: usize, endpoint_data: &[u8], selector_data: &[u8], table_data: &[u8]) -> Result<Etc1sCodebook, TextureError> {
+        if endpoint_count == 0 || selector_count == 0 {
+            return Err(TextureError::Parse("Empty ETC1S codebook".to_string()));
+        }
+
+        // endpoints are delta coded against the previous endpoint
+        let mut reader = BitReader::new(endpoint_data);
+        let color_models = [reader.huffman_table()?, reader.huffman_table()?, reader.huffman_table()?];
+        let intensity_model = reader.huffman_table()?;
+        let grayscale = reader.bits(1)? != 0;
+        let (mut previous_color, mut previous_intensity) = ([16u32; 3], 0u32);
+        let mut endpoints = Vec::with_capacity(endpoint_count);
+        for _ in 0..endpoint_count {
+            let intensity = (reader.huffman(&intensity_model)? + previous_intensity) & 7;
+            previous_intensity = intensity;
+            let mut color = [0u8; 3];
+            for c in 0..if grayscale { 1 } else { 3 } {
+                let model = match previous_color[c] {
+                    0..=9 => &color_models[0],
+                    10..=21 => &color_models[1],
+                    _ => &color_models[2],
+                };
+                previous_color[c] = (previous_color[c] + reader.huffman(model)?) & 31;
+                color[c] = previous_color[c] as u8;
+            }
+            if grayscale {
+                color = [color[0]; 3];
+            }
+            endpoints.push(Etc1sEndpoint { color, intensity: intensity as u8 });
+        }
+
+        // selectors are either raw or xor delta coded against the previous selector
+        let mut reader = BitReader::new(selector_data);
+        if reader.bits(1)? != 0 || reader.bits(1)? != 0 {
+            return Err(TextureError::Unsupported("ETC1S global selector codebooks".to_string()));
+        }
+        let raw = reader.bits(1)? != 0;
+        let delta_model = if raw { None } else { Some(reader.huffman_table()?) };
+        let mut selectors: Vec<[u8; 4]> = Vec::with_capacity(selector_count);
+        for i in 0..selector_count {
+            let mut selector = [0u8; 4];
+            for (row, byte) in selector.iter_mut().enumerate() {
+                *byte = match (&delta_model, i) {
+                    (Some(model), 1..) => (reader.huffman(model)? as u8) ^ selectors[i - 1][row],
+                    _ => reader.bits(8)? as u8,
+                };
+            }
+            selectors.push(selector);
+        }
+
+        let mut reader = BitReader::new(table_data);
+        Ok(Etc1sCodebook {
+            endpoints,
+            selectors,
+            endpoint_pred: reader.huffman_table()?,
+            delta_endpoint: reader.huffman_table()?,
+            selector: reader.huffman_table()?,
+            selector_rle: reader.huffman_table()?,
+            history_size: reader.bits(13)? as usize,
+        })
+    }
+
+    fn decode_slice(&self, data: &[u8], blocks_x: usize, blocks_y: usize) -> Result<Vec<Block>, TextureError> {
+        let invalid = |what: &str| TextureError::Parse(format!("Invalid ETC1S {}", what));
+        let (endpoint_count, selector_count) = (self.endpoints.len(), self.selectors.len());
+        let mut reader = BitReader::new(data);
+        let mut history = SelectorHistory::new(self.history_size);
+
+        // endpoint predictions are coded once per 2x2 blocks, the upper half is kept for the odd row
+        let mut rows = [vec![(0usize, 0u32); blocks_x], vec![(0usize, 0u32); blocks_x]];
+        let (mut pred_bits, mut previous_pred, mut pred_repeat) = (0u32, 0u32, 0usize);
+        let (mut previous_endpoint, mut selector_repeat) = (0usize, 0usize);
+        let mut blocks = Vec::with_capacity(blocks_x * blocks_y);
+        for y in 0..blocks_y {
+            let row = y & 1;
+            for x in 0..blocks_x {
+                if x & 1 == 0 {
+                    if row == 0 {
+                        if pred_repeat > 0 {
+                            pred_repeat -= 1;
+                            pred_bits = previous_pred;
+                        } else {
+                            pred_bits = reader.huffman(&self.endpoint_pred)?;
+                            if pred_bits == ENDPOINT_PRED_REPEAT {
+                                pred_repeat = reader.vlc(4)? as usize + 2;
+                                pred_bits = previous_pred;
+                            } else {
+                                previous_pred = pred_bits;
+                            }
+                        }
+                        rows[1][x].1 = pred_bits >> 4;
+                    } else {
+                        pred_bits = rows[1][x].1;
+                    }
+                }
+
+                let endpoint = match pred_bits & 3 {
+                    0 if x > 0 => previous_endpoint,
+                    1 if y > 0 => rows[row ^ 1][x].0,
+                    2 if x > 0 && y > 0 => rows[row ^ 1][x - 1].0,
+                    3 => (reader.huffman(&self.delta_endpoint)? as usize + previous_endpoint) % endpoint_count,
+                    _ => return Err(invalid("endpoint prediction")),
+                };
+                pred_bits >>= 2;
+                rows[row][x].0 = endpoint;
+                previous_endpoint = endpoint;
+
+                let symbol = if selector_repeat > 0 {
+                    selector_repeat -= 1;
+                    selector_count
+                } else {
+                    let symbol = reader.huffman(&self.selector)? as usize;
+                    if symbol == selector_count + self.history_size {
+                        let run = reader.huffman(&self.selector_rle)?;
+                        let run = if run == SELECTOR_RLE_VLC { reader.vlc(7)? } else { run } as usize + 3;
+                        if run > blocks_x * blocks_y {
+                            return Err(invalid("selector run"));
+                        }
+                        selector_repeat = run - 1;
+                        selector_count
+                    } else {
+                        symbol
+                    }
+                };
+                let selector = if symbol >= selector_count {
+                    history.take(symbol - selector_count).ok_or_else(|| invalid("selector history index"))?
+                } else {
+                    history.add(symbol);
+                    symbol
+                };
+
+                let (endpoint, selector) = (self.endpoints[endpoint], self.selectors.get(selector).ok_or_else(|| invalid("selector"))?);
+                blocks.push(std::array::from_fn(|i| {
+                    let modifier = ETC1_INTENSITIES[endpoint.intensity as usize][((selector[i / 4] >> ((i % 4) * 2)) & 3) as usize];
+                    let [r, g, b] = endpoint.color.map(|c| (expand5(c) as i32 + modifier).clamp(0, 255) as u8);
+                    [r, g, b, 255]
+                }));
+            }
+        }
+        Ok(blocks)
+    }
+}
+
+impl SelectorHistory {
+    fn new(size: usize) -> SelectorHistory {
+        SelectorHistory {
+            values: vec![0; size],
+            rover: size / 2,
+        }
+    }
+
+    fn add(&mut self, value: usize) {
+        if !self.values.is_empty() {
+            self.values[self.rover] = value;
+            self.rover += 1;
+            if self.rover == self.values.len() {
+                self.rover = self.values.len() / 2;
+            }
+        }
+    }
+
+    // returns the value at `index` and moves it halfway towards the front
+    fn take(&mut self, index: usize) -> Option<usize> {
+        let value = *self.values.get(index)?;
+        self.values.swap(index / 2, index);
+        Some(value)
+    }
+}
+
+//////////////////////////////////////////////////
+// UASTC
+
+fn decode_uastc(data: &[u8]) -> Result<Block, TextureError> {
+    let block = u128::from_le_bytes(std::array::from_fn(|i| data[i]));
+    let mut position = 0;
+    let mut bits = |count: u32| {
+        let value = ((block >> position) & ((1u128 << count) - 1)) as u32;
+        position += count;
+        value
+    };
+
+    let mode = UASTC_MODE_CODES
+        .iter()
+        .position(|&(code, length)| block as u32 & ((1 << length) - 1) == code)
+        .ok_or_else(|| TextureError::Parse("Invalid UASTC mode".to_string()))?;
+    bits(UASTC_MODE_CODES[mode].1);
+    if mode == UASTC_SOLID_MODE {
+        let color = [bits(8) as u8, bits(8) as u8, bits(8) as u8, bits(8) as u8];
+        return Ok([color; 16]);
+    }
+    bits(UASTC_HINT_BITS[mode]);
+
+    let partition: [usize; 16] = match mode {
+        2 | 4 | 9 | 16 => astc_partitions(*UASTC_PATTERNS_2.get(bits(5) as usize).ok_or_else(|| TextureError::Parse("Invalid UASTC pattern".to_string()))?, 2),
+        7 => astc_partitions(*UASTC_PATTERNS_ASTC_2.get(bits(5) as usize).ok_or_else(|| TextureError::Parse("Invalid UASTC pattern".to_string()))?, 2),
+        3 => astc_partitions(*UASTC_PATTERNS_3.get(bits(4) as usize).ok_or_else(|| TextureError::Parse("Invalid UASTC pattern".to_string()))?, 3),
+        _ => [0; 16],
+    };
+    let subsets = partition.iter().max().map_or(1, |max| max + 1);
+    let planes = if matches!(mode, 6 | 11 | 13 | 17) { 2 } else { 1 };
+    let plane_channel = match mode {
+        6 | 11 | 13 => bits(2) as usize,
+        17 => 3,
+        _ => 4,
+    };
+
+    // trits and quints are packed in bundles ahead of the low endpoint bits
+    let components = UASTC_COMPONENTS[mode];
+    let range = UASTC_ENDPOINT_RANGES[mode];
+    let (endpoint_bits, trits, quints) = ISE_RANGES[range];
+    let count = subsets * components * 2;
+    let (base, bundle, bundle_bits): (u32, usize, &[u32]) = match (trits, quints) {
+        (true, _) => (3, 5, &[0, 2, 4, 5, 7, 8]),
+        (_, true) => (5, 3, &[0, 3, 5, 7]),
+        _ => (1, 1, &[0, 0]),
+    };
+    let bundles: Vec<u32> = (0..count).step_by(bundle).map(|first| bits(bundle_bits[bundle.min(count - first)])).collect();
+    let endpoints: Vec<u8> = (0..count)
+        .map(|i| {
+            let packed = (bundles[i / bundle] / base.pow((i % bundle) as u32)) % base;
+            let value = bits(endpoint_bits) | (packed << endpoint_bits);
+            unquantize_endpoint(value, range)
+        })
+        .collect();
+
+    // the first pixel of every subset stores one weight bit less
+    let weight_bits = UASTC_WEIGHT_BITS[mode];
+    let mut weights = [[0u32; 2]; 16];
+    for (i, weight) in weights.iter_mut().enumerate() {
+        let anchor = !partition[..i].contains(&partition[i]);
+        for plane in weight.iter_mut().take(planes) {
+            *plane = unquantize_weight(bits(weight_bits - anchor as u32), weight_bits);
+        }
+    }
+
+    Ok(std::array::from_fn(|i| {
+        let subset = &endpoints[partition[i] * components * 2..];
+        std::array::from_fn(|channel| {
+            let component = match (components, channel) {
+                (2, 3) => 1,
+                (2, _) => 0,
+                (3, 3) => return 255,
+                _ => channel,
+            };
+            let weight = weights[i][(planes == 2 && channel == plane_channel) as usize];
+            let (low, high) = (subset[component * 2] as u32 * 257, subset[component * 2 + 1] as u32 * 257);
+            (((low * (64 - weight) + high * weight + 32) >> 6) >> 8) as u8
+        })
+    }))
+}
+
+// astc partition function for 4x4 blocks
+fn astc_partitions(seed: u32, count: u32) -> [usize; 16] {
+    let seed = seed + (count - 1) * 1024;
+    let mut random = seed;
+    random ^= random >> 15;
+    random = random.wrapping_sub(random << 17);
+    random = random.wrapping_add(random << 7);
+    random = random.wrapping_add(random << 4);
+    random ^= random >> 5;
+    random = random.wrapping_add(random << 16);
+    random ^= random >> 7;
+    random ^= random >> 3;
+    random ^= random << 6;
+    random ^= random >> 17;
+
+    let mut s: [u32; 8] = std::array::from_fn(|i| (random >> (i * 4)) & 0xF);
+    s.iter_mut().for_each(|s| *s *= *s);
+    let (shift1, shift2) = match (seed & 1 != 0, seed & 2 != 0) {
+        (true, true) => (4, if count == 3 { 6 } else { 5 }),
+        (true, false) => (5, if count == 3 { 6 } else { 5 }),
+        (false, true) => (if count == 3 { 6 } else { 5 }, 4),
+        (false, false) => (if count == 3 { 6 } else { 5 }, 5),
+    };
+    s.iter_mut().enumerate().for_each(|(i, s)| *s >>= if i % 2 == 0 { shift1 } else { shift2 });
+
+    std::array::from_fn(|i| {
+        let (x, y) = ((i % 4) as u32 * 2, (i / 4) as u32 * 2);
+        let a = (s[0] * x + s[1] * y + (random >> 14)) & 0x3F;
+        let b = (s[2] * x + s[3] * y + (random >> 10)) & 0x3F;
+        let c = if count < 3 { 0 } else { (s[4] * x + s[5] * y + (random >> 6)) & 0x3F };
+        if a >= b && a >= c {
+            0
+        } else if b >= c {
+            1
+        } else {
+            2
+        }
+    })
+}
+
+fn unquantize_endpoint(value: u32, range: usize) -> u8 {
+    let (bits, trits, quints) = ISE_RANGES[range];
+    if !trits && !quints {
+        // replicate the bits to fill a byte
+        return (0..8).step_by(bits as usize).fold(0u32, |result, shift| result | ((value << (8 - bits)) >> shift)) as u8;
+    }
+    let (digit, low) = (value >> bits, value & ((1 << bits) - 1));
+    let a = if low & 1 != 0 { 0x1FF } else { 0 };
+    let x = low >> 1;
+    let (c, b) = match (trits, bits) {
+        (true, 1) => (204, 0),
+        (true, 2) => (93, x * 0x116),
+        (true, 3) => (44, (x << 7) | (x << 2) | x),
+        (true, 4) => (22, (x << 6) | x),
+        (true, 5) => (11, (x << 5) | (x >> 2)),
+        (true, _) => (5, (x << 4) | (x >> 4)),
+        (false, 1) => (113, 0),
+        (false, 2) => (54, x * 0x10C),
+        (false, 3) => (26, (x << 7) | (x << 1) | (x >> 1)),
+        (false, 4) => (13, (x << 6) | (x >> 1)),
+        (false, _) => (6, (x << 5) | (x >> 3)),
+    };
+    let t = (digit * c + b) ^ a;
+    ((a & 0x80) | (t >> 2)) as u8
+}
+
+fn unquantize_weight(value: u32, bits: u32) -> u32 {
+    let weight = (0..6).step_by(bits as usize).fold(0, |result, shift| result | ((value << (6 - bits)) >> shift));
+    weight + (weight > 32) as u32
+}
+
+//////////////////////////////////////////////////
+// Block encoding
+
+// endpoints along the principal axis of the first `channels` channels
+fn block_endpoints(block: &Block, channels: usize) -> ([u8; 4], [u8; 4]) {
+    let mean: [f32; 4] = std::array::from_fn(|c| block.iter().map(|p| p[c] as f32).sum::<f32>() / 16.0);
+    let mut covariance = [[0f32; 4]; 4];
+    for pixel in block {
+        let d: [f32; 4] = std::array::from_fn(|c| if c < channels { pixel[c] as f32 - mean[c] } else { 0.0 });
+        (0..4).for_each(|i| (0..4).for_each(|j| covariance[i][j] += d[i] * d[j]));
+    }
+    let mut axis = covariance[(0..4).max_by(|&i, &j| covariance[i][i].total_cmp(&covariance[j][j])).unwrap_or(0)];
+    for _ in 0..8 {
+        let next: [f32; 4] = std::array::from_fn(|i| (0..4).map(|j| covariance[i][j] * axis[j]).sum());
+        let length = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if length < 1e-6 {
+            break;
+        }
+        axis = next.map(|v| v / length);
+    }
+    let project = |pixel: &[u8; 4]| (0..channels).map(|c| (pixel[c] as f32 - mean[c]) * axis[c]).sum::<f32>();
+    let low = block.iter().min_by(|a, b| project(a).total_cmp(&project(b))).copied().unwrap_or_default();
+    let high = block.iter().max_by(|a, b| project(a).total_cmp(&project(b))).copied().unwrap_or_default();
+    (low, high)
+}
+
+// index of the palette entry closest to each pixel
+fn closest(block: &Block, palette: &[[u8; 4]], channels: usize) -> [usize; 16] {
+    std::array::from_fn(|i| {
+        let error = |entry: &[u8; 4]| (0..channels).map(|c| (entry[c] as i32 - block[i][c] as i32).pow(2)).sum::<i32>();
+        (0..palette.len()).min_by_key(|&j| error(&palette[j])).unwrap_or(0)
+    })
+}
+
+fn interpolate(low: [u8; 4], high: [u8; 4], weight: u32, total: u32) -> [u8; 4] {
+    std::array::from_fn(|c| ((low[c] as u32 * (total - weight) + high[c] as u32 * weight + total / 2) / total) as u8)
+}
+
+fn encode_astc(block: &Block) -> [u8; 16] {
+    let alpha = block.iter().any(|pixel| pixel[3] != 255);
+    let (mut low, mut high) = block_endpoints(block, 4);
+    // a darker second endpoint selects blue contraction
+    if high[..3].iter().map(|&c| c as u32).sum::<u32>() < low[..3].iter().map(|&c| c as u32).sum::<u32>() {
+        std::mem::swap(&mut low, &mut high);
+    }
+    // single partition with 8 bit endpoints, rgb with 3 bit weights or rgba with 2 bit weights
+    let (block_mode, endpoint_mode, weights, channels): (u128, u128, &[u32], usize) = if alpha { (0x42, 12, &ASTC_WEIGHTS_2, 4) } else { (0x53, 8, &ASTC_WEIGHTS_3, 3) };
+    let palette: Vec<[u8; 4]> = weights
+        .iter()
+        .map(|&w| std::array::from_fn(|c| (((low[c] as u32 * 257 * (64 - w) + high[c] as u32 * 257 * w + 32) >> 6) >> 8) as u8))
+        .collect();
+    let indices = closest(block, &palette, channels);
+
+    let mut bits = block_mode | (endpoint_mode << 13);
+    (0..channels).for_each(|c| bits |= ((low[c] as u128) << (17 + c * 16)) | ((high[c] as u128) << (25 + c * 16)));
+    // weights are stored bit reversed from the top of the block
+    let weight_bits = weights.len().trailing_zeros() as usize;
+    for (i, index) in indices.iter().enumerate() {
+        (0..weight_bits).filter(|b| index >> b & 1 != 0).for_each(|b| bits |= 1 << (127 - i * weight_bits - b));
+    }
+    bits.to_le_bytes()
+}
+
+fn encode_bc7(block: &Block) -> [u8; 16] {
+    // mode 6, 7 bit rgba endpoints with a p-bit each and 4 bit indices
+    let (low, high) = block_endpoints(block, 4);
+    let quantize = |color: [u8; 4]| {
+        let p = (color.iter().filter(|&&c| c & 1 != 0).count() >= 2) as u8;
+        (color.map(|c| ((c as i32 - p as i32 + 1).clamp(0, 254) >> 1) as u8), p)
+    };
+    let (mut endpoints, mut p) = ([quantize(low).0, quantize(high).0], [quantize(low).1, quantize(high).1]);
+    let expand = |e: [u8; 4], p: u8| e.map(|c| (c << 1) | p);
+    let palette: Vec<[u8; 4]> = BC7_WEIGHTS.iter().map(|&w| interpolate(expand(endpoints[0], p[0]), expand(endpoints[1], p[1]), w, 64)).collect();
+    let mut indices = closest(block, &palette, 4);
+    // the anchor index has an implicit zero msb
+    if indices[0] >= 8 {
+        endpoints.swap(0, 1);
+        p.swap(0, 1);
+        indices.iter_mut().for_each(|index| *index = 15 - *index);
+    }
+
+    let mut bits = 1u128 << 6;
+    let mut position = 7;
+    let mut write = |value: u128, count: usize| {
+        bits |= value << position;
+        position += count;
+    };
+    (0..4).for_each(|c| endpoints.iter().for_each(|endpoint| write(endpoint[c] as u128, 7)));
+    p.iter().for_each(|&p| write(p as u128, 1));
+    indices.iter().enumerate().for_each(|(i, &index)| write(index as u128, if i == 0 { 3 } else { 4 }));
+    bits.to_le_bytes()
+}
+
+fn encode_bc3(block: &Block) -> [u8; 16] {
+    let mut output = [0u8; 16];
+
+    // eight value alpha palette between the extremes
+    let (a0, a1) = block.iter().fold((0u8, 255u8), |(max, min), pixel| (max.max(pixel[3]), min.min(pixel[3])));
+    let alpha: Vec<[u8; 4]> = (0..8u32)
+        .map(|i| {
+            let a = match i {
+                0 => a0 as u32,
+                1 => a1 as u32,
+                _ => ((8 - i) * a0 as u32 + (i - 1) * a1 as u32) / 7,
+            };
+            [0, 0, 0, a as u8]
+        })
+        .collect();
+    let alpha_indices = closest(&block.map(|pixel| [0, 0, 0, pixel[3]]), &alpha, 4);
+    let alpha_bits = alpha_indices.iter().enumerate().fold(0u64, |bits, (i, &index)| bits | (index as u64) << (i * 3));
+    output[0] = a0;
+    output[1] = a1;
+    output[2..8].copy_from_slice(&alpha_bits.to_le_bytes()[..6]);
+
+    // four color palette, the larger endpoint first
+    let (low, high) = block_endpoints(block, 3);
+    let to565 = |c: [u8; 4]| (((c[0] as u16 * 31 + 127) / 255) << 11) | (((c[1] as u16 * 63 + 127) / 255) << 5) | ((c[2] as u16 * 31 + 127) / 255);
+    let (c0, c1) = (to565(high).max(to565(low)), to565(high).min(to565(low)));
+    let color_bits = if c0 == c1 {
+        0
+    } else {
+        let (p0, p1) = (rgb565(c0), rgb565(c1));
+        let palette = [p0, p1, interpolate(p0, p1, 1, 3), interpolate(p0, p1, 2, 3)];
+        closest(block, &palette, 3).iter().enumerate().fold(0u32, |bits, (i, &index)| bits | (index as u32) << (i * 2))
+    };
+    output[8..10].copy_from_slice(&c0.to_le_bytes());
+    output[10..12].copy_from_slice(&c1.to_le_bytes());
+    output[12..16].copy_from_slice(&color_bits.to_le_bytes());
+    output
+}
+
+fn encode_etc2(block: &Block) -> [u8; 16] {
+    let mut output = [0u8; 16];
+    output[..8].copy_from_slice(&encode_eac_alpha(block));
+    output[8..].copy_from_slice(&encode_etc1(block));
+    output
+}
+
+fn encode_eac_alpha(block: &Block) -> [u8; 8] {
+    let (min, max) = block.iter().fold((255i32, 0i32), |(min, max), pixel| (min.min(pixel[3] as i32), max.max(pixel[3] as i32)));
+    // stretch every modifier table over the alpha range, keep the closest fit
+    let (_, base, multiplier, table, indices) = EAC_MODIFIERS
+        .iter()
+        .enumerate()
+        .map(|(table, modifiers)| {
+            let multiplier = ((max - min) as f32 / (modifiers[7] - modifiers[3]) as f32).ceil().clamp(1.0, 15.0) as i32;
+            let base = (min - modifiers[3] * multiplier).clamp(0, 255);
+            let mut error = 0;
+            let indices: [u64; 16] = std::array::from_fn(|i| {
+                let alpha = block[(i % 4) * 4 + i / 4][3] as i32;
+                let (index, e) = (0..8)
+                    .map(|j| (j, ((base + modifiers[j] * multiplier).clamp(0, 255) - alpha).abs()))
+                    .min_by_key(|&(_, e)| e)
+                    .unwrap_or((0, 0));
+                error += e;
+                index as u64
+            });
+            (error, base, multiplier, table, indices)
+        })
+        .min_by_key(|candidate| candidate.0)
+        .unwrap_or((0, 255, 1, 13, [4; 16]));
+    // pixels are stored column major
+    let bits = indices
+        .iter()
+        .enumerate()
+        .fold((base as u64) << 56 | (multiplier as u64) << 52 | (table as u64) << 48, |bits, (i, &index)| bits | index << (45 - i * 3));
+    bits.to_be_bytes()
+}
+
+fn encode_etc1(block: &Block) -> [u8; 8] {
+    [false, true]
+        .iter()
+        .map(|&flip| {
+            let halves: [Vec<usize>; 2] = [0, 1].map(|half| (0..16).filter(|&i| (if flip { i / 4 } else { i % 4 }) / 2 == half).collect());
+            let average = |half: &[usize]| -> [u32; 3] { std::array::from_fn(|c| half.iter().map(|&i| block[i][c] as u32).sum::<u32>() / half.len() as u32) };
+            let averages = [average(&halves[0]), average(&halves[1])];
+
+            // differential mode when both halves are close enough, individual mode otherwise
+            let colors5 = averages.map(|a| a.map(|c| (c * 31 + 127) / 255));
+            let differential = (0..3).all(|c| (-4..=3).contains(&(colors5[1][c] as i32 - colors5[0][c] as i32)));
+            let (mut bits, bases) = if differential {
+                let bits = (0..3).fold(0u64, |bits, c| {
+                    bits | (colors5[0][c] as u64) << (59 - c * 8) | ((colors5[1][c] as i32 - colors5[0][c] as i32) as u64 & 7) << (56 - c * 8)
+                });
+                (bits | 1 << 33, colors5.map(|color| color.map(|c| expand5(c as u8) as i32)))
+            } else {
+                let colors4 = averages.map(|a| a.map(|c| (c * 15 + 127) / 255));
+                let bits = (0..3).fold(0u64, |bits, c| bits | (colors4[0][c] as u64) << (60 - c * 8) | (colors4[1][c] as u64) << (56 - c * 8));
+                (bits, colors4.map(|color| color.map(|c| (c * 17) as i32)))
+            };
+            bits |= (flip as u64) << 32;
+
+            // pick the intensity table and modifiers with the lowest error per half
+            let mut error = 0;
+            for (half, pixels) in halves.iter().enumerate() {
+                let (half_error, table, selectors) = ETC1_INTENSITIES
+                    .iter()
+                    .enumerate()
+                    .map(|(table, modifiers)| {
+                        let mut error = 0;
+                        let selectors: Vec<usize> = pixels
+                            .iter()
+                            .map(|&i| {
+                                let error_of = |m: i32| (0..3).map(|c| ((bases[half][c] + m).clamp(0, 255) - block[i][c] as i32).pow(2)).sum::<i32>();
+                                let (selector, e) = (0..4).map(|s| (s, error_of(modifiers[s]))).min_by_key(|&(_, e)| e).unwrap_or((0, 0));
+                                error += e;
+                                selector
+                            })
+                            .collect();
+                        (error, table, selectors)
+                    })
+                    .min_by_key(|candidate| candidate.0)
+                    .unwrap_or_default();
+                error += half_error;
+                bits |= (table as u64) << (37 - half * 3);
+                for (&i, &selector) in pixels.iter().zip(&selectors) {
+                    // pixels are stored column major, msb and lsb planes separately
+                    let (index, value) = ((i % 4) * 4 + i / 4, ETC1_SELECTORS[selector]);
+                    bits |= (value >> 1) << (16 + index) | (value & 1) << index;
+                }
+            }
+            (error, bits)
+        })
+        .min_by_key(|candidate| candidate.0)
+        .map_or(0, |(_, bits)| bits)
+        .to_be_bytes()
+}
+
+fn expand5(c: u8) -> u8 {
+    (c << 3) | (c >> 2)
+}
+
+fn rgb565(color: u16) -> [u8; 4] {
+    let (r, g, b) = ((color >> 11) as u8 & 0x1F, (color >> 5) as u8 & 0x3F, color as u8 & 0x1F);
+    [expand5(r), (g << 2) | (g >> 4), expand5(b), 255]
+}
+
+//////////////////////////////////////////////////
+// Bit reading
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader { data, position: 0 }
+    }
+
+    // reads `count` bits, least significant bit first
+    fn bits(&mut self, count: u32) -> Result<u32, TextureError> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self.data.get(self.position / 8).ok_or_else(|| TextureError::Parse("Truncated ETC1S data".to_string()))?;
+            value |= (((byte >> (self.position % 8)) & 1) as u32) << i;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    // variable length value in chunks of `chunk_bits`, a set high bit continues with the next chunk
+    fn vlc(&mut self, chunk_bits: u32) -> Result<u32, TextureError> {
+        let mut value = 0;
+        for shift in (0..32).step_by(chunk_bits as usize) {
+            let chunk = self.bits(chunk_bits + 1)?;
+            value |= (chunk & ((1 << chunk_bits) - 1)) << shift;
+            if chunk & (1 << chunk_bits) == 0 {
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    fn huffman(&mut self, table: &Huffman) -> Result<u32, TextureError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &table.counts[1..] {
+            code |= self.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(table.symbols[(index + code - first) as usize] as u32);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(TextureError::Parse("Invalid ETC1S huffman code".to_string()))
+    }
+
+    // code lengths are huffman coded themselves, with zero runs and repeats of the previous length
+    fn huffman_table(&mut self) -> Result<Huffman, TextureError> {
+        let invalid = || TextureError::Parse("Invalid ETC1S huffman table".to_string());
+        let total = self.bits(14)? as usize;
+        let code_length_count = self.bits(5)? as usize;
+        if total == 0 || code_length_count == 0 || code_length_count > CODE_LENGTH_ORDER.len() {
+            return Err(invalid());
+        }
+        let mut code_length_sizes = [0u8; 21];
+        for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+            code_length_sizes[symbol] = self.bits(3)? as u8;
+        }
+        let code_lengths = Huffman::new(&code_length_sizes)?;
+
+        let mut lengths = vec![0u8; total];
+        let mut current = 0;
+        while current < total {
+            let (run, length) = match self.huffman(&code_lengths)? {
+                length @ 0..=16 => (1, length as u8),
+                17 => (self.bits(3)? as usize + 3, 0),
+                18 => (self.bits(7)? as usize + 11, 0),
+                symbol => {
+                    let previous = lengths[..current].last().copied().filter(|&length| length > 0).ok_or_else(invalid)?;
+                    (if symbol == 19 { self.bits(2)? as usize + 3 } else { self.bits(7)? as usize + 7 }, previous)
+                }
+            };
+            lengths.get_mut(current..current + run).ok_or_else(invalid)?.fill(length);
+            current += run;
+        }
+        Huffman::new(&lengths)
+    }
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Huffman, TextureError> {
+        let mut counts = [0u16; 17];
+        lengths.iter().filter(|&&length| length > 0).for_each(|&length| counts[length.min(16) as usize] += 1);
+        // reject over subscribed codes, incomplete codes are valid
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(TextureError::Parse("Over subscribed ETC1S huffman table".to_string()));
+            }
+        }
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16).filter(|&symbol| lengths[symbol as usize] > 0).collect();
+        if symbols.is_empty() {
+            return Err(TextureError::Parse("Empty ETC1S huffman table".to_string()));
+        }
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+        Ok(Huffman { counts, symbols })
+    }
+}
+
+//////////////////////////////////////////////////
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use ktx2::{DataFormatFlags, DfdBlockHeaderBasic, DfdHeader, Header, Index, LevelIndex};
+
+    use super::*;
+    use crate::texture::{decode_bc, DdsFormat};
+
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        position: usize,
+    }
+
+    impl BitWriter {
+        fn bits(&mut self, value: u32, count: u32) -> &mut Self {
+            for i in 0..count {
+                if self.position / 8 == self.bytes.len() {
+                    self.bytes.push(0);
+                }
+                self.bytes[self.position / 8] |= ((value.checked_shr(i).unwrap_or(0) & 1) as u8) << (self.position % 8);
+                self.position += 1;
+            }
+            self
+        }
+
+        // canonical code of `symbol`, written most significant bit first
+        fn code(&mut self, lengths: &[u8], symbol: usize) -> &mut Self {
+            let mut code = 0;
+            for length in 1..=16 {
+                for (s, _) in lengths.iter().enumerate().filter(|&(_, &l)| l == length) {
+                    if s == symbol {
+                        (0..length).rev().for_each(|bit| {
+                            self.bits((code >> bit) & 1, 1);
+                        });
+                        return self;
+                    }
+                    code += 1;
+                }
+                code <<= 1;
+            }
+            panic!("symbol {} has no code", symbol);
+        }
+
+        // code lengths are written as literals with a four bit code each
+        fn huffman_table(&mut self, lengths: &[u8]) -> &mut Self {
+            self.bits(lengths.len() as u32, 14).bits(21, 5);
+            CODE_LENGTH_ORDER.iter().for_each(|&symbol| {
+                self.bits(if symbol < 16 { 4 } else { 0 }, 3);
+            });
+            lengths.iter().for_each(|&length| {
+                self.code(&[4; 16], length as usize);
+            });
+            self
+        }
+    }
+
+    fn ktx2(color_model: ColorModel, scheme: Option<SupercompressionScheme>, width: u32, height: u32, global: &[u8], level: &[u8]) -> Vec<u8> {
+        let (dfd_offset, dfd_length) = (Header::LENGTH + LevelIndex::LENGTH, 44);
+        let sgd_offset = dfd_offset + dfd_length;
+        let level_offset = sgd_offset + global.len();
+        let header = Header {
+            format: None,
+            type_size: 1,
+            pixel_width: width,
+            pixel_height: height,
+            pixel_depth: 0,
+            layer_count: 0,
+            face_count: 1,
+            level_count: 1,
+            supercompression_scheme: scheme,
+            index: Index {
+                dfd_byte_offset: dfd_offset as u32,
+                dfd_byte_length: dfd_length as u32,
+                kvd_byte_offset: 0,
+                kvd_byte_length: 0,
+                sgd_byte_offset: sgd_offset as u64,
+                sgd_byte_length: global.len() as u64,
+            },
+        };
+        let dfd = DfdBlockHeaderBasic {
+            color_model: Some(color_model),
+            color_primaries: None,
+            transfer_function: Some(TransferFunction::Linear),
+            flags: DataFormatFlags::empty(),
+            texel_block_dimensions: [4, 4, 1, 1].map(|d| NonZeroU8::new(d).unwrap()),
+            bytes_planes: [16, 0, 0, 0, 0, 0, 0, 0],
+        };
+        let level_index = LevelIndex {
+            byte_offset: level_offset as u64,
+            byte_length: level.len() as u64,
+            uncompressed_byte_length: level.len() as u64,
+        };
+
+        let mut bytes = header.as_bytes().to_vec();
+        bytes.extend_from_slice(&level_index.as_bytes());
+        bytes.extend_from_slice(&(dfd_length as u32).to_le_bytes());
+        bytes.extend_from_slice(&DfdHeader::BASIC.as_bytes(40));
+        bytes.extend_from_slice(&dfd.as_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+        bytes.extend_from_slice(global);
+        bytes.extend_from_slice(level);
+        bytes
+    }
+
+    // 12x4 pixels: a solid block, a single subset gray ramp and two subsets in red and blue
+    fn uastc_file() -> Vec<u8> {
+        let mut level = Vec::new();
+        let mut solid = BitWriter::default();
+        solid.bits(0x17, 5).bits(10, 8).bits(20, 8).bits(30, 8).bits(40, 8).bits(0, 128 - 37);
+        level.extend_from_slice(&solid.bytes);
+
+        let mut ramp = BitWriter::default();
+        ramp.bits(0x0B, 5).bits(0, 15);
+        [0, 255, 0, 255, 0, 255].iter().for_each(|&value| {
+            ramp.bits(value, 8);
+        });
+        (0..16).for_each(|i| {
+            ramp.bits(i % 8, if i == 0 { 2 } else { 3 });
+        });
+        ramp.bits(0, 128 - ramp.position as u32);
+        level.extend_from_slice(&ramp.bytes);
+
+        let mut subsets = BitWriter::default();
+        subsets.bits(0x1D, 5).bits(0, 15).bits(0, 5);
+        [15, 15, 0, 0, 0, 0, 0, 0, 0, 0, 15, 15].iter().for_each(|&value| {
+            subsets.bits(value, 4);
+        });
+        subsets.bits(0, 128 - subsets.position as u32);
+        level.extend_from_slice(&subsets.bytes);
+
+        ktx2(ColorModel::UASTC, None, 12, 4, &[], &level)
+    }
+
+    // 8x8 pixels with two endpoints and two selectors, using delta, upper and left predictions and the selector history
+    fn etc1s_file() -> Vec<u8> {
+        let mut endpoints = BitWriter::default();
+        (0..3).for_each(|_| {
+            endpoints.huffman_table(&[5; 32]);
+        });
+        endpoints.huffman_table(&[3; 8]).bits(0, 1);
+        endpoints.code(&[3; 8], 0).code(&[5; 32], 15).code(&[5; 32], 16).code(&[5; 32], 16);
+        endpoints.code(&[3; 8], 1).code(&[5; 32], 1).code(&[5; 32], 0).code(&[5; 32], 31);
+
+        let mut selectors = BitWriter::default();
+        selectors.bits(0, 1).bits(0, 1).bits(1, 1).bits(0, 32).bits(0xE4E4_E4E4, 32);
+
+        let mut pred = [0u8; 257];
+        pred[0x1F] = 1;
+        pred[256] = 1;
+        let mut tables = BitWriter::default();
+        tables.huffman_table(&pred).huffman_table(&[1, 1]).huffman_table(&[3; 7]).huffman_table(&[6; 64]).bits(4, 13);
+
+        let mut slice = BitWriter::default();
+        slice.code(&pred, 0x1F).code(&[1, 1], 0).code(&[3; 7], 0);
+        slice.code(&[1, 1], 1).code(&[3; 7], 1);
+        slice.code(&[3; 7], 5);
+        slice.code(&[3; 7], 6).code(&[6; 64], 0);
+
+        let mut global = Vec::new();
+        [2u16, 2].iter().for_each(|count| global.extend_from_slice(&count.to_le_bytes()));
+        [endpoints.bytes.len(), selectors.bytes.len(), tables.bytes.len(), 0, 0, 0, slice.bytes.len(), 0, 0]
+            .iter()
+            .for_each(|&value| global.extend_from_slice(&(value as u32).to_le_bytes()));
+        [&endpoints, &selectors, &tables].iter().for_each(|writer| global.extend_from_slice(&writer.bytes));
+        ktx2(ColorModel::ETC1S, Some(SupercompressionScheme::BasisLZ), 8, 8, &global, &slice.bytes)
+    }
+
+    fn transcode_file(bytes: &[u8], target: TranscodeTarget) -> Vec<u8> {
+        let reader = ktx2::Reader::new(bytes).unwrap();
+        let (format, srgb) = basis_format(&reader).unwrap();
+        assert!(!srgb);
+        let mut levels = transcode(&reader, format, target).unwrap();
+        assert_eq!(levels.len(), 1);
+        levels.remove(0)
+    }
+
+    fn row(pixels: &[u8], width: usize, y: usize, x: std::ops::Range<usize>) -> Vec<[u8; 4]> {
+        x.map(|x| std::array::from_fn(|c| pixels[(y * width + x) * 4 + c])).collect()
+    }
+
+    #[test]
+    fn partitions_match_bc7_patterns() {
+        // bc7 masks of subset 1, flipped where the astc pattern assigns the subsets the other way round
+        let masks: [u16; 30] = [
+            0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80, 0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00, 0xFFF0, 0xF000, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+            0x088C, 0x3110, 0x6666, 0x0FF0, 0xAAAA, 0xF0F0, 0xC936,
+        ];
+        let inverted = [0, 0, 1, 0, 1, 0, 1, 1, 0, 1, 0, 1, 1, 1, 0, 1, 1, 1, 0, 0, 0, 1, 1, 0, 1, 0, 1, 1, 1, 1];
+        for ((&seed, &mask), &inverted) in UASTC_PATTERNS_2.iter().zip(&masks).zip(&inverted) {
+            let partitions = astc_partitions(seed, 2);
+            let expected: Vec<usize> = (0..16).map(|i| ((mask >> i) & 1) as usize ^ inverted).collect();
+            assert_eq!(partitions.to_vec(), expected, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn selects_best_supported_target() {
+        assert_eq!(TranscodeTarget::select(&[gl::COMPRESSED_RGBA8_ETC2_EAC, gl::COMPRESSED_RGBA_ASTC_4x4]), TranscodeTarget::Astc4x4);
+        assert_eq!(TranscodeTarget::select(&[gl::COMPRESSED_RGBA8_ETC2_EAC, gl::COMPRESSED_RGBA_S3TC_DXT5_EXT]), TranscodeTarget::Bc3);
+        assert_eq!(TranscodeTarget::select(&[gl::COMPRESSED_RGBA8_ETC2_EAC]), TranscodeTarget::Etc2Rgba8);
+        assert_eq!(TranscodeTarget::select(&[]), TranscodeTarget::Rgba8);
+        assert_eq!(TranscodeTarget::Bc7.internal_format(true), gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM_EXT);
+    }
+
+    #[test]
+    fn transcodes_uastc_file() {
+        let pixels = transcode_file(&uastc_file(), TranscodeTarget::Rgba8);
+        assert_eq!(pixels.len(), 12 * 4 * 4);
+        assert_eq!(row(&pixels, 12, 3, 0..4), [[10, 20, 30, 40]; 4]);
+        let ramp: Vec<u8> = (0..8).map(|i| row(&pixels, 12, i / 4, 4 + i % 4..5 + i % 4)[0][0]).collect();
+        assert_eq!(ramp, [0, 36, 72, 108, 147, 183, 219, 255]);
+        assert_eq!(row(&pixels, 12, 1, 4..5), [[147, 147, 147, 255]]);
+        assert_eq!(row(&pixels, 12, 2, 8..12), [[255, 0, 0, 255], [255, 0, 0, 255], [0, 0, 255, 255], [0, 0, 255, 255]]);
+    }
+
+    #[test]
+    fn transcodes_etc1s_file() {
+        let pixels = transcode_file(&etc1s_file(), TranscodeTarget::Rgba8);
+        assert_eq!(pixels.len(), 8 * 8 * 4);
+        assert_eq!(row(&pixels, 8, 0, 0..4), [[247, 0, 0, 255]; 4]);
+        assert_eq!(row(&pixels, 8, 3, 4..8), [[0, 0, 238, 255], [0, 0, 250, 255], [5, 5, 255, 255], [17, 17, 255, 255]]);
+        assert_eq!(row(&pixels, 8, 4, 0..4), [[247, 0, 0, 255], [253, 0, 0, 255], [255, 2, 2, 255], [255, 8, 8, 255]]);
+        assert_eq!(row(&pixels, 8, 7, 4..8), [[247, 0, 0, 255]; 4]);
+    }
+
+    #[test]
+    fn encodes_compressed_targets() {
+        let file = uastc_file();
+        for target in [TranscodeTarget::Astc4x4, TranscodeTarget::Bc7, TranscodeTarget::Bc3, TranscodeTarget::Etc2Rgba8] {
+            assert_eq!(transcode_file(&file, target).len(), 3 * 16, "{:?}", target);
+        }
+
+        // bc3 stays within one palette step of the source
+        let source = transcode_file(&file, TranscodeTarget::Rgba8);
+        let decoded = decode_bc(DdsFormat::Bc3, 12, 4, &transcode_file(&file, TranscodeTarget::Bc3));
+        let error = source.iter().zip(&decoded).map(|(&a, &b)| (a as i32 - b as i32).abs()).max();
+        assert!(error <= Some(43), "{:?}", error);
+        assert_eq!(row(&decoded, 12, 0, 8..12), [[255, 0, 0, 255], [255, 0, 0, 255], [0, 0, 255, 255], [0, 0, 255, 255]]);
+    }
+}
@@ -0,0 +1,191 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use image::RgbaImage;
+
+use crate::audio::SoundBuffer;
+use crate::file::{FileError, Files};
+use crate::pipeline::AssetPipeline;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Asset type that can be stored in an `AssetCache`.
+pub trait CachedAsset: Sized + 'static {
+    /// Decodes the asset from the bytes of its file.
+    fn decode(bytes: Vec<u8>) -> Result<Self, String>;
+
+    /// Approximate memory held by the asset in bytes.
+    fn size(&self) -> usize;
+}
+
+/// Raw font file, e.g. for `TextRenderer::new`.
+#[derive(Debug, Clone)]
+pub struct FontData(pub Vec<u8>);
+
+/// Shader source, e.g. for `GpuResources::load_shader`.
+#[derive(Debug, Clone)]
+pub struct ShaderSource(pub String);
+
+/// Decoded assets shared by path. Each asset is loaded once per type and handed out as `Rc`, so game states
+/// referencing the same file share its memory. Assets stay cached until unloaded explicitly or, once no one
+/// else holds a reference, by `unload_unused`.
+pub struct AssetCache {
+    files: Files,
+    pipeline: AssetPipeline,
+    entries: HashMap<(TypeId, String), CacheEntry>,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    pub assets: usize,
+    /// Assets referenced outside of the cache.
+    pub in_use: usize,
+    pub bytes: usize,
+}
+
+struct CacheEntry {
+    value: Rc<dyn Any>,
+    type_name: &'static str,
+    bytes: usize,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl AssetCache {
+    pub fn new(files: Files) -> AssetCache {
+        AssetCache {
+            files,
+            pipeline: AssetPipeline::default(),
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Processors applied to assets before decoding, already cached assets are not affected.
+    pub fn set_pipeline(&mut self, pipeline: AssetPipeline) {
+        self.pipeline = pipeline;
+    }
+
+    /// Cached asset or loads and caches it.
+    pub fn load<T: CachedAsset>(&mut self, path: &str) -> Result<Rc<T>, FileError> {
+        if let Some(asset) = self.get(path) {
+            return Ok(asset);
+        }
+        let bytes = self.pipeline.load(&self.files, path)?;
+        let asset = Rc::new(T::decode(bytes).map_err(|e| FileError::Decode(path.to_string(), e))?);
+        let entry = CacheEntry {
+            value: asset.clone(),
+            type_name: std::any::type_name::<T>(),
+            bytes: asset.size(),
+        };
+        self.entries.insert((TypeId::of::<T>(), path.to_string()), entry);
+        Ok(asset)
+    }
+
+    /// Cached asset without loading.
+    pub fn get<T: CachedAsset>(&self, path: &str) -> Option<Rc<T>> {
+        self.entries.get(&(TypeId::of::<T>(), path.to_string())).and_then(|entry| entry.value.clone().downcast().ok())
+    }
+
+    pub fn contains<T: CachedAsset>(&self, path: &str) -> bool {
+        self.entries.contains_key(&(TypeId::of::<T>(), path.to_string()))
+    }
+
+    /// References held outside of the cache, 0 for unused or missing assets.
+    pub fn ref_count<T: CachedAsset>(&self, path: &str) -> usize {
+        self.entries.get(&(TypeId::of::<T>(), path.to_string())).map_or(0, |entry| Rc::strong_count(&entry.value) - 1)
+    }
+
+    /// Removes the asset from the cache. Its memory is freed once all outside references are dropped.
+    pub fn unload<T: CachedAsset>(&mut self, path: &str) -> bool {
+        self.entries.remove(&(TypeId::of::<T>(), path.to_string())).is_some()
+    }
+
+    /// Removes all assets not referenced outside of the cache, returns the number of removed assets.
+    pub fn unload_unused(&mut self) -> usize {
+        let count = self.entries.len();
+        self.entries.retain(|_, entry| Rc::strong_count(&entry.value) > 1);
+        count - self.entries.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.entries.values().fold(CacheStats::default(), |stats, entry| CacheStats {
+            assets: stats.assets + 1,
+            in_use: stats.in_use + (Rc::strong_count(&entry.value) > 1) as usize,
+            bytes: stats.bytes + entry.bytes,
+        })
+    }
+
+    /// Stats of a single asset type.
+    pub fn stats_of<T: CachedAsset>(&self) -> CacheStats {
+        self.entries
+            .iter()
+            .filter(|((type_id, _), _)| *type_id == TypeId::of::<T>())
+            .fold(CacheStats::default(), |stats, (_, entry)| CacheStats {
+                assets: stats.assets + 1,
+                in_use: stats.in_use + (Rc::strong_count(&entry.value) > 1) as usize,
+                bytes: stats.bytes + entry.bytes,
+            })
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl CachedAsset for RgbaImage {
+    fn decode(bytes: Vec<u8>) -> Result<Self, String> {
+        image::load_from_memory(&bytes).map(|image| image.to_rgba8()).map_err(|e| e.to_string())
+    }
+
+    fn size(&self) -> usize {
+        self.as_raw().len()
+    }
+}
+
+impl CachedAsset for FontData {
+    fn decode(bytes: Vec<u8>) -> Result<Self, String> {
+        ab_glyph::FontRef::try_from_slice(&bytes).map_err(|e| e.to_string())?;
+        Ok(FontData(bytes))
+    }
+
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl CachedAsset for ShaderSource {
+    fn decode(bytes: Vec<u8>) -> Result<Self, String> {
+        String::from_utf8(bytes).map(ShaderSource).map_err(|e| e.to_string())
+    }
+
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl CachedAsset for SoundBuffer {
+    fn decode(bytes: Vec<u8>) -> Result<Self, String> {
+        SoundBuffer::decode(&bytes).map_err(|e| e.to_string())
+    }
+
+    fn size(&self) -> usize {
+        self.samples.len() * std::mem::size_of::<f32>()
+    }
+}
+
+impl std::fmt::Debug for AssetCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut assets: Vec<_> = self.entries.iter().map(|((_, path), entry)| (entry.type_name, path)).collect();
+        assets.sort();
+        f.debug_struct("AssetCache").field("assets", &assets).field("stats", &self.stats()).finish()
+    }
+}
@@ -0,0 +1,213 @@
+//////////////////////////////////////////////////
+// Using
+
+use nalgebra_glm as glm;
+
+use crate::input::Location;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Column major 4x4 matrix, layout compatible with a std140 `mat4`.
+pub type Mat4 = [[f32; 4]; 4];
+
+/// Orthographic 2D camera. World space has y pointing up, screen space is in pixels with y pointing down.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera2D {
+    /// World position shown at the center of the screen.
+    pub position: [f32; 2],
+    pub zoom: f32,
+    /// Counter-clockwise rotation in radians.
+    pub rotation: f32,
+    /// World area visible at zoom 1, extended on one axis to match the screen aspect. `None` maps one unit to one pixel.
+    pub virtual_size: Option<[f32; 2]>,
+    screen_size: [f32; 2],
+}
+
+/// Perspective camera, right handed with clip space depth in [-1, 1] as OpenGL expects.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera3D {
+    pub position: glm::Vec3,
+    pub view: CameraView,
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+    aspect: f32,
+}
+
+/// Where a `Camera3D` is looking.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CameraView {
+    LookAt { target: glm::Vec3, up: glm::Vec3 },
+    /// Rotation of the camera, which looks down -z when not rotated.
+    Rotation(glm::Quat),
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Camera2D {
+    pub fn new(virtual_size: Option<[f32; 2]>) -> Camera2D {
+        Camera2D {
+            virtual_size,
+            ..Default::default()
+        }
+    }
+
+    /// Called with the surface size on every resize.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.screen_size = [width as f32, height as f32];
+    }
+
+    pub fn screen_size(&self) -> [f32; 2] {
+        self.screen_size
+    }
+
+    /// World area currently visible, taking aspect ratio and zoom into account.
+    pub fn visible_size(&self) -> [f32; 2] {
+        let [sw, sh] = [self.screen_size[0].max(1.0), self.screen_size[1].max(1.0)];
+        let size = match self.virtual_size {
+            Some([vw, vh]) if sw / sh > vw / vh => [vh * sw / sh, vh],
+            Some([vw, _]) => [vw, vw * sh / sw],
+            None => [sw, sh],
+        };
+        [size[0] / self.zoom, size[1] / self.zoom]
+    }
+
+    /// Maps world coordinates to normalized device coordinates, ready to be uploaded into a uniform buffer.
+    pub fn view_projection(&self) -> Mat4 {
+        let [sx, sy] = self.scale();
+        let (s, c) = (-self.rotation).sin_cos();
+        let [px, py] = self.position;
+        [
+            [sx * c, sy * s, 0.0, 0.0],
+            [-sx * s, sy * c, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [sx * (-c * px + s * py), sy * (-s * px - c * py), 0.0, 1.0],
+        ]
+    }
+
+    pub fn world_to_ndc(&self, world: [f32; 2]) -> [f32; 2] {
+        let [sx, sy] = self.scale();
+        let (s, c) = (-self.rotation).sin_cos();
+        let [dx, dy] = [world[0] - self.position[0], world[1] - self.position[1]];
+        [sx * (c * dx - s * dy), sy * (s * dx + c * dy)]
+    }
+
+    pub fn ndc_to_world(&self, ndc: [f32; 2]) -> [f32; 2] {
+        let [sx, sy] = self.scale();
+        let (s, c) = self.rotation.sin_cos();
+        let [dx, dy] = [ndc[0] / sx, ndc[1] / sy];
+        [c * dx - s * dy + self.position[0], s * dx + c * dy + self.position[1]]
+    }
+
+    pub fn world_to_screen(&self, world: [f32; 2]) -> [f32; 2] {
+        let [x, y] = self.world_to_ndc(world);
+        [(x + 1.0) * 0.5 * self.screen_size[0], (1.0 - y) * 0.5 * self.screen_size[1]]
+    }
+
+    /// Converts a cursor or touch location in pixels to world coordinates.
+    pub fn screen_to_world(&self, location: Location) -> [f32; 2] {
+        let [sw, sh] = [self.screen_size[0].max(1.0), self.screen_size[1].max(1.0)];
+        self.ndc_to_world([location.x / sw * 2.0 - 1.0, 1.0 - location.y / sh * 2.0])
+    }
+
+    fn scale(&self) -> [f32; 2] {
+        let [w, h] = self.visible_size();
+        [2.0 / w, 2.0 / h]
+    }
+}
+
+impl Camera3D {
+    pub fn new(fov_y: f32, near: f32, far: f32) -> Camera3D {
+        Camera3D {
+            fov_y,
+            near,
+            far,
+            ..Default::default()
+        }
+    }
+
+    /// Called with the surface size on every resize.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width.max(1) as f32 / height.max(1) as f32;
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.aspect
+    }
+
+    pub fn look_at(&mut self, target: glm::Vec3) {
+        self.view = CameraView::LookAt { target, up: glm::Vec3::y() };
+    }
+
+    pub fn set_rotation(&mut self, rotation: glm::Quat) {
+        self.view = CameraView::Rotation(rotation);
+    }
+
+    /// Normalized viewing direction.
+    pub fn forward(&self) -> glm::Vec3 {
+        match self.view {
+            CameraView::LookAt { target, .. } => glm::normalize(&(target - self.position)),
+            CameraView::Rotation(rotation) => glm::quat_rotate_vec3(&rotation, &glm::vec3(0.0, 0.0, -1.0)),
+        }
+    }
+
+    pub fn view_matrix(&self) -> glm::Mat4 {
+        match self.view {
+            CameraView::LookAt { target, up } => glm::look_at(&self.position, &target, &up),
+            CameraView::Rotation(rotation) => glm::quat_to_mat4(&glm::quat_inverse(&rotation)) * glm::translation(&-self.position),
+        }
+    }
+
+    pub fn projection_matrix(&self) -> glm::Mat4 {
+        glm::perspective(self.aspect, self.fov_y, self.near, self.far)
+    }
+
+    /// Combined matrix, `.into()` converts it to a column major `Mat4` for uniform buffers.
+    pub fn view_projection(&self) -> glm::Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+}
+
+/// Model matrix applying scale, then rotation, then translation.
+pub fn transform(translation: &glm::Vec3, rotation: &glm::Quat, scale: &glm::Vec3) -> glm::Mat4 {
+    glm::translation(translation) * glm::quat_to_mat4(rotation) * glm::scaling(scale)
+}
+
+/// Matrix for transforming normals by `model`, padded to a `mat4` for std140 layouts.
+pub fn normal_matrix(model: &glm::Mat4) -> glm::Mat4 {
+    glm::mat3_to_mat4(&glm::transpose(&glm::inverse(&glm::mat4_to_mat3(model))))
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl Default for Camera2D {
+    fn default() -> Camera2D {
+        Camera2D {
+            position: [0.0, 0.0],
+            zoom: 1.0,
+            rotation: 0.0,
+            virtual_size: None,
+            screen_size: [1.0, 1.0],
+        }
+    }
+}
+
+impl Default for Camera3D {
+    fn default() -> Camera3D {
+        Camera3D {
+            position: glm::vec3(0.0, 0.0, 1.0),
+            view: CameraView::LookAt {
+                target: glm::Vec3::zeros(),
+                up: glm::Vec3::y(),
+            },
+            fov_y: std::f32::consts::FRAC_PI_4,
+            near: 0.1,
+            far: 100.0,
+            aspect: 1.0,
+        }
+    }
+}
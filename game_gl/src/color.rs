@@ -0,0 +1,144 @@
+//////////////////////////////////////////////////
+// Definition
+
+/// RGBA color with components in [0, 1]. Components are sRGB encoded unless converted with `to_linear`.
+/// Layout matches a GLSL `vec4`, so colors can be packed into vertex and uniform buffers directly.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    pub const RED: Color = Color::rgb(1.0, 0.0, 0.0);
+    pub const GREEN: Color = Color::rgb(0.0, 1.0, 0.0);
+    pub const BLUE: Color = Color::rgb(0.0, 0.0, 1.0);
+
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color { r, g, b, a }
+    }
+
+    /// Opaque color.
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Color {
+        Color::new(r, g, b, 1.0)
+    }
+
+    pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0)
+    }
+
+    /// Parses `rgb`, `rgba`, `rrggbb` or `rrggbbaa` hex notation with optional leading `#`.
+    pub fn from_hex(hex: &str) -> Option<Color> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.is_ascii() {
+            return None;
+        }
+        let digits: Vec<u8> = match hex.len() {
+            // short notation repeats each digit, "f80" is "ff8800"
+            3 | 4 => hex.chars().map(|c| c.to_digit(16).map(|d| (d * 17) as u8)).collect::<Option<_>>()?,
+            6 | 8 => (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect::<Option<_>>()?,
+            _ => return None,
+        };
+        Some(Color::from_rgba8(digits[0], digits[1], digits[2], digits.get(3).copied().unwrap_or(255)))
+    }
+
+    /// Hue in degrees, saturation and value in [0, 1].
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32, alpha: f32) -> Color {
+        let h = hue.rem_euclid(360.0) / 60.0;
+        let c = value * saturation;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let m = value - c;
+        Color::new(r + m, g + m, b + m, alpha)
+    }
+
+    /// Hue in degrees, saturation and value.
+    pub fn to_hsv(&self) -> [f32; 3] {
+        let max = self.r.max(self.g).max(self.b);
+        let delta = max - self.r.min(self.g).min(self.b);
+        let hue = if delta <= 0.0 {
+            0.0
+        } else if max == self.r {
+            60.0 * ((self.g - self.b) / delta).rem_euclid(6.0)
+        } else if max == self.g {
+            60.0 * ((self.b - self.r) / delta + 2.0)
+        } else {
+            60.0 * ((self.r - self.g) / delta + 4.0)
+        };
+        let saturation = if max <= 0.0 { 0.0 } else { delta / max };
+        [hue, saturation, max]
+    }
+
+    /// Converts sRGB encoded components to linear light, alpha is unchanged. Use for colors going into lighting or blending in linear space.
+    pub fn to_linear(&self) -> Color {
+        let f = |c: f32| if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+        Color::new(f(self.r), f(self.g), f(self.b), self.a)
+    }
+
+    /// Converts linear components back to sRGB encoding, alpha is unchanged.
+    pub fn to_srgb(&self) -> Color {
+        let f = |c: f32| if c <= 0.003_130_8 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+        Color::new(f(self.r), f(self.g), f(self.b), self.a)
+    }
+
+    /// Component-wise interpolation, `t` in [0, 1].
+    pub fn lerp(&self, other: Color, t: f32) -> Color {
+        Color::new(
+            self.r + (other.r - self.r) * t,
+            self.g + (other.g - self.g) * t,
+            self.b + (other.b - self.b) * t,
+            self.a + (other.a - self.a) * t,
+        )
+    }
+
+    pub fn with_alpha(self, a: f32) -> Color {
+        Color { a, ..self }
+    }
+
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl From<[f32; 4]> for Color {
+    fn from(c: [f32; 4]) -> Color {
+        Color::new(c[0], c[1], c[2], c[3])
+    }
+}
+
+impl From<[f32; 3]> for Color {
+    fn from(c: [f32; 3]) -> Color {
+        Color::rgb(c[0], c[1], c[2])
+    }
+}
+
+impl From<(f32, f32, f32, f32)> for Color {
+    fn from((r, g, b, a): (f32, f32, f32, f32)) -> Color {
+        Color::new(r, g, b, a)
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(c: Color) -> [f32; 4] {
+        c.to_array()
+    }
+}
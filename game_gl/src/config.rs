@@ -0,0 +1,130 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::DisplayMode;
+use crate::file::{FileError, Files};
+use crate::input::Key;
+
+//////////////////////////////////////////////////
+// Definition
+
+// settings file in the user data directory, or shipped in the assets as defaults
+const CONFIG_FILE: &str = "config.toml";
+
+/// User settings, loaded from `config.toml` at startup and saved back with `GameContext::save_config`.
+/// Settings saved in the user data directory take precedence over a `config.toml` shipped in the assets.
+/// Missing entries keep their default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Window size in physical pixels, `None` leaves it to the platform. Ignored on Android.
+    pub resolution: Option<[u32; 2]>,
+    /// Window position on the desktop in physical pixels, `None` leaves it to the platform. Ignored on Android.
+    pub window_position: Option<[i32; 2]>,
+    /// Windowed or fullscreen. Ignored on Android.
+    pub display_mode: DisplayMode,
+    /// Name of the screen showing the game, `None` leaves it to the platform. Ignored on Android.
+    pub monitor: Option<String>,
+    pub vsync: bool,
+    /// Keys by action name.
+    pub key_bindings: BTreeMap<String, Key>,
+    /// Volumes in [0, 1] by name, e.g. "master", "music" and "effects".
+    pub volumes: BTreeMap<String, f32>,
+    /// Game specific settings.
+    pub custom: toml::Table,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The settings file could not be read or written.
+    File(FileError),
+    /// The settings file could not be parsed.
+    Parse(String),
+    /// The settings could not be serialized.
+    Serialize(String),
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Config {
+    /// Settings of the user data directory, the assets or the defaults, in that order.
+    pub fn load(files: &Files) -> Config {
+        let loaded = files
+            .load_user_string(CONFIG_FILE)
+            .or_else(|_| files.load_string(CONFIG_FILE))
+            .map_err(ConfigError::File)
+            .and_then(|config| Config::parse(&config));
+        match loaded {
+            Ok(config) => config,
+            Err(ConfigError::File(FileError::NotFound(_))) => Config::default(),
+            Err(e) => {
+                log::error!("Failed to load config, using defaults: {}", e);
+                Config::default()
+            }
+        }
+    }
+
+    /// Writes the settings into the user data directory.
+    pub fn save(&self, files: &Files) -> Result<(), ConfigError> {
+        files.save_string(CONFIG_FILE, &self.to_toml()?).map_err(ConfigError::File)
+    }
+
+    pub fn parse(config: &str) -> Result<Config, ConfigError> {
+        toml::from_str(config).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string_pretty(self).map_err(|e| ConfigError::Serialize(e.to_string()))
+    }
+
+    /// Key bound to an action.
+    pub fn key(&self, action: &str) -> Option<Key> {
+        self.key_bindings.get(action).copied()
+    }
+
+    /// Action bound to a key.
+    pub fn action(&self, key: Key) -> Option<&str> {
+        self.key_bindings.iter().find(|(_, bound)| **bound == key).map(|(action, _)| action.as_str())
+    }
+
+    /// Volume by name, 1.0 if not set.
+    pub fn volume(&self, name: &str) -> f32 {
+        self.volumes.get(name).copied().unwrap_or(1.0)
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            resolution: None,
+            window_position: None,
+            display_mode: DisplayMode::Windowed,
+            monitor: None,
+            vsync: true,
+            key_bindings: BTreeMap::new(),
+            volumes: BTreeMap::new(),
+            custom: toml::Table::new(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::File(err) => write!(f, "Failed to access config: {}", err),
+            ConfigError::Parse(err) => write!(f, "Failed to parse config: {}", err),
+            ConfigError::Serialize(err) => write!(f, "Failed to serialize config: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -0,0 +1,150 @@
+//////////////////////////////////////////////////
+// Using
+
+use crate::camera::Camera2D;
+use crate::color::Color;
+use crate::shape::ShapeRenderer;
+use crate::text::TextRenderer;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Immediate-mode drawing in world coordinates for visualizing physics, AI and the like. Shapes can be
+/// queued from any callback and are drawn with the context camera after `render`, then cleared.
+/// Text needs a font set with `set_font`.
+#[derive(Debug)]
+pub struct DebugDraw {
+    enabled: bool,
+    /// Line thickness in pixels, independent of zoom.
+    pub thickness: f32,
+    commands: Vec<DebugCommand>,
+    font: Option<Vec<u8>>,
+    shapes: Option<ShapeRenderer>,
+    text: Option<TextRenderer>,
+}
+
+#[derive(Debug, Clone)]
+enum DebugCommand {
+    Line { from: [f32; 2], to: [f32; 2], color: Color },
+    Circle { center: [f32; 2], radius: f32, color: Color },
+    Text { position: [f32; 2], text: String, size: f32, color: Color },
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl DebugDraw {
+    /// Disabled debug draw drops all commands, so calls can stay in release builds.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.commands.clear();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Font for `text`, e.g. loaded with `Files::load_bytes`.
+    pub fn set_font(&mut self, font: Vec<u8>) {
+        self.font = Some(font);
+        self.text = None;
+    }
+
+    pub fn line(&mut self, from: [f32; 2], to: [f32; 2], color: impl Into<Color>) {
+        self.push(DebugCommand::Line { from, to, color: color.into() });
+    }
+
+    /// Line from `origin` along `direction`, which does not need to be normalized.
+    pub fn ray(&mut self, origin: [f32; 2], direction: [f32; 2], length: f32, color: impl Into<Color>) {
+        let norm = (direction[0] * direction[0] + direction[1] * direction[1]).sqrt().max(f32::EPSILON);
+        self.line(origin, [origin[0] + direction[0] / norm * length, origin[1] + direction[1] / norm * length], color);
+    }
+
+    /// Axis aligned box outline.
+    pub fn aabb(&mut self, min: [f32; 2], max: [f32; 2], color: impl Into<Color>) {
+        let color = color.into();
+        let corners = [min, [max[0], min[1]], max, [min[0], max[1]]];
+        (0..4).for_each(|i| self.line(corners[i], corners[(i + 1) % 4], color));
+    }
+
+    pub fn circle(&mut self, center: [f32; 2], radius: f32, color: impl Into<Color>) {
+        self.push(DebugCommand::Circle { center, radius, color: color.into() });
+    }
+
+    /// Text with its top left corner at a world position, `size` in pixels.
+    pub fn text(&mut self, position: [f32; 2], text: &str, size: f32, color: impl Into<Color>) {
+        self.push(DebugCommand::Text {
+            position,
+            text: text.to_string(),
+            size,
+            color: color.into(),
+        });
+    }
+
+    /// Drops all queued commands.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    /// Draws and clears all queued commands into the current viewport.
+    pub(crate) fn flush(&mut self, gl: &Gl, camera: &Camera2D) {
+        if self.commands.is_empty() {
+            return;
+        }
+        let shapes = self.shapes.get_or_insert_with(|| ShapeRenderer::new(gl));
+        if self.text.is_none() {
+            if let Some(font) = self.font.as_ref() {
+                self.text = TextRenderer::new(gl, font.clone()).map_err(|e| log::error!("Invalid debug draw font: {}", e)).ok();
+            }
+        }
+
+        // world units per pixel, keeps lines equally thick at every zoom
+        let thickness = self.thickness * camera.visible_size()[0] / camera.screen_size()[0].max(1.0);
+        for command in self.commands.drain(..) {
+            match command {
+                DebugCommand::Line { from, to, color } => shapes.line(from, to, thickness, color),
+                DebugCommand::Circle { center, radius, color } => shapes.circle_outline(center, radius, thickness, color),
+                DebugCommand::Text { position, text, size, color } => {
+                    if let Some(renderer) = self.text.as_mut() {
+                        renderer.draw_text(&text, camera.world_to_screen(position), size, color);
+                    }
+                }
+            }
+        }
+        shapes.render(gl, &camera.view_projection());
+        if let Some(text) = self.text.as_mut() {
+            let [width, height] = camera.screen_size();
+            text.render(gl, (width as u32, height as u32));
+        }
+    }
+
+    pub(crate) fn release(&mut self) {
+        self.shapes = None;
+        self.text = None;
+    }
+
+    fn push(&mut self, command: DebugCommand) {
+        if self.enabled {
+            self.commands.push(command);
+        }
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl Default for DebugDraw {
+    fn default() -> DebugDraw {
+        DebugDraw {
+            enabled: cfg!(debug_assertions),
+            thickness: 1.0,
+            commands: Vec::new(),
+            font: None,
+            shapes: None,
+            text: None,
+        }
+    }
+}
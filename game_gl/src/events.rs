@@ -0,0 +1,244 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::time::Duration;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Typed event queues owned by the `GameContext`. Events of any type can be sent and are kept until the end
+/// of the next frame, so every reader checking once per frame sees them. Use an `EventReader` per consumer to
+/// see each event once, or `drain` when there is a single consumer. Events are read by priority, highest first,
+/// and in the order they were sent for equal priorities.
+#[derive(Default)]
+pub struct Events {
+    frame: u64,
+    queues: HashMap<TypeId, Box<dyn EventQueue>>,
+    delayed: Vec<Delayed>,
+    next_delayed: u64,
+}
+
+/// Event waiting to be sent by `Events::send_delayed`, e.g. a power-up expiring. Handles are cheap copies,
+/// using a handle after its event was sent or cancelled does nothing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DelayedEventHandle(u64);
+
+/// Cursor into the events of one type, remembers which events were already read.
+pub struct EventReader<T> {
+    next: u64,
+    marker: PhantomData<fn() -> T>,
+}
+
+/// A file in the `assets` folder or a mounted folder was created or modified, sent while `GameContext::watch_assets`
+/// is active. The path is relative to the assets, as passed to `Files`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssetChanged(pub String);
+
+/// The window was moved on the desktop, with the new position of its top left corner in physical pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WindowMoved(pub [i32; 2]);
+
+/// The app was opened with a url while running, e.g. a shared level link or a notification tap. The url the app
+/// was started with is `GameContext::launch_url`. Sent on Android when the activity's intent changed on resume.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UrlOpened(pub String);
+
+// type erased queue, so old events of all types can be dropped
+trait EventQueue {
+    fn update(&mut self, frame: u64);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+struct Queue<T> {
+    // in the order sent
+    events: VecDeque<Entry<T>>,
+    next: u64,
+}
+
+struct Delayed {
+    id: u64,
+    remaining: Duration,
+    send: Box<dyn FnOnce(&mut Events)>,
+}
+
+struct Entry<T> {
+    // increases with every sent event
+    id: u64,
+    frame: u64,
+    priority: i32,
+    event: T,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Events {
+    /// Sends an event with priority 0.
+    pub fn send<T: 'static>(&mut self, event: T) {
+        self.send_with_priority(event, 0);
+    }
+
+    /// Sends an event read before events of lower priority, e.g. quit requests before gameplay events.
+    pub fn send_with_priority<T: 'static>(&mut self, event: T, priority: i32) {
+        let frame = self.frame;
+        let queue = self.queue_mut::<T>();
+        queue.events.push_back(Entry {
+            id: queue.next,
+            frame,
+            priority,
+            event,
+        });
+        queue.next += 1;
+    }
+
+    /// Sends an event once `delay` passed, at the start of the frame the delay ends in.
+    pub fn send_delayed<T: 'static>(&mut self, event: T, delay: Duration) -> DelayedEventHandle {
+        let id = self.next_delayed;
+        self.next_delayed += 1;
+        self.delayed.push(Delayed {
+            id,
+            remaining: delay,
+            send: Box::new(move |events: &mut Events| events.send(event)),
+        });
+        DelayedEventHandle(id)
+    }
+
+    /// All events of this and the previous frame.
+    pub fn iter<T: 'static>(&self) -> impl Iterator<Item = &T> {
+        by_priority(self.queue::<T>().into_iter().flat_map(|queue| queue.events.iter()))
+    }
+
+    /// Removes and returns all events of a type.
+    pub fn drain<T: 'static>(&mut self) -> Vec<T> {
+        let mut entries: Vec<Entry<T>> = self.queue_mut::<T>().events.drain(..).collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.priority));
+        entries.into_iter().map(|entry| entry.event).collect()
+    }
+
+    /// Reader only seeing events sent from now on.
+    pub fn reader<T: 'static>(&self) -> EventReader<T> {
+        EventReader {
+            next: self.queue::<T>().map_or(0, |queue| queue.next),
+            marker: PhantomData,
+        }
+    }
+
+    /// Drops events older than the previous frame and sends due delayed events, called by the game loop before `input`.
+    pub(crate) fn update(&mut self, elapsed_time: f32) {
+        self.frame += 1;
+        let frame = self.frame;
+        self.queues.values_mut().for_each(|queue| queue.update(frame));
+
+        let elapsed = Duration::from_secs_f32(elapsed_time.max(0.0));
+        self.delayed.iter_mut().for_each(|delayed| delayed.remaining = delayed.remaining.saturating_sub(elapsed));
+        // due events are sent in the order they were scheduled
+        let (due, pending) = std::mem::take(&mut self.delayed).into_iter().partition(|delayed| delayed.remaining.is_zero());
+        self.delayed = pending;
+        due.into_iter().for_each(|delayed: Delayed| (delayed.send)(self));
+    }
+
+    fn delayed_mut(&mut self, handle: DelayedEventHandle) -> Option<&mut Delayed> {
+        self.delayed.iter_mut().find(|delayed| delayed.id == handle.0)
+    }
+
+    fn queue<T: 'static>(&self) -> Option<&Queue<T>> {
+        self.queues.get(&TypeId::of::<T>()).and_then(|queue| queue.as_any().downcast_ref())
+    }
+
+    fn queue_mut<T: 'static>(&mut self) -> &mut Queue<T> {
+        self.queues
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Queue::<T> { events: VecDeque::new(), next: 0 }))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("Event queue of wrong type")
+    }
+}
+
+impl<T: 'static> EventReader<T> {
+    /// Events sent since the last call. Events dropped by the game loop before they were read are skipped.
+    pub fn read<'a>(&mut self, events: &'a Events) -> impl Iterator<Item = &'a T> {
+        let next = self.next;
+        if let Some(queue) = events.queue::<T>() {
+            self.next = queue.next;
+        }
+        by_priority(events.queue::<T>().into_iter().flat_map(move |queue| queue.events.iter().filter(move |entry| entry.id >= next)))
+    }
+}
+
+impl DelayedEventHandle {
+    /// Drops the event, returns false if it was already sent or cancelled.
+    pub fn cancel(&self, events: &mut Events) -> bool {
+        let len = events.delayed.len();
+        events.delayed.retain(|delayed| delayed.id != self.0);
+        events.delayed.len() != len
+    }
+
+    /// Sends the event after `delay` from now instead, returns false if it was already sent or cancelled.
+    pub fn reschedule(&self, events: &mut Events, delay: Duration) -> bool {
+        events.delayed_mut(*self).map(|delayed| delayed.remaining = delay).is_some()
+    }
+
+    /// Time until the event is sent, `None` if it was already sent or cancelled.
+    pub fn remaining(&self, events: &Events) -> Option<Duration> {
+        events.delayed.iter().find(|delayed| delayed.id == self.0).map(|delayed| delayed.remaining)
+    }
+
+    pub fn is_pending(&self, events: &Events) -> bool {
+        self.remaining(events).is_some()
+    }
+}
+
+// stable, so events of equal priority keep the order they were sent in
+fn by_priority<'a, T: 'a>(entries: impl Iterator<Item = &'a Entry<T>>) -> impl Iterator<Item = &'a T> {
+    let mut entries: Vec<&Entry<T>> = entries.collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.priority));
+    entries.into_iter().map(|entry| &entry.event)
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl<T: 'static> EventQueue for Queue<T> {
+    fn update(&mut self, frame: u64) {
+        while self.events.front().is_some_and(|entry| entry.frame + 1 < frame) {
+            self.events.pop_front();
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// manual impls, derives would require `T` to implement the traits as well
+impl<T> Default for EventReader<T> {
+    /// Reader seeing all events still kept, including those sent before it was created.
+    fn default() -> Self {
+        EventReader { next: 0, marker: PhantomData }
+    }
+}
+impl<T> Clone for EventReader<T> {
+    fn clone(&self) -> Self {
+        EventReader { next: self.next, marker: PhantomData }
+    }
+}
+impl<T> std::fmt::Debug for EventReader<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventReader").field("next", &self.next).finish()
+    }
+}
+
+impl std::fmt::Debug for Events {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Events").field("frame", &self.frame).field("queues", &self.queues.len()).field("delayed", &self.delayed.len()).finish()
+    }
+}
@@ -1,6 +1,14 @@
 //////////////////////////////////////////////////
 // Using
 
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::io::{Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use zip::ZipArchive;
+
 #[cfg(target_os = "android")]
 use ndk::asset::AssetManager;
 #[cfg(target_os = "android")]
@@ -8,14 +16,64 @@ use std::ffi::CString;
 #[cfg(target_os = "android")]
 use winit::platform::android::activity::AndroidApp;
 
+//////////////////////////////////////////////////
+// Macro
+
+/// Embeds files of the calling crate's `assets` folder into the binary, for `Files::mount_embedded`.
+#[macro_export]
+macro_rules! embed_assets {
+    ($($name:literal),* $(,)?) => {
+        &[$(($name, include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/", $name)) as &'static [u8])),*]
+    };
+}
+
 //////////////////////////////////////////////////
 // Definition
 
+#[derive(Debug)]
+pub enum FileError {
+    /// The file is neither in a mount nor in the assets.
+    NotFound(String),
+    /// The file exists but may not be read.
+    PermissionDenied(String),
+    /// The content could not be decoded, e.g. invalid UTF-8 or a corrupt archive.
+    Decode(String, String),
+    /// The Android asset manager failed to read an existing asset.
+    AndroidAsset(String),
+    /// The name leaves the user data directory, e.g. an absolute path or one containing `..`.
+    InvalidPath(String),
+    /// Any other I/O error.
+    Io(String, std::io::Error),
+}
+
 pub struct Files {
     #[cfg(target_os = "android")]
     asset_manager: AssetManager,
+    #[cfg(target_os = "android")]
+    data_path: Option<PathBuf>,
+    mounts: Mounts,
+}
+
+// mounts shared by all `Files` of a context, in lookup order
+pub(crate) type Mounts = Arc<RwLock<Vec<Mount>>>;
+
+pub(crate) struct Mount {
+    name: String,
+    // virtual folder the mount appears in, empty or ending with '/'
+    point: String,
+    priority: i32,
+    source: MountSource,
 }
 
+enum MountSource {
+    Dir(PathBuf),
+    Zip(Mutex<ZipArchive<Box<dyn ReadSeek>>>),
+    Embedded(HashMap<String, &'static [u8]>),
+}
+
+/// Readable and seekable file content, see `Files::open_stream`.
+pub trait ReadSeek: Read + Seek + Send {}
+
 //////////////////////////////////////////////////
 // Implementations
 
@@ -24,28 +82,417 @@ impl Files {
     pub fn new(android_app: &AndroidApp) -> Self {
         Files {
             asset_manager: android_app.asset_manager(),
+            data_path: android_app.internal_data_path(),
+            mounts: Mounts::default(),
         }
     }
 
-    pub fn load_bytes(&self, filename: &str) -> Option<Vec<u8>> {
-        let mut asset = CString::new(filename).ok().and_then(|filename| self.asset_manager.open(&filename));
-        asset.as_mut().and_then(|asset| asset.buffer().ok()).map(|buffer| buffer.to_vec())
+    fn load_native(&self, filename: &str) -> Result<Vec<u8>, FileError> {
+        let name = CString::new(filename).map_err(|_| FileError::NotFound(filename.to_string()))?;
+        let mut asset = self.asset_manager.open(&name).ok_or_else(|| FileError::NotFound(filename.to_string()))?;
+        asset.buffer().map(|buffer| buffer.to_vec()).map_err(|e| FileError::AndroidAsset(format!("{}: {}", filename, e)))
+    }
+
+    // the asset manager only lists files, not folders
+    fn list_native(&self, dir: &str) -> Vec<String> {
+        let Ok(name) = CString::new(dir) else { return Vec::new() };
+        let Some(asset_dir) = self.asset_manager.open_dir(&name) else { return Vec::new() };
+        asset_dir.map(|name| name.to_string_lossy().into_owned()).collect()
+    }
+
+    /// The app's internal storage.
+    pub fn user_data_dir(&self) -> Option<PathBuf> {
+        self.data_path.clone()
+    }
+
+    // assets can't be shared between threads, the archive is read into memory
+    fn open_archive(&self, filename: &str) -> Result<Box<dyn ReadSeek>, FileError> {
+        Ok(Box::new(std::io::Cursor::new(self.load_native(filename)?)))
+    }
+
+    // same as archives, assets are read into memory
+    fn open_native(&self, filename: &str) -> Result<Box<dyn ReadSeek>, FileError> {
+        self.open_archive(filename)
     }
 }
 
 #[cfg(not(target_os = "android"))]
 impl Files {
     pub fn new() -> Self {
-        Files {}
+        Files { mounts: Mounts::default() }
+    }
+
+    fn load_native(&self, filename: &str) -> Result<Vec<u8>, FileError> {
+        std::fs::read(format!("assets/{}", filename)).map_err(|e| FileError::from_io(filename, e))
+    }
+
+    fn list_native(&self, dir: &str) -> Vec<String> {
+        list_dir(&PathBuf::from("assets").join(dir))
+    }
+
+    /// Folder named after the executable in the platform's data directory, e.g. `~/.local/share` on Linux,
+    /// `%APPDATA%` on Windows and `~/Library/Application Support` on macOS.
+    pub fn user_data_dir(&self) -> Option<PathBuf> {
+        let exe = std::env::current_exe().ok()?;
+        Some(dirs::data_dir()?.join(exe.file_stem()?))
+    }
+
+    fn open_archive(&self, filename: &str) -> Result<Box<dyn ReadSeek>, FileError> {
+        Ok(Box::new(std::fs::File::open(filename).map_err(|e| FileError::from_io(filename, e))?))
     }
 
-    pub fn load_bytes(&self, filename: &str) -> Option<Vec<u8>> {
-        std::fs::read(format!("assets/{}", filename)).ok()
+    fn open_native(&self, filename: &str) -> Result<Box<dyn ReadSeek>, FileError> {
+        Ok(Box::new(std::fs::File::open(format!("assets/{}", filename)).map_err(|e| FileError::from_io(filename, e))?))
+    }
+
+    /// Folders files are read from with the virtual folder they appear in, mounts first and the assets last.
+    /// Archives and embedded files never change and are left out.
+    pub(crate) fn source_dirs(&self) -> Vec<(PathBuf, String)> {
+        let mounts = self.mounts.read().expect("Poisoned mounts");
+        let mut dirs: Vec<_> = mounts
+            .iter()
+            .filter_map(|mount| match &mount.source {
+                MountSource::Dir(dir) => Some((dir.clone(), mount.point.clone())),
+                _ => None,
+            })
+            .collect();
+        dirs.push((PathBuf::from("assets"), String::new()));
+        dirs
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl Default for Files {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Files {
-    pub fn load_string(&self, filename: &str) -> Option<String> {
-        self.load_bytes(filename).and_then(|bytes| String::from_utf8(bytes).ok())
+    /// Reads a file from the mounts in lookup order and falls back to the assets.
+    pub fn load_bytes(&self, filename: &str) -> Result<Vec<u8>, FileError> {
+        self.load_mounted(filename).unwrap_or_else(|| self.load_native(filename))
+    }
+
+    /// Opens a file for reading in parts, looked up like `load_bytes`. Files in folders are read from disk,
+    /// other files are read into memory first, e.g. zipped files and Android assets.
+    pub fn open_stream(&self, filename: &str) -> Result<Box<dyn ReadSeek>, FileError> {
+        self.open_mounted(filename).unwrap_or_else(|| self.open_native(filename))
+    }
+
+    pub fn load_string(&self, filename: &str) -> Result<String, FileError> {
+        String::from_utf8(self.load_bytes(filename)?).map_err(|e| FileError::Decode(filename.to_string(), e.to_string()))
+    }
+
+    /// Sorted paths of the files in an asset folder, as passed to `load_bytes`, including mounted files.
+    /// Subfolders are not listed.
+    pub fn list(&self, dir: &str) -> Vec<String> {
+        let dir = dir.trim_matches('/');
+        let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+        let mut names: BTreeSet<String> = self.list_native(dir).into_iter().collect();
+        if let Ok(mounts) = self.mounts.read() {
+            for mount in mounts.iter() {
+                let Some(local) = prefix.strip_prefix(&mount.point) else { continue };
+                match &mount.source {
+                    MountSource::Dir(root) => names.extend(list_dir(&root.join(local))),
+                    MountSource::Zip(zip) => {
+                        if let Ok(zip) = zip.lock() {
+                            names.extend(zip.file_names().filter_map(|name| child_name(name, local)));
+                        }
+                    }
+                    MountSource::Embedded(files) => names.extend(files.keys().filter_map(|name| child_name(name, local))),
+                }
+            }
+        }
+        names.into_iter().map(|name| format!("{}{}", prefix, name)).collect()
+    }
+
+    /// Writes into `user_data_dir`, creating missing folders. Assets are read-only.
+    /// The file is written and synced next to the target and renamed, so a crash never leaves a partially written file.
+    pub fn save_bytes(&self, filename: &str, bytes: &[u8]) -> Result<(), FileError> {
+        let path = self.user_path(filename)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| FileError::from_io(filename, e))?;
+        }
+        write_atomic(&path, bytes).map_err(|e| FileError::from_io(filename, e))
+    }
+
+    pub fn save_string(&self, filename: &str, string: &str) -> Result<(), FileError> {
+        self.save_bytes(filename, string.as_bytes())
+    }
+
+    /// Reads a file written with `save_bytes`.
+    pub fn load_user_bytes(&self, filename: &str) -> Result<Vec<u8>, FileError> {
+        std::fs::read(self.user_path(filename)?).map_err(|e| FileError::from_io(filename, e))
+    }
+
+    pub fn load_user_string(&self, filename: &str) -> Result<String, FileError> {
+        String::from_utf8(self.load_user_bytes(filename)?).map_err(|e| FileError::Decode(filename.to_string(), e.to_string()))
+    }
+
+    pub fn remove_user_file(&self, filename: &str) -> Result<(), FileError> {
+        std::fs::remove_file(self.user_path(filename)?).map_err(|e| FileError::from_io(filename, e))
+    }
+
+    /// Sorted paths of the files in a folder of `user_data_dir`, as passed to `load_user_bytes`.
+    pub fn list_user(&self, dir: &str) -> Vec<String> {
+        let dir = dir.trim_matches('/');
+        let Ok(path) = self.user_path(dir) else { return Vec::new() };
+        let mut names = list_dir(&path);
+        names.sort();
+        names.into_iter().map(|name| if dir.is_empty() { name } else { format!("{}/{}", dir, name) }).collect()
+    }
+
+    /// Mounts a folder, e.g. a mods folder in `user_data_dir`. Mounts are shared by all `Files` of the same
+    /// `GameContext` and looked up before the assets, by priority and newest first within the same priority.
+    pub fn mount_dir(&self, dir: impl Into<PathBuf>) -> Result<(), FileError> {
+        let dir = dir.into();
+        let name = dir.to_string_lossy().into_owned();
+        if !dir.is_dir() {
+            return Err(FileError::NotFound(name));
+        }
+        self.mount(&name, MountSource::Dir(dir));
+        Ok(())
+    }
+
+    /// Mounts a zip archive, see `mount_dir`. On desktop `filename` is relative to the working directory,
+    /// on Android it is an asset.
+    pub fn mount_archive(&self, filename: &str) -> Result<(), FileError> {
+        let zip = ZipArchive::new(self.open_archive(filename)?).map_err(|e| FileError::Decode(filename.to_string(), e.to_string()))?;
+        log::debug!("Mounted {} with {} files", filename, zip.len());
+        self.mount(filename, MountSource::Zip(Mutex::new(zip)));
+        Ok(())
+    }
+
+    /// Mounts files compiled into the binary, e.g. with `embed_assets!["lena.png"]`, so no asset folder has to be shipped.
+    pub fn mount_embedded(&self, name: &str, assets: &[(&str, &'static [u8])]) {
+        let files = assets.iter().map(|(filename, bytes)| (filename.to_string(), *bytes)).collect();
+        self.mount(name, MountSource::Embedded(files));
+    }
+
+    /// Moves a mount into a virtual folder, e.g. "dlc/" serves "dlc/map.json" from the mount's "map.json".
+    pub fn set_mount_point(&self, name: &str, point: &str) -> bool {
+        let point = match point.trim_matches('/') {
+            "" => String::new(),
+            point => format!("{}/", point),
+        };
+        let mut mounts = self.mounts.write().expect("Poisoned mounts");
+        let mut found = false;
+        for mount in mounts.iter_mut().filter(|mount| mount.name == name) {
+            mount.point = point.clone();
+            found = true;
+        }
+        found
+    }
+
+    /// Mounts with higher priority override lower ones, e.g. mods over DLC over base packages. Default is 0.
+    pub fn set_mount_priority(&self, name: &str, priority: i32) -> bool {
+        let mut mounts = self.mounts.write().expect("Poisoned mounts");
+        let (mut changed, kept): (Vec<_>, Vec<_>) = mounts.drain(..).partition(|mount| mount.name == name);
+        *mounts = kept;
+        let found = !changed.is_empty();
+        changed.iter_mut().for_each(|mount| mount.priority = priority);
+        changed.into_iter().rev().for_each(|mount| insert_mount(&mut mounts, mount));
+        found
+    }
+
+    /// Removes all mounts of `name`, returns false if there were none.
+    pub fn unmount(&self, name: &str) -> bool {
+        let mut mounts = self.mounts.write().expect("Poisoned mounts");
+        let count = mounts.len();
+        mounts.retain(|mount| mount.name != name);
+        mounts.len() != count
+    }
+
+    /// Names of all mounts in lookup order.
+    pub fn mounts(&self) -> Vec<String> {
+        self.mounts.read().expect("Poisoned mounts").iter().map(|mount| mount.name.clone()).collect()
+    }
+
+    pub(crate) fn with_mounts(mut self, mounts: Mounts) -> Self {
+        self.mounts = mounts;
+        self
+    }
+
+    fn user_path(&self, filename: &str) -> Result<PathBuf, FileError> {
+        let dir = self.user_data_dir().ok_or_else(|| FileError::Io(filename.to_string(), std::io::Error::new(std::io::ErrorKind::NotFound, "No user data directory")))?;
+        Ok(dir.join(relative_path(filename)?))
+    }
+
+    fn mount(&self, name: &str, source: MountSource) {
+        let mount = Mount {
+            name: name.to_string(),
+            point: String::new(),
+            priority: 0,
+            source,
+        };
+        insert_mount(&mut self.mounts.write().expect("Poisoned mounts"), mount);
+    }
+
+    // `None` if no mount contains the file
+    fn load_mounted(&self, filename: &str) -> Option<Result<Vec<u8>, FileError>> {
+        let mounts = self.mounts.read().ok()?;
+        mounts.iter().find_map(|mount| match (&mount.source, filename.strip_prefix(&mount.point)?) {
+            (MountSource::Dir(dir), filename) => match std::fs::read(dir.join(filename)) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                result => Some(result.map_err(|e| FileError::from_io(filename, e))),
+            },
+            (MountSource::Zip(zip), filename) => {
+                let mut zip = zip.lock().ok()?;
+                let mut file = zip.by_name(filename).ok()?;
+                let mut bytes = Vec::with_capacity(file.size() as usize);
+                Some(file.read_to_end(&mut bytes).map(|_| bytes).map_err(|e| FileError::Decode(filename.to_string(), format!("{} in {}", e, mount.name))))
+            }
+            (MountSource::Embedded(files), filename) => files.get(filename).map(|bytes| Ok(bytes.to_vec())),
+        })
+    }
+
+    // `None` if no mount contains the file
+    fn open_mounted(&self, filename: &str) -> Option<Result<Box<dyn ReadSeek>, FileError>> {
+        let mounts = self.mounts.read().ok()?;
+        mounts.iter().find_map(|mount| match (&mount.source, filename.strip_prefix(&mount.point)?) {
+            (MountSource::Dir(dir), filename) => match std::fs::File::open(dir.join(filename)) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                result => Some(result.map(|file| Box::new(file) as Box<dyn ReadSeek>).map_err(|e| FileError::from_io(filename, e))),
+            },
+            // zipped files can't seek, they are read into memory
+            (MountSource::Zip(zip), filename) => {
+                let mut zip = zip.lock().ok()?;
+                let mut file = zip.by_name(filename).ok()?;
+                let mut bytes = Vec::with_capacity(file.size() as usize);
+                let result = file.read_to_end(&mut bytes).map_err(|e| FileError::Decode(filename.to_string(), format!("{} in {}", e, mount.name)));
+                Some(result.map(|_| Box::new(std::io::Cursor::new(bytes)) as Box<dyn ReadSeek>))
+            }
+            (MountSource::Embedded(files), filename) => files.get(filename).map(|bytes| Ok(Box::new(std::io::Cursor::new(*bytes)) as Box<dyn ReadSeek>)),
+        })
+    }
+}
+
+// only plain folder and file names, so user files can't escape their directory
+fn relative_path(filename: &str) -> Result<&Path, FileError> {
+    let path = Path::new(filename);
+    if path.components().all(|component| matches!(component, Component::Normal(_))) {
+        Ok(path)
+    } else {
+        Err(FileError::InvalidPath(filename.to_string()))
+    }
+}
+
+// writes and syncs a `.tmp` file next to `path` and renames it, the folder is synced too so the rename survives
+// a power loss. The temporary file is removed on failure
+fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let result = std::fs::File::create(&temp)
+        .and_then(|mut file| file.write_all(bytes).and_then(|_| file.sync_all()))
+        .and_then(|_| std::fs::rename(&temp, path))
+        .and_then(|_| sync_parent(path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(parent) => std::fs::File::open(parent)?.sync_all(),
+        None => Ok(()),
+    }
+}
+
+// folders can't be opened for syncing on other platforms
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+// names of the files in a folder, empty if it doesn't exist
+fn list_dir(dir: &std::path::Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect()
+}
+
+// name of a file directly inside `prefix`, `None` for other files and folders
+fn child_name(path: &str, prefix: &str) -> Option<String> {
+    path.strip_prefix(prefix).filter(|name| !name.is_empty() && !name.contains('/')).map(|name| name.to_string())
+}
+
+// keeps mounts sorted by priority, the new mount goes before older ones of the same priority
+fn insert_mount(mounts: &mut Vec<Mount>, mount: Mount) {
+    let index = mounts.iter().position(|other| other.priority <= mount.priority).unwrap_or(mounts.len());
+    mounts.insert(index, mount);
+}
+
+impl FileError {
+    fn from_io(filename: &str, err: std::io::Error) -> FileError {
+        match err.kind() {
+            std::io::ErrorKind::NotFound => FileError::NotFound(filename.to_string()),
+            std::io::ErrorKind::PermissionDenied => FileError::PermissionDenied(filename.to_string()),
+            _ => FileError::Io(filename.to_string(), err),
+        }
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::NotFound(file) => write!(f, "File not found: {}", file),
+            FileError::PermissionDenied(file) => write!(f, "Permission denied: {}", file),
+            FileError::Decode(file, err) => write!(f, "Failed to decode {}: {}", file, err),
+            FileError::AndroidAsset(err) => write!(f, "Failed to read asset: {}", err),
+            FileError::InvalidPath(file) => write!(f, "Invalid user file path: {}", file),
+            FileError::Io(file, err) => write!(f, "Failed to read {}: {}", file, err),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileError::Io(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+//////////////////////////////////////////////////
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_paths_leaving_the_user_directory() {
+        assert_eq!(relative_path("saves/slot.sav").unwrap(), Path::new("saves/slot.sav"));
+        assert_eq!(relative_path("").unwrap(), Path::new(""));
+        for filename in ["/etc/passwd", "../settings.json", "saves/../../settings.json", "./settings.json"] {
+            assert!(matches!(relative_path(filename), Err(FileError::InvalidPath(_))), "{}", filename);
+        }
+    }
+
+    #[test]
+    fn replaces_files_atomically() {
+        let dir = std::env::temp_dir().join(format!("game_gl_files_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("folder")).unwrap();
+        write_atomic(&dir.join("save.json"), b"level 4").unwrap();
+        write_atomic(&dir.join("save.json"), b"level 5").unwrap();
+        assert_eq!(std::fs::read(dir.join("save.json")).unwrap(), b"level 5");
+        assert!(!dir.join("save.json.tmp").exists());
+        // renaming onto a folder fails, the temporary file must not stay behind
+        assert!(write_atomic(&dir.join("folder"), b"level 6").is_err());
+        assert!(!dir.join("folder.tmp").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,248 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::gl::types::*;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Passes declaring which targets they read and write. The graph creates and resizes the targets
+/// and runs the passes in dependency order, e.g. shadow -> main -> post.
+#[derive(Debug, Default)]
+pub struct RenderGraph {
+    gl: Option<Gl>,
+    surface: (u32, u32),
+    targets: HashMap<String, RenderTarget>,
+    passes: Vec<RenderPass>,
+    order: Option<Vec<usize>>,
+}
+
+/// Description of a render target owned by the graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderTargetDesc {
+    pub internal_formats: Vec<GLenum>,
+    pub samples: u32,
+    pub size: TargetSize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TargetSize {
+    /// Follows the surface size.
+    Surface,
+    /// Follows the surface size multiplied by a factor, e.g. 0.5 for half resolution bloom.
+    Scaled(f32),
+    Fixed(u32, u32),
+}
+
+#[derive(Debug)]
+pub enum RenderGraphError {
+    UnknownTarget { pass: String, target: String },
+    Cycle(Vec<String>),
+}
+
+/// Access to the targets of the pass being executed.
+pub struct PassContext<'a> {
+    pub gl: &'a Gl,
+    name: &'a str,
+    targets: &'a mut HashMap<String, RenderTarget>,
+    output: Option<&'a str>,
+}
+
+#[derive(Debug)]
+struct RenderTarget {
+    desc: RenderTargetDesc,
+    framebuffer: Option<GlFramebuffer>,
+}
+
+#[derive(Debug)]
+struct RenderPass {
+    name: String,
+    inputs: Vec<String>,
+    output: Option<String>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl RenderGraph {
+    pub fn new(gl: &Gl, width: u32, height: u32) -> RenderGraph {
+        RenderGraph {
+            gl: Some(gl.clone()),
+            surface: (width, height),
+            targets: HashMap::new(),
+            passes: Vec::new(),
+            order: None,
+        }
+    }
+
+    /// Declares a target, replacing an existing one of the same name. Its framebuffer is created on first use.
+    pub fn add_target(&mut self, name: &str, desc: RenderTargetDesc) {
+        self.targets.insert(name.to_string(), RenderTarget { desc, framebuffer: None });
+    }
+
+    /// Declares a pass sampling `inputs` and drawing into `output`, `None` is the default framebuffer.
+    pub fn add_pass(&mut self, name: &str, inputs: &[&str], output: Option<&str>) {
+        self.passes.push(RenderPass {
+            name: name.to_string(),
+            inputs: inputs.iter().map(|input| input.to_string()).collect(),
+            output: output.map(str::to_string),
+        });
+        self.order = None;
+    }
+
+    /// Resizes all surface dependent targets, call from `resize_device`.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.surface = (width, height);
+        let surface = self.surface;
+        self.targets.values_mut().for_each(|target| {
+            let (width, height) = target.desc.size.resolve(surface);
+            if let Some(framebuffer) = target.framebuffer.as_mut() {
+                framebuffer.resize(width, height);
+            }
+        });
+    }
+
+    /// Orders the passes so every target is written before it is read. Called by `execute` after passes changed.
+    pub fn compile(&mut self) -> Result<(), RenderGraphError> {
+        for pass in self.passes.iter() {
+            if let Some(target) = pass.inputs.iter().chain(pass.output.iter()).find(|target| !self.targets.contains_key(*target)) {
+                return Err(RenderGraphError::UnknownTarget {
+                    pass: pass.name.clone(),
+                    target: target.clone(),
+                });
+            }
+        }
+
+        // a pass depends on every other pass writing one of its inputs
+        let dependencies: Vec<Vec<usize>> = self
+            .passes
+            .iter()
+            .map(|pass| (0..self.passes.len()).filter(|&other| self.passes[other].output.as_ref().is_some_and(|output| pass.inputs.contains(output) && self.passes[other].name != pass.name)).collect())
+            .collect();
+
+        // kahn's algorithm, keeping declaration order among independent passes
+        let mut order = Vec::with_capacity(self.passes.len());
+        let mut done = vec![false; self.passes.len()];
+        while order.len() < self.passes.len() {
+            match (0..self.passes.len()).find(|&i| !done[i] && dependencies[i].iter().all(|&d| done[d])) {
+                Some(i) => {
+                    done[i] = true;
+                    order.push(i);
+                }
+                None => {
+                    let remaining = (0..self.passes.len()).filter(|&i| !done[i]).map(|i| self.passes[i].name.clone()).collect();
+                    return Err(RenderGraphError::Cycle(remaining));
+                }
+            }
+        }
+        log::debug!("Compiled render graph: {:?}", order.iter().map(|&i| self.passes[i].name.as_str()).collect::<Vec<_>>());
+        self.order = Some(order);
+        Ok(())
+    }
+
+    /// Runs all passes in order, calling `f` with each pass while its output is bound.
+    pub fn execute(&mut self, mut f: impl FnMut(&mut PassContext)) {
+        if self.order.is_none() {
+            if let Err(err) = self.compile() {
+                log::error!("Failed to compile render graph: {}", err);
+                return;
+            }
+        }
+        let gl = self.gl.clone().expect("Missing OpenGL Context!");
+        let surface = self.surface;
+        self.targets.values_mut().filter(|target| target.framebuffer.is_none()).for_each(|target| {
+            let (width, height) = target.desc.size.resolve(surface);
+            target.framebuffer = Some(GlFramebuffer::with_attachments(&gl, width, height, &target.desc.internal_formats, target.desc.samples));
+        });
+
+        let (passes, targets) = (&self.passes, &mut self.targets);
+        for pass in self.order.iter().flatten().map(|&i| &passes[i]) {
+            let rect = match pass.output.as_ref().and_then(|output| targets.get_mut(output)).and_then(|target| target.framebuffer.as_mut()) {
+                Some(framebuffer) => {
+                    framebuffer.bind();
+                    framebuffer.rect()
+                }
+                None => GlRect::new(0, 0, surface.0 as i32, surface.1 as i32),
+            };
+            gl.set_viewport(rect);
+            gl.debug_group(&pass.name, || {
+                f(&mut PassContext {
+                    gl: &gl,
+                    name: &pass.name,
+                    targets: &mut *targets,
+                    output: pass.output.as_deref(),
+                })
+            });
+            if let Some(framebuffer) = pass.output.as_ref().and_then(|output| targets.get_mut(output)).and_then(|target| target.framebuffer.as_mut()) {
+                framebuffer.unbind();
+                framebuffer.resolve();
+            }
+        }
+        gl.set_viewport(GlRect::new(0, 0, surface.0 as i32, surface.1 as i32));
+    }
+
+    /// Framebuffer of a target, `None` before the first `execute`.
+    pub fn target(&mut self, name: &str) -> Option<&mut GlFramebuffer> {
+        self.targets.get_mut(name).and_then(|target| target.framebuffer.as_mut())
+    }
+}
+
+impl PassContext<'_> {
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// Resolved texture of a target's attachment, e.g. to bind as shader input.
+    pub fn input(&mut self, target: &str, attachment: usize) -> Option<&mut GlTexture> {
+        self.targets.get_mut(target).and_then(|target| target.framebuffer.as_mut()).map(|framebuffer| framebuffer.texture_at(attachment))
+    }
+
+    /// Framebuffer being drawn to, `None` for the default framebuffer.
+    pub fn output(&mut self) -> Option<&mut GlFramebuffer> {
+        let output = self.output?;
+        self.targets.get_mut(output).and_then(|target| target.framebuffer.as_mut())
+    }
+}
+
+impl TargetSize {
+    fn resolve(&self, surface: (u32, u32)) -> (u32, u32) {
+        match *self {
+            TargetSize::Surface => surface,
+            TargetSize::Scaled(factor) => (((surface.0 as f32 * factor) as u32).max(1), ((surface.1 as f32 * factor) as u32).max(1)),
+            TargetSize::Fixed(width, height) => (width, height),
+        }
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderGraphError::UnknownTarget { pass, target } => write!(f, "Pass {} uses undeclared target {}", pass, target),
+            RenderGraphError::Cycle(passes) => write!(f, "Cyclic dependency between passes {:?}", passes),
+        }
+    }
+}
+
+impl std::error::Error for RenderGraphError {}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl GlResource for RenderGraph {
+    fn release(&mut self) {
+        self.targets.values_mut().for_each(|target| target.framebuffer = None);
+    }
+}
+impl Drop for RenderGraph {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
@@ -0,0 +1,669 @@
+//////////////////////////////////////////////////
+// Using
+
+use nalgebra_glm as glm;
+
+use crate::atlas::AtlasRegion;
+use crate::color::Color;
+use crate::events::Events;
+use crate::input::{InputEvent, MouseButton, MouseState, TouchState};
+use crate::opengl::*;
+use crate::shape::ShapeRenderer;
+use crate::sprite::{NinePatch, Sprite, SpriteBatch};
+use crate::text::TextRenderer;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+// line height of labels and captions in pixels
+const DEFAULT_TEXT_SIZE: f32 = 20.0;
+
+/// Handle of a widget, stays valid until the widget is removed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WidgetId(usize);
+
+/// Rectangle of a widget relative to its parent, in logical pixels with the origin top left. The anchors are
+/// fractions of the parent's size, so widgets follow the parent when the surface is resized, the offsets are
+/// added to the anchored corners in pixels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Layout {
+    pub anchor_min: [f32; 2],
+    pub anchor_max: [f32; 2],
+    pub offset_min: [f32; 2],
+    pub offset_max: [f32; 2],
+}
+
+/// Widget rectangle in logical pixels.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct GuiRect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TextAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WidgetKind {
+    /// Background, drawn as a nine patch of the gui texture or as plain color.
+    Panel { color: Color, patch: Option<NinePatch> },
+    /// Clickable area with a centered caption, colored by the `GuiStyle`.
+    Button { text: String, patch: Option<NinePatch> },
+    /// Text vertically centered in the widget.
+    Label { text: String, size: f32, color: Color, align: TextAlign },
+    /// Region of the gui texture stretched over the widget.
+    Image { uv_min: [f32; 2], uv_max: [f32; 2], color: Color },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Widget {
+    /// Name to find the widget with `Gui::find`, may be empty.
+    pub name: String,
+    pub kind: WidgetKind,
+    pub layout: Layout,
+    /// Hidden widgets and their children are neither drawn nor hit.
+    pub visible: bool,
+    /// Disabled buttons are drawn in the disabled color and send no events.
+    pub enabled: bool,
+}
+
+/// Colors and text of buttons.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuiStyle {
+    pub button: Color,
+    pub hovered: Color,
+    pub pressed: Color,
+    pub disabled: Color,
+    pub text_color: Color,
+    pub text_size: f32,
+}
+
+/// Sent into the `Events` passed to `Gui::input`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GuiEvent {
+    pub kind: GuiEventKind,
+    pub widget: WidgetId,
+    /// Name of the widget, e.g. to match events of menus built with `GuiBuilder`.
+    pub name: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GuiEventKind {
+    /// A button was pressed down.
+    Pressed,
+    /// A button was pressed and released while the pointer stayed on it.
+    Clicked,
+}
+
+/// Retained widget tree of panels, buttons, labels and images, e.g. for menus and HUDs. Widgets are laid out
+/// relative to their parent, the roots relative to the size set by `resize`. Mouse and touch input is hit-tested
+/// against the widgets, buttons send `GuiEvent`s. Labels and captions need a font set with `set_font`, images
+/// and nine patches a texture passed to `render`.
+#[derive(Debug, Default)]
+pub struct Gui {
+    nodes: Vec<Option<Node>>,
+    roots: Vec<WidgetId>,
+    size: [f32; 2],
+    // layout changed since the rectangles were computed
+    dirty: bool,
+    style: GuiStyle,
+    cursor: Option<[f32; 2]>,
+    // button held down and the pointer holding it
+    pressed: Option<(WidgetId, Pointer)>,
+    font: Option<Vec<u8>>,
+    shapes: Option<ShapeRenderer>,
+    sprites: Option<SpriteBatch>,
+    text: Option<TextRenderer>,
+}
+
+/// Builds a `Gui` from nested widgets, e.g. a menu panel with its buttons as children.
+#[derive(Debug, Default)]
+pub struct GuiBuilder {
+    gui: Gui,
+    parent: Option<WidgetId>,
+}
+
+#[derive(Debug)]
+struct Node {
+    widget: Widget,
+    parent: Option<WidgetId>,
+    children: Vec<WidgetId>,
+    rect: GuiRect,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Pointer {
+    Mouse,
+    Touch(u64),
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Layout {
+    /// Covers the whole parent.
+    pub fn fill() -> Layout {
+        Layout::percent([0.0, 0.0], [1.0, 1.0])
+    }
+
+    /// Covers a part of the parent given in fractions of its size, e.g. `[0.0, 0.9]` to `[1.0, 1.0]` for a bottom bar.
+    pub fn percent(min: [f32; 2], max: [f32; 2]) -> Layout {
+        Layout {
+            anchor_min: min,
+            anchor_max: max,
+            offset_min: [0.0, 0.0],
+            offset_max: [0.0, 0.0],
+        }
+    }
+
+    /// Fixed size placed at an anchor point of the parent, e.g. `[0.5, 1.0]` puts the bottom center of the widget at
+    /// the bottom center of the parent. `offset` moves the widget in pixels.
+    pub fn anchored(anchor: [f32; 2], size: [f32; 2], offset: [f32; 2]) -> Layout {
+        Layout {
+            anchor_min: anchor,
+            anchor_max: anchor,
+            offset_min: [offset[0] - size[0] * anchor[0], offset[1] - size[1] * anchor[1]],
+            offset_max: [offset[0] + size[0] * (1.0 - anchor[0]), offset[1] + size[1] * (1.0 - anchor[1])],
+        }
+    }
+
+    /// Shrinks the widget by `margin` pixels on all sides.
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        self.offset_min = self.offset_min.map(|offset| offset + margin);
+        self.offset_max = self.offset_max.map(|offset| offset - margin);
+        self
+    }
+
+    pub fn rect(&self, parent: &GuiRect) -> GuiRect {
+        let size = parent.size();
+        let corner = |anchor: [f32; 2], offset: [f32; 2]| [0, 1].map(|i| parent.min[i] + anchor[i] * size[i] + offset[i]);
+        GuiRect {
+            min: corner(self.anchor_min, self.offset_min),
+            max: corner(self.anchor_max, self.offset_max),
+        }
+    }
+}
+
+impl GuiRect {
+    pub fn size(&self) -> [f32; 2] {
+        [self.max[0] - self.min[0], self.max[1] - self.min[1]]
+    }
+
+    pub fn center(&self) -> [f32; 2] {
+        [(self.min[0] + self.max[0]) * 0.5, (self.min[1] + self.max[1]) * 0.5]
+    }
+
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        (0..2).all(|i| point[i] >= self.min[i] && point[i] < self.max[i])
+    }
+}
+
+impl Widget {
+    /// Visible, enabled widget filling its parent.
+    pub fn new(kind: WidgetKind) -> Widget {
+        Widget {
+            name: String::new(),
+            kind,
+            layout: Layout::fill(),
+            visible: true,
+            enabled: true,
+        }
+    }
+
+    pub fn panel(color: impl Into<Color>) -> Widget {
+        Widget::new(WidgetKind::Panel { color: color.into(), patch: None })
+    }
+
+    pub fn button(text: &str) -> Widget {
+        Widget::new(WidgetKind::Button { text: text.to_string(), patch: None })
+    }
+
+    pub fn label(text: &str) -> Widget {
+        Widget::new(WidgetKind::Label {
+            text: text.to_string(),
+            size: DEFAULT_TEXT_SIZE,
+            color: Color::WHITE,
+            align: TextAlign::Center,
+        })
+    }
+
+    pub fn image(region: &AtlasRegion) -> Widget {
+        Widget::new(WidgetKind::Image {
+            uv_min: region.uv_min,
+            uv_max: region.uv_max,
+            color: Color::WHITE,
+        })
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Draws panels and buttons as nine patch of the gui texture.
+    pub fn with_patch(mut self, nine_patch: NinePatch) -> Self {
+        if let WidgetKind::Panel { patch, .. } | WidgetKind::Button { patch, .. } = &mut self.kind {
+            *patch = Some(nine_patch);
+        }
+        self
+    }
+
+    /// Color of panels, labels and images, buttons are colored by the `GuiStyle`.
+    pub fn with_color(mut self, new_color: impl Into<Color>) -> Self {
+        if let WidgetKind::Panel { color, .. } | WidgetKind::Label { color, .. } | WidgetKind::Image { color, .. } = &mut self.kind {
+            *color = new_color.into();
+        }
+        self
+    }
+
+    /// Text size and alignment of labels.
+    pub fn with_text(mut self, new_size: f32, new_align: TextAlign) -> Self {
+        if let WidgetKind::Label { size, align, .. } = &mut self.kind {
+            *size = new_size;
+            *align = new_align;
+        }
+        self
+    }
+
+    pub fn with_visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    // panels and buttons block the widgets below, labels and images let the pointer through
+    fn is_hit(&self) -> bool {
+        matches!(self.kind, WidgetKind::Panel { .. } | WidgetKind::Button { .. })
+    }
+}
+
+impl Gui {
+    pub fn new() -> Gui {
+        Default::default()
+    }
+
+    /// Adds a widget as last child of `parent`, or as root drawn above all others with `None`.
+    pub fn add(&mut self, parent: Option<WidgetId>, widget: Widget) -> WidgetId {
+        let id = WidgetId(self.nodes.len());
+        match parent.and_then(|parent| self.node_mut(parent)) {
+            Some(node) => node.children.push(id),
+            None => self.roots.push(id),
+        }
+        self.nodes.push(Some(Node {
+            widget,
+            parent,
+            children: Vec::new(),
+            rect: GuiRect::default(),
+        }));
+        self.dirty = true;
+        id
+    }
+
+    /// Removes a widget and its children, returns false if it was already removed.
+    pub fn remove(&mut self, id: WidgetId) -> bool {
+        let Some(node) = self.nodes.get_mut(id.0).and_then(|node| node.take()) else { return false };
+        match node.parent.and_then(|parent| self.node_mut(parent)) {
+            Some(parent) => parent.children.retain(|child| *child != id),
+            None => self.roots.retain(|root| *root != id),
+        }
+        let mut removed = node.children;
+        while let Some(child) = removed.pop() {
+            if let Some(node) = self.nodes[child.0].take() {
+                removed.extend(node.children);
+            }
+        }
+        if self.pressed.is_some_and(|(pressed, _)| self.node(pressed).is_none()) {
+            self.pressed = None;
+        }
+        true
+    }
+
+    /// Removes all widgets.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.roots.clear();
+        self.pressed = None;
+    }
+
+    pub fn widget(&self, id: WidgetId) -> Option<&Widget> {
+        self.node(id).map(|node| &node.widget)
+    }
+
+    /// Widget to change, the layout is updated before the next input or render.
+    pub fn widget_mut(&mut self, id: WidgetId) -> Option<&mut Widget> {
+        self.dirty = true;
+        self.node_mut(id).map(|node| &mut node.widget)
+    }
+
+    /// First widget with this name.
+    pub fn find(&self, name: &str) -> Option<WidgetId> {
+        self.nodes.iter().position(|node| node.as_ref().is_some_and(|node| node.widget.name == name)).map(WidgetId)
+    }
+
+    pub fn parent(&self, id: WidgetId) -> Option<WidgetId> {
+        self.node(id).and_then(|node| node.parent)
+    }
+
+    pub fn children(&self, id: WidgetId) -> &[WidgetId] {
+        self.node(id).map_or(&[], |node| node.children.as_slice())
+    }
+
+    pub fn set_visible(&mut self, id: WidgetId, visible: bool) {
+        if let Some(widget) = self.widget_mut(id) {
+            widget.visible = visible;
+        }
+    }
+
+    pub fn set_enabled(&mut self, id: WidgetId, enabled: bool) {
+        if let Some(widget) = self.widget_mut(id) {
+            widget.enabled = enabled;
+        }
+    }
+
+    /// Replaces the text of a label or the caption of a button.
+    pub fn set_text(&mut self, id: WidgetId, new_text: &str) {
+        if let Some(WidgetKind::Label { text, .. } | WidgetKind::Button { text, .. }) = self.widget_mut(id).map(|widget| &mut widget.kind) {
+            *text = new_text.to_string();
+        }
+    }
+
+    /// Rectangle of a widget after layout.
+    pub fn rect(&mut self, id: WidgetId) -> Option<GuiRect> {
+        self.update_layout();
+        self.node(id).map(|node| node.rect)
+    }
+
+    /// Size the root widgets are laid out in, call it from `resize_device` with the logical size of the viewport.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.size = [width as f32, height as f32];
+        self.dirty = true;
+    }
+
+    pub fn set_style(&mut self, style: GuiStyle) {
+        self.style = style;
+    }
+
+    pub fn style(&self) -> &GuiStyle {
+        &self.style
+    }
+
+    /// Font of labels and captions, e.g. loaded with `Files::load_bytes`.
+    pub fn set_font(&mut self, font: Vec<u8>) {
+        self.font = Some(font);
+        self.text = None;
+    }
+
+    /// Enabled button under the mouse cursor.
+    pub fn hovered(&mut self) -> Option<WidgetId> {
+        self.update_layout();
+        self.cursor.and_then(|cursor| self.hit(cursor)).filter(|id| self.is_button(*id))
+    }
+
+    /// Hit-tests mouse and touch input against the widgets and sends `GuiEvent`s into `events`. Locations are
+    /// expected in logical pixels, as passed to `GameLoop::input`. Returns true if a press or release hit the gui,
+    /// so the game can ignore it.
+    pub fn input(&mut self, events: &mut Events, input_events: &[InputEvent]) -> bool {
+        self.update_layout();
+        let mut consumed = false;
+        for event in input_events {
+            match event {
+                InputEvent::Cursor(cursor) => self.cursor = Some([cursor.location.x, cursor.location.y]),
+                InputEvent::Mouse(mouse) if mouse.button == MouseButton::Left => {
+                    consumed |= match (mouse.state, self.cursor) {
+                        (MouseState::Pressed, Some(cursor)) => self.pointer_down(events, cursor, Pointer::Mouse),
+                        (MouseState::Released, Some(cursor)) => self.pointer_up(events, cursor, Pointer::Mouse),
+                        _ => false,
+                    };
+                }
+                InputEvent::Touch(touch) => {
+                    let location = [touch.location.x, touch.location.y];
+                    consumed |= match touch.state {
+                        TouchState::Down => self.pointer_down(events, location, Pointer::Touch(touch.id)),
+                        TouchState::Up => self.pointer_up(events, location, Pointer::Touch(touch.id)),
+                        TouchState::Cancelled => self.pressed.take_if(|(_, pointer)| *pointer == Pointer::Touch(touch.id)).is_some(),
+                        TouchState::Move => false,
+                    };
+                }
+                _ => {}
+            }
+        }
+        consumed
+    }
+
+    // returns true if a widget was hit
+    fn pointer_down(&mut self, events: &mut Events, location: [f32; 2], pointer: Pointer) -> bool {
+        let Some(id) = self.hit(location) else { return false };
+        if self.is_button(id) {
+            self.pressed = Some((id, pointer));
+            self.send(events, GuiEventKind::Pressed, id);
+        }
+        true
+    }
+
+    // returns true if a widget was hit or the pointer held a button
+    fn pointer_up(&mut self, events: &mut Events, location: [f32; 2], pointer: Pointer) -> bool {
+        let hit = self.hit(location);
+        let Some((pressed, _)) = self.pressed.take_if(|(_, pressed)| *pressed == pointer) else { return hit.is_some() };
+        if hit == Some(pressed) && self.is_button(pressed) {
+            self.send(events, GuiEventKind::Clicked, pressed);
+        }
+        true
+    }
+
+    fn send(&self, events: &mut Events, kind: GuiEventKind, widget: WidgetId) {
+        let name = self.widget(widget).map(|widget| widget.name.clone()).unwrap_or_default();
+        events.send(GuiEvent { kind, widget, name });
+    }
+
+    fn is_button(&self, id: WidgetId) -> bool {
+        self.widget(id).is_some_and(|widget| widget.enabled && matches!(widget.kind, WidgetKind::Button { .. }))
+    }
+
+    // topmost visible widget blocking the pointer
+    fn hit(&self, location: [f32; 2]) -> Option<WidgetId> {
+        self.draw_order().into_iter().rev().find(|id| self.node(*id).is_some_and(|node| node.widget.is_hit() && node.rect.contains(location)))
+    }
+
+    /// Draws the visible widgets over the current viewport: panels and buttons first, then images and nine patches
+    /// from `texture`, then text. Widgets using the texture are skipped without one.
+    pub fn render(&mut self, gl: &Gl, texture: Option<&mut GlTexture>) {
+        self.update_layout();
+        let order = self.draw_order();
+        let hovered = self.hovered();
+        let pressed = self.pressed.map(|(id, _)| id);
+
+        if self.text.is_none() {
+            if let Some(font) = self.font.as_ref() {
+                self.text = TextRenderer::new(gl, font.clone()).map_err(|e| log::error!("Invalid gui font: {}", e)).ok();
+            }
+        }
+        let Gui { nodes, size, style, shapes, sprites, text, .. } = self;
+        let shapes = shapes.get_or_insert_with(|| ShapeRenderer::new(gl));
+        let mut sprites = texture.is_some().then(|| sprites.get_or_insert_with(|| SpriteBatch::new(gl)));
+
+        // sprites expect y to point up, the gui has its origin top left
+        let [width, height] = *size;
+        let flip = |rect: GuiRect| ([rect.min[0], height - rect.max[1]], [rect.max[0], height - rect.min[1]]);
+        let mut texts = Vec::new();
+        for &id in order.iter() {
+            let Some(node) = nodes[id.0].as_ref() else { continue };
+            let rect = node.rect;
+            let (color, patch) = match &node.widget.kind {
+                WidgetKind::Panel { color, patch } => (*color, patch.as_ref()),
+                WidgetKind::Button { text, patch } => {
+                    texts.push((text.as_str(), rect, style.text_size, style.text_color, TextAlign::Center));
+                    let color = if !node.widget.enabled {
+                        style.disabled
+                    } else if pressed == Some(id) {
+                        style.pressed
+                    } else if hovered == Some(id) {
+                        style.hovered
+                    } else {
+                        style.button
+                    };
+                    (color, patch.as_ref())
+                }
+                WidgetKind::Label { text, size, color, align } => {
+                    texts.push((text.as_str(), rect, *size, *color, *align));
+                    continue;
+                }
+                WidgetKind::Image { uv_min, uv_max, color } => {
+                    if let Some(sprites) = sprites.as_mut() {
+                        let (min, max) = flip(rect);
+                        let sprite = Sprite {
+                            position: [(min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5],
+                            size: rect.size(),
+                            rotation: 0.0,
+                            uv_min: *uv_min,
+                            uv_max: *uv_max,
+                            color: *color,
+                        };
+                        sprites.draw(&sprite);
+                    }
+                    continue;
+                }
+            };
+            match (patch, sprites.as_mut()) {
+                (Some(patch), Some(sprites)) => {
+                    let (min, max) = flip(rect);
+                    sprites.draw_nine_patch(patch, min, max, color);
+                }
+                (Some(_), None) => {}
+                (None, _) => shapes.rect(rect.min, rect.max, color.to_array()),
+            }
+        }
+
+        let projection: glm::Mat4 = glm::ortho(0.0, width, height, 0.0, -1.0, 1.0);
+        shapes.render(gl, &projection.into());
+        if let (Some(sprites), Some(texture)) = (sprites, texture) {
+            let projection: glm::Mat4 = glm::ortho(0.0, width, 0.0, height, -1.0, 1.0);
+            sprites.render(gl, texture, &projection.into());
+        }
+        if let Some(renderer) = text.as_mut() {
+            for (text, rect, size, color, align) in texts {
+                let [text_width, text_height] = renderer.measure_text(text, size);
+                let x = match align {
+                    TextAlign::Left => rect.min[0],
+                    TextAlign::Center => rect.center()[0] - text_width * 0.5,
+                    TextAlign::Right => rect.max[0] - text_width,
+                };
+                renderer.draw_text(text, [x, rect.center()[1] - text_height * 0.5], size, color);
+            }
+            renderer.render(gl, (width as u32, height as u32));
+        }
+    }
+
+    /// Releases the GPU resources, call it from `destroy_device`. They are recreated by the next render.
+    pub fn release(&mut self) {
+        self.shapes = None;
+        self.sprites = None;
+        self.text = None;
+    }
+
+    fn node(&self, id: WidgetId) -> Option<&Node> {
+        self.nodes.get(id.0).and_then(|node| node.as_ref())
+    }
+
+    fn node_mut(&mut self, id: WidgetId) -> Option<&mut Node> {
+        self.nodes.get_mut(id.0).and_then(|node| node.as_mut())
+    }
+
+    // visible widgets, parents before their children
+    fn draw_order(&self) -> Vec<WidgetId> {
+        let mut order = Vec::new();
+        let mut stack: Vec<WidgetId> = self.roots.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.node(id).filter(|node| node.widget.visible) else { continue };
+            order.push(id);
+            stack.extend(node.children.iter().rev());
+        }
+        order
+    }
+
+    fn update_layout(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        let screen = GuiRect { min: [0.0, 0.0], max: self.size };
+        let mut stack: Vec<(WidgetId, GuiRect)> = self.roots.iter().map(|root| (*root, screen)).collect();
+        while let Some((id, parent)) = stack.pop() {
+            let Some(node) = self.node_mut(id) else { continue };
+            node.rect = node.widget.layout.rect(&parent);
+            let rect = node.rect;
+            stack.extend(node.children.iter().map(|child| (*child, rect)));
+        }
+    }
+}
+
+impl GuiBuilder {
+    pub fn new() -> GuiBuilder {
+        Default::default()
+    }
+
+    pub fn style(mut self, style: GuiStyle) -> Self {
+        self.gui.set_style(style);
+        self
+    }
+
+    pub fn font(mut self, font: Vec<u8>) -> Self {
+        self.gui.set_font(font);
+        self
+    }
+
+    /// Adds a widget to the current parent.
+    pub fn widget(mut self, widget: Widget) -> Self {
+        self.gui.add(self.parent, widget);
+        self
+    }
+
+    /// Adds a widget and the widgets added by `children` as its children.
+    pub fn widget_with_children(mut self, widget: Widget, children: impl FnOnce(GuiBuilder) -> GuiBuilder) -> Self {
+        let parent = self.parent;
+        self.parent = Some(self.gui.add(parent, widget));
+        let mut builder = children(self);
+        builder.parent = parent;
+        builder
+    }
+
+    pub fn build(self) -> Gui {
+        self.gui
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout::fill()
+    }
+}
+
+impl Default for GuiStyle {
+    fn default() -> GuiStyle {
+        GuiStyle {
+            button: Color::rgb(0.25, 0.25, 0.3),
+            hovered: Color::rgb(0.35, 0.35, 0.42),
+            pressed: Color::rgb(0.18, 0.18, 0.22),
+            disabled: Color::new(0.25, 0.25, 0.3, 0.5),
+            text_color: Color::WHITE,
+            text_size: DEFAULT_TEXT_SIZE,
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//////////////////////////////////////////////////
+// Using
+
+use crate::gl;
+use crate::opengl::*;
+use crate::postprocess::FULLSCREEN_VS;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Shader
+
+const BRIGHT_FS: &[u8] = b"#version 300 es
+precision highp float;
+precision highp sampler2DArray;
+
+in vec2 v_TexCoord;
+
+uniform sampler2DArray t_Source;
+
+layout(std140) uniform Hdr {
+    vec4 u_Params;
+    vec4 u_Blur;
+};
+
+layout(location = 0) out vec4 target0;
+
+void main() {
+    vec3 color = texture(t_Source, vec3(v_TexCoord, 0.0)).rgb;
+    float brightness = max(max(color.r, color.g), color.b);
+    target0 = vec4(color * max(brightness - u_Params.y, 0.0) / max(brightness, 0.0001), 1.0);
+}
+";
+
+const BLUR_FS: &[u8] = b"#version 300 es
+precision highp float;
+precision highp sampler2DArray;
+
+in vec2 v_TexCoord;
+
+uniform sampler2DArray t_Source;
+
+layout(std140) uniform Hdr {
+    vec4 u_Params;
+    vec4 u_Blur;
+};
+
+layout(location = 0) out vec4 target0;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec3 color = texture(t_Source, vec3(v_TexCoord, 0.0)).rgb * weights[0];
+    for (int i = 1; i < 5; i++) {
+        vec2 offset = u_Blur.xy * float(i);
+        color += texture(t_Source, vec3(v_TexCoord + offset, 0.0)).rgb * weights[i];
+        color += texture(t_Source, vec3(v_TexCoord - offset, 0.0)).rgb * weights[i];
+    }
+    target0 = vec4(color, 1.0);
+}
+";
+
+const TONEMAP_FS: &[u8] = b"#version 300 es
+precision highp float;
+precision highp sampler2DArray;
+
+in vec2 v_TexCoord;
+
+uniform sampler2DArray t_Scene;
+uniform sampler2DArray t_Bloom;
+
+layout(std140) uniform Hdr {
+    vec4 u_Params;
+    vec4 u_Blur;
+};
+
+layout(location = 0) out vec4 target0;
+
+void main() {
+    vec4 scene = texture(t_Scene, vec3(v_TexCoord, 0.0));
+    vec3 color = (scene.rgb + texture(t_Bloom, vec3(v_TexCoord, 0.0)).rgb * u_Params.z) * u_Params.x;
+    if (u_Params.w < 0.5) {
+        color = color / (1.0 + color);
+    } else {
+        // fitted ACES curve by Krzysztof Narkowicz
+        color = clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+    }
+    if (u_Blur.z > 0.5) {
+        color = pow(color, vec3(1.0 / 2.2));
+    }
+    target0 = vec4(color, scene.a);
+}
+";
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Renders the scene into a floating point target and tone maps it to the default framebuffer, with optional bloom.
+/// Falls back to an `RGBA8` target if the device cannot render to `RGBA16F`, tone mapping still applies.
+#[derive(Debug, Default)]
+pub struct HdrRenderer {
+    /// Multiplier applied before tone mapping.
+    pub exposure: f32,
+    pub tone_mapping: ToneMapping,
+    /// `None` disables bloom.
+    pub bloom: Option<Bloom>,
+    /// Applies gamma 2.2 after tone mapping. Disable for sRGB framebuffers, which encode on write.
+    pub gamma_correct: bool,
+    scene: GlFramebuffer,
+    bloom_targets: [GlFramebuffer; 2],
+    bright: GlShader,
+    blur: GlShader,
+    tonemap: GlShader,
+    uniforms: GlUniformBuffer<HdrUniforms>,
+    vao: GlVertexArrayObject,
+    hdr: bool,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ToneMapping {
+    #[default]
+    Reinhard,
+    Aces,
+}
+
+/// Bright parts of the image bleeding into their surroundings, blurred at half resolution.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bloom {
+    /// Brightness above which pixels contribute.
+    pub threshold: f32,
+    pub intensity: f32,
+    /// Blur passes, each widens the glow.
+    pub iterations: u32,
+}
+
+// exposure, threshold, intensity, tone mapping / texel offset, gamma
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct HdrUniforms {
+    params: [f32; 4],
+    blur: [f32; 4],
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl HdrRenderer {
+    pub fn new(gl: &Gl, capabilities: &GlCapabilities, width: u32, height: u32, samples: u32) -> HdrRenderer {
+        let hdr = capabilities.color_buffer_half_float;
+        if !hdr {
+            log::warn!("RGBA16F render targets not supported, HDR falls back to RGBA8");
+        }
+        let format = if hdr { gl::RGBA16F } else { gl::RGBA8 };
+        let (bloom_width, bloom_height) = ((width / 2).max(1), (height / 2).max(1));
+        HdrRenderer {
+            exposure: 1.0,
+            tone_mapping: ToneMapping::default(),
+            bloom: None,
+            gamma_correct: !capabilities.srgb_framebuffer,
+            scene: GlFramebuffer::new(gl, width, height, format, samples),
+            bloom_targets: [GlFramebuffer::new(gl, bloom_width, bloom_height, format, 1), GlFramebuffer::new(gl, bloom_width, bloom_height, format, 1)],
+            bright: GlShader::new(gl, FULLSCREEN_VS, BRIGHT_FS),
+            blur: GlShader::new(gl, FULLSCREEN_VS, BLUR_FS),
+            tonemap: GlShader::new(gl, FULLSCREEN_VS, TONEMAP_FS),
+            uniforms: GlUniformBuffer::new(gl, gl::DYNAMIC_DRAW, &HdrUniforms::default()),
+            vao: GlVertexArrayObject::new(gl),
+            hdr,
+        }
+    }
+
+    /// False if the scene target fell back to `RGBA8`.
+    pub fn is_hdr(&self) -> bool {
+        self.hdr
+    }
+
+    /// Recreates all targets, call from `resize_device`.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.scene.resize(width, height);
+        self.bloom_targets.iter_mut().for_each(|target| target.resize((width / 2).max(1), (height / 2).max(1)));
+    }
+
+    /// Offscreen target receiving the scene.
+    pub fn scene(&mut self) -> &mut GlFramebuffer {
+        &mut self.scene
+    }
+
+    /// Binds the scene target, everything drawn until `end` is tone mapped.
+    pub fn begin(&mut self) {
+        self.scene.bind();
+    }
+
+    /// Applies bloom and tone mapping and presents the result to the default framebuffer.
+    pub fn end(&mut self, gl: &Gl) {
+        self.scene.unbind();
+        self.scene.resolve();
+
+        let tone_mapping = match self.tone_mapping {
+            ToneMapping::Reinhard => 0.0,
+            ToneMapping::Aces => 1.0,
+        };
+        let gamma = if self.gamma_correct { 1.0 } else { 0.0 };
+        let (threshold, intensity) = self.bloom.map(|bloom| (bloom.threshold, bloom.intensity)).unwrap_or((0.0, 0.0));
+        let mut uniforms = HdrUniforms {
+            params: [self.exposure, threshold, intensity, tone_mapping],
+            blur: [0.0, 0.0, gamma, 0.0],
+        };
+
+        GlRenderState::default().apply(gl, None);
+        self.vao.bind();
+        self.uniforms.bind(0);
+        if let Some(bloom) = self.bloom {
+            let rect = self.bloom_targets[0].rect();
+            gl.set_viewport(rect);
+            self.uniforms.update(&uniforms);
+            self.bloom_targets[0].bind();
+            self.scene.texture().bind(0);
+            draw_fullscreen(&mut self.bright, "t_Source");
+            self.scene.texture().unbind();
+
+            // separable gaussian, horizontal into the second target and vertical back into the first
+            let texel = [1.0 / rect.width as f32, 1.0 / rect.height as f32];
+            for _ in 0..bloom.iterations {
+                for (direction, (source, target)) in [[texel[0], 0.0], [0.0, texel[1]]].iter().zip([(0, 1), (1, 0)]) {
+                    uniforms.blur = [direction[0], direction[1], gamma, 0.0];
+                    self.uniforms.update(&uniforms);
+                    self.bloom_targets[target].bind();
+                    self.bloom_targets[source].texture().bind(0);
+                    draw_fullscreen(&mut self.blur, "t_Source");
+                    self.bloom_targets[source].texture().unbind();
+                }
+            }
+            self.bloom_targets[0].unbind();
+        }
+
+        uniforms.blur = [0.0, 0.0, gamma, 0.0];
+        self.uniforms.update(&uniforms);
+        gl.set_viewport(self.scene.rect());
+        // without bloom the scene is bound as bloom texture too, weighted by zero intensity
+        let bloom_texture = if self.bloom.is_some() { self.bloom_targets[0].texture() } else { self.scene.texture() };
+        bloom_texture.bind(1);
+        self.scene.texture().bind(0);
+        self.tonemap.bind();
+        self.tonemap.link_texture(1, "t_Bloom");
+        draw_fullscreen(&mut self.tonemap, "t_Scene");
+        self.bloom_targets[0].texture().unbind();
+        self.scene.texture().unbind();
+        self.uniforms.unbind();
+        self.vao.unbind();
+    }
+}
+
+// draws the fullscreen triangle with the source texture on unit 0
+fn draw_fullscreen(shader: &mut GlShader, source: &str) {
+    shader.bind();
+    shader.link_texture(0, source);
+    shader.link_uniform(0, "Hdr");
+    shader.draw_arrays(gl::TRIANGLES, 3);
+    shader.unbind();
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl Default for Bloom {
+    fn default() -> Bloom {
+        Bloom {
+            threshold: 1.0,
+            intensity: 0.5,
+            iterations: 4,
+        }
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl GlResource for HdrRenderer {
+    fn release(&mut self) {
+        self.scene.release();
+        self.bloom_targets.iter_mut().for_each(|target| target.release());
+        self.bright.release();
+        self.blur.release();
+        self.tonemap.release();
+        self.uniforms.release();
+        self.vao.release();
+    }
+}
+impl Drop for HdrRenderer {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
@@ -0,0 +1,198 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::events::{AssetChanged, EventReader};
+use crate::file::Files;
+use crate::opengl::*;
+use crate::resources::Handle;
+use crate::{GameContext, Gl};
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Reports changes of files in the `assets` folder and the folders mounted with `Files::mount_dir`.
+/// Desktop only, meant for development builds.
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    receiver: Receiver<notify::Result<Event>>,
+    files: Files,
+    // watched folders with the virtual folder of their files, in lookup order
+    roots: Vec<(PathBuf, String)>,
+}
+
+/// Re-uploads textures of `GpuResources` when their image files change, driven by `AssetChanged` events.
+/// Needs `GameContext::watch_assets`.
+#[derive(Debug, Default)]
+pub struct TextureReloader {
+    changes: EventReader<AssetChanged>,
+    textures: Vec<(Vec<String>, Handle<GlTexture>)>,
+}
+
+/// Recompiles shaders of `GpuResources` when their source files change, driven by `AssetChanged` events.
+/// Needs `GameContext::watch_assets`. Programs that fail to compile keep running the previous version,
+/// the error is logged and kept for display.
+#[derive(Debug, Default)]
+pub struct ShaderReloader {
+    changes: EventReader<AssetChanged>,
+    shaders: Vec<(String, String, Handle<GlShader>)>,
+    last_error: Option<String>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl FileWatcher {
+    /// Watches the folders `files` reads from, following later changes of its mounts.
+    pub fn new(files: Files) -> notify::Result<FileWatcher> {
+        let (sender, receiver) = channel();
+        let watcher = notify::recommended_watcher(sender)?;
+        let mut file_watcher = FileWatcher {
+            watcher,
+            receiver,
+            files,
+            roots: Vec::new(),
+        };
+        file_watcher.update_roots();
+        Ok(file_watcher)
+    }
+
+    /// Asset filenames, as passed to `Files`, that were created or modified since the last call.
+    pub fn changed(&mut self) -> HashSet<String> {
+        self.update_roots();
+        let roots = &self.roots;
+        self.receiver
+            .try_iter()
+            .filter_map(|event| event.map_err(|e| log::warn!("File watcher error: {}", e)).ok())
+            .filter(|event| matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)))
+            .flat_map(|event| event.paths)
+            .flat_map(|path| {
+                roots
+                    .iter()
+                    .filter_map(|(root, point)| path.strip_prefix(root).ok().map(|path| format!("{}{}", point, path.to_string_lossy().replace('\\', "/"))))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    // watches added mount folders and stops watching removed ones
+    fn update_roots(&mut self) {
+        // watching the folder instead of single files survives editors replacing files on save
+        let roots: Vec<_> = self.files.source_dirs().into_iter().filter_map(|(dir, point)| Some((dir.canonicalize().ok()?, point))).collect();
+        let watched = std::mem::take(&mut self.roots);
+        for (root, _) in watched.iter().filter(|(root, _)| !roots.iter().any(|(other, _)| other == root)) {
+            if let Err(e) = self.watcher.unwatch(root) {
+                log::warn!("Failed to stop watching {:?}: {}", root, e);
+            }
+        }
+        for (root, _) in roots.iter().filter(|(root, _)| !watched.iter().any(|(other, _)| other == root)) {
+            match self.watcher.watch(root, RecursiveMode::Recursive) {
+                Ok(()) => log::info!("Watching {:?} for changes", root),
+                Err(e) => log::warn!("Failed to watch {:?}: {}", root, e),
+            }
+        }
+        self.roots = roots;
+    }
+}
+
+impl TextureReloader {
+    pub fn new() -> TextureReloader {
+        TextureReloader::default()
+    }
+
+    /// Reloads `texture` when one of `filenames` changes, one layer per file in order.
+    pub fn watch(&mut self, filenames: &[&str], texture: Handle<GlTexture>) {
+        self.textures.push((filenames.iter().map(|filename| filename.to_string()).collect(), texture));
+    }
+
+    /// Applies pending changes, call between frames, e.g. at the start of `render`.
+    /// Files that fail to decode keep the old texture.
+    pub fn update(&mut self, gl: &Gl, ctx: &mut GameContext) {
+        let changed: HashSet<_> = self.changes.read(&ctx.events).map(|AssetChanged(path)| path.as_str()).collect();
+        let files = ctx.files();
+        let resources = &mut ctx.resources;
+        if changed.is_empty() {
+            return;
+        }
+        self.textures.retain(|(_, texture)| resources.textures.contains(*texture));
+        for (filenames, texture) in self.textures.iter().filter(|(filenames, _)| filenames.iter().any(|filename| changed.contains(filename.as_str()))) {
+            let images: Result<Vec<_>, _> = filenames
+                .iter()
+                .map(|filename| {
+                    let bytes = files.load_bytes(filename).map_err(|e| e.to_string())?;
+                    image::load_from_memory(&bytes).map(|image| image.to_rgba8()).map_err(|e| e.to_string())
+                })
+                .collect();
+            match images {
+                Ok(images) => {
+                    resources.textures.replace(gl, *texture, move |gl| GlTexture::new(gl, &images));
+                    log::info!("Reloaded texture {:?}", filenames);
+                }
+                Err(e) => log::error!("Failed to reload texture {:?}: {}", filenames, e),
+            }
+        }
+    }
+}
+
+impl ShaderReloader {
+    pub fn new() -> ShaderReloader {
+        ShaderReloader::default()
+    }
+
+    /// Recompiles `shader` when the vertex or fragment source file changes.
+    pub fn watch(&mut self, vert: &str, frag: &str, shader: Handle<GlShader>) {
+        self.shaders.push((vert.to_string(), frag.to_string(), shader));
+    }
+
+    /// Applies pending changes, call between frames, e.g. at the start of `render`.
+    pub fn update(&mut self, gl: &Gl, ctx: &mut GameContext) {
+        let changed: HashSet<_> = self.changes.read(&ctx.events).map(|AssetChanged(path)| path.as_str()).collect();
+        let files = ctx.files();
+        let resources = &mut ctx.resources;
+        if changed.is_empty() {
+            return;
+        }
+        self.shaders.retain(|(_, _, shader)| resources.shaders.contains(*shader));
+        for (vert, frag, shader) in self.shaders.iter().filter(|(vert, frag, _)| changed.contains(vert.as_str()) || changed.contains(frag.as_str())) {
+            let sources = files.load_bytes(vert).and_then(|vs| Ok((vs, files.load_bytes(frag)?)));
+            let result = sources.map_err(|e| e.to_string()).and_then(|(vs, fs)| match GlShader::try_new(gl, &vs, &fs) {
+                Ok(program) => Ok((program, vs, fs)),
+                Err(e) => Err(e.to_string()),
+            });
+            match result {
+                Ok((program, vs, fs)) => {
+                    // the validated program is used right away, later device restores compile the same sources again
+                    let program = Cell::new(Some(program));
+                    resources.shaders.replace(gl, *shader, move |gl| program.take().unwrap_or_else(|| GlShader::new(gl, &vs, &fs)));
+                    self.last_error = None;
+                    log::info!("Reloaded shader {} / {}", vert, frag);
+                }
+                Err(e) => {
+                    log::error!("Failed to reload shader {} / {}: {}", vert, frag, e);
+                    self.last_error = Some(format!("{} / {}: {}", vert, frag, e));
+                }
+            }
+        }
+    }
+
+    /// Error of the last failed reload, cleared by the next successful one.
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileWatcher").field("roots", &self.roots).finish()
+    }
+}
@@ -0,0 +1,117 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::HashMap;
+
+use crate::color::Color;
+use crate::file::{FileError, Files};
+use crate::text::TextRenderer;
+
+//////////////////////////////////////////////////
+// Definition
+
+// folder of the string tables, one `<language>.toml` per language
+const LANGUAGE_DIR: &str = "lang";
+
+/// Translated strings loaded from `lang/<language>.toml`, e.g. `lang/de.toml` or `lang/pt-BR.toml`. Tables are
+/// flat or nested TOML tables of strings, nested keys are joined with dots ("menu.start"). Strings may contain
+/// `{name}` placeholders filled by `tr`. Missing keys fall back to the fallback language, then to the key itself.
+#[derive(Debug, Default)]
+pub struct Localization {
+    language: String,
+    fallback: String,
+    strings: HashMap<String, String>,
+    fallback_strings: HashMap<String, String>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Localization {
+    /// Loads the fallback language, which is also the initial language.
+    pub fn new(files: &Files, fallback: &str) -> Localization {
+        let fallback_strings = load_table(files, fallback).unwrap_or_else(|e| {
+            log::error!("Failed to load fallback language {}: {}", fallback, e);
+            HashMap::new()
+        });
+        Localization {
+            language: fallback.to_string(),
+            fallback: fallback.to_string(),
+            strings: HashMap::new(),
+            fallback_strings,
+        }
+    }
+
+    /// Current language code.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Languages with a string table, including mounted ones.
+    pub fn languages(files: &Files) -> Vec<String> {
+        files.list(LANGUAGE_DIR).iter().filter_map(|path| path.strip_prefix("lang/")?.strip_suffix(".toml").map(|code| code.to_string())).collect()
+    }
+
+    /// Switches to a language, "de-AT" falls back to "de" if there is no table for the region.
+    pub fn set_language(&mut self, files: &Files, language: &str) -> Result<(), FileError> {
+        if language == self.fallback {
+            self.strings.clear();
+            self.language = language.to_string();
+            return Ok(());
+        }
+        let primary = language.split('-').next().unwrap_or(language);
+        let (code, strings) = match load_table(files, language) {
+            Err(FileError::NotFound(_)) if primary != language => (primary, load_table(files, primary)?),
+            result => (language, result?),
+        };
+        self.strings = strings;
+        self.language = code.to_string();
+        log::info!("Switched language to {}", code);
+        Ok(())
+    }
+
+    /// Translation of `key` with `{name}` placeholders replaced by `args`.
+    pub fn tr(&self, key: &str, args: &[(&str, &str)]) -> String {
+        args.iter().fold(self.text(key).to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+    }
+
+    /// Translation of `key` without placeholders.
+    pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings.get(key).or_else(|| self.fallback_strings.get(key)).map_or(key, |text| text.as_str())
+    }
+
+    /// Queues the translation of `key` on a text renderer, see `TextRenderer::draw_text`.
+    pub fn draw_text(&self, renderer: &mut TextRenderer, key: &str, args: &[(&str, &str)], position: [f32; 2], size: f32, color: impl Into<Color>) {
+        renderer.draw_text(&self.tr(key, args), position, size, color);
+    }
+}
+
+/// Converts a POSIX locale like "de_AT.UTF-8" into a language code like "de-AT".
+pub fn parse_locale(locale: &str) -> Option<String> {
+    let code = locale.split(['.', '@']).next()?.replace('_', "-");
+    (!code.is_empty() && code != "C" && code != "POSIX").then_some(code)
+}
+
+// flattens nested tables into dotted keys
+fn load_table(files: &Files, language: &str) -> Result<HashMap<String, String>, FileError> {
+    let filename = format!("{}/{}.toml", LANGUAGE_DIR, language);
+    let table: toml::Table = toml::from_str(&files.load_string(&filename)?).map_err(|e| FileError::Decode(filename.clone(), e.to_string()))?;
+    let mut strings = HashMap::new();
+    flatten("", &table, &mut strings);
+    Ok(strings)
+}
+
+fn flatten(prefix: &str, table: &toml::Table, strings: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::String(text) => {
+                strings.insert(key, text.clone());
+            }
+            toml::Value::Table(table) => flatten(&key, table, strings),
+            other => {
+                strings.insert(key, other.to_string());
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //////////////////////////////////////////////////
 // Input
 
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 
 #[derive(Debug, Copy, Clone)]
@@ -28,7 +29,7 @@ pub enum MouseState {
     Released,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MouseButton {
     Left,
     Middle,
@@ -68,12 +69,78 @@ pub enum KeyState {
     Released,
 }
 
+/// Text typed while `GameContext::show_soft_keyboard` is active, sent through `GameContext::events`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TextInput {
+    /// Text to insert at the cursor.
+    Commit(String),
+    /// Text being composed by the input method, replaced by the next preedit or commit. Empty when the
+    /// composition was cancelled.
+    Preedit(String),
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Location {
     pub x: f32,
     pub y: f32,
 }
 
+/// Input state after the events of the current frame, for games polling input instead of handling events.
+#[derive(Debug, Default, Clone)]
+pub struct InputSnapshot {
+    cursor: Option<Location>,
+    keys: HashSet<Key>,
+    buttons: HashSet<MouseButton>,
+    touches: HashMap<u64, Location>,
+}
+
+impl InputSnapshot {
+    /// Applies the events of a frame.
+    pub fn update(&mut self, input_events: &[InputEvent]) {
+        for event in input_events {
+            match event {
+                InputEvent::Cursor(CursorEvent { location }) => self.cursor = Some(*location),
+                InputEvent::Mouse(MouseEvent { state: MouseState::Pressed, button }) => {
+                    self.buttons.insert(*button);
+                }
+                InputEvent::Mouse(MouseEvent { state: MouseState::Released, button }) => {
+                    self.buttons.remove(button);
+                }
+                InputEvent::Touch(TouchEvent { state: TouchState::Down | TouchState::Move, location, id }) => {
+                    self.touches.insert(*id, *location);
+                }
+                InputEvent::Touch(TouchEvent { id, .. }) => {
+                    self.touches.remove(id);
+                }
+                InputEvent::Keyboard(KeyboardEvent { state: KeyState::Pressed, key }) => {
+                    self.keys.insert(*key);
+                }
+                InputEvent::Keyboard(KeyboardEvent { state: KeyState::Released, key }) => {
+                    self.keys.remove(key);
+                }
+            }
+        }
+    }
+
+    /// Last cursor location in logical pixels.
+    pub fn cursor(&self) -> Option<Location> {
+        self.cursor
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    /// Active touches by id.
+    pub fn touches(&self) -> &HashMap<u64, Location> {
+        &self.touches
+    }
+}
+
 impl From<winit::dpi::PhysicalPosition<f64>> for Location {
     fn from(e: winit::dpi::PhysicalPosition<f64>) -> Location {
         Location { x: e.x as f32, y: e.y as f32 }
@@ -1,10 +1,58 @@
 //////////////////////////////////////////////////
 // Module
 
+pub mod animation;
 pub mod app;
+pub mod assetpack;
+pub mod atlas;
+pub mod audio;
+pub mod basis;
+pub mod cache;
+pub mod camera;
+pub mod color;
+pub mod config;
+pub mod debug;
+pub mod events;
 pub mod file;
+pub mod graph;
+pub mod gui;
+pub mod hdr;
+#[cfg(not(target_os = "android"))]
+pub mod hotreload;
+pub mod i18n;
 pub mod input;
+pub mod loader;
+pub mod material;
+pub mod mesh;
+pub mod mixer;
+pub mod model;
+pub mod music;
 pub mod opengl;
+#[cfg(feature = "audio")]
+pub mod output;
+pub mod overlay;
+pub mod particle;
+pub mod permission;
+pub mod pipeline;
+pub mod platform;
+pub mod postprocess;
+pub mod prefab;
+pub mod profiler;
+pub mod recorder;
+pub mod resources;
+pub mod save;
+pub mod scene;
+pub mod schedule;
+pub mod shape;
+pub mod sprite;
+pub mod state;
+pub mod store;
+pub mod target;
+pub mod tasks;
+pub mod text;
+pub mod texture;
+pub mod time;
+pub mod transition;
 
 //////////////////////////////////////////////////
 // OpenGL binding
@@ -20,8 +68,15 @@ pub mod gl {
 pub mod prelude {
     pub use crate::gl;
     pub use crate::gl::types::*;
+    pub use crate::app::{DisplayMode, WindowConfig};
+    pub use crate::camera::{Camera2D, Camera3D};
+    pub use crate::color::Color;
+    pub use crate::opengl::{GlClearFlags, GlExt};
+    pub use crate::state::{GameState, StateStack, Transition};
+    pub use crate::transition::{SlideDirection, TransitionEffect};
     pub use crate::{input::InputEvent, Game, GameContext, GameLoop, Gl};
     pub use image;
+    pub use nalgebra_glm as glm;
     #[cfg(target_os = "android")]
     pub use winit::platform::android::activity::AndroidApp;
 }
@@ -33,11 +88,33 @@ use std::convert::TryInto;
 use std::rc::Rc;
 use std::time::Instant;
 
-use file::Files;
-use input::{CursorEvent, MouseEvent};
+use assetpack::AssetPacks;
+use cache::AssetCache;
+use camera::Camera2D;
+use color::Color;
+use config::{Config, ConfigError};
+use debug::DebugDraw;
+use events::Events;
+use file::{Files, Mounts};
+use i18n::Localization;
+use gl::types::GLenum;
+use input::{CursorEvent, InputSnapshot, Location, MouseEvent, TouchEvent};
+use loader::AssetLoader;
+use mixer::{Bus, Mixer};
+use music::MusicPlayer;
+use opengl::{GlCapabilities, GlClearFlags, GlDrawStats, GlExt, GlViewport, GlVirtualResolution};
+use overlay::DebugOverlay;
+use permission::Permissions;
+use platform::{PlatformInfo, Vibration};
+use profiler::GpuProfiler;
+use resources::GpuResources;
+use store::KvStore;
+use tasks::Tasks;
+use target::{RenderTargetHandle, RenderTargets};
+use time::GameTime;
 use log::LevelFilter;
 use winit::application::ApplicationHandler;
-use winit::event::WindowEvent;
+use winit::event::{Ime, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, EventLoop};
 
 #[cfg(target_os = "android")]
@@ -45,14 +122,23 @@ use winit::platform::android::activity::AndroidApp;
 #[cfg(target_os = "android")]
 use winit::platform::android::EventLoopBuilderExtAndroid;
 
-use crate::app::App;
-use crate::input::InputEvent;
+use crate::app::{App, CursorIcon, CursorImage, DisplayMode, Orientation, WindowConfig, WindowLevel};
+use crate::input::{InputEvent, TextInput};
 
 //////////////////////////////////////////////////
 // Types
 
 pub type Gl = Rc<gl::Gles2>;
 
+//////////////////////////////////////////////////
+// Constants
+
+// decoding threads of the asset loader
+const ASSET_WORKERS: usize = 2;
+
+// fallback of the localization
+const DEFAULT_LANGUAGE: &str = "en";
+
 //////////////////////////////////////////////////
 // Definition
 
@@ -62,17 +148,71 @@ pub struct Game<L: GameLoop> {
     game_time: Instant,
     game_context: GameContext,
     input_events: Vec<InputEvent>,
+    // key text is dropped while the input method composes
+    ime_composing: bool,
 }
 
 pub struct GameContext {
     #[cfg(target_os = "android")]
     android_app: AndroidApp,
     request_quit: bool,
+    // applied by the game loop after update
+    requested_size: Option<(u32, u32)>,
+    requested_position: Option<[i32; 2]>,
+    requested_display_mode: Option<DisplayMode>,
+    requested_monitor: Option<Option<String>>,
+    requested_cursor: Option<CursorImage>,
+    requested_window_level: Option<WindowLevel>,
+    cursor: CursorImage,
+    requested_orientation: Option<Orientation>,
+    orientation: Orientation,
+    requested_soft_keyboard: Option<bool>,
+    soft_keyboard: bool,
+    keep_screen_on: bool,
+    profiler: GpuProfiler,
+    viewport: GlViewport,
+    capabilities: GlCapabilities,
+    camera: Camera2D,
+    clear_color: Color,
+    render_targets: RenderTargets,
+    resources: GpuResources,
+    overlay: DebugOverlay,
+    debug_draw: DebugDraw,
+    assets: Option<AssetLoader>,
+    asset_packs: Option<AssetPacks>,
+    cache: Option<AssetCache>,
+    mounts: Mounts,
+    events: Events,
+    tasks: Tasks,
+    time: GameTime,
+    input: InputSnapshot,
+    platform: PlatformInfo,
+    window_position: Option<[i32; 2]>,
+    permissions: Option<Permissions>,
+    launch_url: Option<String>,
+    // last url of the activity's intent, a changed url is sent as event
+    #[cfg(target_os = "android")]
+    intent_url: Option<String>,
+    config: Config,
+    localization: Option<Localization>,
+    store: Option<KvStore>,
+    mixer: Mixer,
+    listener_follows_camera: bool,
+    music: Option<MusicPlayer>,
+    #[cfg(feature = "audio")]
+    audio_output: Option<output::AudioOutput>,
+    #[cfg(not(target_os = "android"))]
+    watcher: Option<hotreload::FileWatcher>,
 }
 
 pub trait GameLoop: Default {
     fn title(&self) -> &str;
 
+    /// Surface options, read once when the window is created.
+    fn window_config(&self) -> WindowConfig {
+        WindowConfig::default()
+    }
+
     fn init(&mut self, ctx: &mut GameContext);
 
     fn cleanup(&mut self, ctx: &mut GameContext);
@@ -96,22 +236,150 @@ pub trait GameLoop: Default {
 #[cfg(target_os = "android")]
 impl GameContext {
     pub fn new(android_app: AndroidApp) -> Self {
-        GameContext { android_app, request_quit: false }
+        let config = Config::load(&Files::new(&android_app));
+        let platform = PlatformInfo::query(&android_app);
+        let launch_url = platform::intent_url(&android_app);
+        GameContext {
+            android_app,
+            request_quit: false,
+            requested_size: None,
+            requested_position: None,
+            requested_display_mode: None,
+            requested_monitor: None,
+            requested_cursor: None,
+            requested_window_level: None,
+            cursor: CursorImage::Icon(CursorIcon::Default),
+            requested_orientation: None,
+            orientation: Orientation::Sensor,
+            requested_soft_keyboard: None,
+            soft_keyboard: false,
+            keep_screen_on: false,
+            profiler: GpuProfiler::default(),
+            viewport: GlViewport::default(),
+            capabilities: GlCapabilities::default(),
+            camera: Camera2D::default(),
+            clear_color: Color::BLACK,
+            render_targets: RenderTargets::default(),
+            resources: GpuResources::default(),
+            overlay: DebugOverlay::default(),
+            debug_draw: DebugDraw::default(),
+            assets: None,
+            asset_packs: None,
+            cache: None,
+            mounts: Mounts::default(),
+            events: Events::default(),
+            tasks: Tasks::default(),
+            time: GameTime::default(),
+            input: InputSnapshot::default(),
+            platform,
+            window_position: None,
+            permissions: None,
+            launch_url: launch_url.clone(),
+            intent_url: launch_url,
+            config,
+            localization: None,
+            store: None,
+            mixer: Mixer::default(),
+            listener_follows_camera: true,
+            music: None,
+            #[cfg(feature = "audio")]
+            audio_output: None,
+        }
     }
 
+    /// Asset access sharing the mounts of `Files::mount_archive` and `Files::mount_embedded`.
     pub fn files(&self) -> Files {
-        Files::new(&self.android_app)
+        Files::new(&self.android_app).with_mounts(self.mounts.clone())
+    }
+
+    /// Language code of the device, e.g. "de-AT".
+    pub fn system_language(&self) -> Option<String> {
+        let config = self.android_app.config();
+        let language = config.language()?;
+        Some(config.country().map_or(language.clone(), |country| format!("{}-{}", language, country)))
     }
 }
 
 #[cfg(not(target_os = "android"))]
 impl GameContext {
     pub fn new() -> Self {
-        GameContext { request_quit: false }
+        GameContext {
+            request_quit: false,
+            requested_size: None,
+            requested_position: None,
+            requested_display_mode: None,
+            requested_monitor: None,
+            requested_cursor: None,
+            requested_window_level: None,
+            cursor: CursorImage::Icon(CursorIcon::Default),
+            requested_orientation: None,
+            orientation: Orientation::Sensor,
+            requested_soft_keyboard: None,
+            soft_keyboard: false,
+            keep_screen_on: false,
+            profiler: GpuProfiler::default(),
+            viewport: GlViewport::default(),
+            capabilities: GlCapabilities::default(),
+            camera: Camera2D::default(),
+            clear_color: Color::BLACK,
+            render_targets: RenderTargets::default(),
+            resources: GpuResources::default(),
+            overlay: DebugOverlay::default(),
+            debug_draw: DebugDraw::default(),
+            assets: None,
+            asset_packs: None,
+            cache: None,
+            mounts: Mounts::default(),
+            events: Events::default(),
+            tasks: Tasks::default(),
+            time: GameTime::default(),
+            input: InputSnapshot::default(),
+            platform: PlatformInfo::query(),
+            window_position: None,
+            permissions: None,
+            launch_url: platform::command_line_url(),
+            config: Config::load(&Files::new()),
+            localization: None,
+            store: None,
+            mixer: Mixer::default(),
+            listener_follows_camera: true,
+            music: None,
+            #[cfg(feature = "audio")]
+            audio_output: None,
+            watcher: None,
+        }
     }
 
+    /// Asset access sharing the mounts of `Files::mount_archive` and `Files::mount_embedded`.
     pub fn files(&self) -> Files {
-        Files::new()
+        Files::new().with_mounts(self.mounts.clone())
+    }
+
+    /// Language code of the user's locale, e.g. "de-AT", read from the POSIX locale variables.
+    pub fn system_language(&self) -> Option<String> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"].iter().filter_map(|name| std::env::var(name).ok()).find_map(|locale| i18n::parse_locale(&locale))
+    }
+
+    /// Watches the `assets` folder and the folders mounted with `Files::mount_dir`, and sends `AssetChanged` for every
+    /// created or modified file. Meant for development builds.
+    pub fn watch_assets(&mut self) -> notify::Result<()> {
+        if self.watcher.is_none() {
+            self.watcher = Some(hotreload::FileWatcher::new(self.files())?);
+        }
+        Ok(())
+    }
+
+    fn send_asset_events(&mut self) {
+        if let Some(watcher) = self.watcher.as_mut() {
+            watcher.changed().into_iter().for_each(|path| self.events.send(events::AssetChanged(path)));
+        }
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl Default for GameContext {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -120,6 +388,337 @@ impl GameContext {
         self.request_quit = true;
     }
 
+    /// Resizes the window after the current update, e.g. from a settings menu. The size is kept in the config,
+    /// so `save_config` restores it on the next start. Ignored on Android.
+    pub fn request_window_size(&mut self, width: u32, height: u32) {
+        self.requested_size = Some((width, height));
+        self.config.resolution = Some([width, height]);
+    }
+
+    /// Moves the window after the current update, kept in the config like `request_window_size`, e.g. for tools
+    /// restoring their placement. Ignored on Android and Wayland.
+    pub fn set_window_position(&mut self, x: i32, y: i32) {
+        self.requested_position = Some([x, y]);
+        self.config.window_position = Some([x, y]);
+    }
+
+    /// Position of the window's top left corner on the desktop in physical pixels, `None` where unknown, e.g. on
+    /// Android and Wayland. Moves are sent as `events::WindowMoved`.
+    pub fn window_position(&self) -> Option<[i32; 2]> {
+        self.window_position
+    }
+
+    /// Switches between windowed and fullscreen after the current update, kept in the config like
+    /// `request_window_size`. Exclusive fullscreen uses the window size as resolution. Ignored on Android.
+    pub fn request_display_mode(&mut self, display_mode: DisplayMode) {
+        self.requested_display_mode = Some(display_mode);
+        self.config.display_mode = display_mode;
+    }
+
+    /// Requested display mode, applied after the current update.
+    pub fn display_mode(&self) -> DisplayMode {
+        self.config.display_mode
+    }
+
+    /// Moves the window onto a screen of `PlatformInfo::monitors` after the current update, fullscreen switches to
+    /// that screen. The screen is kept in the config by name like `request_window_size`, `None` leaves it to the
+    /// platform. Ignored on Android.
+    pub fn request_monitor(&mut self, index: Option<usize>) {
+        let name = index.and_then(|index| self.platform.monitors.get(index)).and_then(|monitor| monitor.name.clone());
+        self.requested_monitor = Some(name.clone());
+        self.config.monitor = name;
+    }
+
+    /// Keeps the window above or below other windows after the current update, overriding
+    /// `WindowConfig::window_level`. Ignored on Android.
+    pub fn set_window_level(&mut self, level: WindowLevel) {
+        self.requested_window_level = Some(level);
+    }
+
+    /// Changes the mouse pointer after the current update, e.g. to a themed image of the assets. Ignored on Android.
+    pub fn set_cursor(&mut self, cursor: CursorImage) {
+        self.requested_cursor = Some(cursor.clone());
+        self.cursor = cursor;
+    }
+
+    /// Requested cursor, applied after the current update.
+    pub fn cursor(&self) -> &CursorImage {
+        &self.cursor
+    }
+
+    /// Locks the screen orientation after the current update, e.g. for a portrait-only puzzle. On Android the
+    /// activity is rotated, on desktop the window is kept in the aspect of the orientation.
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.requested_orientation = Some(orientation);
+        self.orientation = orientation;
+    }
+
+    /// Requested orientation, applied after the current update.
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Starts or stops text input after the current update, e.g. when a text field gains focus. On Android the
+    /// soft keyboard is shown through the `InputMethodManager`, on desktop the input method of the window is
+    /// enabled. Typed text is sent as `input::TextInput` events, keys are still passed to `GameLoop::input`.
+    pub fn show_soft_keyboard(&mut self, show: bool) {
+        self.requested_soft_keyboard = Some(show);
+        self.soft_keyboard = show;
+    }
+
+    /// Requested text input, applied after the current update.
+    pub fn is_soft_keyboard_shown(&self) -> bool {
+        self.soft_keyboard
+    }
+
+    /// Stops the screen from dimming and sleeping, e.g. during gameplay using only the accelerometer or a gamepad.
+    /// Supported on Android and Windows, ignored on other desktops.
+    pub fn keep_screen_on(&mut self, keep_on: bool) {
+        self.keep_screen_on = keep_on;
+        #[cfg(target_os = "android")]
+        platform::set_keep_screen_on(&self.android_app, keep_on);
+        #[cfg(not(target_os = "android"))]
+        platform::set_keep_screen_on(keep_on);
+    }
+
+    pub fn is_screen_kept_on(&self) -> bool {
+        self.keep_screen_on
+    }
+
+    /// Vibrates the device, e.g. as hit feedback. Needs the `android.permission.VIBRATE` permission in the manifest.
+    /// Ignored on desktop.
+    pub fn vibrate(&mut self, duration_ms: u32) {
+        self.vibrate_pattern(&[Vibration::on(duration_ms)]);
+    }
+
+    /// Vibrates with the steps of `pattern` in order, replacing a running vibration. Ignored on desktop.
+    pub fn vibrate_pattern(&mut self, pattern: &[Vibration]) {
+        #[cfg(target_os = "android")]
+        platform::vibrate(&self.android_app, pattern);
+        #[cfg(not(target_os = "android"))]
+        let _ = pattern;
+    }
+
+    pub fn cancel_vibration(&mut self) {
+        #[cfg(target_os = "android")]
+        platform::cancel_vibration(&self.android_app);
+    }
+
+    pub fn profiler(&mut self) -> &mut GpuProfiler {
+        &mut self.profiler
+    }
+
+    pub fn viewport(&mut self) -> &mut GlViewport {
+        &mut self.viewport
+    }
+
+    /// Loads textures in the background into `resources`, worker threads start on first use.
+    pub fn assets(&mut self) -> &mut AssetLoader {
+        if self.assets.is_none() {
+            self.assets = Some(AssetLoader::new(self.files(), ASSET_WORKERS));
+        }
+        self.assets.as_mut().expect("Missing asset loader")
+    }
+
+    /// Downloads Play Asset Delivery packs on Android and mounts them into `files`, created on first use.
+    pub fn asset_packs(&mut self) -> &mut AssetPacks {
+        if self.asset_packs.is_none() {
+            #[cfg(target_os = "android")]
+            let asset_packs = AssetPacks::new(self.android_app.clone(), self.files());
+            #[cfg(not(target_os = "android"))]
+            let asset_packs = AssetPacks::new();
+            self.asset_packs = Some(asset_packs);
+        }
+        self.asset_packs.as_mut().expect("Missing asset packs")
+    }
+
+    /// Decoded images, fonts, shader sources and sounds shared by path, kept across game states.
+    pub fn cache(&mut self) -> &mut AssetCache {
+        if self.cache.is_none() {
+            self.cache = Some(AssetCache::new(self.files()));
+        }
+        self.cache.as_mut().expect("Missing asset cache")
+    }
+
+    /// Settings loaded at startup. Resolution and vsync are applied when the window is created.
+    pub fn config(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    /// Writes the settings into the user data directory.
+    pub fn save_config(&self) -> Result<(), ConfigError> {
+        self.config.save(&self.files())
+    }
+
+    /// Translated strings, starting in the system language with English as fallback.
+    pub fn localization(&mut self) -> &mut Localization {
+        if self.localization.is_none() {
+            let files = self.files();
+            let mut localization = Localization::new(&files, DEFAULT_LANGUAGE);
+            if let Some(language) = self.system_language() {
+                if let Err(e) = localization.set_language(&files, &language) {
+                    log::warn!("System language {} not available: {}", language, e);
+                }
+            }
+            self.localization = Some(localization);
+        }
+        self.localization.as_mut().expect("Missing localization")
+    }
+
+    /// Persistent values in the user data directory, saved whenever the game is suspended.
+    pub fn store(&mut self) -> &mut KvStore {
+        if self.store.is_none() {
+            self.store = Some(KvStore::load(self.files()));
+        }
+        self.store.as_mut().expect("Missing store")
+    }
+
+    /// Software mixer playing sounds. With the `audio` feature it is played by an `AudioOutput`, which is opened
+    /// when the game resumes, paused while suspended and reopened when the output device changes.
+    pub fn mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+
+    /// Sets the volume of a bus and stores it in the config, see `save_config`.
+    pub fn set_volume(&mut self, bus: Bus, volume: f32) {
+        self.mixer.set_bus_volume(bus, volume);
+        self.config.volumes.insert(bus.name().to_string(), volume);
+    }
+
+    pub fn volume(&self, bus: Bus) -> f32 {
+        self.mixer.bus_volume(bus)
+    }
+
+    /// Moves the mixer's listener with the 2D camera after every update, enabled by default.
+    /// 3D games disable it and call `Mixer::set_listener` with their `Camera3D`.
+    pub fn set_listener_follows_camera(&mut self, follow: bool) {
+        self.listener_follows_camera = follow;
+    }
+
+    /// Streamed music played through `mixer`, paused while the game is suspended.
+    pub fn music(&mut self) -> &mut MusicPlayer {
+        if self.music.is_none() {
+            self.music = Some(MusicPlayer::new(self.mixer.clone(), self.files()));
+        }
+        self.music.as_mut().expect("Missing music player")
+    }
+
+    /// Events sent by the game and the engine, e.g. `AssetChanged`.
+    pub fn events(&mut self) -> &mut Events {
+        &mut self.events
+    }
+
+    /// Async tasks polled once per frame after `GameLoop::update`.
+    pub fn tasks(&mut self) -> &mut Tasks {
+        &mut self.tasks
+    }
+
+    /// Delta and total time and the frame number of the current frame.
+    pub fn time(&self) -> GameTime {
+        self.time
+    }
+
+    /// OS, device and screen properties, e.g. to pick a quality preset. Screen properties are known after resume.
+    pub fn platform_info(&self) -> &PlatformInfo {
+        &self.platform
+    }
+
+    /// Runtime permissions on Android, created on first use. All permissions are granted on desktop.
+    pub fn permissions(&mut self) -> &mut Permissions {
+        if self.permissions.is_none() {
+            #[cfg(target_os = "android")]
+            let permissions = Permissions::new(self.android_app.clone());
+            #[cfg(not(target_os = "android"))]
+            let permissions = Permissions::new();
+            self.permissions = Some(permissions);
+        }
+        self.permissions.as_mut().expect("Missing permissions")
+    }
+
+    /// Url the app was started with, e.g. from a deep link on Android or the command line on desktop. Urls opened
+    /// while running are sent as `events::UrlOpened`.
+    pub fn launch_url(&self) -> Option<&str> {
+        self.launch_url.as_deref()
+    }
+
+    /// Keys, buttons, cursor and touches after this frame's input events.
+    pub fn input(&self) -> &InputSnapshot {
+        &self.input
+    }
+
+    /// Fps, draw call and GPU timing overlay drawn after `render`.
+    pub fn overlay(&mut self) -> &mut DebugOverlay {
+        &mut self.overlay
+    }
+
+    /// World space debug shapes, drawn with the context camera after `render`. Enabled in debug builds.
+    pub fn debug_draw(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
+    }
+
+    /// Limits of the current device, available from `create_device` on.
+    pub fn capabilities(&self) -> &GlCapabilities {
+        &self.capabilities
+    }
+
+    /// Camera kept in sync with the surface size.
+    pub fn camera(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    /// Renders at a fixed design resolution scaled to the surface. Viewport, camera and cursor and touch
+    /// locations all switch to virtual coordinates, `None` goes back to surface pixels.
+    pub fn set_virtual_resolution(&mut self, gl: &Gl, virtual_resolution: Option<GlVirtualResolution>) {
+        self.viewport.set_virtual_resolution(gl, virtual_resolution);
+        let (width, height) = self.viewport.logical_size();
+        self.camera.resize(width, height);
+    }
+
+    /// Color used by `clear`, black by default.
+    pub fn set_clear_color(&mut self, color: impl Into<Color>) {
+        self.clear_color = color.into();
+    }
+
+    pub fn clear_color(&self) -> Color {
+        self.clear_color
+    }
+
+    /// Clears the selected buffers of the bound framebuffer to the clear color, depth 1 and stencil 0.
+    pub fn clear(&self, gl: &Gl, flags: GlClearFlags) {
+        gl.clear(flags, self.clear_color.to_array(), 1.0, 0);
+    }
+
+    /// Reads back the default framebuffer, call at the end of `render` before the buffers are swapped.
+    pub fn capture_frame(&self, gl: &Gl) -> image::RgbaImage {
+        unsafe { gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0) };
+        gl.read_pixels(self.viewport.size())
+    }
+
+    /// Captures the frame and saves it as png into `Files::user_data_dir`.
+    pub fn save_frame(&self, gl: &Gl, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut png = std::io::Cursor::new(Vec::new());
+        self.capture_frame(gl).write_to(&mut png, image::ImageFormat::Png)?;
+        self.files().save_bytes(filename, png.get_ref())?;
+        log::info!("Saved frame to {}", filename);
+        Ok(())
+    }
+
+    /// Textures, shaders and meshes that are recreated automatically when the device is lost and restored.
+    pub fn resources(&mut self) -> &mut GpuResources {
+        &mut self.resources
+    }
+
+    /// Offscreen target of fixed size, e.g. for minimaps or mirrors. It is recreated automatically when the
+    /// device is lost and restored, as long as the game keeps the handle.
+    pub fn create_render_target(&mut self, gl: &Gl, width: u32, height: u32, internal_format: GLenum) -> RenderTargetHandle {
+        self.render_targets.create(gl, width, height, internal_format)
+    }
+
+    fn to_logical(&self, location: Location) -> Location {
+        let (x, y) = self.viewport.to_logical(location.x, location.y);
+        Location { x, y }
+    }
+
     fn request_quit(&self) -> bool {
         self.request_quit
     }
@@ -134,6 +733,7 @@ impl<L: GameLoop> Game<L> {
             game_time: Instant::now(),
             game_context: GameContext::new(android_app),
             input_events: Vec::with_capacity(10),
+            ime_composing: false,
         }
     }
 
@@ -152,6 +752,7 @@ impl<L: GameLoop> Game<L> {
             game_time: Instant::now(),
             game_context: GameContext::new(),
             input_events: Vec::with_capacity(10),
+            ime_composing: false,
         }
     }
 
@@ -176,17 +777,53 @@ impl<L: GameLoop> Game<L> {
         // init application
         let template = glutin::config::ConfigTemplateBuilder::new().with_alpha_size(8).with_transparency(cfg!(cgl_backend));
         let window = winit::window::Window::default_attributes().with_transparent(true).with_title(self.game_loop.title());
-        self.app = Some(App::new(template, window));
+        self.app = Some(App::new(template, window, self.game_loop.window_config()));
 
         // call init callback
         self.game_loop.init(&mut self.game_context);
 
+        // apply settings, the window is created on resume
+        for bus in Bus::ALL {
+            self.game_context.mixer.set_bus_volume(bus, self.game_context.config.volume(bus.name()));
+        }
+        if let Some(app) = self.app.as_mut() {
+            let config = &self.game_context.config;
+            app.set_vsync(config.vsync);
+            #[cfg(not(target_os = "android"))]
+            if let Some([width, height]) = config.resolution {
+                app.set_size(width, height);
+            }
+            #[cfg(not(target_os = "android"))]
+            if let Some([x, y]) = config.window_position {
+                app.set_position(x, y);
+            }
+            #[cfg(not(target_os = "android"))]
+            app.set_display_mode(config.display_mode);
+            #[cfg(not(target_os = "android"))]
+            app.set_monitor(config.monitor.clone());
+        }
+
         // init game time
         self.game_time = Instant::now();
 
         log::info!("Running game loop...");
         event_loop.run_app(self).unwrap();
     }
+
+    fn apply_soft_keyboard(&mut self, show: bool) {
+        self.ime_composing = false;
+        // winit only requests the keyboard implicitly on Android, which is ignored without a focused text view
+        #[cfg(target_os = "android")]
+        if show {
+            self.game_context.android_app.show_soft_input(false);
+        } else {
+            self.game_context.android_app.hide_soft_input(false);
+        }
+        #[cfg(not(target_os = "android"))]
+        if let Some(app) = self.app.as_mut() {
+            app.set_ime_allowed(show);
+        }
+    }
 }
 
 impl<L: GameLoop> ApplicationHandler for Game<L> {
@@ -194,16 +831,67 @@ impl<L: GameLoop> ApplicationHandler for Game<L> {
         log::info!("Resuming game loop ...");
         if let Some(app) = self.app.as_mut() {
             app.resume(event_loop);
+            if let Some(window) = app.window() {
+                self.game_context.platform.update_display(window);
+                self.game_context.window_position = window.outer_position().ok().map(|position| position.into());
+            }
+            self.game_context.capabilities = GlCapabilities {
+                srgb_framebuffer: app.is_srgb(),
+                ..GlCapabilities::query(app.renderer())
+            };
+            self.game_context.profiler = GpuProfiler::new(app.renderer());
+            self.game_context.render_targets.restore(app.renderer());
+            self.game_context.resources.restore(app.renderer());
             self.game_loop.create_device(&mut self.game_context, app.renderer());
         }
+        // the activity may have been opened with another intent
+        #[cfg(target_os = "android")]
+        if let Some(url) = platform::intent_url(&self.game_context.android_app).filter(|url| self.game_context.intent_url.as_ref() != Some(url)) {
+            self.game_context.intent_url = Some(url.clone());
+            self.game_context.events.send(events::UrlOpened(url));
+        }
+        // the keyboard is hidden while the app is in background
+        if self.game_context.soft_keyboard {
+            self.apply_soft_keyboard(true);
+        }
+
+        if let Some(music) = self.game_context.music.as_mut() {
+            music.set_suspended(false);
+        }
+        #[cfg(feature = "audio")]
+        match self.game_context.audio_output.as_mut() {
+            Some(output) => output.resume(),
+            None => match output::AudioOutput::new(&self.game_context.mixer) {
+                Ok(output) => self.game_context.audio_output = Some(output),
+                Err(e) => log::error!("{}", e),
+            },
+        }
     }
 
     fn suspended(&mut self, event_loop: &ActiveEventLoop) {
         log::info!("Suspending game loop ...");
         let _ = event_loop;
 
+        // the app may be killed while suspended
+        if let Some(Err(e)) = self.game_context.store.as_mut().map(|store| store.save()) {
+            log::error!("Failed to save store: {}", e);
+        }
+
+        if let Some(music) = self.game_context.music.as_mut() {
+            music.set_suspended(true);
+        }
+        #[cfg(feature = "audio")]
+        if let Some(output) = self.game_context.audio_output.as_mut() {
+            output.pause();
+        }
+
         if let Some(app) = self.app.as_mut() {
             self.game_loop.destroy_device(&mut self.game_context, app.renderer());
+            self.game_context.profiler.release();
+            self.game_context.render_targets.release();
+            self.game_context.resources.release();
+            self.game_context.overlay.release();
+            self.game_context.debug_draw.release();
             app.suspend();
         }
     }
@@ -213,7 +901,17 @@ impl<L: GameLoop> ApplicationHandler for Game<L> {
             WindowEvent::RedrawRequested => {
                 if let Some(app) = self.app.as_mut() {
                     if app.has_surface_and_context() {
-                        self.game_loop.render(&mut self.game_context, app.renderer());
+                        let ctx = &mut self.game_context;
+                        if let Some(assets) = ctx.assets.as_mut() {
+                            assets.update(app.renderer(), &mut ctx.resources);
+                        }
+                        ctx.profiler.begin_frame();
+                        GlDrawStats::take();
+                        self.game_loop.render(ctx, app.renderer());
+                        ctx.debug_draw.flush(app.renderer(), &ctx.camera);
+                        ctx.overlay.record_stats(GlDrawStats::take());
+                        ctx.overlay.render(app.renderer(), ctx.viewport.size(), ctx.viewport.viewport(), ctx.profiler.results());
+                        ctx.profiler.end_frame();
                         app.swap_buffers();
                     }
                 }
@@ -222,12 +920,16 @@ impl<L: GameLoop> ApplicationHandler for Game<L> {
                 if let Some(app) = self.app.as_mut() {
                     if app.has_surface_and_context() {
                         app.resize(size);
+                        self.game_context.viewport.resize(app.renderer(), size.width, size.height);
+                        let (width, height) = self.game_context.viewport.logical_size();
+                        self.game_context.camera.resize(width, height);
                         self.game_loop.resize_device(&mut self.game_context, app.renderer(), size.width, size.height);
                     }
                 }
             }
             WindowEvent::CursorMoved { position, .. } => {
-                self.input_events.push(InputEvent::Cursor(CursorEvent { location: position.into() }));
+                let location = self.game_context.to_logical(position.into());
+                self.input_events.push(InputEvent::Cursor(CursorEvent { location }));
             }
             WindowEvent::MouseInput { state, button, .. } => {
                 self.input_events.push(InputEvent::Mouse(MouseEvent {
@@ -236,13 +938,44 @@ impl<L: GameLoop> ApplicationHandler for Game<L> {
                 }));
             }
             WindowEvent::Touch(touch) => {
-                self.input_events.push(InputEvent::Touch(touch.into()));
+                let mut touch: TouchEvent = touch.into();
+                touch.location = self.game_context.to_logical(touch.location);
+                self.input_events.push(InputEvent::Touch(touch));
             }
             WindowEvent::KeyboardInput { event, .. } => {
+                // control characters like backspace or enter stay key events
+                let text = event.text.as_ref().filter(|_| event.state.is_pressed()).map(|text| text.chars().filter(|c| !c.is_control()).collect::<String>());
+                if let Some(text) = text.filter(|text| self.game_context.soft_keyboard && !self.ime_composing && !text.is_empty()) {
+                    self.game_context.events.send(TextInput::Commit(text));
+                }
                 if let Ok(event) = event.try_into() {
                     self.input_events.push(InputEvent::Keyboard(event));
                 }
             }
+            WindowEvent::Ime(ime) if self.game_context.soft_keyboard => match ime {
+                Ime::Preedit(text, _) => {
+                    self.ime_composing = !text.is_empty();
+                    self.game_context.events.send(TextInput::Preedit(text));
+                }
+                Ime::Commit(text) => {
+                    self.ime_composing = false;
+                    self.game_context.events.send(TextInput::Commit(text));
+                }
+                Ime::Enabled | Ime::Disabled => self.ime_composing = false,
+            },
+            WindowEvent::Moved(position) => {
+                self.game_context.window_position = Some(position.into());
+                self.game_context.events.send(events::WindowMoved(position.into()));
+                // the window may have moved to another screen
+                if let Some(window) = self.app.as_ref().and_then(|app| app.window()) {
+                    self.game_context.platform.update_display(window);
+                }
+            }
+            WindowEvent::ScaleFactorChanged { .. } => {
+                if let Some(window) = self.app.as_ref().and_then(|app| app.window()) {
+                    self.game_context.platform.update_display(window);
+                }
+            }
             WindowEvent::CloseRequested => event_loop.exit(),
             _ => (),
         }
@@ -256,12 +989,76 @@ impl<L: GameLoop> ApplicationHandler for Game<L> {
         let elapsed_time = new_time.duration_since(self.game_time).as_millis() as f32 / 1000.0;
         self.game_time = new_time;
 
+        self.game_context.events.update(elapsed_time);
+        #[cfg(feature = "audio")]
+        if let Some(output) = self.game_context.audio_output.as_mut() {
+            output.update();
+        }
+        #[cfg(not(target_os = "android"))]
+        self.game_context.send_asset_events();
+        if let Some(asset_packs) = self.game_context.asset_packs.as_mut() {
+            asset_packs.update(&mut self.game_context.events, elapsed_time);
+        }
+        if let Some(permissions) = self.game_context.permissions.as_mut() {
+            permissions.update(&mut self.game_context.events, elapsed_time);
+        }
+        self.game_context.overlay.record_frame(elapsed_time);
+        self.game_context.overlay.input(&self.input_events);
+        self.game_context.time.advance(elapsed_time);
+        self.game_context.input.update(&self.input_events);
+
         // call input callback
         self.game_loop.input(&mut self.game_context, &self.input_events);
         self.input_events.clear();
 
         // call update callback
         self.game_loop.update(&mut self.game_context, elapsed_time);
+        self.game_context.tasks.update(&mut self.game_context.events, elapsed_time);
+        if self.game_context.listener_follows_camera {
+            self.game_context.mixer.set_listener((&self.game_context.camera).into());
+        }
+
+        // apply window changes requested during the frame
+        let requested_size = self.game_context.requested_size.take();
+        let requested_position = self.game_context.requested_position.take();
+        let requested_display_mode = self.game_context.requested_display_mode.take();
+        let requested_orientation = self.game_context.requested_orientation.take();
+        let requested_monitor = self.game_context.requested_monitor.take();
+        let requested_cursor = self.game_context.requested_cursor.take();
+        let requested_window_level = self.game_context.requested_window_level.take();
+        #[cfg(not(target_os = "android"))]
+        if let Some(app) = self.app.as_mut() {
+            if let Some(level) = requested_window_level {
+                app.set_window_level(level);
+            }
+            if let Some(cursor) = requested_cursor.as_ref() {
+                app.set_cursor(event_loop, cursor, &self.game_context.files());
+            }
+            if let Some(monitor) = requested_monitor {
+                app.set_monitor(monitor);
+            }
+            if let Some(orientation) = requested_orientation {
+                app.set_orientation(orientation);
+            }
+            if let Some((width, height)) = requested_size {
+                app.set_size(width, height);
+            }
+            if let Some([x, y]) = requested_position {
+                app.set_position(x, y);
+            }
+            if let Some(display_mode) = requested_display_mode.filter(|display_mode| *display_mode != app.display_mode()) {
+                app.set_display_mode(display_mode);
+            }
+        }
+        #[cfg(target_os = "android")]
+        if let Some(orientation) = requested_orientation {
+            platform::set_orientation(&self.game_context.android_app, orientation);
+        }
+        #[cfg(target_os = "android")]
+        let _ = (requested_size, requested_position, requested_display_mode, requested_monitor, requested_cursor, requested_window_level);
+        if let Some(show) = self.game_context.requested_soft_keyboard.take() {
+            self.apply_soft_keyboard(show);
+        }
 
         if self.game_context.request_quit() {
             event_loop.exit();
@@ -0,0 +1,155 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use image::RgbaImage;
+
+use crate::file::Files;
+use crate::opengl::*;
+use crate::resources::{GpuResources, Handle};
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Decodes assets on worker threads and uploads them on the main thread within a time budget per frame,
+/// so loading large levels does not stall the game loop.
+#[derive(Debug)]
+pub struct AssetLoader {
+    sender: Option<Sender<LoadJob>>,
+    receiver: Receiver<(AssetRequest, Result<RgbaImage, String>)>,
+    decoded: VecDeque<(AssetRequest, RgbaImage)>,
+    states: HashMap<AssetRequest, LoadState>,
+    next_request: u64,
+    upload_budget: Duration,
+}
+
+/// Ticket of a pending load, query it with `AssetLoader::state`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AssetRequest(u64);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadState {
+    Loading,
+    /// Texture in `GpuResources`, recreated after device loss like every other pooled texture.
+    Ready(Handle<GlTexture>),
+    Failed(String),
+}
+
+#[derive(Debug)]
+struct LoadJob {
+    request: AssetRequest,
+    filename: String,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl AssetLoader {
+    /// Starts `workers` decoding threads reading from `files`.
+    pub fn new(files: Files, workers: usize) -> AssetLoader {
+        let files = Arc::new(files);
+        let (sender, jobs) = channel::<LoadJob>();
+        let (results, receiver) = channel();
+        let jobs = Arc::new(Mutex::new(jobs));
+        (0..workers.max(1)).for_each(|i| {
+            let (files, jobs, results) = (files.clone(), jobs.clone(), results.clone());
+            let spawned = std::thread::Builder::new().name(format!("asset-loader-{}", i)).spawn(move || loop {
+                // the lock is only held while waiting for the next job, decoding runs in parallel
+                let job = jobs.lock().map_err(|_| ()).and_then(|jobs| jobs.recv().map_err(|_| ()));
+                let Ok(job) = job else { break };
+                let image = files
+                    .load_bytes(&job.filename)
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| image::load_from_memory(&bytes).map_err(|e| e.to_string()))
+                    .map(|image| image.to_rgba8());
+                if results.send((job.request, image)).is_err() {
+                    break;
+                }
+            });
+            if let Err(e) = spawned {
+                log::error!("Failed to start asset loader thread: {}", e);
+            }
+        });
+        AssetLoader {
+            sender: Some(sender),
+            receiver,
+            decoded: VecDeque::new(),
+            states: HashMap::new(),
+            next_request: 0,
+            upload_budget: Duration::from_millis(4),
+        }
+    }
+
+    /// Queues an image to be decoded and uploaded as single layer texture.
+    pub fn load_texture(&mut self, filename: &str) -> AssetRequest {
+        let request = AssetRequest(self.next_request);
+        self.next_request += 1;
+        let job = LoadJob {
+            request,
+            filename: filename.to_string(),
+        };
+        let state = match self.sender.as_ref().map(|sender| sender.send(job)) {
+            Some(Ok(())) => LoadState::Loading,
+            _ => LoadState::Failed("Asset loader stopped".to_string()),
+        };
+        self.states.insert(request, state);
+        request
+    }
+
+    /// `None` for requests that were forgotten.
+    pub fn state(&self, request: AssetRequest) -> Option<&LoadState> {
+        self.states.get(&request)
+    }
+
+    /// Texture of a finished request.
+    pub fn texture(&self, request: AssetRequest) -> Option<Handle<GlTexture>> {
+        match self.states.get(&request) {
+            Some(LoadState::Ready(texture)) => Some(*texture),
+            _ => None,
+        }
+    }
+
+    /// Drops the state of a request, the texture stays in `GpuResources`.
+    pub fn forget(&mut self, request: AssetRequest) {
+        self.states.remove(&request);
+    }
+
+    /// Number of requests still decoding or waiting for upload.
+    pub fn pending(&self) -> usize {
+        self.states.values().filter(|state| **state == LoadState::Loading).count()
+    }
+
+    /// Time spent uploading per frame, at least one texture is uploaded per frame.
+    pub fn set_upload_budget(&mut self, budget: Duration) {
+        self.upload_budget = budget;
+    }
+
+    /// Collects decoded assets and uploads as many as the budget allows. Called by the game loop before `render`.
+    pub fn update(&mut self, gl: &Gl, resources: &mut GpuResources) {
+        for (request, result) in self.receiver.try_iter() {
+            match result {
+                Ok(image) => self.decoded.push_back((request, image)),
+                Err(e) => {
+                    log::error!("Failed to load asset: {}", e);
+                    self.states.insert(request, LoadState::Failed(e));
+                }
+            }
+        }
+        let start = Instant::now();
+        while let Some((request, image)) = self.decoded.pop_front() {
+            // forgotten requests are dropped, nobody could find their texture
+            let Some(state) = self.states.get_mut(&request) else {
+                continue;
+            };
+            *state = LoadState::Ready(resources.load_texture(gl, vec![image]));
+            if start.elapsed() >= self.upload_budget {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::cell::{RefCell, RefMut};
+use std::rc::Rc;
+
+use crate::gl;
+use crate::gl::types::*;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Types
+
+/// Shader shared between materials.
+pub type ShaderHandle = Rc<RefCell<GlShader>>;
+
+/// Texture shared between materials.
+pub type TextureHandle = Rc<RefCell<GlTexture>>;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Shader, textures, uniform block values and render state needed to draw something.
+/// `T` is the std140 layout of the material's uniform block, `()` for materials without one.
+#[derive(Debug)]
+pub struct Material<T: Default = ()> {
+    shader: ShaderHandle,
+    textures: Vec<TextureBinding>,
+    uniforms: Option<UniformBinding<T>>,
+    render_state: GlRenderState,
+}
+
+#[derive(Debug)]
+struct TextureBinding {
+    unit: GLuint,
+    name: String,
+    texture: TextureHandle,
+}
+
+#[derive(Debug)]
+struct UniformBinding<T: Default> {
+    unit: GLuint,
+    name: String,
+    buffer: GlUniformBuffer<T>,
+    value: T,
+    dirty: bool,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl<T: Default> Material<T> {
+    pub fn new(shader: ShaderHandle) -> Material<T> {
+        Material {
+            shader,
+            textures: Vec::new(),
+            uniforms: None,
+            render_state: GlRenderState::opaque(),
+        }
+    }
+
+    /// Binds `texture` to `unit` and the sampler `name`, replacing any texture on that unit.
+    pub fn with_texture(mut self, unit: GLuint, name: &str, texture: TextureHandle) -> Self {
+        self.set_texture(unit, name, texture);
+        self
+    }
+
+    /// Gives the material its own uniform buffer, bound to `unit` and the uniform block `name`.
+    pub fn with_uniforms(mut self, gl: &Gl, unit: GLuint, name: &str, value: T) -> Self {
+        self.uniforms = Some(UniformBinding {
+            unit,
+            name: name.to_string(),
+            buffer: GlUniformBuffer::new(gl, gl::DYNAMIC_DRAW, &value),
+            value,
+            dirty: false,
+        });
+        self
+    }
+
+    pub fn with_render_state(mut self, render_state: GlRenderState) -> Self {
+        self.render_state = render_state;
+        self
+    }
+
+    pub fn set_texture(&mut self, unit: GLuint, name: &str, texture: TextureHandle) {
+        self.textures.retain(|binding| binding.unit != unit);
+        self.textures.push(TextureBinding { unit, name: name.to_string(), texture });
+        self.textures.sort_by_key(|binding| binding.unit);
+    }
+
+    pub fn uniforms(&self) -> Option<&T> {
+        self.uniforms.as_ref().map(|uniforms| &uniforms.value)
+    }
+
+    /// Mutable uniform values, uploaded on the next `bind`.
+    pub fn uniforms_mut(&mut self) -> Option<&mut T> {
+        self.uniforms.as_mut().map(|uniforms| {
+            uniforms.dirty = true;
+            &mut uniforms.value
+        })
+    }
+
+    pub fn render_state(&self) -> &GlRenderState {
+        &self.render_state
+    }
+
+    pub fn shader(&self) -> RefMut<'_, GlShader> {
+        self.shader.borrow_mut()
+    }
+
+    /// Key for sorting draws: opaque before blended, then by shader, then by first texture.
+    /// Draws with equal keys share all bindings except uniforms and can be merged into instanced draws.
+    pub fn sort_key(&self) -> u64 {
+        let blended = self.render_state.blend.is_some() as u64;
+        let shader = self.shader.borrow().id() as u64 & 0x7FFF_FFFF;
+        let texture = self.textures.first().map(|binding| binding.texture.borrow().id()).unwrap_or(0) as u64;
+        (blended << 63) | (shader << 32) | texture
+    }
+
+    /// Applies render state and binds shader, textures and uniforms. `current` is the state set by the previous material, if any.
+    pub fn bind(&mut self, gl: &Gl, current: Option<&GlRenderState>) {
+        self.render_state.apply(gl, current);
+        let mut shader = self.shader.borrow_mut();
+        shader.bind();
+        self.textures.iter().for_each(|binding| {
+            binding.texture.borrow_mut().bind(binding.unit);
+            shader.link_texture(binding.unit as GLint, &binding.name);
+        });
+        if let Some(uniforms) = self.uniforms.as_mut() {
+            if uniforms.dirty {
+                uniforms.buffer.update(&uniforms.value);
+                uniforms.dirty = false;
+            }
+            uniforms.buffer.bind(uniforms.unit);
+            shader.link_uniform(uniforms.unit, &uniforms.name);
+        }
+    }
+
+    pub fn unbind(&mut self) {
+        if let Some(uniforms) = self.uniforms.as_mut() {
+            uniforms.buffer.unbind();
+        }
+        self.textures.iter().for_each(|binding| binding.texture.borrow_mut().unbind());
+        self.shader.borrow_mut().unbind();
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl<T: Default> GlResource for Material<T> {
+    fn release(&mut self) {
+        if let Some(uniforms) = self.uniforms.as_mut() {
+            uniforms.buffer.release();
+        }
+    }
+}
+impl<T: Default> Drop for Material<T> {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
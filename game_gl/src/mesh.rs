@@ -0,0 +1,98 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::mem::size_of;
+
+use crate::gl;
+use crate::gl::types::*;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Mesh with per-vertex and per-instance data, drawn with a single instanced draw call.
+#[derive(Debug, Default)]
+pub struct InstancedMesh<V: Default, I: Default> {
+    vao: GlVertexArrayObject,
+    vertices: GlVertexBuffer<V>,
+    instances: GlVertexBuffer<I>,
+    indices: GlIndexBuffer,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl<V: Default, I: Default> InstancedMesh<V, I> {
+    pub fn new(gl: &Gl, vertices: &[V], indices: &[u32], max_instances: usize) -> InstancedMesh<V, I> {
+        let mut instances = GlVertexBuffer::new(gl, gl::DYNAMIC_DRAW, &[]);
+        instances.reserve(max_instances);
+        InstancedMesh {
+            vao: GlVertexArrayObject::new(gl),
+            vertices: GlVertexBuffer::new(gl, gl::STATIC_DRAW, vertices),
+            instances,
+            indices: GlIndexBuffer::new(gl, gl::STATIC_DRAW, indices),
+        }
+    }
+
+    /// Binds a per-vertex attribute. Stride is the size of `V`.
+    pub fn vertex_attrib(&mut self, slot: GLuint, count: GLint, type_: GLenum, normalized: GLboolean, offset: usize) {
+        self.vao.bind();
+        self.vao.bind_attrib(&self.vertices, slot, count, type_, normalized, offset, size_of::<V>(), 0);
+        self.vao.unbind();
+    }
+
+    /// Binds a per-instance attribute. Stride is the size of `I`, attributes advance once per instance.
+    /// Matrices need one call per column, each with its own slot and offset.
+    pub fn instance_attrib(&mut self, slot: GLuint, count: GLint, type_: GLenum, normalized: GLboolean, offset: usize) {
+        self.vao.bind();
+        self.vao.bind_attrib(&self.instances, slot, count, type_, normalized, offset, size_of::<I>(), 1);
+        self.vao.unbind();
+    }
+
+    pub fn update_vertices(&mut self, vertices: &[V]) {
+        self.vertices.update(vertices);
+    }
+
+    pub fn update_indices(&mut self, indices: &[u32]) {
+        self.indices.update(indices);
+    }
+
+    /// Uploads instance data, growing the instance buffer if needed.
+    pub fn update_instances(&mut self, instances: &[I]) {
+        self.instances.update(instances);
+    }
+
+    pub fn instance_count(&self) -> usize {
+        self.instances.count()
+    }
+
+    /// Draws all instances of the last update. The shader must be bound.
+    pub fn draw(&mut self, shader: &mut GlShader, mode: GLenum) {
+        if self.instances.count() == 0 {
+            return;
+        }
+        self.vao.bind();
+        self.indices.bind();
+        shader.draw_elements_instanced(mode, self.indices.count(), self.instances.count());
+        self.indices.unbind();
+        self.vao.unbind();
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl<V: Default, I: Default> GlResource for InstancedMesh<V, I> {
+    fn release(&mut self) {
+        self.vao.release();
+        self.vertices.release();
+        self.instances.release();
+        self.indices.release();
+    }
+}
+impl<V: Default, I: Default> Drop for InstancedMesh<V, I> {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
@@ -0,0 +1,483 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use nalgebra_glm as glm;
+
+use crate::audio::SoundBuffer;
+use crate::camera::{Camera2D, Camera3D};
+
+//////////////////////////////////////////////////
+// Definition
+
+// voices playing at once unless changed with `set_max_voices`
+const DEFAULT_MAX_VOICES: usize = 32;
+
+/// Software mixer summing all playing voices into the output stream. Cloning shares the mixer, so the game and the
+/// audio backend can hold it at the same time. Voices can be grouped to cap their polyphony, e.g. to limit footsteps
+/// to four voices while explosions keep playing.
+#[derive(Clone)]
+pub struct Mixer {
+    state: Arc<Mutex<MixerState>>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VoiceId(u64);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayParams {
+    pub gain: f32,
+    /// Playback speed, 2.0 plays an octave higher.
+    pub pitch: f32,
+    pub looping: bool,
+    /// Polyphony group, see `Mixer::set_group_limit`.
+    pub group: Option<String>,
+    pub bus: Bus,
+    /// Stereo balance from -1 (left) to 1 (right).
+    pub pan: f32,
+    /// World position panned and attenuated relative to the listener, `None` plays the sound as is.
+    pub position: Option<glm::Vec3>,
+}
+
+/// Ears of the player, usually following the camera. 2D games use z = 0.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Listener {
+    pub position: glm::Vec3,
+    /// Normalized direction of the right ear.
+    pub right: glm::Vec3,
+}
+
+/// Inverse distance attenuation of positioned voices, in world units.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Attenuation {
+    /// Distance with full volume.
+    pub reference_distance: f32,
+    /// Distance beyond which the volume doesn't drop further.
+    pub max_distance: f32,
+    /// How fast the volume drops, 0 disables attenuation.
+    pub rolloff: f32,
+}
+
+/// Volume group of a voice or source. All buses are scaled by `Master`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Bus {
+    Master,
+    Music,
+    #[default]
+    Sfx,
+    Voice,
+}
+
+/// Samples produced while mixing, e.g. streamed music. Sources are not limited by the polyphony caps.
+pub trait AudioSource: Send {
+    /// Adds interleaved samples to `output`, returns false once the source finished.
+    fn mix(&mut self, output: &mut [f32], sample_rate: u32, channels: usize) -> bool;
+
+    /// Bus scaling the samples.
+    fn bus(&self) -> Bus {
+        Bus::Sfx
+    }
+}
+
+/// Voice to drop when a limit is reached.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum StealPolicy {
+    #[default]
+    Oldest,
+    Quietest,
+    /// Keeps the playing voices and drops the new one.
+    Reject,
+}
+
+struct MixerState {
+    sample_rate: u32,
+    channels: u16,
+    // oldest first
+    voices: Vec<Voice>,
+    next_id: u64,
+    max_voices: usize,
+    policy: StealPolicy,
+    groups: HashMap<String, (usize, StealPolicy)>,
+    sources: Vec<Box<dyn AudioSource>>,
+    // sources are mixed separately to apply their bus volume
+    source_buffer: Vec<f32>,
+    bus_volumes: HashMap<Bus, f32>,
+    listener: Listener,
+    attenuation: Attenuation,
+}
+
+struct Voice {
+    id: VoiceId,
+    sound: SoundBuffer,
+    // in frames of the sound
+    position: f64,
+    gain: f32,
+    pitch: f32,
+    looping: bool,
+    group: Option<String>,
+    bus: Bus,
+    pan: f32,
+    world_position: Option<glm::Vec3>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Mixer {
+    /// Output format until a backend sets the format of its device.
+    pub fn new(sample_rate: u32, channels: u16) -> Mixer {
+        let state = MixerState {
+            sample_rate,
+            channels,
+            voices: Vec::new(),
+            next_id: 0,
+            max_voices: DEFAULT_MAX_VOICES,
+            policy: StealPolicy::default(),
+            groups: HashMap::new(),
+            sources: Vec::new(),
+            source_buffer: Vec::new(),
+            bus_volumes: HashMap::new(),
+            listener: Listener::default(),
+            attenuation: Attenuation::default(),
+        };
+        Mixer { state: Arc::new(Mutex::new(state)) }
+    }
+
+    /// Starts a voice, `None` if it was rejected by a polyphony limit.
+    pub fn play(&self, sound: &SoundBuffer, params: PlayParams) -> Option<VoiceId> {
+        let mut state = self.lock();
+        if let Some(group) = params.group.as_ref() {
+            if let Some(&(limit, policy)) = state.groups.get(group) {
+                let count = state.voices.iter().filter(|voice| voice.group.as_ref() == Some(group)).count();
+                if count >= limit && !state.steal(policy, |voice| voice.group.as_ref() == Some(group)) {
+                    return None;
+                }
+            }
+        }
+        let policy = state.policy;
+        if state.voices.len() >= state.max_voices && !state.steal(policy, |_| true) {
+            return None;
+        }
+        let id = VoiceId(state.next_id);
+        state.next_id += 1;
+        state.voices.push(Voice {
+            id,
+            sound: sound.clone(),
+            position: 0.0,
+            gain: params.gain,
+            pitch: params.pitch,
+            looping: params.looping,
+            group: params.group,
+            bus: params.bus,
+            pan: params.pan,
+            world_position: params.position,
+        });
+        Some(id)
+    }
+
+    pub fn stop(&self, voice: VoiceId) {
+        self.lock().voices.retain(|other| other.id != voice);
+    }
+
+    /// Mixes a source until it finished.
+    pub fn add_source(&self, source: impl AudioSource + 'static) {
+        self.lock().sources.push(Box::new(source));
+    }
+
+    /// Stops all voices, sources keep playing.
+    pub fn stop_all(&self) {
+        self.lock().voices.clear();
+    }
+
+    /// False once the voice finished or was stopped.
+    pub fn is_playing(&self, voice: VoiceId) -> bool {
+        self.lock().voices.iter().any(|other| other.id == voice)
+    }
+
+    pub fn set_gain(&self, voice: VoiceId, gain: f32) {
+        self.with_voice(voice, |voice| voice.gain = gain);
+    }
+
+    pub fn set_pitch(&self, voice: VoiceId, pitch: f32) {
+        self.with_voice(voice, |voice| voice.pitch = pitch);
+    }
+
+    pub fn set_pan(&self, voice: VoiceId, pan: f32) {
+        self.with_voice(voice, |voice| voice.pan = pan);
+    }
+
+    /// Moves a positioned voice, e.g. with the entity playing it.
+    pub fn set_position(&self, voice: VoiceId, position: Option<glm::Vec3>) {
+        self.with_voice(voice, |voice| voice.world_position = position);
+    }
+
+    /// Listener of positioned voices. `GameContext` moves it with its 2D camera unless
+    /// `GameContext::set_listener_follows_camera` disabled it.
+    pub fn set_listener(&self, listener: Listener) {
+        self.lock().listener = listener;
+    }
+
+    pub fn listener(&self) -> Listener {
+        self.lock().listener
+    }
+
+    pub fn set_attenuation(&self, attenuation: Attenuation) {
+        self.lock().attenuation = attenuation;
+    }
+
+    /// Volume of a bus, multiplied with the master volume for other buses.
+    pub fn set_bus_volume(&self, bus: Bus, volume: f32) {
+        self.lock().bus_volumes.insert(bus, volume.max(0.0));
+    }
+
+    /// Volume of a bus as set, 1.0 by default.
+    pub fn bus_volume(&self, bus: Bus) -> f32 {
+        self.lock().bus_volume(bus)
+    }
+
+    /// Number of playing voices.
+    pub fn voices(&self) -> usize {
+        self.lock().voices.len()
+    }
+
+    /// Caps all voices, 32 by default.
+    pub fn set_max_voices(&self, max_voices: usize, policy: StealPolicy) {
+        let mut state = self.lock();
+        state.max_voices = max_voices;
+        state.policy = policy;
+    }
+
+    /// Caps the voices of a group, `None` removes the limit.
+    pub fn set_group_limit(&self, group: &str, limit: Option<(usize, StealPolicy)>) {
+        let mut state = self.lock();
+        match limit {
+            Some(limit) => state.groups.insert(group.to_string(), limit),
+            None => state.groups.remove(group),
+        };
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.lock().sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.lock().channels
+    }
+
+    /// Output format, called by audio backends. Playing voices are resampled.
+    pub fn set_format(&self, sample_rate: u32, channels: u16) {
+        let mut state = self.lock();
+        state.sample_rate = sample_rate;
+        state.channels = channels.max(1);
+    }
+
+    /// Fills interleaved output samples, called by audio backends.
+    pub fn mix(&self, output: &mut [f32]) {
+        output.iter_mut().for_each(|sample| *sample = 0.0);
+        let mut state = self.lock();
+        let state = &mut *state;
+        let (sample_rate, channels, listener, attenuation) = (state.sample_rate, state.channels as usize, state.listener, state.attenuation);
+        let volumes = Bus::ALL.map(|bus| state.output_volume(bus));
+        state.voices.retain_mut(|voice| {
+            let (gain, pan) = listener.spatialize(voice.world_position, &attenuation);
+            voice.mix(output, sample_rate, channels, voice.gain * gain * volumes[voice.bus as usize], (voice.pan + pan).clamp(-1.0, 1.0))
+        });
+        let buffer = &mut state.source_buffer;
+        state.sources.retain_mut(|source| {
+            buffer.clear();
+            buffer.resize(output.len(), 0.0);
+            let playing = source.mix(buffer, sample_rate, channels);
+            let volume = volumes[source.bus() as usize];
+            output.iter_mut().zip(buffer.iter()).for_each(|(sample, value)| *sample += value * volume);
+            playing
+        });
+        output.iter_mut().for_each(|sample| *sample = sample.clamp(-1.0, 1.0));
+    }
+
+    fn with_voice(&self, voice: VoiceId, f: impl FnOnce(&mut Voice)) {
+        if let Some(voice) = self.lock().voices.iter_mut().find(|other| other.id == voice) {
+            f(voice);
+        }
+    }
+
+    // a panicking game thread must not silence the audio thread
+    fn lock(&self) -> MutexGuard<'_, MixerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Bus {
+    pub const ALL: [Bus; 4] = [Bus::Master, Bus::Music, Bus::Sfx, Bus::Voice];
+
+    /// Name in `Config::volumes`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Bus::Master => "master",
+            Bus::Music => "music",
+            Bus::Sfx => "sfx",
+            Bus::Voice => "voice",
+        }
+    }
+}
+
+impl MixerState {
+    fn bus_volume(&self, bus: Bus) -> f32 {
+        self.bus_volumes.get(&bus).copied().unwrap_or(1.0)
+    }
+
+    // bus volume scaled by the master volume
+    fn output_volume(&self, bus: Bus) -> f32 {
+        match bus {
+            Bus::Master => self.bus_volume(Bus::Master),
+            bus => self.bus_volume(bus) * self.bus_volume(Bus::Master),
+        }
+    }
+
+    // drops a voice matching `filter` to make room, false if none may be dropped
+    fn steal(&mut self, policy: StealPolicy, filter: impl Fn(&Voice) -> bool) -> bool {
+        let candidates = self.voices.iter().enumerate().filter(|(_, voice)| filter(voice));
+        let index = match policy {
+            StealPolicy::Oldest => candidates.map(|(index, _)| index).next(),
+            StealPolicy::Quietest => candidates.min_by(|(_, a), (_, b)| a.gain.total_cmp(&b.gain)).map(|(index, _)| index),
+            StealPolicy::Reject => None,
+        };
+        index.map(|index| self.voices.remove(index)).is_some()
+    }
+}
+
+impl Listener {
+    // gain and pan of a voice at `position`
+    fn spatialize(&self, position: Option<glm::Vec3>, attenuation: &Attenuation) -> (f32, f32) {
+        let Some(position) = position else { return (1.0, 0.0) };
+        let offset = position - self.position;
+        let distance = glm::length(&offset);
+        let pan = if distance > f32::EPSILON { glm::dot(&(offset / distance), &self.right) } else { 0.0 };
+        (attenuation.gain(distance), pan)
+    }
+}
+
+impl Attenuation {
+    pub fn gain(&self, distance: f32) -> f32 {
+        let reference = self.reference_distance.max(f32::EPSILON);
+        let distance = distance.clamp(reference, self.max_distance.max(reference));
+        reference / (reference + self.rolloff * (distance - reference))
+    }
+}
+
+impl Voice {
+    // adds the voice to the output, false once it finished. Panning balances the first two output channels.
+    fn mix(&mut self, output: &mut [f32], sample_rate: u32, channels: usize, gain: f32, pan: f32) -> bool {
+        let balance = [(1.0 - pan).min(1.0), (1.0 + pan).min(1.0)];
+        let source_channels = self.sound.channels.max(1) as usize;
+        let frames = self.sound.samples.len() / source_channels;
+        if frames == 0 {
+            return false;
+        }
+        let step = self.pitch.max(0.0) as f64 * self.sound.sample_rate as f64 / sample_rate.max(1) as f64;
+        for frame in output.chunks_mut(channels) {
+            if self.position >= frames as f64 {
+                if !self.looping {
+                    return false;
+                }
+                self.position %= frames as f64;
+            }
+            let index = self.position as usize;
+            let next = if index + 1 < frames { index + 1 } else if self.looping { 0 } else { index };
+            let t = self.position.fract() as f32;
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let value = |frame: usize| -> f32 {
+                    let samples = &self.sound.samples[frame * source_channels..(frame + 1) * source_channels];
+                    // mono output mixes all source channels down, otherwise channels repeat
+                    if channels == 1 {
+                        samples.iter().sum::<f32>() / source_channels as f32
+                    } else {
+                        samples[channel % source_channels]
+                    }
+                };
+                let balance = if channels > 1 { balance.get(channel).copied().unwrap_or(1.0) } else { 1.0 };
+                *sample += (value(index) * (1.0 - t) + value(next) * t) * gain * balance;
+            }
+            self.position += step;
+        }
+        true
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl Default for Mixer {
+    /// Stereo at 48 kHz.
+    fn default() -> Mixer {
+        Mixer::new(48000, 2)
+    }
+}
+
+impl Default for PlayParams {
+    fn default() -> PlayParams {
+        PlayParams {
+            gain: 1.0,
+            pitch: 1.0,
+            looping: false,
+            group: None,
+            bus: Bus::default(),
+            pan: 0.0,
+            position: None,
+        }
+    }
+}
+
+impl Default for Listener {
+    fn default() -> Listener {
+        Listener {
+            position: glm::Vec3::zeros(),
+            right: glm::Vec3::x(),
+        }
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Attenuation {
+        Attenuation {
+            reference_distance: 1.0,
+            max_distance: 100.0,
+            rolloff: 1.0,
+        }
+    }
+}
+
+impl From<&Camera2D> for Listener {
+    fn from(camera: &Camera2D) -> Listener {
+        Listener {
+            position: glm::vec3(camera.position[0], camera.position[1], 0.0),
+            right: glm::vec3(camera.rotation.cos(), camera.rotation.sin(), 0.0),
+        }
+    }
+}
+
+impl From<&Camera3D> for Listener {
+    fn from(camera: &Camera3D) -> Listener {
+        let up = match camera.view {
+            crate::camera::CameraView::LookAt { up, .. } => up,
+            crate::camera::CameraView::Rotation(rotation) => glm::quat_rotate_vec3(&rotation, &glm::Vec3::y()),
+        };
+        Listener {
+            position: camera.position,
+            right: glm::normalize(&glm::cross(&camera.forward(), &up)),
+        }
+    }
+}
+
+impl std::fmt::Debug for Mixer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.lock();
+        f.debug_struct("Mixer")
+            .field("sample_rate", &state.sample_rate)
+            .field("channels", &state.channels)
+            .field("voices", &state.voices.len())
+            .field("sources", &state.sources.len())
+            .finish()
+    }
+}
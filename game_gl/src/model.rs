@@ -0,0 +1,450 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::cell::RefCell;
+use std::fmt;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use base64::Engine;
+use nalgebra_glm as glm;
+
+use crate::animation::*;
+use crate::file::{FileError, Files};
+use crate::gl;
+use crate::gl::types::*;
+use crate::material::TextureHandle;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+#[derive(Debug)]
+pub enum ModelError {
+    /// The model or one of its buffers or images could not be read.
+    File(FileError),
+    /// The model or one of its images could not be parsed.
+    Parse(String),
+    /// Buffers or textures could not be created.
+    Gl(GlError),
+}
+
+/// Meshes, materials, textures, node hierarchy, skins and animations of a glTF 2.0 file.
+#[derive(Debug, Default)]
+pub struct Model {
+    pub meshes: Vec<ModelMesh>,
+    pub materials: Vec<ModelMaterial>,
+    pub textures: Vec<TextureHandle>,
+    pub nodes: Vec<ModelNode>,
+    pub skins: Vec<ModelSkin>,
+    pub animations: Vec<Animation>,
+}
+
+#[derive(Debug, Default)]
+pub struct ModelMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<ModelPrimitive>,
+}
+
+/// Indexed geometry with a single material. Attributes are bound to slots 0 (position), 1 (normal), 2 (tex coord) and 3 (tangent),
+/// skinned primitives add 4 (joints) and 5 (weights).
+#[derive(Debug, Default)]
+pub struct ModelPrimitive {
+    vao: GlVertexArrayObject,
+    vertices: GlVertexBuffer<ModelVertex>,
+    skin: Option<GlVertexBuffer<ModelSkinVertex>>,
+    indices: GlIndexBuffer,
+    mode: GLenum,
+    /// Index into `Model::materials`.
+    pub material: Option<usize>,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ModelVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coord: [f32; 2],
+    /// Tangent with handedness in w.
+    pub tangent: [f32; 4],
+}
+
+/// Joint indices and weights, joints as floats for plain float attributes.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ModelSkinVertex {
+    pub joints: [f32; 4],
+    pub weights: [f32; 4],
+}
+
+/// Metallic-roughness material parameters. Texture fields index into `Model::textures`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelMaterial {
+    pub name: Option<String>,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<usize>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<usize>,
+    pub normal_texture: Option<usize>,
+    pub occlusion_texture: Option<usize>,
+    pub emissive_factor: [f32; 3],
+    pub emissive_texture: Option<usize>,
+    pub alpha_cutoff: Option<f32>,
+    pub blend: bool,
+    pub double_sided: bool,
+}
+
+//////////////////////////////////////////////////
+// Loading
+
+/// Loads a `.gltf` or `.glb` file. External buffers and images are resolved relative to `filename`.
+pub fn load_gltf(gl: &Gl, files: &Files, filename: &str) -> Result<Model, ModelError> {
+    let bytes = files.load_bytes(filename).map_err(ModelError::File)?;
+    let gltf = gltf::Gltf::from_slice(&bytes).map_err(|err| ModelError::Parse(format!("{}: {}", filename, err)))?;
+    let base = filename.rfind('/').map(|i| &filename[..=i]).unwrap_or("");
+
+    let buffers = gltf
+        .buffers()
+        .map(|buffer| match buffer.source() {
+            gltf::buffer::Source::Bin => gltf.blob.clone().ok_or_else(|| ModelError::Parse(format!("{}: Missing binary chunk", filename))),
+            gltf::buffer::Source::Uri(uri) => load_uri(files, base, uri),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let textures = gltf
+        .images()
+        .map(|image| {
+            let bytes = match image.source() {
+                gltf::image::Source::View { view, .. } => buffers[view.buffer().index()][view.offset()..view.offset() + view.length()].to_vec(),
+                gltf::image::Source::Uri { uri, .. } => load_uri(files, base, uri)?,
+            };
+            let image = image::load_from_memory(&bytes).map_err(|err| ModelError::Parse(format!("{}: {}", filename, err)))?;
+            let texture = GlTexture::try_new(gl, &[image.to_rgba8()]).map_err(ModelError::Gl)?;
+            Ok(Rc::new(RefCell::new(texture)))
+        })
+        .collect::<Result<Vec<_>, ModelError>>()?;
+
+    // materials reference textures, which in turn reference images
+    let texture_image = |texture: gltf::Texture| texture.source().index();
+    let materials: Vec<ModelMaterial> = gltf
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            ModelMaterial {
+                name: material.name().map(str::to_string),
+                base_color_factor: pbr.base_color_factor(),
+                base_color_texture: pbr.base_color_texture().map(|info| texture_image(info.texture())),
+                metallic_factor: pbr.metallic_factor(),
+                roughness_factor: pbr.roughness_factor(),
+                metallic_roughness_texture: pbr.metallic_roughness_texture().map(|info| texture_image(info.texture())),
+                normal_texture: material.normal_texture().map(|info| texture_image(info.texture())),
+                occlusion_texture: material.occlusion_texture().map(|info| texture_image(info.texture())),
+                emissive_factor: material.emissive_factor(),
+                emissive_texture: material.emissive_texture().map(|info| texture_image(info.texture())),
+                alpha_cutoff: material.alpha_cutoff(),
+                blend: material.alpha_mode() == gltf::material::AlphaMode::Blend,
+                double_sided: material.double_sided(),
+            }
+        })
+        .collect();
+
+    let meshes = gltf
+        .meshes()
+        .map(|mesh| {
+            let primitives = mesh
+                .primitives()
+                .map(|primitive| {
+                    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+                    let positions: Vec<[f32; 3]> = reader.read_positions().map(|positions| positions.collect()).unwrap_or_default();
+                    let indices: Vec<u32> = reader.read_indices().map(|indices| indices.into_u32().collect()).unwrap_or_else(|| (0..positions.len() as u32).collect());
+                    let mut vertices: Vec<ModelVertex> = positions.iter().map(|&position| ModelVertex { position, ..Default::default() }).collect();
+                    if let Some(tex_coords) = reader.read_tex_coords(0) {
+                        vertices.iter_mut().zip(tex_coords.into_f32()).for_each(|(vertex, tex_coord)| vertex.tex_coord = tex_coord);
+                    }
+                    match reader.read_normals() {
+                        Some(normals) => vertices.iter_mut().zip(normals).for_each(|(vertex, normal)| vertex.normal = normal),
+                        None => generate_normals(&mut vertices, &indices),
+                    }
+                    match reader.read_tangents() {
+                        Some(tangents) => vertices.iter_mut().zip(tangents).for_each(|(vertex, tangent)| vertex.tangent = tangent),
+                        None => generate_tangents(&mut vertices, &indices),
+                    }
+                    let skin: Option<Vec<ModelSkinVertex>> = reader.read_joints(0).zip(reader.read_weights(0)).map(|(joints, weights)| {
+                        joints
+                            .into_u16()
+                            .zip(weights.into_f32())
+                            .map(|(joints, weights)| ModelSkinVertex {
+                                joints: joints.map(f32::from),
+                                weights,
+                            })
+                            .collect()
+                    });
+                    let material = primitive.material().index();
+                    ModelPrimitive::try_new(gl, &vertices, skin.as_deref(), &indices, primitive.mode().as_gl_enum(), material)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(ModelMesh {
+                name: mesh.name().map(str::to_string),
+                primitives,
+            })
+        })
+        .collect::<Result<Vec<_>, ModelError>>()?;
+
+    let mut nodes: Vec<ModelNode> = gltf
+        .nodes()
+        .map(|node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            ModelNode {
+                name: node.name().map(str::to_string),
+                parent: None,
+                children: node.children().map(|child| child.index()).collect(),
+                transform: NodeTransform {
+                    translation: translation.into(),
+                    rotation: glm::quat(rotation[0], rotation[1], rotation[2], rotation[3]),
+                    scale: scale.into(),
+                },
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                skin: node.skin().map(|skin| skin.index()),
+            }
+        })
+        .collect();
+    (0..nodes.len()).for_each(|parent| nodes[parent].children.clone().into_iter().for_each(|child| nodes[child].parent = Some(parent)));
+
+    let skins = gltf
+        .skins()
+        .map(|skin| {
+            let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+            let reader = skin.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+            let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
+                Some(matrices) => matrices.map(glm::Mat4::from).collect(),
+                None => vec![glm::Mat4::identity(); joints.len()],
+            };
+            ModelSkin {
+                name: skin.name().map(str::to_string),
+                joints,
+                inverse_bind_matrices,
+            }
+        })
+        .collect();
+
+    let animations = gltf
+        .animations()
+        .map(|animation| {
+            let channels: Vec<AnimationChannel> = animation
+                .channels()
+                .filter_map(|channel| {
+                    let reader = channel.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+                    let times: Vec<f32> = reader.read_inputs()?.collect();
+                    let (property, values): (AnimationProperty, Vec<[f32; 4]>) = match reader.read_outputs()? {
+                        gltf::animation::util::ReadOutputs::Translations(values) => (AnimationProperty::Translation, values.map(|[x, y, z]| [x, y, z, 0.0]).collect()),
+                        gltf::animation::util::ReadOutputs::Rotations(values) => (AnimationProperty::Rotation, values.into_f32().collect()),
+                        gltf::animation::util::ReadOutputs::Scales(values) => (AnimationProperty::Scale, values.map(|[x, y, z]| [x, y, z, 0.0]).collect()),
+                        // morph targets are not supported
+                        gltf::animation::util::ReadOutputs::MorphTargetWeights(_) => return None,
+                    };
+                    let interpolation = match channel.sampler().interpolation() {
+                        gltf::animation::Interpolation::Step => Interpolation::Step,
+                        gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                        gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                    };
+                    Some(AnimationChannel {
+                        node: channel.target().node().index(),
+                        property,
+                        interpolation,
+                        times,
+                        values,
+                    })
+                })
+                .collect();
+            Animation {
+                name: animation.name().map(str::to_string),
+                duration: channels.iter().filter_map(|channel| channel.times.last().copied()).fold(0.0, f32::max),
+                channels,
+            }
+        })
+        .collect();
+
+    log::debug!("Loaded model {} with {} meshes, {} materials and {} textures", filename, meshes.len(), materials.len(), textures.len());
+    Ok(Model {
+        meshes,
+        materials,
+        textures,
+        nodes,
+        skins,
+        animations,
+    })
+}
+
+// resolves embedded base64 data or a file relative to the model
+fn load_uri(files: &Files, base: &str, uri: &str) -> Result<Vec<u8>, ModelError> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let encoded = data.split_once(";base64,").map(|(_, encoded)| encoded).ok_or_else(|| ModelError::Parse(format!("Unsupported data uri: {}", uri)))?;
+        return base64::engine::general_purpose::STANDARD.decode(encoded).map_err(|err| ModelError::Parse(err.to_string()));
+    }
+    let filename = format!("{}{}", base, uri.replace("%20", " "));
+    files.load_bytes(&filename).map_err(ModelError::File)
+}
+
+// smooth normals weighted by triangle area, for primitives without normals
+fn generate_normals(vertices: &mut [ModelVertex], indices: &[u32]) {
+    indices.chunks_exact(3).for_each(|triangle| {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize].position);
+        let normal = cross(sub(b, a), sub(c, a));
+        triangle.iter().for_each(|&i| vertices[i as usize].normal = add(vertices[i as usize].normal, normal));
+    });
+    vertices.iter_mut().for_each(|vertex| vertex.normal = normalize(vertex.normal));
+}
+
+// per-vertex tangents from uv gradients, orthogonalized against the normal
+fn generate_tangents(vertices: &mut [ModelVertex], indices: &[u32]) {
+    let mut bitangents = vec![[0.0; 3]; vertices.len()];
+    let mut tangents = vec![[0.0; 3]; vertices.len()];
+    indices.chunks_exact(3).for_each(|triangle| {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+        let (e1, e2) = (sub(b.position, a.position), sub(c.position, a.position));
+        let (du1, dv1) = (b.tex_coord[0] - a.tex_coord[0], b.tex_coord[1] - a.tex_coord[1]);
+        let (du2, dv2) = (c.tex_coord[0] - a.tex_coord[0], c.tex_coord[1] - a.tex_coord[1]);
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() <= f32::EPSILON {
+            return;
+        }
+        let r = 1.0 / det;
+        let tangent = [(e1[0] * dv2 - e2[0] * dv1) * r, (e1[1] * dv2 - e2[1] * dv1) * r, (e1[2] * dv2 - e2[2] * dv1) * r];
+        let bitangent = [(e2[0] * du1 - e1[0] * du2) * r, (e2[1] * du1 - e1[1] * du2) * r, (e2[2] * du1 - e1[2] * du2) * r];
+        triangle.iter().for_each(|&i| {
+            tangents[i as usize] = add(tangents[i as usize], tangent);
+            bitangents[i as usize] = add(bitangents[i as usize], bitangent);
+        });
+    });
+    vertices.iter_mut().zip(tangents.iter().zip(bitangents.iter())).for_each(|(vertex, (&t, &b))| {
+        let n = vertex.normal;
+        let t = normalize(sub(t, scale(n, dot(n, t))));
+        let w = if dot(cross(n, t), b) < 0.0 { -1.0 } else { 1.0 };
+        vertex.tangent = [t[0], t[1], t[2], w];
+    });
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let length = dot(a, a).sqrt();
+    if length > f32::EPSILON {
+        scale(a, 1.0 / length)
+    } else {
+        a
+    }
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl ModelPrimitive {
+    pub fn try_new(gl: &Gl, vertices: &[ModelVertex], skin: Option<&[ModelSkinVertex]>, indices: &[u32], mode: GLenum, material: Option<usize>) -> Result<ModelPrimitive, ModelError> {
+        let mut primitive = ModelPrimitive {
+            vao: GlVertexArrayObject::try_new(gl).map_err(ModelError::Gl)?,
+            vertices: GlVertexBuffer::try_new(gl, gl::STATIC_DRAW, vertices).map_err(ModelError::Gl)?,
+            skin: skin.map(|skin| GlVertexBuffer::try_new(gl, gl::STATIC_DRAW, skin)).transpose().map_err(ModelError::Gl)?,
+            indices: GlIndexBuffer::try_new(gl, gl::STATIC_DRAW, indices).map_err(ModelError::Gl)?,
+            mode,
+            material,
+        };
+        let stride = size_of::<ModelVertex>();
+        primitive.vao.bind();
+        primitive.vao.bind_attrib(&primitive.vertices, 0, 3, gl::FLOAT, gl::FALSE, 0, stride, 0);
+        primitive.vao.bind_attrib(&primitive.vertices, 1, 3, gl::FLOAT, gl::FALSE, 3 * size_of::<f32>(), stride, 0);
+        primitive.vao.bind_attrib(&primitive.vertices, 2, 2, gl::FLOAT, gl::FALSE, 6 * size_of::<f32>(), stride, 0);
+        primitive.vao.bind_attrib(&primitive.vertices, 3, 4, gl::FLOAT, gl::FALSE, 8 * size_of::<f32>(), stride, 0);
+        if let Some(skin) = primitive.skin.as_ref() {
+            let stride = size_of::<ModelSkinVertex>();
+            primitive.vao.bind_attrib(skin, 4, 4, gl::FLOAT, gl::FALSE, 0, stride, 0);
+            primitive.vao.bind_attrib(skin, 5, 4, gl::FLOAT, gl::FALSE, 4 * size_of::<f32>(), stride, 0);
+        }
+        primitive.vao.unbind();
+        Ok(primitive)
+    }
+
+    pub fn is_skinned(&self) -> bool {
+        self.skin.is_some()
+    }
+
+    /// Draws the primitive. The shader must be bound.
+    pub fn draw(&mut self, shader: &mut GlShader) {
+        self.vao.bind();
+        self.indices.bind();
+        shader.draw_elements(self.mode, self.indices.count());
+        self.indices.unbind();
+        self.vao.unbind();
+    }
+}
+
+impl Model {
+    /// Local transforms of all nodes in their rest pose, the starting point for `Animation::sample`.
+    pub fn rest_pose(&self) -> Vec<NodeTransform> {
+        self.nodes.iter().map(|node| node.transform).collect()
+    }
+
+    pub fn animation(&self, name: &str) -> Option<&Animation> {
+        self.animations.iter().find(|animation| animation.name.as_deref() == Some(name))
+    }
+}
+
+impl ModelMesh {
+    /// Draws all primitives with the same shader, ignoring their materials.
+    pub fn draw(&mut self, shader: &mut GlShader) {
+        self.primitives.iter_mut().for_each(|primitive| primitive.draw(shader));
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::File(err) => write!(f, "Failed to load model: {}", err),
+            ModelError::Parse(err) => write!(f, "Failed to parse model: {}", err),
+            ModelError::Gl(err) => write!(f, "Failed to create model: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl GlResource for ModelPrimitive {
+    fn release(&mut self) {
+        self.vao.release();
+        self.vertices.release();
+        if let Some(skin) = self.skin.as_mut() {
+            skin.release();
+        }
+        self.indices.release();
+    }
+}
+impl Drop for ModelPrimitive {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
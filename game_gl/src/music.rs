@@ -0,0 +1,290 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{sync_channel, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use lewton::inside_ogg::OggStreamReader;
+
+use crate::audio::AudioError;
+use crate::file::{Files, ReadSeek};
+use crate::mixer::{AudioSource, Bus, Mixer};
+
+//////////////////////////////////////////////////
+// Definition
+
+// frames decoded per buffer, about 0.1s at 44.1 kHz
+const BUFFER_FRAMES: usize = 4096;
+
+// decoded buffers waiting for the mixer, the decoder fills the next one meanwhile
+const QUEUED_BUFFERS: usize = 1;
+
+/// Streams long Ogg Vorbis tracks from `Files::open_stream` into the mixer. Tracks are decoded on a worker thread
+/// while playing, so only a few buffers are kept in memory. Switching tracks cross-fades the old into the new track.
+/// Tracks play on `Bus::Music`.
+pub struct MusicPlayer {
+    mixer: Mixer,
+    files: Files,
+    volume: f32,
+    paused: bool,
+    suspended: bool,
+    current: Option<(String, Arc<Mutex<TrackControl>>)>,
+    // fading out
+    previous: Vec<Arc<Mutex<TrackControl>>>,
+}
+
+// shared by the player and the mixed track
+#[derive(Debug)]
+struct TrackControl {
+    gain: f32,
+    target: f32,
+    // gain change per second
+    fade_speed: f32,
+    paused: bool,
+    // removed once faded out
+    stopping: bool,
+    finished: bool,
+}
+
+struct MusicTrack {
+    control: Arc<Mutex<TrackControl>>,
+    buffers: Receiver<Vec<f32>>,
+    sample_rate: u32,
+    channels: usize,
+    // decoded samples, the first frame is at `position`
+    samples: VecDeque<f32>,
+    position: f64,
+    decoded: bool,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl MusicPlayer {
+    pub fn new(mixer: Mixer, files: Files) -> MusicPlayer {
+        MusicPlayer {
+            mixer,
+            files,
+            volume: 1.0,
+            paused: false,
+            suspended: false,
+            current: None,
+            previous: Vec::new(),
+        }
+    }
+
+    /// Starts a track, cross-fading from the current one over `fade`. Looping tracks restart seamlessly.
+    pub fn play(&mut self, filename: &str, looping: bool, fade: Duration) -> Result<(), AudioError> {
+        let stream = self.files.open_stream(filename).map_err(AudioError::File)?;
+        let reader = OggStreamReader::new(stream).map_err(|e| AudioError::Decode(format!("{}: {}", filename, e)))?;
+        let sample_rate = reader.ident_hdr.audio_sample_rate;
+        let channels = reader.ident_hdr.audio_channels as usize;
+
+        let (sender, buffers) = sync_channel(QUEUED_BUFFERS);
+        let name = filename.to_string();
+        std::thread::Builder::new()
+            .name(format!("music {}", filename))
+            .spawn(move || decode(reader, looping, &name, |buffer| sender.send(buffer).is_ok()))
+            .map_err(|e| AudioError::Decode(e.to_string()))?;
+
+        self.stop(fade);
+        let fading = !fade.is_zero();
+        let control = Arc::new(Mutex::new(TrackControl {
+            gain: if fading { 0.0 } else { self.volume },
+            target: self.volume,
+            fade_speed: fade_speed(fade),
+            paused: self.paused || self.suspended,
+            stopping: false,
+            finished: false,
+        }));
+        self.mixer.add_source(MusicTrack {
+            control: control.clone(),
+            buffers,
+            sample_rate,
+            channels,
+            samples: VecDeque::new(),
+            position: 0.0,
+            decoded: false,
+        });
+        self.current = Some((filename.to_string(), control));
+        Ok(())
+    }
+
+    /// Fades out the current track.
+    pub fn stop(&mut self, fade: Duration) {
+        if let Some((_, control)) = self.current.take() {
+            {
+                let mut control = lock(&control);
+                control.target = 0.0;
+                control.fade_speed = fade_speed(fade);
+                control.stopping = true;
+            }
+            self.previous.push(control);
+        }
+        self.previous.retain(|control| !lock(control).finished);
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.update_paused();
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.update_paused();
+    }
+
+    /// Pauses while the app is in the background, independent of `pause`.
+    pub(crate) fn set_suspended(&mut self, suspended: bool) {
+        self.suspended = suspended;
+        self.update_paused();
+    }
+
+    /// Volume of the current and future tracks, the current track fades with the speed it was started with.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        if let Some((_, control)) = self.current.as_ref() {
+            lock(control).target = volume;
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// The playing track, `None` after `stop` or once a track without looping ended.
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_ref().filter(|(_, control)| !lock(control).finished).map(|(filename, _)| filename.as_str())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn update_paused(&self) {
+        let paused = self.paused || self.suspended;
+        self.current.iter().map(|(_, control)| control).chain(self.previous.iter()).for_each(|control| lock(control).paused = paused);
+    }
+}
+
+impl AudioSource for MusicTrack {
+    fn mix(&mut self, output: &mut [f32], sample_rate: u32, channels: usize) -> bool {
+        let mut control = lock(&self.control);
+        if control.paused {
+            return true;
+        }
+        let step = self.sample_rate as f64 / sample_rate.max(1) as f64;
+        let fade_step = control.fade_speed / sample_rate.max(1) as f32;
+        let source_channels = self.channels.max(1);
+        for frame in output.chunks_mut(channels) {
+            // two frames are needed to interpolate
+            while self.samples.len() < 2 * source_channels && !self.decoded {
+                match self.buffers.try_recv() {
+                    Ok(buffer) => self.samples.extend(buffer),
+                    Err(TryRecvError::Disconnected) => self.decoded = true,
+                    // the decoder fell behind, skip this callback instead of blocking the audio thread
+                    Err(TryRecvError::Empty) => return true,
+                }
+            }
+            if self.samples.len() < source_channels {
+                control.finished = true;
+                return false;
+            }
+            let next = if self.samples.len() >= 2 * source_channels { source_channels } else { 0 };
+            let t = self.position as f32;
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let value = |offset: usize| -> f32 {
+                    if channels == 1 {
+                        (0..source_channels).map(|channel| self.samples[offset + channel]).sum::<f32>() / source_channels as f32
+                    } else {
+                        self.samples[offset + channel % source_channels]
+                    }
+                };
+                *sample += (value(0) * (1.0 - t) + value(next) * t) * control.gain;
+            }
+
+            // fade towards the target gain
+            control.gain = if control.gain < control.target {
+                (control.gain + fade_step).min(control.target)
+            } else {
+                (control.gain - fade_step).max(control.target)
+            };
+            if control.stopping && control.gain <= 0.0 {
+                control.finished = true;
+                return false;
+            }
+
+            self.position += step;
+            while self.position >= 1.0 && self.samples.len() >= source_channels {
+                self.samples.drain(..source_channels);
+                self.position -= 1.0;
+            }
+        }
+        true
+    }
+
+    fn bus(&self) -> Bus {
+        Bus::Music
+    }
+}
+
+// decodes buffers until the track ends or `send` fails because the track was dropped
+fn decode(mut reader: OggStreamReader<Box<dyn ReadSeek>>, looping: bool, filename: &str, send: impl Fn(Vec<f32>) -> bool) {
+    let buffer_size = BUFFER_FRAMES * reader.ident_hdr.audio_channels as usize;
+    let mut buffer = Vec::with_capacity(buffer_size);
+    loop {
+        match reader.read_dec_packet_itl() {
+            Ok(Some(packet)) => buffer.extend(packet.into_iter().map(|sample| sample as f32 / 32768.0)),
+            Ok(None) if looping => {
+                if let Err(e) = reader.seek_absgp_pg(0) {
+                    log::error!("Failed to restart {}: {}", filename, e);
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                log::error!("Failed to decode {}: {}", filename, e);
+                break;
+            }
+        }
+        if buffer.len() >= buffer_size {
+            let full = std::mem::replace(&mut buffer, Vec::with_capacity(buffer_size));
+            if !send(full) {
+                return;
+            }
+        }
+    }
+    if !buffer.is_empty() {
+        send(buffer);
+    }
+}
+
+// gain change per second to fade from silence to full volume
+fn fade_speed(fade: Duration) -> f32 {
+    if fade.is_zero() {
+        f32::INFINITY
+    } else {
+        1.0 / fade.as_secs_f32()
+    }
+}
+
+// the audio thread keeps running if the game thread panicked
+fn lock(control: &Mutex<TrackControl>) -> MutexGuard<'_, TrackControl> {
+    control.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl std::fmt::Debug for MusicPlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MusicPlayer")
+            .field("current", &self.current())
+            .field("volume", &self.volume)
+            .field("paused", &self.paused)
+            .field("suspended", &self.suspended)
+            .finish()
+    }
+}
@@ -2,8 +2,10 @@
 // Using
 
 use image;
+use std::cell::Cell;
+use std::collections::HashSet;
 use std::ffi::CStr;
-use std::mem::size_of;
+use std::mem::{size_of, size_of_val};
 
 use crate::gl;
 use crate::gl::types::*;
@@ -17,6 +19,28 @@ pub trait GlResource: Drop {
     fn release(&mut self);
 }
 
+/// Error returned by fallible `try_*` resource constructors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GlError {
+    InvalidEnum,
+    InvalidValue,
+    InvalidOperation,
+    InvalidFramebufferOperation,
+    OutOfMemory,
+    /// Error code without a dedicated variant.
+    Unknown(GLenum),
+    /// Framebuffer status returned by `CheckFramebufferStatus`.
+    IncompleteFramebuffer(GLenum),
+    /// Info log of the failed shader.
+    ShaderCompile(String),
+    /// Info log of the failed program.
+    ProgramLink(String),
+    /// Arguments not describing a valid object, e.g. texture data of the wrong size.
+    InvalidArgument(String),
+    /// Pixel format without a matching GL format.
+    UnsupportedFormat(String),
+}
+
 #[derive(Debug, Default)]
 pub struct GlVertexArrayObject {
     gl: Option<Gl>,
@@ -28,6 +52,7 @@ pub struct GlVertexArrayObject {
 pub struct GlVertexBuffer<T: Default> {
     gl: Option<Gl>,
     id: GLuint,
+    usage: GLenum,
     count: usize,
     max_count: usize,
     phantom: std::marker::PhantomData<T>,
@@ -37,6 +62,7 @@ pub struct GlVertexBuffer<T: Default> {
 pub struct GlIndexBuffer {
     gl: Option<Gl>,
     id: GLuint,
+    usage: GLenum,
     count: usize,
     max_count: usize,
 }
@@ -49,6 +75,77 @@ pub struct GlUniformBuffer<T: Default> {
     phantom: std::marker::PhantomData<T>,
 }
 
+#[derive(Debug, Default)]
+pub struct GlStorageBuffer<T: Default> {
+    gl: Option<Gl>,
+    id: GLuint,
+    usage: GLenum,
+    count: usize,
+    max_count: usize,
+    active_slots: [bool; 32],
+    phantom: std::marker::PhantomData<T>,
+}
+
+#[derive(Debug, Default)]
+pub struct GlIndirectBuffer {
+    gl: Option<Gl>,
+    id: GLuint,
+    usage: GLenum,
+    count: usize,
+    max_count: usize,
+}
+
+/// Draw parameters for indirect element draws, layout defined by OpenGL ES 3.1.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct GlDrawElementsIndirectCommand {
+    pub count: GLuint,
+    pub instance_count: GLuint,
+    pub first_index: GLuint,
+    pub base_vertex: GLint,
+    pub base_instance: GLuint,
+}
+
+#[derive(Debug, Default)]
+pub struct GlQuery {
+    gl: Option<Gl>,
+    id: GLuint,
+    target: GLenum,
+    active: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct GlFence {
+    gl: Option<Gl>,
+    sync: Option<GLsync>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GlTextureFormat {
+    R8,
+    Rg8,
+    Rgb8,
+    Rgba8,
+    Srgb8,
+    Srgb8Alpha8,
+    R16F,
+    Rg16F,
+    Rgba16F,
+    R32F,
+    Rgba32F,
+}
+
+/// Sampling parameters of a texture, applied with `GlTexture::set_options`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GlTextureOptions {
+    /// Minification filter, e.g. `LINEAR` or `LINEAR_MIPMAP_LINEAR` to sample mip levels.
+    pub min_filter: GLenum,
+    /// Magnification filter, `LINEAR` or `NEAREST`.
+    pub mag_filter: GLenum,
+    /// Anisotropic filtering samples, 1.0 disables it. Clamped to the device limit.
+    pub max_anisotropy: f32,
+}
+
 #[derive(Debug, Default)]
 pub struct GlTexture {
     gl: Option<Gl>,
@@ -56,6 +153,26 @@ pub struct GlTexture {
     active_slots: [bool; 32],
 }
 
+#[derive(Debug, Default)]
+pub struct GlRenderbuffer {
+    gl: Option<Gl>,
+    id: GLuint,
+}
+
+#[derive(Debug, Default)]
+pub struct GlFramebuffer {
+    gl: Option<Gl>,
+    id: GLuint,
+    resolve_id: GLuint,
+    width: u32,
+    height: u32,
+    samples: u32,
+    internal_formats: Vec<GLenum>,
+    colors: Vec<GlRenderbuffer>,
+    depth: GlRenderbuffer,
+    textures: Vec<GlTexture>,
+}
+
 #[derive(Debug, Default)]
 pub struct GlShader {
     gl: Option<Gl>,
@@ -66,23 +183,183 @@ pub struct GlShader {
 
 pub struct GlString {}
 
+/// Buffers of the bound framebuffer to clear, combine with `|`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GlClearFlags(GLbitfield);
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct GlRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GlBlendState {
+    pub src_rgb: GLenum,
+    pub dst_rgb: GLenum,
+    pub src_alpha: GLenum,
+    pub dst_alpha: GLenum,
+    pub equation_rgb: GLenum,
+    pub equation_alpha: GLenum,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GlDepthState {
+    pub test: bool,
+    pub write: bool,
+    pub func: GLenum,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GlStencilState {
+    pub func: GLenum,
+    pub reference: GLint,
+    pub read_mask: GLuint,
+    pub write_mask: GLuint,
+    pub fail: GLenum,
+    pub depth_fail: GLenum,
+    pub pass: GLenum,
+}
+
+/// Fixed function state applied in a single call. `None` disables blending, stencil test or culling.
+/// Default matches the initial OpenGL state.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GlRenderState {
+    pub blend: Option<GlBlendState>,
+    pub depth: GlDepthState,
+    pub stencil: Option<GlStencilState>,
+    pub cull_face: Option<GLenum>,
+    pub front_face: GLenum,
+    pub color_mask: [bool; 4],
+}
+
+/// Viewport and scissor stacks, reset to the surface size on every resize.
+#[derive(Debug, Default)]
+pub struct GlViewport {
+    size: GlRect,
+    content: GlRect,
+    virtual_resolution: Option<GlVirtualResolution>,
+    viewports: Vec<GlRect>,
+    scissors: Vec<GlRect>,
+}
+
+/// Fixed design resolution, scaled to the surface while keeping its aspect ratio.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GlVirtualResolution {
+    pub width: u32,
+    pub height: u32,
+    pub mode: GlScaleMode,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GlScaleMode {
+    /// Shows the whole virtual area, with bars on the sides (pillarbox) or top and bottom (letterbox).
+    Fit,
+    /// Fills the surface, cropping the virtual area on one axis.
+    Crop,
+    /// Fills the surface, distorting the aspect ratio.
+    Stretch,
+}
+
+/// Safe helpers on top of the raw OpenGL bindings, usable directly on `Gl`.
+pub trait GlExt {
+    /// Opens a named group for graphics debuggers, e.g. RenderDoc or Android GPU Inspector. No-op without `KHR_debug`.
+    fn push_debug_group(&self, name: &str);
+
+    fn pop_debug_group(&self);
+
+    /// Inserts a single named marker into the command stream. No-op without `KHR_debug`.
+    fn insert_debug_marker(&self, name: &str);
+
+    /// Routes driver messages into `log`, dropping messages below `level`. Returns false without `KHR_debug`.
+    fn enable_debug_output(&self, level: log::LevelFilter) -> bool;
+
+    /// All extensions reported by the driver, e.g. `GL_KHR_debug`.
+    fn extensions(&self) -> HashSet<String>;
+
+    /// Checks a single extension without collecting the whole set.
+    fn has_extension(&self, name: &str) -> bool;
+
+    fn set_viewport(&self, rect: GlRect);
+
+    /// Enables scissor test for `rect`, `None` disables it.
+    fn set_scissor(&self, rect: Option<GlRect>);
+
+    /// Clears the buffers selected by `flags` of the bound framebuffer, values of unselected buffers are ignored.
+    fn clear(&self, flags: GlClearFlags, color: [f32; 4], depth: f32, stencil: i32);
+
+    /// Reads `rect` of the bound read framebuffer into an image with the top row first.
+    fn read_pixels(&self, rect: GlRect) -> image::RgbaImage;
+
+    /// Wraps all commands issued by `f` into a named debug group.
+    fn debug_group<R>(&self, name: &str, f: impl FnOnce() -> R) -> R {
+        self.push_debug_group(name);
+        let result = f();
+        self.pop_debug_group();
+        result
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct GlVersion {
+    pub es: bool,
+    pub major: u32,
+    pub minor: u32,
+}
+
+/// Draw calls and triangles submitted through `GlShader`, counted per thread.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct GlDrawStats {
+    pub draw_calls: u32,
+    /// Triangles of direct draws, indirect draws only count as calls.
+    pub triangles: u64,
+}
+
+/// Device limits, queried once per context.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct GlCapabilities {
+    pub version: GlVersion,
+    pub max_texture_size: u32,
+    pub max_array_texture_layers: u32,
+    pub max_uniform_block_size: u32,
+    pub max_samples: u32,
+    pub max_vertex_attribs: u32,
+    /// Depth bits of the default framebuffer.
+    pub depth_bits: u32,
+    /// Stencil bits of the default framebuffer.
+    pub stencil_bits: u32,
+    /// Whether the default framebuffer converts to sRGB. Filled in from the surface, `query` alone reports false.
+    pub srgb_framebuffer: bool,
+    /// `None` without anisotropic filtering support.
+    pub max_anisotropy: Option<f32>,
+    /// Whether `RGBA16F` textures can be rendered to, needed for HDR targets.
+    pub color_buffer_half_float: bool,
+}
+
 //////////////////////////////////////////////////
 // Vertex Array Object
 
 impl GlVertexArrayObject {
     pub fn new(gl: &Gl) -> GlVertexArrayObject {
+        GlVertexArrayObject::try_new(gl).expect("Failed to create vertex array object")
+    }
+
+    pub fn try_new(gl: &Gl) -> Result<GlVertexArrayObject, GlError> {
         let mut id: GLuint = 0;
         unsafe {
+            clear_error(gl);
             gl.GenVertexArrays(1, &mut id as _);
-            if !check_error(gl, "Failed to create vertex array object") {
-                log::debug!("Created vertex array object {}", id);
-            }
         }
-        GlVertexArrayObject {
+        let vao = GlVertexArrayObject {
             gl: Some(gl.clone()),
             id,
             ..Default::default()
-        }
+        };
+        unsafe { get_error(gl)? };
+        log::debug!("Created vertex array object {}", id);
+        Ok(vao)
     }
 
     pub fn bind(&mut self) {
@@ -101,6 +378,7 @@ impl GlVertexArrayObject {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn bind_attrib<T: Default>(&mut self, vbo: &GlVertexBuffer<T>, slot: GLuint, count: GLint, type_: GLenum, normalized: GLboolean, offset: usize, stride: usize, divisor: GLuint) {
         let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
         unsafe {
@@ -137,41 +415,59 @@ impl GlVertexArrayObject {
 
 impl<T: Default> GlVertexBuffer<T> {
     pub fn new(gl: &Gl, usage: GLenum, data: &[T]) -> GlVertexBuffer<T> {
+        GlVertexBuffer::try_new(gl, usage, data).expect("Failed to create vertex buffer")
+    }
+
+    pub fn try_new(gl: &Gl, usage: GLenum, data: &[T]) -> Result<GlVertexBuffer<T>, GlError> {
         let mut id: GLuint = 0;
         unsafe {
+            clear_error(gl);
             gl.GenBuffers(1, &mut id);
             gl.BindBuffer(gl::ARRAY_BUFFER, id);
-            gl.BufferData(gl::ARRAY_BUFFER, (data.len() * size_of::<T>()) as GLsizeiptr, data.as_ptr() as *const _, usage);
+            gl.BufferData(gl::ARRAY_BUFFER, size_of_val(data) as GLsizeiptr, data.as_ptr() as *const _, usage);
             gl.BindBuffer(gl::ARRAY_BUFFER, 0);
-            if !check_error(gl, "Failed to create vertex buffer") {
-                log::debug!("Created vertex buffer {}", id)
-            }
         }
         let count = data.len();
         let max_count = data.len();
-        GlVertexBuffer {
+        let buffer = GlVertexBuffer {
             gl: Some(gl.clone()),
             id,
+            usage,
             phantom: std::marker::PhantomData,
             count,
             max_count,
-        }
+        };
+        unsafe { get_error(gl)? };
+        log::debug!("Created vertex buffer {}", id);
+        Ok(buffer)
     }
 
     pub fn update(&mut self, data: &[T]) {
-        assert!(data.len() <= self.max_count, "Update data must fit into buffer");
-        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
-        unsafe {
-            gl.BindBuffer(gl::ARRAY_BUFFER, self.id);
-            gl.BufferSubData(gl::ARRAY_BUFFER, 0, (data.len() * size_of::<T>()) as GLsizeiptr, data.as_ptr() as *const _);
-            gl.BindBuffer(gl::ARRAY_BUFFER, 0);
-            if !check_error(gl, "Failed to update vertex buffer") {
-                log::debug!("Updated vertex buffer {}", self.id)
+        if data.len() > self.max_count {
+            // reallocate storage, data is uploaded with the new allocation
+            self.allocate(grow_count(self.max_count, data.len()), Some(data));
+        } else {
+            let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+            unsafe {
+                gl.BindBuffer(gl::ARRAY_BUFFER, self.id);
+                gl.BufferSubData(gl::ARRAY_BUFFER, 0, size_of_val(data) as GLsizeiptr, data.as_ptr() as *const _);
+                gl.BindBuffer(gl::ARRAY_BUFFER, 0);
+                if !check_error(gl, "Failed to update vertex buffer") {
+                    log::debug!("Updated vertex buffer {}", self.id)
+                }
             }
         }
         self.count = data.len();
     }
 
+    /// Ensures the buffer can hold at least `max_count` elements. Growing the buffer discards its content.
+    pub fn reserve(&mut self, max_count: usize) {
+        if max_count > self.max_count {
+            self.allocate(max_count, None);
+            self.count = 0;
+        }
+    }
+
     pub fn count(&self) -> usize {
         self.count
     }
@@ -179,6 +475,22 @@ impl<T: Default> GlVertexBuffer<T> {
     pub fn max_count(&self) -> usize {
         self.max_count
     }
+
+    fn allocate(&mut self, max_count: usize, data: Option<&[T]>) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.BindBuffer(gl::ARRAY_BUFFER, self.id);
+            gl.BufferData(gl::ARRAY_BUFFER, (max_count * size_of::<T>()) as GLsizeiptr, std::ptr::null(), self.usage);
+            if let Some(data) = data {
+                gl.BufferSubData(gl::ARRAY_BUFFER, 0, size_of_val(data) as GLsizeiptr, data.as_ptr() as *const _);
+            }
+            gl.BindBuffer(gl::ARRAY_BUFFER, 0);
+            if !check_error(gl, "Failed to grow vertex buffer") {
+                log::debug!("Resized vertex buffer {} from {} to {} elements", self.id, self.max_count, max_count)
+            }
+        }
+        self.max_count = max_count;
+    }
 }
 
 //////////////////////////////////////////////////
@@ -186,24 +498,30 @@ impl<T: Default> GlVertexBuffer<T> {
 
 impl GlIndexBuffer {
     pub fn new(gl: &Gl, usage: GLenum, indices: &[u32]) -> GlIndexBuffer {
+        GlIndexBuffer::try_new(gl, usage, indices).expect("Failed to create index buffer")
+    }
+
+    pub fn try_new(gl: &Gl, usage: GLenum, indices: &[u32]) -> Result<GlIndexBuffer, GlError> {
         let mut id: GLuint = 0;
         unsafe {
+            clear_error(gl);
             gl.GenBuffers(1, &mut id);
             gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, id);
-            gl.BufferData(gl::ELEMENT_ARRAY_BUFFER, (indices.len() * size_of::<u32>()) as GLsizeiptr, indices.as_ptr() as *const _, usage);
+            gl.BufferData(gl::ELEMENT_ARRAY_BUFFER, size_of_val(indices) as GLsizeiptr, indices.as_ptr() as *const _, usage);
             gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
-            if !check_error(gl, "Failed to create index buffer") {
-                log::debug!("Created index buffer {}", id)
-            }
         }
         let count = indices.len();
         let max_count = indices.len();
-        GlIndexBuffer {
+        let buffer = GlIndexBuffer {
             gl: Some(gl.clone()),
             id,
+            usage,
             count,
             max_count,
-        }
+        };
+        unsafe { get_error(gl)? };
+        log::debug!("Created index buffer {}", id);
+        Ok(buffer)
     }
 
     pub fn bind(&mut self) {
@@ -223,19 +541,31 @@ impl GlIndexBuffer {
     }
 
     pub fn update(&mut self, indices: &[u32]) {
-        assert!(indices.len() <= self.max_count, "Update data must fit into buffer");
-        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
-        unsafe {
-            gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id);
-            gl.BufferSubData(gl::ELEMENT_ARRAY_BUFFER, 0, (indices.len() * size_of::<u32>()) as GLsizeiptr, indices.as_ptr() as *const _);
-            gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
-            if !check_error(gl, "Failed to update index buffer") {
-                log::debug!("Updated index buffer {}", self.id)
+        if indices.len() > self.max_count {
+            // reallocate storage, indices are uploaded with the new allocation
+            self.allocate(grow_count(self.max_count, indices.len()), Some(indices));
+        } else {
+            let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+            unsafe {
+                gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id);
+                gl.BufferSubData(gl::ELEMENT_ARRAY_BUFFER, 0, size_of_val(indices) as GLsizeiptr, indices.as_ptr() as *const _);
+                gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+                if !check_error(gl, "Failed to update index buffer") {
+                    log::debug!("Updated index buffer {}", self.id)
+                }
             }
         }
         self.count = indices.len();
     }
 
+    /// Ensures the buffer can hold at least `max_count` indices. Growing the buffer discards its content.
+    pub fn reserve(&mut self, max_count: usize) {
+        if max_count > self.max_count {
+            self.allocate(max_count, None);
+            self.count = 0;
+        }
+    }
+
     pub fn count(&self) -> usize {
         self.count
     }
@@ -243,6 +573,22 @@ impl GlIndexBuffer {
     pub fn max_count(&self) -> usize {
         self.max_count
     }
+
+    fn allocate(&mut self, max_count: usize, indices: Option<&[u32]>) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id);
+            gl.BufferData(gl::ELEMENT_ARRAY_BUFFER, (max_count * size_of::<u32>()) as GLsizeiptr, std::ptr::null(), self.usage);
+            if let Some(indices) = indices {
+                gl.BufferSubData(gl::ELEMENT_ARRAY_BUFFER, 0, size_of_val(indices) as GLsizeiptr, indices.as_ptr() as *const _);
+            }
+            gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+            if !check_error(gl, "Failed to grow index buffer") {
+                log::debug!("Resized index buffer {} from {} to {} indices", self.id, self.max_count, max_count)
+            }
+        }
+        self.max_count = max_count;
+    }
 }
 
 //////////////////////////////////////////////////
@@ -250,22 +596,27 @@ impl GlIndexBuffer {
 
 impl<T: Default> GlUniformBuffer<T> {
     pub fn new(gl: &Gl, usage: GLenum, data: &T) -> GlUniformBuffer<T> {
+        GlUniformBuffer::try_new(gl, usage, data).expect("Failed to create uniform buffer")
+    }
+
+    pub fn try_new(gl: &Gl, usage: GLenum, data: &T) -> Result<GlUniformBuffer<T>, GlError> {
         let mut id: GLuint = 0;
         unsafe {
+            clear_error(gl);
             gl.GenBuffers(1, &mut id);
             gl.BindBuffer(gl::UNIFORM_BUFFER, id);
             gl.BufferData(gl::UNIFORM_BUFFER, size_of::<T>() as GLsizeiptr, data as *const T as *const _, usage);
             gl.BindBuffer(gl::UNIFORM_BUFFER, 0);
-            if !check_error(gl, "Failed to create index buffer") {
-                log::debug!("Created uniform buffer {}", id)
-            }
         }
-        GlUniformBuffer {
+        let buffer = GlUniformBuffer {
             gl: Some(gl.clone()),
             id,
             phantom: std::marker::PhantomData,
             ..Default::default()
-        }
+        };
+        unsafe { get_error(gl)? };
+        log::debug!("Created uniform buffer {}", id);
+        Ok(buffer)
     }
 
     pub fn bind(&mut self, unit: GLuint) {
@@ -304,86 +655,46 @@ impl<T: Default> GlUniformBuffer<T> {
 }
 
 //////////////////////////////////////////////////
-// Texture
+// Storage Buffer
 
-impl GlTexture {
-    pub fn new<P, Container>(gl: &Gl, images: &[image::ImageBuffer<P, Container>]) -> GlTexture
-    where
-        P: image::PixelWithColorType + 'static,
-        P::Subpixel: 'static,
-        Container: std::ops::Deref<Target = [P::Subpixel]>,
-    {
-        // all textures need same size
-        assert!(!images.is_empty());
-        assert!(images.windows(2).all(|w| w[0].dimensions() == w[1].dimensions()));
-        // get specs from first image
-        let img = images.first().unwrap();
-        let pixel_type = if size_of::<P::Subpixel>() == 1 { gl::UNSIGNED_BYTE } else { gl::UNSIGNED_SHORT };
-        let (format, internal_format) = match <P as image::PixelWithColorType>::COLOR_TYPE {
-            image::ExtendedColorType::L8 => (gl::RED, gl::R8),
-            image::ExtendedColorType::Rgb8 => (gl::RGB, gl::RGB8),
-            image::ExtendedColorType::Rgb16 => (gl::RGB, gl::RGBA16F),
-            image::ExtendedColorType::Rgba8 => (gl::RGBA, gl::RGBA8),
-            image::ExtendedColorType::Rgba16 => (gl::RGBA, gl::RGBA16F),
-            _ => unimplemented!(),
-        };
-        let num_mip_map = 1 + (img.width().min(img.height()) as f32).log2().floor() as i32;
+impl<T: Default> GlStorageBuffer<T> {
+    /// Shader storage buffers require OpenGL ES 3.1 or OpenGL 4.3.
+    pub fn is_supported(gl: &Gl) -> bool {
+        GlVersion::get(gl).at_least(3, 1, 4, 3)
+    }
+
+    pub fn new(gl: &Gl, usage: GLenum, data: &[T]) -> GlStorageBuffer<T> {
+        GlStorageBuffer::try_new(gl, usage, data).expect("Failed to create storage buffer")
+    }
 
+    pub fn try_new(gl: &Gl, usage: GLenum, data: &[T]) -> Result<GlStorageBuffer<T>, GlError> {
         let mut id: GLuint = 0;
         unsafe {
-            gl.GenTextures(1, &mut id);
-            gl.BindTexture(gl::TEXTURE_2D_ARRAY, id);
-            gl.TexStorage3D(
-                gl::TEXTURE_2D_ARRAY,
-                num_mip_map,
-                internal_format,
-                img.width() as GLsizei,
-                img.height() as GLsizei,
-                images.len() as GLsizei,
-            );
-            images.iter().enumerate().for_each(|(i, img)| {
-                gl.TexSubImage3D(
-                    gl::TEXTURE_2D_ARRAY,
-                    0,
-                    0,
-                    0,
-                    i as GLint,
-                    img.width() as GLsizei,
-                    img.height() as GLsizei,
-                    1,
-                    format,
-                    pixel_type,
-                    img.as_ptr() as *const _,
-                );
-            });
-            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
-            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
-            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
-            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
-            if !check_error(gl, "Failed to create texture array") {
-                log::debug!("Created texture array {}", id)
-            }
-
-            gl.GenerateMipmap(gl::TEXTURE_2D_ARRAY);
-            if !check_error(gl, "Failed to create texture mipmapping") {
-                log::debug!("Created mipmapping for texture {}", id)
-            }
-
-            gl.BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+            clear_error(gl);
+            gl.GenBuffers(1, &mut id);
+            gl.BindBuffer(gl::SHADER_STORAGE_BUFFER, id);
+            gl.BufferData(gl::SHADER_STORAGE_BUFFER, size_of_val(data) as GLsizeiptr, data.as_ptr() as *const _, usage);
+            gl.BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
         }
-        GlTexture {
+        let buffer = GlStorageBuffer {
             gl: Some(gl.clone()),
             id,
+            usage,
+            count: data.len(),
+            max_count: data.len(),
+            phantom: std::marker::PhantomData,
             ..Default::default()
-        }
+        };
+        unsafe { get_error(gl)? };
+        log::debug!("Created storage buffer {}", id);
+        Ok(buffer)
     }
 
     pub fn bind(&mut self, unit: GLuint) {
         let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
         unsafe {
-            gl.ActiveTexture(gl::TEXTURE0 + unit as GLuint);
-            gl.BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
-            check_error(gl, "Failed to bind texture");
+            gl.BindBufferBase(gl::SHADER_STORAGE_BUFFER, unit, self.id);
+            check_error(gl, "Failed to bind storage buffer");
         }
         self.active_slots[unit as usize] = true;
     }
@@ -393,164 +704,1752 @@ impl GlTexture {
         unsafe {
             self.active_slots.iter_mut().enumerate().for_each(|(slot, active)| {
                 if *active {
-                    gl.ActiveTexture(gl::TEXTURE0 + slot as GLuint);
-                    gl.BindTexture(gl::TEXTURE_2D_ARRAY, 0);
-                    check_error(gl, "Failed to unbind texture");
+                    gl.BindBufferBase(gl::SHADER_STORAGE_BUFFER, slot as GLuint, 0);
+                    check_error(gl, "Failed to unbind storage buffer");
                     *active = false;
                 }
             });
         }
     }
-}
-
-//////////////////////////////////////////////////
-// Shader
-
-impl GlShader {
-    pub fn new(gl: &Gl, vert: &[u8], frag: &[u8]) -> GlShader {
-        // ensure null termination
-        let vert_string = std::ffi::CString::new(vert).expect("Failed to convert to C-String");
-        let frag_string = std::ffi::CString::new(frag).expect("Failed to convert to C-String");
-        let vert = vert_string.as_bytes_with_nul();
-        let frag = frag_string.as_bytes_with_nul();
 
+    pub fn update(&mut self, data: &[T]) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
         unsafe {
-            let vs = gl.CreateShader(gl::VERTEX_SHADER);
-            if !check_error(gl, "Failed to create shaders") {
-                log::debug!("Created vertex shader {}", vs);
+            gl.BindBuffer(gl::SHADER_STORAGE_BUFFER, self.id);
+            if data.len() > self.max_count {
+                self.max_count = grow_count(self.max_count, data.len());
+                gl.BufferData(gl::SHADER_STORAGE_BUFFER, (self.max_count * size_of::<T>()) as GLsizeiptr, std::ptr::null(), self.usage);
             }
-            let fs = gl.CreateShader(gl::FRAGMENT_SHADER);
-            if !check_error(gl, "Failed to create shaders") {
-                log::debug!("Created fragment shader {}", fs);
-            }
-
-            gl.ShaderSource(vs, 1, [vert.as_ptr() as *const _].as_ptr(), std::ptr::null());
-            gl.CompileShader(vs);
-            let mut status = 0;
-            gl.GetShaderiv(vs, gl::COMPILE_STATUS, &mut status);
-            if status == 0 {
-                log::error!("Failed to compile vertex shader");
-                print_shader_log(gl, vs);
-            } else {
-                log::debug!("Compiled vertex shader {}", vs);
+            gl.BufferSubData(gl::SHADER_STORAGE_BUFFER, 0, size_of_val(data) as GLsizeiptr, data.as_ptr() as *const _);
+            gl.BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+            if !check_error(gl, "Failed to update storage buffer") {
+                log::debug!("Updated storage buffer {}", self.id)
             }
+        }
+        self.count = data.len();
+    }
 
-            gl.ShaderSource(fs, 1, [frag.as_ptr() as *const _].as_ptr(), std::ptr::null());
-            gl.CompileShader(fs);
-            let mut status = 0;
-            gl.GetShaderiv(fs, gl::COMPILE_STATUS, &mut status);
-            if status == 0 {
-                log::error!("Failed to compile fragment shader");
-                print_shader_log(gl, fs);
-            } else {
-                log::debug!("Compiled fragment shader {}", fs);
+    /// Maps the used part of the buffer for reading and writing, e.g. to read back compute shader results.
+    /// Writes must be made visible to shaders with a memory barrier before mapping.
+    pub fn map<R>(&mut self, f: impl FnOnce(&mut [T]) -> R) -> Option<R> {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.BindBuffer(gl::SHADER_STORAGE_BUFFER, self.id);
+            let ptr = gl.MapBufferRange(gl::SHADER_STORAGE_BUFFER, 0, (self.count * size_of::<T>()) as GLsizeiptr, gl::MAP_READ_BIT | gl::MAP_WRITE_BIT);
+            let result = (!ptr.is_null()).then(|| f(std::slice::from_raw_parts_mut(ptr as *mut T, self.count)));
+            if result.is_some() {
+                gl.UnmapBuffer(gl::SHADER_STORAGE_BUFFER);
             }
+            gl.BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+            check_error(gl, "Failed to map storage buffer");
+            result
+        }
+    }
 
-            let program = gl.CreateProgram();
-            if !check_error(gl, "Failed to create shader program") {
-                log::debug!("Created shader program {}", program);
-            }
+    pub fn count(&self) -> usize {
+        self.count
+    }
 
-            gl.AttachShader(program, vs);
-            if !check_error(gl, "Failed to attach vertex shader") {
-                log::debug!("Attached vertex shader {} to program {}", vs, program);
-            }
+    pub fn max_count(&self) -> usize {
+        self.max_count
+    }
+}
 
-            gl.AttachShader(program, fs);
-            if !check_error(gl, "Failed to attach fragment shader") {
-                log::debug!("Attached fragment shader {} to program {}", fs, program);
-            }
+//////////////////////////////////////////////////
+// Indirect Buffer
 
-            gl.LinkProgram(program);
-            //print_program_info(gl, program);
-            if !check_error(gl, "Failed to link program") {
-                log::debug!("Linked program {}", program);
-            }
+impl GlIndirectBuffer {
+    pub fn new(gl: &Gl, usage: GLenum, commands: &[GlDrawElementsIndirectCommand]) -> GlIndirectBuffer {
+        GlIndirectBuffer::try_new(gl, usage, commands).expect("Failed to create indirect buffer")
+    }
 
-            GlShader {
-                gl: Some(gl.clone()),
-                vs,
-                fs,
-                program,
-            }
+    pub fn try_new(gl: &Gl, usage: GLenum, commands: &[GlDrawElementsIndirectCommand]) -> Result<GlIndirectBuffer, GlError> {
+        let mut id: GLuint = 0;
+        unsafe {
+            clear_error(gl);
+            gl.GenBuffers(1, &mut id);
+            gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, id);
+            gl.BufferData(gl::DRAW_INDIRECT_BUFFER, size_of_val(commands) as GLsizeiptr, commands.as_ptr() as *const _, usage);
+            gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
         }
+        let buffer = GlIndirectBuffer {
+            gl: Some(gl.clone()),
+            id,
+            usage,
+            count: commands.len(),
+            max_count: commands.len(),
+        };
+        unsafe { get_error(gl)? };
+        log::debug!("Created indirect buffer {}", id);
+        Ok(buffer)
     }
 
     pub fn bind(&mut self) {
         let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
         unsafe {
-            gl.UseProgram(self.program);
-            check_error(gl, "Failed to bind program");
+            gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, self.id);
+            check_error(gl, "Failed to bind indirect buffer");
         }
     }
 
     pub fn unbind(&mut self) {
         let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
         unsafe {
-            gl.UseProgram(0);
-            check_error(gl, "Failed to unbind program");
+            gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
+            check_error(gl, "Failed to unbind indirect buffer");
         }
     }
 
-    pub fn link_uniform(&mut self, unit: GLuint, location: &str) {
+    pub fn update(&mut self, commands: &[GlDrawElementsIndirectCommand]) {
         let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
         unsafe {
-            let c_location = std::ffi::CString::new(location).unwrap();
-            let loc = gl.GetUniformBlockIndex(self.program, c_location.as_ptr());
-            gl.UniformBlockBinding(self.program, loc, unit);
-            check_error(gl, "Failed to bind uniform");
+            gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, self.id);
+            if commands.len() > self.max_count {
+                self.max_count = grow_count(self.max_count, commands.len());
+                gl.BufferData(gl::DRAW_INDIRECT_BUFFER, (self.max_count * size_of::<GlDrawElementsIndirectCommand>()) as GLsizeiptr, std::ptr::null(), self.usage);
+            }
+            gl.BufferSubData(gl::DRAW_INDIRECT_BUFFER, 0, size_of_val(commands) as GLsizeiptr, commands.as_ptr() as *const _);
+            gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
+            if !check_error(gl, "Failed to update indirect buffer") {
+                log::debug!("Updated indirect buffer {}", self.id)
+            }
         }
+        self.count = commands.len();
     }
 
-    pub fn link_texture(&mut self, unit: GLint, location: &str) {
-        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
-        unsafe {
-            let c_location = std::ffi::CString::new(location).unwrap();
-            let loc = gl.GetUniformLocation(self.program, c_location.as_ptr());
-            gl.Uniform1i(loc, unit);
-            check_error(gl, "Failed to bind texture");
-        }
+    pub fn count(&self) -> usize {
+        self.count
     }
 
-    pub fn draw_arrays(&mut self, mode: GLenum, vertex_count: usize) {
-        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
-        unsafe {
-            gl.DrawArrays(mode, 0, vertex_count as GLsizei);
+    pub fn max_count(&self) -> usize {
+        self.max_count
+    }
+}
+
+//////////////////////////////////////////////////
+// Query
+
+impl GlQuery {
+    /// Timer queries (`TIME_ELAPSED_EXT`, `TIMESTAMP_EXT`) require `EXT_disjoint_timer_query` or desktop OpenGL.
+    pub fn is_timer_supported(gl: &Gl) -> bool {
+        let supported = gl.has_extension("GL_EXT_disjoint_timer_query") || !GlVersion::get(gl).es;
+        supported && gl.GetQueryObjectui64vEXT.is_loaded() && gl.QueryCounterEXT.is_loaded()
+    }
+
+    /// Creates a query for `target`, e.g. `ANY_SAMPLES_PASSED`, `TIME_ELAPSED_EXT` or `TIMESTAMP_EXT`.
+    pub fn new(gl: &Gl, target: GLenum) -> GlQuery {
+        GlQuery::try_new(gl, target).expect("Failed to create query")
+    }
+
+    pub fn try_new(gl: &Gl, target: GLenum) -> Result<GlQuery, GlError> {
+        let mut id: GLuint = 0;
+        unsafe {
+            clear_error(gl);
+            gl.GenQueries(1, &mut id);
+        }
+        let query = GlQuery {
+            gl: Some(gl.clone()),
+            id,
+            target,
+            active: false,
+        };
+        unsafe { get_error(gl)? };
+        log::debug!("Created query {}", id);
+        Ok(query)
+    }
+
+    pub fn begin(&mut self) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.BeginQuery(self.target, self.id);
+            check_error(gl, "Failed to begin query");
+        }
+        self.active = true;
+    }
+
+    pub fn end(&mut self) {
+        if self.active {
+            let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+            unsafe {
+                gl.EndQuery(self.target);
+                check_error(gl, "Failed to end query");
+            }
+            self.active = false;
+        }
+    }
+
+    /// Records the current GPU time, only valid for `TIMESTAMP_EXT` queries.
+    pub fn timestamp(&mut self) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.QueryCounterEXT(self.id, gl::TIMESTAMP_EXT);
+            check_error(gl, "Failed to record timestamp");
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        let mut available: GLuint = 0;
+        unsafe {
+            gl.GetQueryObjectuiv(self.id, gl::QUERY_RESULT_AVAILABLE, &mut available);
+            check_error(gl, "Failed to get query state");
+        }
+        available != 0
+    }
+
+    /// Returns the query result without stalling, `None` if it is not available yet.
+    /// Occlusion queries return 0 or 1, timer queries return nanoseconds.
+    pub fn result(&self) -> Option<u64> {
+        self.is_available().then(|| {
+            let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+            unsafe {
+                let result = match self.target {
+                    gl::TIME_ELAPSED_EXT | gl::TIMESTAMP_EXT => {
+                        let mut result: GLuint64 = 0;
+                        gl.GetQueryObjectui64vEXT(self.id, gl::QUERY_RESULT, &mut result);
+                        result
+                    }
+                    _ => {
+                        let mut result: GLuint = 0;
+                        gl.GetQueryObjectuiv(self.id, gl::QUERY_RESULT, &mut result);
+                        result as u64
+                    }
+                };
+                check_error(gl, "Failed to get query result");
+                result
+            }
+        })
+    }
+
+    /// Checks if the GPU timer was disturbed since the last call, timer results are invalid in this case.
+    pub fn is_disjoint(gl: &Gl) -> bool {
+        let mut disjoint: GLint = 0;
+        unsafe {
+            gl.GetIntegerv(gl::GPU_DISJOINT_EXT, &mut disjoint);
+            check_error(gl, "Failed to get disjoint state");
+        }
+        disjoint != 0
+    }
+}
+
+//////////////////////////////////////////////////
+// Fence
+
+impl GlFence {
+    /// Inserts a fence signaled once all previously submitted commands are completed.
+    pub fn new(gl: &Gl) -> GlFence {
+        GlFence::try_new(gl).expect("Failed to create fence")
+    }
+
+    pub fn try_new(gl: &Gl) -> Result<GlFence, GlError> {
+        let sync = unsafe {
+            clear_error(gl);
+            gl.FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0)
+        };
+        let fence = GlFence {
+            gl: Some(gl.clone()),
+            sync: (!sync.is_null()).then_some(sync),
+        };
+        unsafe { get_error(gl)? };
+        Ok(fence)
+    }
+
+    /// Replaces the fence with a new one at the current position in the command stream.
+    pub fn insert(&mut self) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            if let Some(sync) = self.sync.take() {
+                gl.DeleteSync(sync);
+            }
+            let sync = gl.FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            if !check_error(gl, "Failed to create fence") {
+                self.sync = Some(sync);
+            }
+        }
+    }
+
+    /// Checks without blocking, whether the fence is signaled.
+    pub fn is_signaled(&self) -> bool {
+        self.client_wait(0)
+    }
+
+    /// Blocks the CPU until the fence is signaled or the timeout elapsed. Returns true if signaled.
+    pub fn client_wait(&self, timeout_ns: u64) -> bool {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        match self.sync {
+            Some(sync) => unsafe {
+                let status = gl.ClientWaitSync(sync, gl::SYNC_FLUSH_COMMANDS_BIT, timeout_ns);
+                check_error(gl, "Failed to wait for fence");
+                status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+            },
+            None => true,
+        }
+    }
+
+    /// Lets the GPU wait for the fence before executing further commands, without blocking the CPU.
+    pub fn wait(&self) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        if let Some(sync) = self.sync {
+            unsafe {
+                gl.WaitSync(sync, 0, gl::TIMEOUT_IGNORED);
+                check_error(gl, "Failed to wait for fence");
+            }
+        }
+    }
+}
+
+//////////////////////////////////////////////////
+// Texture
+
+impl GlTexture {
+    pub fn new<P, Container>(gl: &Gl, images: &[image::ImageBuffer<P, Container>]) -> GlTexture
+    where
+        P: image::PixelWithColorType + 'static,
+        P::Subpixel: 'static,
+        Container: std::ops::Deref<Target = [P::Subpixel]>,
+    {
+        GlTexture::try_new(gl, images).expect("Failed to create texture")
+    }
+
+    pub fn try_new<P, Container>(gl: &Gl, images: &[image::ImageBuffer<P, Container>]) -> Result<GlTexture, GlError>
+    where
+        P: image::PixelWithColorType + 'static,
+        P::Subpixel: 'static,
+        Container: std::ops::Deref<Target = [P::Subpixel]>,
+    {
+        // all textures need same size
+        let img = images.first().ok_or_else(|| GlError::InvalidArgument("Texture needs at least one image".to_string()))?;
+        if images.iter().any(|other| other.dimensions() != img.dimensions()) {
+            return Err(GlError::InvalidArgument("All texture images need the same size".to_string()));
+        }
+        let raw_bytes = |img: &image::ImageBuffer<P, Container>| {
+            let raw: &[P::Subpixel] = img.as_raw();
+            unsafe { std::slice::from_raw_parts(raw.as_ptr() as *const u8, size_of_val(raw)) }
+        };
+        let color_type = <P as image::PixelWithColorType>::COLOR_TYPE;
+        let (format, internal_format, pixel_type) = match color_type {
+            image::ExtendedColorType::L8 => (gl::RED, gl::R8, gl::UNSIGNED_BYTE),
+            image::ExtendedColorType::Rgb8 => (gl::RGB, gl::RGB8, gl::UNSIGNED_BYTE),
+            image::ExtendedColorType::Rgba8 => (gl::RGBA, gl::RGBA8, gl::UNSIGNED_BYTE),
+            // there are no normalized 16 bit formats in GLES, they are converted to half floats
+            image::ExtendedColorType::Rgb16 | image::ExtendedColorType::Rgba16 => (gl::RGBA, gl::RGBA16F, gl::HALF_FLOAT),
+            other => return Err(GlError::UnsupportedFormat(format!("{:?}", other))),
+        };
+        let converted: Vec<Vec<u8>> = match color_type {
+            image::ExtendedColorType::Rgb16 => images.iter().map(|img| unorm16_to_half_rgba(raw_bytes(img), 3)).collect(),
+            image::ExtendedColorType::Rgba16 => images.iter().map(|img| unorm16_to_half_rgba(raw_bytes(img), 4)).collect(),
+            _ => Vec::new(),
+        };
+        let layers: Vec<&[u8]> = if converted.is_empty() {
+            images.iter().map(raw_bytes).collect()
+        } else {
+            converted.iter().map(|layer| layer.as_slice()).collect()
+        };
+        GlTexture::try_create(gl, img.width(), img.height(), &layers, (format, internal_format, pixel_type))
+    }
+
+    /// Creates a single layer texture from tightly packed pixel rows.
+    pub fn from_raw(gl: &Gl, width: u32, height: u32, format: GlTextureFormat, data: &[u8]) -> GlTexture {
+        GlTexture::try_from_raw(gl, width, height, format, data).expect("Failed to create texture")
+    }
+
+    pub fn try_from_raw(gl: &Gl, width: u32, height: u32, format: GlTextureFormat, data: &[u8]) -> Result<GlTexture, GlError> {
+        GlTexture::try_from_raw_layers(gl, width, height, format, &[data])
+    }
+
+    /// Creates a texture array with one layer per slice, all layers sharing size and format.
+    pub fn from_raw_layers(gl: &Gl, width: u32, height: u32, format: GlTextureFormat, layers: &[&[u8]]) -> GlTexture {
+        GlTexture::try_from_raw_layers(gl, width, height, format, layers).expect("Failed to create texture")
+    }
+
+    pub fn try_from_raw_layers(gl: &Gl, width: u32, height: u32, format: GlTextureFormat, layers: &[&[u8]]) -> Result<GlTexture, GlError> {
+        if layers.is_empty() {
+            return Err(GlError::InvalidArgument("Texture needs at least one layer".to_string()));
+        }
+        let layer_size = (width * height) as usize * format.pixel_size();
+        if layers.iter().any(|layer| layer.len() != layer_size) {
+            return Err(GlError::InvalidArgument("Texture data must match width * height * pixel size".to_string()));
+        }
+        GlTexture::try_create(gl, width, height, layers, format.gl_formats())
+    }
+
+    /// Creates a texture array from pre-filtered mip images instead of generating them.
+    /// Each layer holds its levels starting with the full size image, all layers need the same number of levels.
+    /// Select a mipmap min filter with `set_options` to sample them.
+    pub fn from_raw_mips(gl: &Gl, width: u32, height: u32, format: GlTextureFormat, layers: &[&[&[u8]]]) -> GlTexture {
+        GlTexture::try_from_raw_mips(gl, width, height, format, layers).expect("Failed to create texture")
+    }
+
+    pub fn try_from_raw_mips(gl: &Gl, width: u32, height: u32, format: GlTextureFormat, layers: &[&[&[u8]]]) -> Result<GlTexture, GlError> {
+        let level_count = layers.first().map_or(0, |levels| levels.len());
+        if level_count == 0 {
+            return Err(GlError::InvalidArgument("Texture needs at least one layer and mip level".to_string()));
+        }
+        if layers.iter().any(|levels| levels.len() != level_count) {
+            return Err(GlError::InvalidArgument("All layers need the same number of mip levels".to_string()));
+        }
+        let (pixel_format, internal_format, pixel_type) = format.gl_formats();
+        let levels: Vec<Vec<u8>> = (0..level_count)
+            .map(|level| {
+                let level_size = ((width >> level).max(1) * (height >> level).max(1)) as usize * format.pixel_size();
+                if layers.iter().any(|levels| levels[level].len() != level_size) {
+                    return Err(GlError::InvalidArgument(format!("Mip level {} must match its size * pixel size", level)));
+                }
+                Ok(layers.iter().flat_map(|levels| levels[level].iter().copied()).collect())
+            })
+            .collect::<Result<_, _>>()?;
+        let levels: Vec<&[u8]> = levels.iter().map(|level| level.as_slice()).collect();
+        GlTexture::try_create_levels(gl, width, height, layers.len() as u32, internal_format, Some((pixel_format, pixel_type)), &levels)
+    }
+
+    /// Creates a texture array from a complete mip chain of compressed data, e.g. ETC2, ASTC or BCn.
+    /// Each level holds the blocks of all layers back to back.
+    pub fn from_compressed_levels(gl: &Gl, width: u32, height: u32, layers: u32, internal_format: GLenum, levels: &[&[u8]]) -> GlTexture {
+        GlTexture::try_from_compressed_levels(gl, width, height, layers, internal_format, levels).expect("Failed to create texture")
+    }
+
+    pub fn try_from_compressed_levels(gl: &Gl, width: u32, height: u32, layers: u32, internal_format: GLenum, levels: &[&[u8]]) -> Result<GlTexture, GlError> {
+        GlTexture::try_create_levels(gl, width, height, layers, internal_format, None, levels)
+    }
+
+    /// Creates a texture array from a mip chain, each level holding all layers back to back.
+    /// Missing levels are generated for uncompressed formats if only the base level is given.
+    pub(crate) fn try_create_levels(
+        gl: &Gl,
+        width: u32,
+        height: u32,
+        layers: u32,
+        internal_format: GLenum,
+        format: Option<(GLenum, GLenum)>,
+        levels: &[&[u8]],
+    ) -> Result<GlTexture, GlError> {
+        if levels.is_empty() {
+            return Err(GlError::InvalidArgument("Texture needs at least one mip level".to_string()));
+        }
+        let generate_mipmaps = levels.len() == 1 && format.is_some();
+        let num_mip_map = if generate_mipmaps { 1 + (width.min(height) as f32).log2().floor() as i32 } else { levels.len() as i32 };
+
+        let mut id: GLuint = 0;
+        unsafe {
+            clear_error(gl);
+            gl.GenTextures(1, &mut id);
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, id);
+            gl.TexStorage3D(gl::TEXTURE_2D_ARRAY, num_mip_map, internal_format, width as GLsizei, height as GLsizei, layers as GLsizei);
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            levels.iter().enumerate().for_each(|(level, data)| {
+                let level_width = (width >> level).max(1) as GLsizei;
+                let level_height = (height >> level).max(1) as GLsizei;
+                match format {
+                    Some((format, pixel_type)) => {
+                        gl.TexSubImage3D(gl::TEXTURE_2D_ARRAY, level as GLint, 0, 0, 0, level_width, level_height, layers as GLsizei, format, pixel_type, data.as_ptr() as *const _);
+                    }
+                    None => {
+                        gl.CompressedTexSubImage3D(
+                            gl::TEXTURE_2D_ARRAY,
+                            level as GLint,
+                            0,
+                            0,
+                            0,
+                            level_width,
+                            level_height,
+                            layers as GLsizei,
+                            internal_format,
+                            data.len() as GLsizei,
+                            data.as_ptr() as *const _,
+                        );
+                    }
+                }
+            });
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            if generate_mipmaps {
+                gl.GenerateMipmap(gl::TEXTURE_2D_ARRAY);
+            }
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+        }
+        let texture = GlTexture {
+            gl: Some(gl.clone()),
+            id,
+            ..Default::default()
+        };
+        unsafe { get_error(gl)? };
+        log::debug!("Created texture array {} with {} levels", id, if generate_mipmaps { num_mip_map as usize } else { levels.len() });
+        Ok(texture)
+    }
+
+    fn try_create(gl: &Gl, width: u32, height: u32, layers: &[&[u8]], (format, internal_format, pixel_type): (GLenum, GLenum, GLenum)) -> Result<GlTexture, GlError> {
+        let num_mip_map = 1 + (width.min(height) as f32).log2().floor() as i32;
+
+        let mut id: GLuint = 0;
+        unsafe {
+            clear_error(gl);
+            gl.GenTextures(1, &mut id);
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, id);
+            gl.TexStorage3D(gl::TEXTURE_2D_ARRAY, num_mip_map, internal_format, width as GLsizei, height as GLsizei, layers.len() as GLsizei);
+            // rows are tightly packed
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            layers.iter().enumerate().for_each(|(i, layer)| {
+                gl.TexSubImage3D(
+                    gl::TEXTURE_2D_ARRAY,
+                    0,
+                    0,
+                    0,
+                    i as GLint,
+                    width as GLsizei,
+                    height as GLsizei,
+                    1,
+                    format,
+                    pixel_type,
+                    layer.as_ptr() as *const _,
+                );
+            });
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl.GenerateMipmap(gl::TEXTURE_2D_ARRAY);
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+        }
+        let texture = GlTexture {
+            gl: Some(gl.clone()),
+            id,
+            ..Default::default()
+        };
+        unsafe { get_error(gl)? };
+        log::debug!("Created texture array {} with {} levels", id, num_mip_map);
+        Ok(texture)
+    }
+
+    /// Creates a texture array with uninitialized storage and a single mip level, e.g. as render target.
+    pub fn with_storage(gl: &Gl, width: u32, height: u32, layers: u32, internal_format: GLenum) -> GlTexture {
+        GlTexture::try_with_storage(gl, width, height, layers, internal_format).expect("Failed to create texture storage")
+    }
+
+    pub fn try_with_storage(gl: &Gl, width: u32, height: u32, layers: u32, internal_format: GLenum) -> Result<GlTexture, GlError> {
+        let mut id: GLuint = 0;
+        unsafe {
+            clear_error(gl);
+            gl.GenTextures(1, &mut id);
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, id);
+            gl.TexStorage3D(gl::TEXTURE_2D_ARRAY, 1, internal_format, width as GLsizei, height as GLsizei, layers as GLsizei);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+        }
+        let texture = GlTexture {
+            gl: Some(gl.clone()),
+            id,
+            ..Default::default()
+        };
+        unsafe { get_error(gl)? };
+        log::debug!("Created texture storage {}", id);
+        Ok(texture)
+    }
+
+    /// Uploads tightly packed pixels into a region of mip level 0 of `layer`. Mipmaps are not regenerated.
+    pub fn update_region(&mut self, layer: u32, rect: GlRect, format: GlTextureFormat, data: &[u8]) {
+        assert_eq!(data.len(), (rect.width * rect.height) as usize * format.pixel_size(), "Texture data must match region size * pixel size");
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        let (pixel_format, _, pixel_type) = format.gl_formats();
+        unsafe {
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl.TexSubImage3D(gl::TEXTURE_2D_ARRAY, 0, rect.x, rect.y, layer as GLint, rect.width, rect.height, 1, pixel_format, pixel_type, data.as_ptr() as *const _);
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+            check_error(gl, "Failed to update texture region");
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Applies sampling options. Unsupported options are skipped.
+    pub fn set_options(&mut self, options: &GlTextureOptions) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, options.min_filter as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, options.mag_filter as GLint);
+            if let Some(limit) = GlTextureOptions::max_anisotropy_limit(gl) {
+                gl.TexParameterf(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAX_ANISOTROPY_EXT, options.max_anisotropy.clamp(1.0, limit));
+            }
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+            check_error(gl, "Failed to set texture options");
+        }
+    }
+
+    pub fn bind(&mut self, unit: GLuint) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.ActiveTexture(gl::TEXTURE0 + unit as GLuint);
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+            check_error(gl, "Failed to bind texture");
+        }
+        self.active_slots[unit as usize] = true;
+    }
+
+    pub fn unbind(&mut self) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            self.active_slots.iter_mut().enumerate().for_each(|(slot, active)| {
+                if *active {
+                    gl.ActiveTexture(gl::TEXTURE0 + slot as GLuint);
+                    gl.BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+                    check_error(gl, "Failed to unbind texture");
+                    *active = false;
+                }
+            });
+        }
+    }
+}
+
+//////////////////////////////////////////////////
+// Texture Options
+
+impl GlTextureOptions {
+    /// Highest supported anisotropy, `None` without anisotropic filtering support.
+    pub fn max_anisotropy_limit(gl: &Gl) -> Option<f32> {
+        if !gl.has_extension("GL_EXT_texture_filter_anisotropic") && !gl.has_extension("GL_ARB_texture_filter_anisotropic") {
+            return None;
+        }
+        let mut limit: GLfloat = 1.0;
+        unsafe {
+            gl.GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut limit);
+        }
+        Some(limit)
+    }
+}
+
+impl Default for GlTextureOptions {
+    fn default() -> Self {
+        GlTextureOptions {
+            min_filter: gl::LINEAR,
+            mag_filter: gl::LINEAR,
+            max_anisotropy: 1.0,
+        }
+    }
+}
+
+//////////////////////////////////////////////////
+// Texture Format
+
+impl GlTextureFormat {
+    /// Size of a single pixel of upload data in bytes.
+    pub fn pixel_size(&self) -> usize {
+        match self {
+            GlTextureFormat::R8 => 1,
+            GlTextureFormat::Rg8 | GlTextureFormat::R16F => 2,
+            GlTextureFormat::Rgb8 | GlTextureFormat::Srgb8 => 3,
+            GlTextureFormat::Rgba8 | GlTextureFormat::Srgb8Alpha8 | GlTextureFormat::Rg16F | GlTextureFormat::R32F => 4,
+            GlTextureFormat::Rgba16F => 8,
+            GlTextureFormat::Rgba32F => 16,
+        }
+    }
+
+    /// Pixel format, internal format and pixel type of the upload data.
+    pub fn gl_formats(&self) -> (GLenum, GLenum, GLenum) {
+        match self {
+            GlTextureFormat::R8 => (gl::RED, gl::R8, gl::UNSIGNED_BYTE),
+            GlTextureFormat::Rg8 => (gl::RG, gl::RG8, gl::UNSIGNED_BYTE),
+            GlTextureFormat::Rgb8 => (gl::RGB, gl::RGB8, gl::UNSIGNED_BYTE),
+            GlTextureFormat::Rgba8 => (gl::RGBA, gl::RGBA8, gl::UNSIGNED_BYTE),
+            GlTextureFormat::Srgb8 => (gl::RGB, gl::SRGB8, gl::UNSIGNED_BYTE),
+            GlTextureFormat::Srgb8Alpha8 => (gl::RGBA, gl::SRGB8_ALPHA8, gl::UNSIGNED_BYTE),
+            GlTextureFormat::R16F => (gl::RED, gl::R16F, gl::HALF_FLOAT),
+            GlTextureFormat::Rg16F => (gl::RG, gl::RG16F, gl::HALF_FLOAT),
+            GlTextureFormat::Rgba16F => (gl::RGBA, gl::RGBA16F, gl::HALF_FLOAT),
+            GlTextureFormat::R32F => (gl::RED, gl::R32F, gl::FLOAT),
+            GlTextureFormat::Rgba32F => (gl::RGBA, gl::RGBA32F, gl::FLOAT),
+        }
+    }
+}
+
+// native endian 16 bit channels in [0, 65535] to half float rgba, missing alpha is opaque
+fn unorm16_to_half_rgba(data: &[u8], channels: usize) -> Vec<u8> {
+    let values: Vec<u16> = data.chunks_exact(2).map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]])).collect();
+    let mut converted = Vec::with_capacity(values.len() / channels * 8);
+    for pixel in values.chunks_exact(channels) {
+        let alpha = pixel.get(3).map_or(1.0, |&alpha| alpha as f32 / 65535.0);
+        let rgba = [pixel[0] as f32 / 65535.0, pixel[1] as f32 / 65535.0, pixel[2] as f32 / 65535.0, alpha];
+        rgba.iter().for_each(|&value| converted.extend_from_slice(&half_from_f32(value).to_ne_bytes()));
+    }
+    converted
+}
+
+// rounds to the nearest half float, out of range values become infinity
+fn half_from_f32(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent >= 31 {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // subnormal half, too small values flush to zero
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = (mantissa | 0x80_0000) >> (1 - exponent);
+        return sign | ((mantissa + 0x1000) >> 13) as u16;
+    }
+    // a rounding carry moves into the exponent
+    sign | (((exponent as u32) << 10) + ((mantissa + 0x1000) >> 13)) as u16
+}
+
+//////////////////////////////////////////////////
+// Renderbuffer
+
+impl GlRenderbuffer {
+    pub fn new(gl: &Gl, internal_format: GLenum, width: u32, height: u32, samples: u32) -> GlRenderbuffer {
+        GlRenderbuffer::try_new(gl, internal_format, width, height, samples).expect("Failed to create renderbuffer")
+    }
+
+    pub fn try_new(gl: &Gl, internal_format: GLenum, width: u32, height: u32, samples: u32) -> Result<GlRenderbuffer, GlError> {
+        let mut id: GLuint = 0;
+        unsafe {
+            clear_error(gl);
+            gl.GenRenderbuffers(1, &mut id);
+            gl.BindRenderbuffer(gl::RENDERBUFFER, id);
+            if samples > 1 {
+                gl.RenderbufferStorageMultisample(gl::RENDERBUFFER, samples as GLsizei, internal_format, width as GLsizei, height as GLsizei);
+            } else {
+                gl.RenderbufferStorage(gl::RENDERBUFFER, internal_format, width as GLsizei, height as GLsizei);
+            }
+            gl.BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+        let renderbuffer = GlRenderbuffer { gl: Some(gl.clone()), id };
+        unsafe { get_error(gl)? };
+        log::debug!("Created renderbuffer {} with {} samples", id, samples);
+        Ok(renderbuffer)
+    }
+}
+
+//////////////////////////////////////////////////
+// Framebuffer
+
+impl GlFramebuffer {
+    /// Whether `RGBA16F` color attachments are renderable. Always true on desktop, GLES needs an extension.
+    pub fn is_half_float_supported(gl: &Gl) -> bool {
+        !GlVersion::get(gl).es || gl.has_extension("GL_EXT_color_buffer_half_float") || gl.has_extension("GL_EXT_color_buffer_float")
+    }
+
+    /// Creates an offscreen target with color and depth-stencil attachments. With `samples > 1` rendering
+    /// goes to multisampled renderbuffers, which are resolved into the single-sampled texture by `resolve`.
+    pub fn new(gl: &Gl, width: u32, height: u32, internal_format: GLenum, samples: u32) -> GlFramebuffer {
+        GlFramebuffer::try_new(gl, width, height, internal_format, samples).expect("Failed to create framebuffer")
+    }
+
+    pub fn try_new(gl: &Gl, width: u32, height: u32, internal_format: GLenum, samples: u32) -> Result<GlFramebuffer, GlError> {
+        GlFramebuffer::try_with_attachments(gl, width, height, &[internal_format], samples)
+    }
+
+    /// Creates an offscreen target with one color attachment per format, written by fragment outputs
+    /// `layout(location = i)` in order. All attachments share size and sample count.
+    pub fn with_attachments(gl: &Gl, width: u32, height: u32, internal_formats: &[GLenum], samples: u32) -> GlFramebuffer {
+        GlFramebuffer::try_with_attachments(gl, width, height, internal_formats, samples).expect("Failed to create framebuffer")
+    }
+
+    pub fn try_with_attachments(gl: &Gl, width: u32, height: u32, internal_formats: &[GLenum], samples: u32) -> Result<GlFramebuffer, GlError> {
+        if internal_formats.is_empty() {
+            return Err(GlError::InvalidArgument("Framebuffer needs at least one color attachment".to_string()));
+        }
+        let samples = samples.min(max_samples(gl)).max(1);
+        let textures = internal_formats
+            .iter()
+            .map(|&internal_format| GlTexture::try_with_storage(gl, width, height, 1, internal_format))
+            .collect::<Result<Vec<_>, _>>()?;
+        let colors = if samples > 1 {
+            internal_formats
+                .iter()
+                .map(|&internal_format| GlRenderbuffer::try_new(gl, internal_format, width, height, samples))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            Vec::new()
+        };
+        let depth = GlRenderbuffer::try_new(gl, gl::DEPTH24_STENCIL8, width, height, samples)?;
+
+        let mut ids = [0; 2];
+        unsafe {
+            clear_error(gl);
+            gl.GenFramebuffers(if samples > 1 { 2 } else { 1 }, ids.as_mut_ptr());
+        }
+        let framebuffer = GlFramebuffer {
+            gl: Some(gl.clone()),
+            id: ids[0],
+            resolve_id: ids[1],
+            width,
+            height,
+            samples,
+            internal_formats: internal_formats.to_vec(),
+            colors,
+            depth,
+            textures,
+        };
+        let draw_buffers: Vec<GLenum> = (0..internal_formats.len() as GLenum).map(|i| gl::COLOR_ATTACHMENT0 + i).collect();
+        unsafe {
+            gl.BindFramebuffer(gl::FRAMEBUFFER, ids[0]);
+            if samples > 1 {
+                framebuffer.colors.iter().zip(&draw_buffers).for_each(|(color, &attachment)| {
+                    gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER, color.id);
+                });
+            } else {
+                framebuffer.textures.iter().zip(&draw_buffers).for_each(|(texture, &attachment)| {
+                    gl.FramebufferTextureLayer(gl::FRAMEBUFFER, attachment, texture.id, 0, 0);
+                });
+            }
+            gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, framebuffer.depth.id);
+            gl.DrawBuffers(draw_buffers.len() as GLsizei, draw_buffers.as_ptr());
+            let mut status = framebuffer_status(gl);
+
+            if samples > 1 && status.is_ok() {
+                gl.BindFramebuffer(gl::FRAMEBUFFER, ids[1]);
+                framebuffer.textures.iter().zip(&draw_buffers).for_each(|(texture, &attachment)| {
+                    gl.FramebufferTextureLayer(gl::FRAMEBUFFER, attachment, texture.id, 0, 0);
+                });
+                status = framebuffer_status(gl);
+            }
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+            get_error(gl)?;
+            status?;
+        }
+        log::debug!(
+            "Created framebuffer {} ({} x {}, {} attachments, {} samples)",
+            ids[0],
+            width,
+            height,
+            internal_formats.len(),
+            samples
+        );
+        Ok(framebuffer)
+    }
+
+    /// Recreates all attachments with a new size, e.g. on `resize_device`.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) != (self.width, self.height) {
+            let gl = self.gl.clone().expect("Missing OpenGL Context!");
+            *self = GlFramebuffer::with_attachments(&gl, width, height, &self.internal_formats, self.samples);
+        }
+    }
+
+    pub fn bind(&mut self) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            check_error(gl, "Failed to bind framebuffer");
+        }
+    }
+
+    pub fn unbind(&mut self) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+            check_error(gl, "Failed to unbind framebuffer");
+        }
+    }
+
+    /// Resolves multisampled color into the textures. No-op for single-sampled framebuffers.
+    pub fn resolve(&mut self) {
+        if self.samples <= 1 {
+            return;
+        }
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        if self.textures.len() == 1 {
+            blit_framebuffer(gl, self.id, self.rect(), self.resolve_id, self.rect(), gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            return;
+        }
+        // blits only copy the read buffer, so resolve attachments one by one
+        let rect = self.rect();
+        let count = self.textures.len() as GLenum;
+        unsafe {
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.resolve_id);
+            (0..count).for_each(|i| {
+                let draw_buffers: Vec<GLenum> = (0..count).map(|j| if j == i { gl::COLOR_ATTACHMENT0 + i } else { gl::NONE }).collect();
+                gl.ReadBuffer(gl::COLOR_ATTACHMENT0 + i);
+                gl.DrawBuffers(count as GLsizei, draw_buffers.as_ptr());
+                gl.BlitFramebuffer(0, 0, rect.width, rect.height, 0, 0, rect.width, rect.height, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            });
+            let draw_buffers: Vec<GLenum> = (0..count).map(|i| gl::COLOR_ATTACHMENT0 + i).collect();
+            gl.ReadBuffer(gl::COLOR_ATTACHMENT0);
+            gl.DrawBuffers(count as GLsizei, draw_buffers.as_ptr());
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+            check_error(gl, "Failed to resolve framebuffer");
+        }
+    }
+
+    /// Clears a single color attachment. The framebuffer must be bound.
+    pub fn clear_color(&mut self, attachment: usize, color: [f32; 4]) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.ClearBufferfv(gl::COLOR, attachment as GLint, color.as_ptr());
+            check_error(gl, "Failed to clear color attachment");
+        }
+    }
+
+    /// Clears all color attachments to the same color. The framebuffer must be bound.
+    pub fn clear_colors(&mut self, color: [f32; 4]) {
+        (0..self.textures.len()).for_each(|attachment| self.clear_color(attachment, color));
+    }
+
+    /// Clears depth and stencil attachment. The framebuffer must be bound.
+    pub fn clear_depth_stencil(&mut self, depth: f32, stencil: i32) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.ClearBufferfi(gl::DEPTH_STENCIL, 0, depth, stencil);
+            check_error(gl, "Failed to clear depth stencil attachment");
+        }
+    }
+
+    /// Copies a region between framebuffers, `None` is the default framebuffer. `mask` selects color, depth and stencil.
+    /// Depth and stencil require `NEAREST` filtering, multisampled sources require equal rect sizes.
+    pub fn blit(gl: &Gl, src: Option<&GlFramebuffer>, src_rect: GlRect, dst: Option<&GlFramebuffer>, dst_rect: GlRect, mask: GLbitfield, filter: GLenum) {
+        if mask & (gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT) != 0 && filter != gl::NEAREST {
+            log::error!("Depth and stencil blits require NEAREST filtering");
+            return;
+        }
+        let src_id = src.map(|fb| fb.id).unwrap_or(0);
+        let dst_id = dst.map(|fb| fb.id).unwrap_or(0);
+        blit_framebuffer(gl, src_id, src_rect, dst_id, dst_rect, mask, filter);
+    }
+
+    /// Full size of the framebuffer.
+    pub fn rect(&self) -> GlRect {
+        GlRect {
+            x: 0,
+            y: 0,
+            width: self.width as i32,
+            height: self.height as i32,
+        }
+    }
+
+    /// Single-sampled color result of the first attachment, resolve multisampled framebuffers before sampling.
+    pub fn texture(&mut self) -> &mut GlTexture {
+        &mut self.textures[0]
+    }
+
+    /// Single-sampled color result of `attachment`.
+    pub fn texture_at(&mut self, attachment: usize) -> &mut GlTexture {
+        &mut self.textures[attachment]
+    }
+
+    pub fn attachment_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+fn blit_framebuffer(gl: &Gl, src_id: GLuint, src: GlRect, dst_id: GLuint, dst: GlRect, mask: GLbitfield, filter: GLenum) {
+    unsafe {
+        gl.BindFramebuffer(gl::READ_FRAMEBUFFER, src_id);
+        gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, dst_id);
+        gl.BlitFramebuffer(src.x, src.y, src.x + src.width, src.y + src.height, dst.x, dst.y, dst.x + dst.width, dst.y + dst.height, mask, filter);
+        gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+        check_error(gl, "Failed to blit framebuffer");
+    }
+}
+
+fn max_samples(gl: &Gl) -> u32 {
+    let mut samples: GLint = 0;
+    unsafe {
+        gl.GetIntegerv(gl::MAX_SAMPLES, &mut samples);
+        check_error(gl, "Failed to get max samples");
+    }
+    samples.max(1) as u32
+}
+
+unsafe fn framebuffer_status(gl: &Gl) -> Result<(), GlError> {
+    match gl.CheckFramebufferStatus(gl::FRAMEBUFFER) {
+        gl::FRAMEBUFFER_COMPLETE => Ok(()),
+        status => Err(GlError::IncompleteFramebuffer(status)),
+    }
+}
+
+//////////////////////////////////////////////////
+// Shader
+
+impl GlShader {
+    /// Compiles and links a program, logging failures and returning an invalid shader that draws nothing.
+    /// Use `try_new` for shader source that is not known to compile, e.g. user supplied effects.
+    pub fn new(gl: &Gl, vert: &[u8], frag: &[u8]) -> GlShader {
+        GlShader::try_new(gl, vert, frag).unwrap_or_else(|err| {
+            log::error!("Failed to create shader: {}", err);
+            // program 0 keeps bind and draw calls valid without rendering anything
+            GlShader {
+                gl: Some(gl.clone()),
+                ..Default::default()
+            }
+        })
+    }
+
+    /// Compiles and links a program, returning the info log on compile or link failure.
+    pub fn try_new(gl: &Gl, vert: &[u8], frag: &[u8]) -> Result<GlShader, GlError> {
+        // ensure null termination
+        let vert_string = std::ffi::CString::new(vert).map_err(|_| GlError::ShaderCompile("Vertex shader contains null byte".to_string()))?;
+        let frag_string = std::ffi::CString::new(frag).map_err(|_| GlError::ShaderCompile("Fragment shader contains null byte".to_string()))?;
+        let vert = vert_string.as_bytes_with_nul();
+        let frag = frag_string.as_bytes_with_nul();
+
+        unsafe {
+            clear_error(gl);
+            let shader = GlShader {
+                gl: Some(gl.clone()),
+                vs: gl.CreateShader(gl::VERTEX_SHADER),
+                fs: gl.CreateShader(gl::FRAGMENT_SHADER),
+                program: gl.CreateProgram(),
+            };
+            get_error(gl)?;
+            log::debug!("Created vertex shader {}, fragment shader {} and program {}", shader.vs, shader.fs, shader.program);
+
+            gl.ShaderSource(shader.vs, 1, [vert.as_ptr() as *const _].as_ptr(), std::ptr::null());
+            gl.CompileShader(shader.vs);
+            let mut status = 0;
+            gl.GetShaderiv(shader.vs, gl::COMPILE_STATUS, &mut status);
+            if status == 0 {
+                return Err(GlError::ShaderCompile(format!("Vertex shader: {}", shader_log(gl, shader.vs))));
+            }
+            log::debug!("Compiled vertex shader {}", shader.vs);
+
+            gl.ShaderSource(shader.fs, 1, [frag.as_ptr() as *const _].as_ptr(), std::ptr::null());
+            gl.CompileShader(shader.fs);
+            let mut status = 0;
+            gl.GetShaderiv(shader.fs, gl::COMPILE_STATUS, &mut status);
+            if status == 0 {
+                return Err(GlError::ShaderCompile(format!("Fragment shader: {}", shader_log(gl, shader.fs))));
+            }
+            log::debug!("Compiled fragment shader {}", shader.fs);
+
+            gl.AttachShader(shader.program, shader.vs);
+            gl.AttachShader(shader.program, shader.fs);
+            gl.LinkProgram(shader.program);
+            let mut status = 0;
+            gl.GetProgramiv(shader.program, gl::LINK_STATUS, &mut status);
+            if status == 0 {
+                return Err(GlError::ProgramLink(program_log(gl, shader.program)));
+            }
+            get_error(gl)?;
+            log::debug!("Linked program {}", shader.program);
+
+            Ok(shader)
+        }
+    }
+
+    /// Program name, e.g. for sorting draws by shader.
+    pub fn id(&self) -> GLuint {
+        self.program
+    }
+
+    pub fn bind(&mut self) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.UseProgram(self.program);
+            check_error(gl, "Failed to bind program");
+        }
+    }
+
+    pub fn unbind(&mut self) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.UseProgram(0);
+            check_error(gl, "Failed to unbind program");
+        }
+    }
+
+    pub fn link_uniform(&mut self, unit: GLuint, location: &str) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            let c_location = std::ffi::CString::new(location).unwrap();
+            let loc = gl.GetUniformBlockIndex(self.program, c_location.as_ptr());
+            gl.UniformBlockBinding(self.program, loc, unit);
+            check_error(gl, "Failed to bind uniform");
+        }
+    }
+
+    pub fn link_texture(&mut self, unit: GLint, location: &str) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            let c_location = std::ffi::CString::new(location).unwrap();
+            let loc = gl.GetUniformLocation(self.program, c_location.as_ptr());
+            gl.Uniform1i(loc, unit);
+            check_error(gl, "Failed to bind texture");
+        }
+    }
+
+    pub fn draw_arrays(&mut self, mode: GLenum, vertex_count: usize) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.DrawArrays(mode, 0, vertex_count as GLsizei);
+            check_error(gl, "Failed to draw");
+        }
+        GlDrawStats::record(mode, vertex_count, 1);
+    }
+    pub fn draw_elements(&mut self, mode: GLenum, index_count: usize) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.DrawElements(mode, index_count as GLsizei, gl::UNSIGNED_INT, std::ptr::null::<()>() as *const _);
+            check_error(gl, "Failed to draw");
+        }
+        GlDrawStats::record(mode, index_count, 1);
+    }
+
+    pub fn draw_elements_instanced(&mut self, mode: GLenum, index_count: usize, instance_count: usize) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        unsafe {
+            gl.DrawElementsInstanced(mode, index_count as GLsizei, gl::UNSIGNED_INT, std::ptr::null::<()>() as *const _, instance_count as GLsizei);
             check_error(gl, "Failed to draw");
         }
+        GlDrawStats::record(mode, index_count, instance_count);
+    }
+
+    /// Draws with parameters read from command `index` of the indirect buffer. Vertex array and indices must be bound.
+    pub fn draw_elements_indirect(&mut self, mode: GLenum, indirect: &mut GlIndirectBuffer, index: usize) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        indirect.bind();
+        unsafe {
+            let offset = index * size_of::<GlDrawElementsIndirectCommand>();
+            gl.DrawElementsIndirect(mode, gl::UNSIGNED_INT, offset as *const () as *const _);
+            check_error(gl, "Failed to draw indirect");
+        }
+        GlDrawStats::record(mode, 0, 0);
+        indirect.unbind();
+    }
+
+    /// Draws all commands of the indirect buffer. Uses `EXT_multi_draw_indirect` if available, otherwise issues one draw per command.
+    pub fn multi_draw_elements_indirect(&mut self, mode: GLenum, indirect: &mut GlIndirectBuffer) {
+        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+        indirect.bind();
+        unsafe {
+            if gl.MultiDrawElementsIndirectEXT.is_loaded() {
+                gl.MultiDrawElementsIndirectEXT(mode, gl::UNSIGNED_INT, std::ptr::null(), indirect.count() as GLsizei, 0);
+            } else {
+                (0..indirect.count()).for_each(|index| {
+                    let offset = index * size_of::<GlDrawElementsIndirectCommand>();
+                    gl.DrawElementsIndirect(mode, gl::UNSIGNED_INT, offset as *const () as *const _);
+                });
+            }
+            check_error(gl, "Failed to multi draw indirect");
+        }
+        (0..indirect.count()).for_each(|_| GlDrawStats::record(mode, 0, 0));
+        indirect.unbind();
+    }
+}
+
+//////////////////////////////////////////////////
+// Draw Stats
+
+thread_local! {
+    static DRAW_STATS: Cell<GlDrawStats> = Cell::new(GlDrawStats::default());
+}
+
+impl GlDrawStats {
+    /// Counts since the last call, resets the counters. The game loop takes them once per frame.
+    pub fn take() -> GlDrawStats {
+        DRAW_STATS.with(Cell::take)
+    }
+
+    fn record(mode: GLenum, count: usize, instances: usize) {
+        let triangles = match mode {
+            gl::TRIANGLES => count / 3,
+            gl::TRIANGLE_STRIP | gl::TRIANGLE_FAN => count.saturating_sub(2),
+            _ => 0,
+        };
+        DRAW_STATS.with(|stats| {
+            let mut current = stats.get();
+            current.draw_calls += 1;
+            current.triangles += (triangles * instances) as u64;
+            stats.set(current);
+        });
+    }
+}
+
+//////////////////////////////////////////////////
+// Capabilities
+
+impl GlCapabilities {
+    pub fn query(gl: &Gl) -> GlCapabilities {
+        let get = |gl_enum: GLenum| {
+            let mut value: GLint = 0;
+            unsafe {
+                gl.GetIntegerv(gl_enum, &mut value);
+            }
+            value.max(0) as u32
+        };
+        let capabilities = GlCapabilities {
+            version: GlVersion::get(gl),
+            max_texture_size: get(gl::MAX_TEXTURE_SIZE),
+            max_array_texture_layers: get(gl::MAX_ARRAY_TEXTURE_LAYERS),
+            max_uniform_block_size: get(gl::MAX_UNIFORM_BLOCK_SIZE),
+            max_samples: get(gl::MAX_SAMPLES).max(1),
+            max_vertex_attribs: get(gl::MAX_VERTEX_ATTRIBS),
+            depth_bits: default_framebuffer_bits(gl, gl::DEPTH, gl::FRAMEBUFFER_ATTACHMENT_DEPTH_SIZE),
+            stencil_bits: default_framebuffer_bits(gl, gl::STENCIL, gl::FRAMEBUFFER_ATTACHMENT_STENCIL_SIZE),
+            srgb_framebuffer: false,
+            max_anisotropy: GlTextureOptions::max_anisotropy_limit(gl),
+            color_buffer_half_float: GlFramebuffer::is_half_float_supported(gl),
+        };
+        unsafe {
+            check_error(gl, "Failed to query capabilities");
+        }
+        log::debug!("{:?}", capabilities);
+        capabilities
+    }
+}
+
+fn default_framebuffer_bits(gl: &Gl, attachment: GLenum, pname: GLenum) -> u32 {
+    let (mut object_type, mut bits): (GLint, GLint) = (0, 0);
+    unsafe {
+        gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        // sizes of missing attachments can not be queried
+        gl.GetFramebufferAttachmentParameteriv(gl::FRAMEBUFFER, attachment, gl::FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE, &mut object_type);
+        if object_type != gl::NONE as GLint {
+            gl.GetFramebufferAttachmentParameteriv(gl::FRAMEBUFFER, attachment, pname, &mut bits);
+        }
+    }
+    bits.max(0) as u32
+}
+
+//////////////////////////////////////////////////
+// Error
+
+impl GlError {
+    /// Maps a `GetError` code, `None` for `NO_ERROR`.
+    pub fn from_code(code: GLenum) -> Option<GlError> {
+        match code {
+            gl::NO_ERROR => None,
+            gl::INVALID_ENUM => Some(GlError::InvalidEnum),
+            gl::INVALID_VALUE => Some(GlError::InvalidValue),
+            gl::INVALID_OPERATION => Some(GlError::InvalidOperation),
+            gl::INVALID_FRAMEBUFFER_OPERATION => Some(GlError::InvalidFramebufferOperation),
+            gl::OUT_OF_MEMORY => Some(GlError::OutOfMemory),
+            code => Some(GlError::Unknown(code)),
+        }
+    }
+}
+
+impl std::fmt::Display for GlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GlError::InvalidEnum => write!(f, "Invalid enum"),
+            GlError::InvalidValue => write!(f, "Invalid value"),
+            GlError::InvalidOperation => write!(f, "Invalid operation"),
+            GlError::InvalidFramebufferOperation => write!(f, "Invalid framebuffer operation"),
+            GlError::OutOfMemory => write!(f, "Out of memory"),
+            GlError::Unknown(code) => write!(f, "ErrorCode {}", code),
+            GlError::IncompleteFramebuffer(status) => write!(f, "Framebuffer is incomplete. Status {}", status),
+            GlError::ShaderCompile(log) => write!(f, "Failed to compile shader. {}", log),
+            GlError::ProgramLink(log) => write!(f, "Failed to link program. {}", log),
+            GlError::InvalidArgument(message) => write!(f, "Invalid argument. {}", message),
+            GlError::UnsupportedFormat(format) => write!(f, "Unsupported format {}", format),
+        }
+    }
+}
+
+impl std::error::Error for GlError {}
+
+//////////////////////////////////////////////////
+// String
+
+impl GlString {
+    pub fn get(gl: &gl::Gles2, gl_enum: GLenum) -> Option<String> {
+        unsafe {
+            let s = gl.GetString(gl_enum);
+            (!s.is_null()).then(|| {
+                let bytes = CStr::from_ptr(s.cast()).to_bytes().to_vec();
+                String::from_utf8(bytes).expect("GetString bytes are not valid UTF8")
+            })
+        }
+    }
+
+    pub fn get_indexed(gl: &gl::Gles2, gl_enum: GLenum, index: GLuint) -> Option<String> {
+        unsafe {
+            let s = gl.GetStringi(gl_enum, index);
+            (!s.is_null()).then(|| {
+                let bytes = CStr::from_ptr(s.cast()).to_bytes().to_vec();
+                String::from_utf8(bytes).expect("GetString bytes are not valid UTF8")
+            })
+        }
+    }
+}
+
+fn extension_count(gl: &gl::Gles2) -> GLuint {
+    let mut count: GLint = 0;
+    unsafe {
+        gl.GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+    }
+    count.max(0) as GLuint
+}
+
+//////////////////////////////////////////////////
+// Rect
+
+impl GlRect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> GlRect {
+        GlRect { x, y, width, height }
+    }
+
+    pub fn intersect(&self, other: &GlRect) -> GlRect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let width = ((self.x + self.width).min(other.x + other.width) - x).max(0);
+        let height = ((self.y + self.height).min(other.y + other.height) - y).max(0);
+        GlRect { x, y, width, height }
+    }
+}
+
+//////////////////////////////////////////////////
+// Render State
+
+impl GlBlendState {
+    pub fn alpha() -> GlBlendState {
+        GlBlendState::new(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA)
+    }
+
+    pub fn premultiplied_alpha() -> GlBlendState {
+        GlBlendState::new(gl::ONE, gl::ONE_MINUS_SRC_ALPHA)
+    }
+
+    pub fn additive() -> GlBlendState {
+        GlBlendState::new(gl::SRC_ALPHA, gl::ONE)
+    }
+
+    /// Same factors for color and alpha, additive equation.
+    pub fn new(src: GLenum, dst: GLenum) -> GlBlendState {
+        GlBlendState {
+            src_rgb: src,
+            dst_rgb: dst,
+            src_alpha: src,
+            dst_alpha: dst,
+            equation_rgb: gl::FUNC_ADD,
+            equation_alpha: gl::FUNC_ADD,
+        }
     }
-    pub fn draw_elements(&mut self, mode: GLenum, index_count: usize) {
-        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+}
+
+impl Default for GlDepthState {
+    fn default() -> Self {
+        GlDepthState {
+            test: false,
+            write: true,
+            func: gl::LESS,
+        }
+    }
+}
+
+impl Default for GlStencilState {
+    fn default() -> Self {
+        GlStencilState {
+            func: gl::ALWAYS,
+            reference: 0,
+            read_mask: !0,
+            write_mask: !0,
+            fail: gl::KEEP,
+            depth_fail: gl::KEEP,
+            pass: gl::KEEP,
+        }
+    }
+}
+
+impl Default for GlRenderState {
+    fn default() -> Self {
+        GlRenderState {
+            blend: None,
+            depth: GlDepthState::default(),
+            stencil: None,
+            cull_face: None,
+            front_face: gl::CCW,
+            color_mask: [true; 4],
+        }
+    }
+}
+
+impl GlRenderState {
+    /// Depth tested and written, back faces culled.
+    pub fn opaque() -> GlRenderState {
+        GlRenderState {
+            depth: GlDepthState { test: true, ..Default::default() },
+            cull_face: Some(gl::BACK),
+            ..Default::default()
+        }
+    }
+
+    /// Alpha blended without depth writes, e.g. for sprites and UI.
+    pub fn transparent() -> GlRenderState {
+        GlRenderState {
+            blend: Some(GlBlendState::alpha()),
+            depth: GlDepthState { write: false, ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    /// Applies the state. With `current` given, only states differing from it are set.
+    pub fn apply(&self, gl: &Gl, current: Option<&GlRenderState>) {
         unsafe {
-            gl.DrawElements(mode, index_count as GLsizei, gl::UNSIGNED_INT, std::ptr::null::<()>() as *const _);
-            check_error(gl, "Failed to draw");
+            if current.is_none_or(|c| c.blend != self.blend) {
+                match self.blend {
+                    Some(blend) => {
+                        gl.Enable(gl::BLEND);
+                        gl.BlendFuncSeparate(blend.src_rgb, blend.dst_rgb, blend.src_alpha, blend.dst_alpha);
+                        gl.BlendEquationSeparate(blend.equation_rgb, blend.equation_alpha);
+                    }
+                    None => gl.Disable(gl::BLEND),
+                }
+            }
+            if current.is_none_or(|c| c.depth != self.depth) {
+                set_capability(gl, gl::DEPTH_TEST, self.depth.test);
+                gl.DepthMask(if self.depth.write { gl::TRUE } else { gl::FALSE });
+                gl.DepthFunc(self.depth.func);
+            }
+            if current.is_none_or(|c| c.stencil != self.stencil) {
+                match self.stencil {
+                    Some(stencil) => {
+                        gl.Enable(gl::STENCIL_TEST);
+                        gl.StencilFunc(stencil.func, stencil.reference, stencil.read_mask);
+                        gl.StencilMask(stencil.write_mask);
+                        gl.StencilOp(stencil.fail, stencil.depth_fail, stencil.pass);
+                    }
+                    None => gl.Disable(gl::STENCIL_TEST),
+                }
+            }
+            if current.is_none_or(|c| c.cull_face != self.cull_face) {
+                match self.cull_face {
+                    Some(mode) => {
+                        gl.Enable(gl::CULL_FACE);
+                        gl.CullFace(mode);
+                    }
+                    None => gl.Disable(gl::CULL_FACE),
+                }
+            }
+            if current.is_none_or(|c| c.front_face != self.front_face) {
+                gl.FrontFace(self.front_face);
+            }
+            if current.is_none_or(|c| c.color_mask != self.color_mask) {
+                let [r, g, b, a] = self.color_mask.map(|mask| if mask { gl::TRUE } else { gl::FALSE });
+                gl.ColorMask(r, g, b, a);
+            }
+            check_error(gl, "Failed to apply render state");
         }
     }
+}
 
-    pub fn draw_elements_instanced(&mut self, mode: GLenum, index_count: usize, instance_count: usize) {
-        let gl = self.gl.as_ref().expect("Missing OpenGL Context!");
+unsafe fn set_capability(gl: &Gl, capability: GLenum, enabled: bool) {
+    if enabled {
+        gl.Enable(capability);
+    } else {
+        gl.Disable(capability);
+    }
+}
+
+//////////////////////////////////////////////////
+// Viewport
+
+impl GlVirtualResolution {
+    pub fn new(width: u32, height: u32, mode: GlScaleMode) -> GlVirtualResolution {
+        GlVirtualResolution { width, height, mode }
+    }
+
+    /// Centered surface area the virtual resolution is scaled to.
+    pub fn content_rect(&self, surface_width: u32, surface_height: u32) -> GlRect {
+        let scale_x = surface_width as f32 / self.width.max(1) as f32;
+        let scale_y = surface_height as f32 / self.height.max(1) as f32;
+        let (scale_x, scale_y) = match self.mode {
+            GlScaleMode::Fit => (scale_x.min(scale_y), scale_x.min(scale_y)),
+            GlScaleMode::Crop => (scale_x.max(scale_y), scale_x.max(scale_y)),
+            GlScaleMode::Stretch => (scale_x, scale_y),
+        };
+        let width = (self.width as f32 * scale_x).round() as i32;
+        let height = (self.height as f32 * scale_y).round() as i32;
+        GlRect::new((surface_width as i32 - width) / 2, (surface_height as i32 - height) / 2, width, height)
+    }
+}
+
+impl GlClearFlags {
+    pub const COLOR: GlClearFlags = GlClearFlags(gl::COLOR_BUFFER_BIT);
+    pub const DEPTH: GlClearFlags = GlClearFlags(gl::DEPTH_BUFFER_BIT);
+    pub const STENCIL: GlClearFlags = GlClearFlags(gl::STENCIL_BUFFER_BIT);
+    pub const ALL: GlClearFlags = GlClearFlags(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+
+    pub fn contains(&self, other: GlClearFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn bits(&self) -> GLbitfield {
+        self.0
+    }
+}
+
+impl std::ops::BitOr for GlClearFlags {
+    type Output = GlClearFlags;
+
+    fn bitor(self, rhs: GlClearFlags) -> GlClearFlags {
+        GlClearFlags(self.0 | rhs.0)
+    }
+}
+
+impl GlViewport {
+    /// Resets both stacks and applies the content area as viewport, the full surface without virtual resolution.
+    pub fn resize(&mut self, gl: &Gl, width: u32, height: u32) {
+        self.size = GlRect::new(0, 0, width as i32, height as i32);
+        self.content = match self.virtual_resolution {
+            Some(virtual_resolution) => virtual_resolution.content_rect(width, height),
+            None => self.size,
+        };
+        self.viewports.clear();
+        self.scissors.clear();
+        gl.set_viewport(self.content);
+        gl.set_scissor(None);
+    }
+
+    /// Switches to a design resolution, `None` renders at surface resolution. Applied immediately.
+    pub fn set_virtual_resolution(&mut self, gl: &Gl, virtual_resolution: Option<GlVirtualResolution>) {
+        self.virtual_resolution = virtual_resolution;
+        self.resize(gl, self.size.width as u32, self.size.height as u32);
+    }
+
+    pub fn virtual_resolution(&self) -> Option<GlVirtualResolution> {
+        self.virtual_resolution
+    }
+
+    /// Full surface size of the last resize.
+    pub fn size(&self) -> GlRect {
+        self.size
+    }
+
+    /// Surface area showing the virtual resolution, may exceed the surface when cropping.
+    pub fn content(&self) -> GlRect {
+        self.content
+    }
+
+    /// Size the game renders at: the virtual resolution if set, the surface size otherwise.
+    pub fn logical_size(&self) -> (u32, u32) {
+        match self.virtual_resolution {
+            Some(virtual_resolution) => (virtual_resolution.width, virtual_resolution.height),
+            None => (self.size.width as u32, self.size.height as u32),
+        }
+    }
+
+    /// Maps a surface position in pixels (origin top left, e.g. cursor or touch) to logical coordinates.
+    pub fn to_logical(&self, x: f32, y: f32) -> (f32, f32) {
+        let (width, height) = self.logical_size();
+        if self.content.width <= 0 || self.content.height <= 0 {
+            return (x, y);
+        }
+        let top = (self.size.height - self.content.y - self.content.height) as f32;
+        ((x - self.content.x as f32) * width as f32 / self.content.width as f32, (y - top) * height as f32 / self.content.height as f32)
+    }
+
+    pub fn viewport(&self) -> GlRect {
+        self.viewports.last().copied().unwrap_or(self.content)
+    }
+
+    pub fn push_viewport(&mut self, gl: &Gl, rect: GlRect) {
+        self.viewports.push(rect);
+        gl.set_viewport(rect);
+    }
+
+    pub fn pop_viewport(&mut self, gl: &Gl) {
+        self.viewports.pop();
+        gl.set_viewport(self.viewport());
+    }
+
+    /// Clips rendering to `rect`, nested scissors are intersected with their parent.
+    pub fn push_scissor(&mut self, gl: &Gl, rect: GlRect) {
+        let rect = self.scissors.last().map(|parent| parent.intersect(&rect)).unwrap_or(rect);
+        self.scissors.push(rect);
+        gl.set_scissor(Some(rect));
+    }
+
+    pub fn pop_scissor(&mut self, gl: &Gl) {
+        self.scissors.pop();
+        gl.set_scissor(self.scissors.last().copied());
+    }
+}
+
+//////////////////////////////////////////////////
+// Version
+
+impl GlVersion {
+    pub fn get(gl: &gl::Gles2) -> GlVersion {
+        let mut major = 0;
+        let mut minor = 0;
         unsafe {
-            gl.DrawElementsInstanced(mode, index_count as GLsizei, gl::UNSIGNED_INT, std::ptr::null::<()>() as *const _, instance_count as GLsizei);
-            check_error(gl, "Failed to draw");
+            gl.GetIntegerv(gl::MAJOR_VERSION, &mut major);
+            gl.GetIntegerv(gl::MINOR_VERSION, &mut minor);
+            check_error(gl, "Failed to get version");
+        }
+        GlVersion {
+            es: GlString::get(gl, gl::VERSION).map(|version| version.starts_with("OpenGL ES")).unwrap_or(true),
+            major: major as u32,
+            minor: minor as u32,
         }
     }
+
+    /// Checks the version against the required ES or desktop version, depending on the running context.
+    pub fn at_least(&self, es_major: u32, es_minor: u32, gl_major: u32, gl_minor: u32) -> bool {
+        let required = if self.es { (es_major, es_minor) } else { (gl_major, gl_minor) };
+        (self.major, self.minor) >= required
+    }
 }
 
 //////////////////////////////////////////////////
-// String
+// Trait Impl GlExt
 
-impl GlString {
-    pub fn get(gl: &Gl, gl_enum: GLenum) -> Option<String> {
+impl GlExt for gl::Gles2 {
+    fn set_viewport(&self, rect: GlRect) {
         unsafe {
-            let s = gl.GetString(gl_enum);
-            (!s.is_null()).then(|| {
-                let bytes = CStr::from_ptr(s.cast()).to_bytes().to_vec();
-                String::from_utf8(bytes).expect("GetString bytes are not valid UTF8")
-            })
+            self.Viewport(rect.x, rect.y, rect.width, rect.height);
+            check_error(self, "Failed to set viewport");
+        }
+    }
+
+    fn set_scissor(&self, rect: Option<GlRect>) {
+        unsafe {
+            match rect {
+                Some(rect) => {
+                    self.Enable(gl::SCISSOR_TEST);
+                    self.Scissor(rect.x, rect.y, rect.width, rect.height);
+                }
+                None => self.Disable(gl::SCISSOR_TEST),
+            }
+            check_error(self, "Failed to set scissor");
+        }
+    }
+
+    fn clear(&self, flags: GlClearFlags, color: [f32; 4], depth: f32, stencil: i32) {
+        unsafe {
+            if flags.contains(GlClearFlags::COLOR) {
+                self.ClearColor(color[0], color[1], color[2], color[3]);
+            }
+            if flags.contains(GlClearFlags::DEPTH) {
+                self.ClearDepthf(depth);
+            }
+            if flags.contains(GlClearFlags::STENCIL) {
+                self.ClearStencil(stencil);
+            }
+            self.Clear(flags.0);
+            check_error(self, "Failed to clear");
+        }
+    }
+
+    fn read_pixels(&self, rect: GlRect) -> image::RgbaImage {
+        let (width, height) = (rect.width.max(0) as u32, rect.height.max(0) as u32);
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        unsafe {
+            // rows are tightly packed, the default alignment of 4 would pad odd widths
+            self.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            self.ReadPixels(rect.x, rect.y, rect.width, rect.height, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut _);
+            self.PixelStorei(gl::PACK_ALIGNMENT, 4);
+            check_error(self, "Failed to read pixels");
+        }
+        let mut image = image::RgbaImage::from_raw(width, height, pixels).unwrap_or_default();
+        // OpenGL rows start at the bottom
+        image::imageops::flip_vertical_in_place(&mut image);
+        image
+    }
+
+    fn enable_debug_output(&self, level: log::LevelFilter) -> bool {
+        let supported = self.has_extension("GL_KHR_debug") || GlVersion::get(self).at_least(3, 2, 4, 3);
+        if !supported || !self.DebugMessageCallback.is_loaded() || !self.DebugMessageControl.is_loaded() {
+            return false;
+        }
+        unsafe {
+            self.Enable(gl::DEBUG_OUTPUT);
+            self.Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+            self.DebugMessageCallback(Some(debug_message_callback), std::ptr::null());
+            [
+                (gl::DEBUG_SEVERITY_HIGH, log::Level::Error),
+                (gl::DEBUG_SEVERITY_MEDIUM, log::Level::Warn),
+                (gl::DEBUG_SEVERITY_LOW, log::Level::Info),
+                (gl::DEBUG_SEVERITY_NOTIFICATION, log::Level::Debug),
+            ]
+            .iter()
+            .for_each(|&(severity, severity_level)| {
+                let enabled = if severity_level <= level { gl::TRUE } else { gl::FALSE };
+                self.DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, severity, 0, std::ptr::null(), enabled);
+            });
+            let enabled = !check_error(self, "Failed to enable debug output");
+            DEBUG_OUTPUT.with(|debug_output| debug_output.set(enabled));
+            enabled
+        }
+    }
+
+    fn extensions(&self) -> HashSet<String> {
+        (0..extension_count(self)).filter_map(|i| GlString::get_indexed(self, gl::EXTENSIONS, i)).collect()
+    }
+
+    fn has_extension(&self, name: &str) -> bool {
+        (0..extension_count(self)).any(|i| GlString::get_indexed(self, gl::EXTENSIONS, i).as_deref() == Some(name))
+    }
+
+    fn push_debug_group(&self, name: &str) {
+        if self.PushDebugGroup.is_loaded() {
+            unsafe {
+                self.PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0, name.len() as GLsizei, name.as_ptr() as *const _);
+            }
+        }
+    }
+
+    fn pop_debug_group(&self) {
+        if self.PopDebugGroup.is_loaded() {
+            unsafe {
+                self.PopDebugGroup();
+            }
+        }
+    }
+
+    fn insert_debug_marker(&self, name: &str) {
+        if self.DebugMessageInsert.is_loaded() {
+            unsafe {
+                self.DebugMessageInsert(
+                    gl::DEBUG_SOURCE_APPLICATION,
+                    gl::DEBUG_TYPE_MARKER,
+                    0,
+                    gl::DEBUG_SEVERITY_NOTIFICATION,
+                    name.len() as GLsizei,
+                    name.as_ptr() as *const _,
+                );
+            }
         }
     }
 }
 
+extern "system" fn debug_message_callback(source: GLenum, type_: GLenum, id: GLuint, severity: GLenum, length: GLsizei, message: *const GLchar, _user_param: *mut std::ffi::c_void) {
+    let message = unsafe {
+        if length >= 0 {
+            String::from_utf8_lossy(std::slice::from_raw_parts(message as *const u8, length as usize)).into_owned()
+        } else {
+            CStr::from_ptr(message).to_string_lossy().into_owned()
+        }
+    };
+    let level = match severity {
+        gl::DEBUG_SEVERITY_HIGH => log::Level::Error,
+        gl::DEBUG_SEVERITY_MEDIUM => log::Level::Warn,
+        gl::DEBUG_SEVERITY_LOW => log::Level::Info,
+        _ => log::Level::Debug,
+    };
+    log::log!(level, "OpenGL [source {:#x}, type {:#x}, id {}]: {}", source, type_, id, message);
+}
+
 //////////////////////////////////////////////////
 // Trait Impl GlResource
 
@@ -630,6 +2529,81 @@ impl<T: Default> Drop for GlUniformBuffer<T> {
     }
 }
 
+impl<T: Default> GlResource for GlStorageBuffer<T> {
+    fn release(&mut self) {
+        if let Some(gl) = self.gl.as_ref() {
+            unsafe {
+                gl.DeleteBuffers(1, &self.id);
+                if !check_error(gl, "Failed to release storage buffer") {
+                    log::debug!("Deleted storage buffer {}", self.id)
+                }
+            }
+        }
+        self.gl = None;
+    }
+}
+impl<T: Default> Drop for GlStorageBuffer<T> {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
+
+impl GlResource for GlIndirectBuffer {
+    fn release(&mut self) {
+        if let Some(gl) = self.gl.as_ref() {
+            unsafe {
+                gl.DeleteBuffers(1, &self.id);
+                if !check_error(gl, "Failed to release indirect buffer") {
+                    log::debug!("Deleted indirect buffer {}", self.id);
+                }
+            }
+        }
+        self.gl = None;
+    }
+}
+impl Drop for GlIndirectBuffer {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
+
+impl GlResource for GlQuery {
+    fn release(&mut self) {
+        self.end();
+        if let Some(gl) = self.gl.as_ref() {
+            unsafe {
+                gl.DeleteQueries(1, &self.id);
+                if !check_error(gl, "Failed to release query") {
+                    log::debug!("Deleted query {}", self.id);
+                }
+            }
+        }
+        self.gl = None;
+    }
+}
+impl Drop for GlQuery {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
+
+impl GlResource for GlFence {
+    fn release(&mut self) {
+        if let (Some(gl), Some(sync)) = (self.gl.as_ref(), self.sync.take()) {
+            unsafe {
+                gl.DeleteSync(sync);
+                check_error(gl, "Failed to release fence");
+            }
+        }
+        self.gl = None;
+    }
+}
+impl Drop for GlFence {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
+
 impl GlResource for GlTexture {
     fn release(&mut self) {
         if let Some(gl) = self.gl.as_ref() {
@@ -649,9 +2623,53 @@ impl Drop for GlTexture {
     }
 }
 
-impl GlResource for GlShader {
+impl GlResource for GlRenderbuffer {
+    fn release(&mut self) {
+        if let Some(gl) = self.gl.as_ref() {
+            unsafe {
+                gl.DeleteRenderbuffers(1, &self.id);
+                if !check_error(gl, "Failed to release renderbuffer") {
+                    log::debug!("Deleted renderbuffer {}", self.id);
+                }
+            }
+        }
+        self.gl = None;
+    }
+}
+impl Drop for GlRenderbuffer {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
+
+impl GlResource for GlFramebuffer {
     fn release(&mut self) {
         if let Some(gl) = self.gl.as_ref() {
+            unsafe {
+                gl.DeleteFramebuffers(1, &self.id);
+                if self.resolve_id != 0 {
+                    gl.DeleteFramebuffers(1, &self.resolve_id);
+                }
+                if !check_error(gl, "Failed to release framebuffer") {
+                    log::debug!("Deleted framebuffer {}", self.id);
+                }
+            }
+        }
+        self.colors.iter_mut().for_each(GlResource::release);
+        self.depth.release();
+        self.textures.iter_mut().for_each(GlResource::release);
+        self.gl = None;
+    }
+}
+impl Drop for GlFramebuffer {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
+
+impl GlResource for GlShader {
+    fn release(&mut self) {
+        if let Some(gl) = self.gl.as_ref().filter(|_| self.program != 0) {
             unsafe {
                 gl.DetachShader(self.program, self.vs);
                 if !check_error(gl, "Failed to destroy shaders") {
@@ -684,38 +2702,88 @@ impl Drop for GlShader {
     }
 }
 
+//////////////////////////////////////////////////
+// Buffer growth
+
+#[inline]
+fn grow_count(max_count: usize, required: usize) -> usize {
+    // grow geometrically to avoid reallocating on every small increase
+    required.max(max_count * 2)
+}
+
 //////////////////////////////////////////////////
 // Check error call
 
+thread_local! {
+    // set once the debug callback reports errors, polling them would only stall the driver
+    static DEBUG_OUTPUT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// # Safety
+/// Requires a current OpenGL context.
 #[inline]
 #[cfg(debug_assertions)]
-pub unsafe fn check_error(gl: &Gl, description: &str) -> bool {
-    let mut err = gl.GetError();
+pub unsafe fn check_error(gl: &gl::Gles2, description: &str) -> bool {
+    if DEBUG_OUTPUT.with(Cell::get) {
+        return false;
+    }
     let mut has_error = false;
-    while err != gl::NO_ERROR {
-        log::error!("{}. ErrorCode {}", description, err);
-        err = gl.GetError();
+    while let Some(err) = GlError::from_code(gl.GetError()) {
+        log::error!("{}. {}", description, err);
         has_error = true;
     }
     has_error
 }
 
+/// # Safety
+/// Requires a current OpenGL context.
 #[inline]
 #[cfg(not(debug_assertions))]
-pub unsafe fn check_error(_gl: &Gl, _description: &str) -> bool {
+pub unsafe fn check_error(_gl: &gl::Gles2, _description: &str) -> bool {
     false
 }
 
+/// Reads all pending errors and returns the first one. Unlike `check_error`, errors are also read in release builds.
+///
+/// # Safety
+/// Requires a current OpenGL context.
+pub unsafe fn get_error(gl: &gl::Gles2) -> Result<(), GlError> {
+    let mut first = None;
+    while let Some(err) = GlError::from_code(gl.GetError()) {
+        first.get_or_insert(err);
+    }
+    first.map_or(Ok(()), Err)
+}
+
+// discards errors of earlier calls, so they are not reported by the next resource creation
+unsafe fn clear_error(gl: &gl::Gles2) {
+    if let Err(err) = get_error(gl) {
+        log::warn!("Discarding unhandled OpenGL error: {}", err);
+    }
+}
+
+/// # Safety
+/// Requires a current OpenGL context and a valid shader id.
 pub unsafe fn print_shader_log(gl: &Gl, shader: GLuint) {
+    log::debug!("{}", shader_log(gl, shader));
+}
+
+/// # Safety
+/// Requires a current OpenGL context and a valid program id.
+pub unsafe fn print_program_info(gl: &Gl, program: GLuint) {
+    log::debug!("{}", program_log(gl, program));
+}
+
+unsafe fn shader_log(gl: &Gl, shader: GLuint) -> String {
     let mut buffer = vec![0u8; 2048];
     let mut length = 0;
     gl.GetShaderInfoLog(shader, (buffer.len() * size_of::<u8>()) as GLsizei, &mut length, buffer.as_mut_ptr() as *mut _);
-    log::debug!("{}", &String::from_utf8_lossy(&buffer[..length as usize]));
+    String::from_utf8_lossy(&buffer[..length as usize]).into_owned()
 }
 
-pub unsafe fn print_program_info(gl: &Gl, program: GLuint) {
+unsafe fn program_log(gl: &Gl, program: GLuint) -> String {
     let mut buffer = vec![0u8; 2048];
     let mut length = 0;
     gl.GetProgramInfoLog(program, (buffer.len() * size_of::<u8>()) as GLsizei, &mut length, buffer.as_mut_ptr() as *mut _);
-    log::debug!("{}", &String::from_utf8_lossy(&buffer[..length as usize]));
+    String::from_utf8_lossy(&buffer[..length as usize]).into_owned()
 }
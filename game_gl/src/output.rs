@@ -0,0 +1,201 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(not(target_os = "android"))]
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+#[cfg(target_os = "android")]
+use ndk::audio::{AudioCallbackResult, AudioDirection, AudioFormat, AudioPerformanceMode, AudioSharingMode, AudioStream, AudioStreamBuilder};
+
+use crate::audio::AudioError;
+use crate::mixer::Mixer;
+
+//////////////////////////////////////////////////
+// Definition
+
+// delay between attempts to reopen a disconnected stream
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Plays a mixer on the default output device until dropped. Desktop uses cpal, Android uses low latency
+/// AAudio streams (Android 8.0 and later). When the device goes away, e.g. headphones are unplugged, `update`
+/// reopens the stream on the new default device, retrying every second until one is available.
+pub struct AudioOutput {
+    mixer: Mixer,
+    #[cfg(not(target_os = "android"))]
+    stream: cpal::Stream,
+    #[cfg(target_os = "android")]
+    stream: AudioStream,
+    // set by the error callback, streams can't be reopened from there
+    disconnected: Arc<AtomicBool>,
+    // earliest next reopen after a failed one
+    retry_at: Option<Instant>,
+    paused: bool,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+#[cfg(not(target_os = "android"))]
+impl AudioOutput {
+    /// Opens the default output device and switches the mixer to its format.
+    pub fn new(mixer: &Mixer) -> Result<AudioOutput, AudioError> {
+        let device = cpal::default_host().default_output_device().ok_or_else(|| AudioError::Output("No output device".to_string()))?;
+        let config = device.default_output_config().map_err(|e| AudioError::Output(e.to_string()))?;
+        mixer.set_format(config.sample_rate(), config.channels());
+        let disconnected = Arc::new(AtomicBool::new(false));
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config.config(), mixer.clone(), disconnected.clone()),
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config.config(), mixer.clone(), disconnected.clone()),
+            cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config.config(), mixer.clone(), disconnected.clone()),
+            format => return Err(AudioError::Output(format!("Unsupported sample format {}", format))),
+        }?;
+        stream.play().map_err(|e| AudioError::Output(e.to_string()))?;
+        log::info!("Opened audio output with {} Hz and {} channels", config.sample_rate(), config.channels());
+        Ok(AudioOutput {
+            mixer: mixer.clone(),
+            stream,
+            disconnected,
+            retry_at: None,
+            paused: false,
+        })
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        if let Err(e) = self.stream.pause() {
+            log::warn!("Failed to pause audio output: {}", e);
+        }
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        if let Err(e) = self.stream.play() {
+            log::warn!("Failed to resume audio output: {}", e);
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+impl AudioOutput {
+    /// Opens a low latency stream and switches the mixer to its format.
+    pub fn new(mixer: &Mixer) -> Result<AudioOutput, AudioError> {
+        let disconnected = Arc::new(AtomicBool::new(false));
+        let stream = open_stream(mixer.clone(), disconnected.clone()).map_err(|e| AudioError::Output(e.to_string()))?;
+        mixer.set_format(stream.sample_rate() as u32, stream.channel_count() as u16);
+        stream.request_start().map_err(|e| AudioError::Output(e.to_string()))?;
+        log::info!(
+            "Opened AAudio output with {} Hz, {} channels and {} frames per burst",
+            stream.sample_rate(),
+            stream.channel_count(),
+            stream.frames_per_burst()
+        );
+        Ok(AudioOutput {
+            mixer: mixer.clone(),
+            stream,
+            disconnected,
+            retry_at: None,
+            paused: false,
+        })
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        if let Err(e) = self.stream.request_pause() {
+            log::warn!("Failed to pause audio output: {}", e);
+        }
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        if let Err(e) = self.stream.request_start() {
+            log::warn!("Failed to resume audio output: {}", e);
+        }
+    }
+}
+
+impl AudioOutput {
+    /// Reopens the stream after the device was disconnected, called once per frame.
+    pub fn update(&mut self) {
+        if !self.disconnected.load(Ordering::Acquire) || self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return;
+        }
+        self.disconnected.store(false, Ordering::Release);
+        log::info!("Audio device changed, reopening output");
+        match AudioOutput::new(&self.mixer) {
+            Ok(output) => {
+                let paused = self.paused;
+                *self = output;
+                if paused {
+                    self.pause();
+                }
+            }
+            // keeps the flag set, so the stream is reopened once a device is available again
+            Err(e) => {
+                log::error!("{}", e);
+                self.disconnected.store(true, Ordering::Release);
+                self.retry_at = Some(Instant::now() + RETRY_DELAY);
+            }
+        }
+    }
+}
+
+// mixes in f32 and converts into the sample type of the device
+#[cfg(not(target_os = "android"))]
+fn build_stream<T>(device: &cpal::Device, config: &cpal::StreamConfig, mixer: Mixer, disconnected: Arc<AtomicBool>) -> Result<cpal::Stream, AudioError>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    let mut buffer = Vec::new();
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                buffer.resize(data.len(), 0.0);
+                mixer.mix(&mut buffer);
+                data.iter_mut().zip(buffer.iter()).for_each(|(sample, value)| *sample = T::from_sample(*value));
+            },
+            move |e| {
+                log::error!("Audio output failed: {}", e);
+                if matches!(e, cpal::StreamError::DeviceNotAvailable | cpal::StreamError::StreamInvalidated) {
+                    disconnected.store(true, Ordering::Release);
+                }
+            },
+            None,
+        )
+        .map_err(|e| AudioError::Output(e.to_string()))
+}
+
+// float stream in the device's native rate and channel count, so AAudio can use the fast path without resampling
+// or remixing. The mixer follows the channel count the stream was opened with.
+#[cfg(target_os = "android")]
+fn open_stream(mixer: Mixer, disconnected: Arc<AtomicBool>) -> ndk::audio::Result<AudioStream> {
+    AudioStreamBuilder::new()?
+        .direction(AudioDirection::Output)
+        .performance_mode(AudioPerformanceMode::LowLatency)
+        .sharing_mode(AudioSharingMode::Exclusive)
+        .format(AudioFormat::PCM_Float)
+        .data_callback(Box::new(move |stream, data, frames| {
+            let len = frames.max(0) as usize * stream.channel_count().max(1) as usize;
+            // the stream was opened with float samples
+            let output = unsafe { std::slice::from_raw_parts_mut(data as *mut f32, len) };
+            mixer.mix(output);
+            AudioCallbackResult::Continue
+        }))
+        .error_callback(Box::new(move |_, e| {
+            log::error!("Audio output failed: {}", e);
+            disconnected.store(true, Ordering::Release);
+        }))
+        .open_stream()
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl std::fmt::Debug for AudioOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioOutput").field("paused", &self.paused).finish()
+    }
+}
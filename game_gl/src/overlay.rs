@@ -0,0 +1,152 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::VecDeque;
+
+use nalgebra_glm as glm;
+
+use crate::input::{InputEvent, Key, KeyState};
+use crate::opengl::*;
+use crate::profiler::GpuScopeResult;
+use crate::shape::ShapeRenderer;
+use crate::text::TextRenderer;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+// frame times kept for the graph
+const GRAPH_FRAMES: usize = 120;
+// graph height in milliseconds
+const GRAPH_RANGE_MS: f32 = 50.0;
+
+const PANEL_WIDTH: f32 = 280.0;
+const LINE_HEIGHT: f32 = 16.0;
+const GRAPH_HEIGHT: f32 = 60.0;
+
+/// Shows fps, a frame time graph, draw call and triangle counts and GPU profiler scopes on top of the game.
+/// Toggled by `set_enabled` or the toggle key (F3 by default). Text needs a font set with `set_font`,
+/// without one only the graph is shown.
+#[derive(Debug)]
+pub struct DebugOverlay {
+    enabled: bool,
+    toggle_key: Option<Key>,
+    font: Option<Vec<u8>>,
+    frame_times: VecDeque<f32>,
+    stats: GlDrawStats,
+    shapes: Option<ShapeRenderer>,
+    text: Option<TextRenderer>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl DebugOverlay {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    /// Key toggling the overlay, `None` to only toggle from code.
+    pub fn set_toggle_key(&mut self, key: Option<Key>) {
+        self.toggle_key = key;
+    }
+
+    /// Font for the overlay text, e.g. loaded with `Files::load_bytes`.
+    pub fn set_font(&mut self, font: Vec<u8>) {
+        self.font = Some(font);
+        self.text = None;
+    }
+
+    pub(crate) fn input(&mut self, input_events: &[InputEvent]) {
+        let toggled = input_events.iter().filter(|event| matches!(event, InputEvent::Keyboard(keyboard) if matches!(keyboard.state, KeyState::Pressed) && Some(keyboard.key) == self.toggle_key)).count();
+        if toggled % 2 == 1 {
+            self.toggle();
+        }
+    }
+
+    pub(crate) fn record_frame(&mut self, elapsed_time: f32) {
+        if self.frame_times.len() == GRAPH_FRAMES {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(elapsed_time * 1000.0);
+    }
+
+    /// Stores the stats of the game's draws, taken before the overlay draws itself.
+    pub(crate) fn record_stats(&mut self, stats: GlDrawStats) {
+        self.stats = stats;
+    }
+
+    /// Draws the overlay over the whole surface of `rect` and restores `viewport` afterwards.
+    pub(crate) fn render(&mut self, gl: &Gl, rect: GlRect, viewport: GlRect, gpu_scopes: &[GpuScopeResult]) {
+        if !self.enabled {
+            return;
+        }
+        let shapes = self.shapes.get_or_insert_with(|| ShapeRenderer::new(gl));
+        if self.text.is_none() {
+            if let Some(font) = self.font.as_ref() {
+                self.text = TextRenderer::new(gl, font.clone()).map_err(|e| log::error!("Invalid debug overlay font: {}", e)).ok();
+            }
+        }
+
+        let average_ms = self.frame_times.iter().sum::<f32>() / self.frame_times.len().max(1) as f32;
+        let mut lines = vec![
+            format!("{:.0} fps ({:.2} ms)", 1000.0 / average_ms.max(f32::EPSILON), average_ms),
+            format!("{} draw calls, {} triangles", self.stats.draw_calls, self.stats.triangles),
+        ];
+        lines.extend(gpu_scopes.iter().map(|scope| format!("{}{}: {:.2} ms", "  ".repeat(scope.depth), scope.name, scope.duration_ms)));
+
+        // panel with the graph at the top and text lines below
+        let margin = 8.0;
+        let text_height = if self.text.is_some() { lines.len() as f32 * LINE_HEIGHT } else { 0.0 };
+        shapes.rect([0.0, 0.0], [PANEL_WIDTH, GRAPH_HEIGHT + text_height + 3.0 * margin], [0.0, 0.0, 0.0, 0.6]);
+        let bar_width = (PANEL_WIDTH - 2.0 * margin) / GRAPH_FRAMES as f32;
+        let graph_bottom = margin + GRAPH_HEIGHT;
+        self.frame_times.iter().enumerate().for_each(|(i, &ms)| {
+            let height = (ms / GRAPH_RANGE_MS).min(1.0) * GRAPH_HEIGHT;
+            let color = if ms > 1000.0 / 30.0 { [1.0, 0.2, 0.2, 1.0] } else if ms > 1000.0 / 60.0 + 1.0 { [1.0, 0.8, 0.2, 1.0] } else { [0.2, 1.0, 0.4, 1.0] };
+            let x = margin + i as f32 * bar_width;
+            shapes.rect([x, graph_bottom - height], [x + bar_width, graph_bottom], color);
+        });
+        let target_y = graph_bottom - (1000.0 / 60.0 / GRAPH_RANGE_MS) * GRAPH_HEIGHT;
+        shapes.line([margin, target_y], [PANEL_WIDTH - margin, target_y], 1.0, [1.0, 1.0, 1.0, 0.5]);
+
+        gl.set_viewport(rect);
+        let projection: glm::Mat4 = glm::ortho(0.0, rect.width as f32, rect.height as f32, 0.0, -1.0, 1.0);
+        shapes.render(gl, &projection.into());
+        if let Some(text) = self.text.as_mut() {
+            lines.iter().enumerate().for_each(|(i, line)| text.draw_text(line, [margin, graph_bottom + margin + i as f32 * LINE_HEIGHT], LINE_HEIGHT - 2.0, [1.0; 4]));
+            text.render(gl, (rect.width as u32, rect.height as u32));
+        }
+        gl.set_viewport(viewport);
+    }
+
+    pub(crate) fn release(&mut self) {
+        self.shapes = None;
+        self.text = None;
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl Default for DebugOverlay {
+    fn default() -> DebugOverlay {
+        DebugOverlay {
+            enabled: false,
+            toggle_key: Some(Key::F3),
+            font: None,
+            frame_times: VecDeque::with_capacity(GRAPH_FRAMES),
+            stats: GlDrawStats::default(),
+            shapes: None,
+            text: None,
+        }
+    }
+}
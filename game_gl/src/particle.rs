@@ -0,0 +1,215 @@
+//////////////////////////////////////////////////
+// Using
+
+use crate::sprite::{Sprite, SpriteBatch};
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Emitter settings. Ranges are `[min, max]`, picked uniformly per particle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitterConfig {
+    /// Particles per second, 0 to only emit with `burst`.
+    pub spawn_rate: f32,
+    pub max_particles: usize,
+    pub lifetime: [f32; 2],
+    pub speed: [f32; 2],
+    /// Emission direction in radians, counter-clockwise from the x axis.
+    pub direction: f32,
+    /// Total opening angle around `direction` in radians.
+    pub spread: f32,
+    pub acceleration: [f32; 2],
+    /// Speed multiplier over normalized lifetime.
+    pub speed_over_life: Curve,
+    pub size_over_life: Curve,
+    pub color_over_life: Gradient,
+    /// Texture region of the particle sprite.
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+/// Piecewise linear function over normalized lifetime, keys are `(t, value)` sorted by `t`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve(pub Vec<(f32, f32)>);
+
+/// Piecewise linear colors over normalized lifetime, keys are `(t, color)` sorted by `t`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient(pub Vec<(f32, [f32; 4])>);
+
+/// Spawns and simulates particles on the CPU, drawn through a `SpriteBatch`.
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    pub config: EmitterConfig,
+    pub position: [f32; 2],
+    pub active: bool,
+    particles: Vec<Particle>,
+    spawn_accumulator: f32,
+    seed: u32,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Particle {
+    position: [f32; 2],
+    velocity: [f32; 2],
+    rotation: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl ParticleEmitter {
+    pub fn new(config: EmitterConfig, position: [f32; 2]) -> ParticleEmitter {
+        ParticleEmitter {
+            particles: Vec::with_capacity(config.max_particles),
+            config,
+            position,
+            active: true,
+            spawn_accumulator: 0.0,
+            seed: 0x9E37_79B9,
+        }
+    }
+
+    /// Spawns `count` particles at once, e.g. for explosions.
+    pub fn burst(&mut self, count: usize) {
+        (0..count).for_each(|_| self.spawn());
+    }
+
+    /// Ages and moves particles, removes dead ones and spawns new ones while active.
+    pub fn update(&mut self, elapsed_time: f32) {
+        let config = &self.config;
+        self.particles.iter_mut().for_each(|particle| {
+            particle.age += elapsed_time;
+            let speed = config.speed_over_life.sample(particle.age / particle.lifetime);
+            particle.velocity[0] += config.acceleration[0] * elapsed_time;
+            particle.velocity[1] += config.acceleration[1] * elapsed_time;
+            particle.position[0] += particle.velocity[0] * speed * elapsed_time;
+            particle.position[1] += particle.velocity[1] * speed * elapsed_time;
+        });
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+
+        if self.active && self.config.spawn_rate > 0.0 {
+            self.spawn_accumulator += elapsed_time * self.config.spawn_rate;
+            while self.spawn_accumulator >= 1.0 {
+                self.spawn_accumulator -= 1.0;
+                self.spawn();
+            }
+        }
+    }
+
+    /// Queues all particles as sprites, render the batch with the particle texture afterwards.
+    pub fn draw(&self, batch: &mut SpriteBatch) {
+        self.particles.iter().for_each(|particle| {
+            let t = particle.age / particle.lifetime;
+            let size = self.config.size_over_life.sample(t);
+            batch.draw(&Sprite {
+                position: particle.position,
+                size: [size, size],
+                rotation: particle.rotation,
+                uv_min: self.config.uv_min,
+                uv_max: self.config.uv_max,
+                color: self.config.color_over_life.sample(t).into(),
+            });
+        });
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.spawn_accumulator = 0.0;
+    }
+
+    fn spawn(&mut self) {
+        if self.particles.len() >= self.config.max_particles {
+            return;
+        }
+        let angle = self.config.direction + (self.random() - 0.5) * self.config.spread;
+        let speed = self.random_range(self.config.speed);
+        let lifetime = self.random_range(self.config.lifetime).max(f32::EPSILON);
+        let rotation = self.random() * std::f32::consts::TAU;
+        let (s, c) = angle.sin_cos();
+        self.particles.push(Particle {
+            position: self.position,
+            velocity: [c * speed, s * speed],
+            rotation,
+            age: 0.0,
+            lifetime,
+        });
+    }
+
+    // xorshift, good enough for visual noise and keeps the emitter deterministic
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn random_range(&mut self, range: [f32; 2]) -> f32 {
+        range[0] + (range[1] - range[0]) * self.random()
+    }
+}
+
+impl Curve {
+    pub fn constant(value: f32) -> Curve {
+        Curve(vec![(0.0, value)])
+    }
+
+    pub fn linear(from: f32, to: f32) -> Curve {
+        Curve(vec![(0.0, from), (1.0, to)])
+    }
+
+    pub fn sample(&self, t: f32) -> f32 {
+        sample_keys(&self.0, t, |a, b, f| a + (b - a) * f).unwrap_or(1.0)
+    }
+}
+
+impl Gradient {
+    pub fn constant(color: [f32; 4]) -> Gradient {
+        Gradient(vec![(0.0, color)])
+    }
+
+    pub fn linear(from: [f32; 4], to: [f32; 4]) -> Gradient {
+        Gradient(vec![(0.0, from), (1.0, to)])
+    }
+
+    pub fn sample(&self, t: f32) -> [f32; 4] {
+        sample_keys(&self.0, t, |a, b, f| [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * f)).unwrap_or([1.0; 4])
+    }
+}
+
+fn sample_keys<T: Copy>(keys: &[(f32, T)], t: f32, lerp: impl Fn(T, T, f32) -> T) -> Option<T> {
+    let next = keys.partition_point(|&(key, _)| key <= t);
+    match (next.checked_sub(1).map(|i| keys[i]), keys.get(next)) {
+        (Some((t0, a)), Some(&(t1, b))) => Some(lerp(a, b, (t - t0) / (t1 - t0))),
+        (Some((_, a)), None) => Some(a),
+        (None, Some(&(_, b))) => Some(b),
+        (None, None) => None,
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl Default for EmitterConfig {
+    fn default() -> EmitterConfig {
+        EmitterConfig {
+            spawn_rate: 50.0,
+            max_particles: 1000,
+            lifetime: [1.0, 2.0],
+            speed: [50.0, 100.0],
+            direction: std::f32::consts::FRAC_PI_2,
+            spread: std::f32::consts::FRAC_PI_4,
+            acceleration: [0.0, 0.0],
+            speed_over_life: Curve::constant(1.0),
+            size_over_life: Curve::linear(8.0, 0.0),
+            color_over_life: Gradient::linear([1.0; 4], [1.0, 1.0, 1.0, 0.0]),
+            uv_min: [0.0, 0.0],
+            uv_max: [1.0, 1.0],
+        }
+    }
+}
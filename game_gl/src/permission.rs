@@ -0,0 +1,176 @@
+//////////////////////////////////////////////////
+// Using
+
+use crate::events::Events;
+
+#[cfg(target_os = "android")]
+use jni::objects::JValue;
+#[cfg(target_os = "android")]
+use winit::platform::android::activity::AndroidApp;
+
+#[cfg(target_os = "android")]
+use crate::platform::with_activity;
+
+//////////////////////////////////////////////////
+// Definition
+
+// seconds between two checks of pending requests
+const POLL_INTERVAL: f32 = 0.25;
+
+// seconds the permission dialog may take to show up, a request without dialog was denied right away
+#[cfg(target_os = "android")]
+const DIALOG_DELAY: f32 = 1.0;
+
+/// Permission granted by the user at runtime on Android. All permissions are granted on desktop.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Permission {
+    Microphone,
+    Camera,
+    /// Reading shared files, e.g. imported levels.
+    Storage,
+    Notifications,
+    Location,
+    /// Any other Android permission, e.g. "android.permission.BLUETOOTH_CONNECT".
+    Android(String),
+}
+
+/// Sent through `GameContext::events` once the user answered a `Permissions::request`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PermissionResult {
+    pub permission: Permission,
+    pub granted: bool,
+}
+
+/// Runtime permission requests, answered with a `PermissionResult` event, e.g. awaited in a task with
+/// `task.wait_for_event::<PermissionResult>()`. The permissions have to be declared in the Android manifest.
+#[derive(Debug, Default)]
+pub struct Permissions {
+    #[cfg(target_os = "android")]
+    android_app: Option<AndroidApp>,
+    // requests waiting for the user, with the seconds since requesting
+    pending: Vec<(Permission, f32)>,
+    // answers known without asking, sent with the next update
+    answered: Vec<PermissionResult>,
+    poll_time: f32,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Permission {
+    /// Name of the permission in the Android manifest.
+    pub fn android_name(&self) -> &str {
+        match self {
+            Permission::Microphone => "android.permission.RECORD_AUDIO",
+            Permission::Camera => "android.permission.CAMERA",
+            Permission::Storage => "android.permission.READ_EXTERNAL_STORAGE",
+            Permission::Notifications => "android.permission.POST_NOTIFICATIONS",
+            Permission::Location => "android.permission.ACCESS_FINE_LOCATION",
+            Permission::Android(name) => name,
+        }
+    }
+}
+
+impl Permissions {
+    /// Asks the user for a permission unless it was already granted. The answer is sent as `PermissionResult`.
+    pub fn request(&mut self, permission: Permission) {
+        if self.pending.iter().any(|(other, _)| *other == permission) {
+            return;
+        }
+        if self.is_granted(&permission) {
+            self.answered.push(PermissionResult { permission, granted: true });
+        } else if self.request_native(&permission) {
+            self.pending.push((permission, 0.0));
+        } else {
+            self.answered.push(PermissionResult { permission, granted: false });
+        }
+    }
+
+    /// True while a request waits for the user.
+    pub fn is_pending(&self, permission: &Permission) -> bool {
+        self.pending.iter().any(|(other, _)| other == permission)
+    }
+
+    /// Sends the answers of the user, called by the game loop.
+    pub(crate) fn update(&mut self, events: &mut Events, elapsed_time: f32) {
+        self.answered.drain(..).for_each(|result| events.send(result));
+        self.pending.iter_mut().for_each(|(_, time)| *time += elapsed_time);
+        self.poll_time += elapsed_time;
+        if self.pending.is_empty() || self.poll_time < POLL_INTERVAL {
+            return;
+        }
+        self.poll_time = 0.0;
+
+        for (permission, time) in std::mem::take(&mut self.pending) {
+            match self.answer_native(&permission, time) {
+                Some(granted) => events.send(PermissionResult { permission, granted }),
+                None => self.pending.push((permission, time)),
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+impl Permissions {
+    pub(crate) fn new(android_app: AndroidApp) -> Permissions {
+        Permissions {
+            android_app: Some(android_app),
+            ..Default::default()
+        }
+    }
+
+    /// `Context.checkSelfPermission`, permissions before Android 6 are granted on install.
+    pub fn is_granted(&self, permission: &Permission) -> bool {
+        let Some(android_app) = self.android_app.as_ref() else { return false };
+        with_activity(android_app, "check permission", |env, activity| {
+            if env.get_static_field("android/os/Build$VERSION", "SDK_INT", "I")?.i()? < 23 {
+                return Ok(true);
+            }
+            let name = env.new_string(permission.android_name())?;
+            // PackageManager.PERMISSION_GRANTED
+            Ok(env.call_method(activity, "checkSelfPermission", "(Ljava/lang/String;)I", &[(&name).into()])?.i()? == 0)
+        })
+        .unwrap_or(false)
+    }
+
+    // `Activity.requestPermissions` shows the dialog, its answer only reaches java
+    fn request_native(&mut self, permission: &Permission) -> bool {
+        let Some(android_app) = self.android_app.as_ref() else { return false };
+        with_activity(android_app, "request permission", |env, activity| {
+            let name = env.new_string(permission.android_name())?;
+            let names = env.new_object_array(1, "java/lang/String", &name)?;
+            env.call_method(activity, "requestPermissions", "([Ljava/lang/String;I)V", &[(&names).into(), JValue::Int(0)])?;
+            Ok(())
+        })
+        .is_some()
+    }
+
+    // granted as soon as the permission checks out, denied once the activity has the focus back from the dialog
+    fn answer_native(&self, permission: &Permission, time: f32) -> Option<bool> {
+        if self.is_granted(permission) {
+            return Some(true);
+        }
+        let android_app = self.android_app.as_ref()?;
+        let focused = with_activity(android_app, "check window focus", |env, activity| env.call_method(activity, "hasWindowFocus", "()Z", &[])?.z())?;
+        (focused && time >= DIALOG_DELAY).then_some(false)
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl Permissions {
+    pub(crate) fn new() -> Permissions {
+        Default::default()
+    }
+
+    pub fn is_granted(&self, _permission: &Permission) -> bool {
+        true
+    }
+
+    fn request_native(&mut self, _permission: &Permission) -> bool {
+        true
+    }
+
+    fn answer_native(&self, _permission: &Permission, _time: f32) -> Option<bool> {
+        Some(true)
+    }
+}
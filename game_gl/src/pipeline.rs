@@ -0,0 +1,133 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::io::Cursor;
+
+use image::imageops::FilterType;
+use image::ImageFormat;
+
+use crate::file::{FileError, Files};
+
+//////////////////////////////////////////////////
+// Definition
+
+// folder in the user data directory holding processed assets
+const CACHE_DIR: &str = "processed";
+
+/// Converts source assets into a device-optimized form, e.g. compressing textures or converting audio.
+pub trait AssetProcessor {
+    /// Unique name, part of the cache key.
+    fn name(&self) -> &str;
+
+    /// Bump to invalidate outputs of older versions.
+    fn version(&self) -> u32 {
+        1
+    }
+
+    /// Whether the processor handles the asset, e.g. by extension.
+    fn accepts(&self, filename: &str) -> bool;
+
+    fn process(&self, filename: &str, bytes: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+/// Runs assets through the first accepting processor on first use and caches the output in the user data directory,
+/// keyed by a hash of the source content and the processor, so edited source assets are processed again.
+/// Assets without processor are passed through.
+#[derive(Default)]
+pub struct AssetPipeline {
+    processors: Vec<Box<dyn AssetProcessor>>,
+}
+
+/// Downscales images larger than `max_size` and re-encodes them in `format`.
+#[derive(Debug, Clone)]
+pub struct ImageProcessor {
+    pub max_size: u32,
+    pub format: ImageFormat,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl AssetPipeline {
+    pub fn new() -> AssetPipeline {
+        AssetPipeline::default()
+    }
+
+    pub fn with_processor(mut self, processor: impl AssetProcessor + 'static) -> Self {
+        self.processors.push(Box::new(processor));
+        self
+    }
+
+    /// Processed asset, from the cache if the source did not change.
+    pub fn load(&self, files: &Files, filename: &str) -> Result<Vec<u8>, FileError> {
+        let bytes = files.load_bytes(filename)?;
+        let Some(processor) = self.processors.iter().find(|processor| processor.accepts(filename)) else {
+            return Ok(bytes);
+        };
+        let cached = format!("{}/{}-{:016x}", CACHE_DIR, processor.name(), content_hash(processor.as_ref(), &bytes));
+        if let Ok(output) = files.load_user_bytes(&cached) {
+            return Ok(output);
+        }
+        let output = processor.process(filename, &bytes).map_err(|e| FileError::Decode(filename.to_string(), e))?;
+        if let Err(e) = files.save_bytes(&cached, &output) {
+            log::warn!("Failed to cache processed {}: {}", filename, e);
+        }
+        log::debug!("Processed {} with {}", filename, processor.name());
+        Ok(output)
+    }
+
+    /// Processes all files of an asset folder ahead of time, e.g. on first run. Returns the number of failed files.
+    pub fn process_dir(&self, files: &Files, dir: &str) -> usize {
+        files
+            .list(dir)
+            .iter()
+            .filter(|filename| self.processors.iter().any(|processor| processor.accepts(filename)))
+            .filter(|filename| self.load(files, filename).map_err(|e| log::error!("Failed to process {}: {}", filename, e)).is_err())
+            .count()
+    }
+}
+
+impl ImageProcessor {
+    pub fn new(max_size: u32, format: ImageFormat) -> ImageProcessor {
+        ImageProcessor { max_size, format }
+    }
+}
+
+// FNV-1a, stable across builds unlike the std hasher
+fn content_hash(processor: &dyn AssetProcessor, bytes: &[u8]) -> u64 {
+    let version = processor.version().to_le_bytes();
+    [processor.name().as_bytes(), &version, bytes]
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl AssetProcessor for ImageProcessor {
+    fn name(&self) -> &str {
+        "image"
+    }
+
+    fn accepts(&self, filename: &str) -> bool {
+        ImageFormat::from_path(filename).is_ok_and(|format| format.reading_enabled())
+    }
+
+    fn process(&self, _filename: &str, bytes: &[u8]) -> Result<Vec<u8>, String> {
+        let mut image = image::load_from_memory(bytes).map_err(|e| e.to_string())?;
+        if image.width() > self.max_size || image.height() > self.max_size {
+            image = image.resize(self.max_size, self.max_size, FilterType::Triangle);
+        }
+        let mut output = Cursor::new(Vec::new());
+        image.write_to(&mut output, self.format).map_err(|e| e.to_string())?;
+        Ok(output.into_inner())
+    }
+}
+
+impl std::fmt::Debug for AssetPipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<_> = self.processors.iter().map(|processor| processor.name()).collect();
+        f.debug_struct("AssetPipeline").field("processors", &names).finish()
+    }
+}
@@ -0,0 +1,347 @@
+//////////////////////////////////////////////////
+// Using
+
+use winit::monitor::MonitorHandle;
+use winit::window::Window;
+
+#[cfg(target_os = "android")]
+use winit::platform::android::activity::AndroidApp;
+
+#[cfg(target_os = "android")]
+use jni::objects::JValue;
+
+#[cfg(target_os = "android")]
+use crate::app::Orientation;
+
+//////////////////////////////////////////////////
+// Definition
+
+// pixels per inch of a screen with scale factor 1 on desktop
+const DESKTOP_DPI: f32 = 96.0;
+
+/// Device and screen properties, e.g. to pick a quality preset on the first start. Display properties are
+/// known once the window exists and updated when it moves to another screen.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlatformInfo {
+    /// Operating system, e.g. "android", "linux", "macos" or "windows".
+    pub os: &'static str,
+    /// Device model, e.g. "Pixel 7". Only known on Android.
+    pub device_model: Option<String>,
+    /// Physical memory in bytes.
+    pub total_memory: Option<u64>,
+    /// Logical cpu cores.
+    pub cpu_cores: usize,
+    /// Pixels per inch of the screen. Estimated from the scale factor on desktop.
+    pub dpi: Option<f32>,
+    /// Physical pixels per logical pixel of the screen.
+    pub scale_factor: f64,
+    /// Refresh rate of the screen in Hz.
+    pub refresh_rate: Option<f32>,
+    /// Screens connected to the device, see `GameContext::request_monitor`.
+    pub monitors: Vec<MonitorInfo>,
+    /// Index of the screen showing the window in `monitors`.
+    pub current_monitor: Option<usize>,
+}
+
+/// Screen connected to the device.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    /// Top left corner on the desktop in physical pixels.
+    pub position: [i32; 2],
+    /// Current resolution in physical pixels.
+    pub size: [u32; 2],
+    pub scale_factor: f64,
+    pub refresh_rate: Option<f32>,
+    pub primary: bool,
+    /// Resolutions for exclusive fullscreen.
+    pub video_modes: Vec<VideoModeInfo>,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct VideoModeInfo {
+    pub size: [u32; 2],
+    pub bit_depth: u16,
+    pub refresh_rate: f32,
+}
+
+/// One step of a vibration pattern, see `GameContext::vibrate_pattern`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Vibration {
+    pub duration_ms: u32,
+    /// Strength in [0, 1], 0 pauses. `None` uses the default strength of the device. Devices without amplitude
+    /// control vibrate at full strength.
+    pub amplitude: Option<f32>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Vibration {
+    pub fn on(duration_ms: u32) -> Vibration {
+        Vibration { duration_ms, amplitude: None }
+    }
+
+    pub fn pause(duration_ms: u32) -> Vibration {
+        Vibration {
+            duration_ms,
+            amplitude: Some(0.0),
+        }
+    }
+
+    pub fn is_pause(&self) -> bool {
+        self.amplitude.is_some_and(|amplitude| amplitude <= 0.0)
+    }
+}
+
+impl PlatformInfo {
+    #[cfg(not(target_os = "android"))]
+    pub(crate) fn query() -> PlatformInfo {
+        PlatformInfo {
+            device_model: None,
+            ..PlatformInfo::query_common()
+        }
+    }
+
+    #[cfg(target_os = "android")]
+    pub(crate) fn query(android_app: &AndroidApp) -> PlatformInfo {
+        PlatformInfo {
+            device_model: device_model(android_app),
+            // the density of the configuration is reported in dpi
+            dpi: android_app.config().density().map(|density| density as f32),
+            ..PlatformInfo::query_common()
+        }
+    }
+
+    fn query_common() -> PlatformInfo {
+        PlatformInfo {
+            os: std::env::consts::OS,
+            device_model: None,
+            total_memory: total_memory(),
+            cpu_cores: std::thread::available_parallelism().map_or(1, |cores| cores.get()),
+            dpi: None,
+            scale_factor: 1.0,
+            refresh_rate: None,
+            monitors: Vec::new(),
+            current_monitor: None,
+        }
+    }
+
+    /// Reads the display properties of the screen showing the window, called by the game loop.
+    pub(crate) fn update_display(&mut self, window: &Window) {
+        self.scale_factor = window.scale_factor();
+        if !cfg!(target_os = "android") {
+            self.dpi = Some(self.scale_factor as f32 * DESKTOP_DPI);
+        }
+        self.refresh_rate = window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .map(|millihertz| millihertz as f32 / 1000.0);
+
+        let primary = window.primary_monitor();
+        let current = window.current_monitor();
+        let monitors: Vec<MonitorHandle> = window.available_monitors().collect();
+        self.current_monitor = current.and_then(|current| monitors.iter().position(|monitor| *monitor == current));
+        self.monitors = monitors.iter().map(|monitor| MonitorInfo::new(monitor, primary.as_ref() == Some(monitor))).collect();
+    }
+}
+
+impl MonitorInfo {
+    fn new(monitor: &MonitorHandle, primary: bool) -> MonitorInfo {
+        MonitorInfo {
+            name: monitor.name(),
+            position: monitor.position().into(),
+            size: monitor.size().into(),
+            scale_factor: monitor.scale_factor(),
+            refresh_rate: monitor.refresh_rate_millihertz().map(|millihertz| millihertz as f32 / 1000.0),
+            primary,
+            video_modes: monitor
+                .video_modes()
+                .map(|video_mode| VideoModeInfo {
+                    size: video_mode.size().into(),
+                    bit_depth: video_mode.bit_depth(),
+                    refresh_rate: video_mode.refresh_rate_millihertz() as f32 / 1000.0,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn total_memory() -> Option<u64> {
+    // e.g. "MemTotal:       16318412 kB"
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn total_memory() -> Option<u64> {
+    None
+}
+
+/// Calls into java with the activity of the app, java exceptions are cleared and logged as `action` failed.
+#[cfg(target_os = "android")]
+pub(crate) fn with_activity<T>(
+    android_app: &AndroidApp,
+    action: &str,
+    f: impl FnOnce(&mut jni::JNIEnv, &jni::objects::JObject) -> jni::errors::Result<T>,
+) -> Option<T> {
+    let vm = unsafe { jni::JavaVM::from_raw(android_app.vm_as_ptr() as *mut jni::sys::JavaVM) }.ok()?;
+    let mut env = vm.attach_current_thread().ok()?;
+    let activity = unsafe { jni::objects::JObject::from_raw(android_app.activity_as_ptr() as jni::sys::jobject) };
+    match f(&mut env, &activity) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            if env.exception_check().unwrap_or(false) {
+                let _ = env.exception_describe();
+                let _ = env.exception_clear();
+            }
+            log::warn!("Failed to {}: {}", action, e);
+            None
+        }
+    }
+}
+
+// `android.os.Build.MODEL`, only readable through java
+#[cfg(target_os = "android")]
+fn device_model(android_app: &AndroidApp) -> Option<String> {
+    with_activity(android_app, "read device model", |env, _| {
+        let model = jni::objects::JString::from(env.get_static_field("android/os/Build", "MODEL", "Ljava/lang/String;")?.l()?);
+        let model: String = env.get_string(&model)?.into();
+        Ok(model)
+    })
+}
+
+#[cfg(target_os = "android")]
+fn vibrator<'local>(env: &mut jni::JNIEnv<'local>, activity: &jni::objects::JObject) -> jni::errors::Result<jni::objects::JObject<'local>> {
+    let service = env.new_string("vibrator")?;
+    env.call_method(activity, "getSystemService", "(Ljava/lang/String;)Ljava/lang/Object;", &[(&service).into()])?.l()
+}
+
+/// Plays the pattern with the `Vibrator` service, needs the `android.permission.VIBRATE` permission.
+#[cfg(target_os = "android")]
+pub(crate) fn vibrate(android_app: &AndroidApp, pattern: &[Vibration]) {
+    with_activity(android_app, "vibrate", |env, activity| {
+        let vibrator = vibrator(env, activity)?;
+        if vibrator.is_null() || pattern.is_empty() {
+            return Ok(());
+        }
+        let sdk = env.get_static_field("android/os/Build$VERSION", "SDK_INT", "I")?.i()?;
+        if sdk >= 26 {
+            let timings: Vec<i64> = pattern.iter().map(|step| step.duration_ms as i64).collect();
+            // 1 to 255, -1 is the default amplitude
+            let amplitudes: Vec<i32> = pattern.iter().map(|step| step.amplitude.map_or(-1, |amplitude| (amplitude.clamp(0.0, 1.0) * 255.0).round() as i32)).collect();
+            let timings_array = env.new_long_array(timings.len() as i32)?;
+            env.set_long_array_region(&timings_array, 0, &timings)?;
+            let amplitudes_array = env.new_int_array(amplitudes.len() as i32)?;
+            env.set_int_array_region(&amplitudes_array, 0, &amplitudes)?;
+            let effect = env
+                .call_static_method(
+                    "android/os/VibrationEffect",
+                    "createWaveform",
+                    "([J[II)Landroid/os/VibrationEffect;",
+                    &[(&timings_array).into(), (&amplitudes_array).into(), JValue::Int(-1)],
+                )?
+                .l()?;
+            env.call_method(&vibrator, "vibrate", "(Landroid/os/VibrationEffect;)V", &[(&effect).into()])?;
+        } else {
+            // older devices take alternating pause and vibration durations, starting with a pause
+            let mut timings: Vec<i64> = vec![0];
+            for step in pattern {
+                let pause = timings.len() % 2 == 1;
+                if step.is_pause() == pause {
+                    *timings.last_mut().unwrap() += step.duration_ms as i64;
+                } else {
+                    timings.push(step.duration_ms as i64);
+                }
+            }
+            let timings_array = env.new_long_array(timings.len() as i32)?;
+            env.set_long_array_region(&timings_array, 0, &timings)?;
+            env.call_method(&vibrator, "vibrate", "([JI)V", &[(&timings_array).into(), JValue::Int(-1)])?;
+        }
+        Ok(())
+    });
+}
+
+#[cfg(target_os = "android")]
+pub(crate) fn cancel_vibration(android_app: &AndroidApp) {
+    with_activity(android_app, "cancel vibration", |env, activity| {
+        let vibrator = vibrator(env, activity)?;
+        if !vibrator.is_null() {
+            env.call_method(&vibrator, "cancel", "()V", &[])?;
+        }
+        Ok(())
+    });
+}
+
+/// Keeps the display on while the app is shown with the window flag `FLAG_KEEP_SCREEN_ON`.
+#[cfg(target_os = "android")]
+pub(crate) fn set_keep_screen_on(android_app: &AndroidApp, keep_on: bool) {
+    use winit::platform::android::activity::WindowManagerFlags;
+    if keep_on {
+        android_app.set_window_flags(WindowManagerFlags::KEEP_SCREEN_ON, WindowManagerFlags::empty());
+    } else {
+        android_app.set_window_flags(WindowManagerFlags::empty(), WindowManagerFlags::KEEP_SCREEN_ON);
+    }
+}
+
+/// Keeps the display on while the game thread runs, windows only allows the thread setting it to release it.
+#[cfg(windows)]
+pub(crate) fn set_keep_screen_on(keep_on: bool) {
+    use windows_sys::Win32::System::Power::{SetThreadExecutionState, ES_CONTINUOUS, ES_DISPLAY_REQUIRED};
+    let flags = if keep_on { ES_CONTINUOUS | ES_DISPLAY_REQUIRED } else { ES_CONTINUOUS };
+    if unsafe { SetThreadExecutionState(flags) } == 0 {
+        log::warn!("Failed to set execution state");
+    }
+}
+
+// no screen saver inhibitor without a desktop specific dependency
+#[cfg(not(any(windows, target_os = "android")))]
+pub(crate) fn set_keep_screen_on(keep_on: bool) {
+    if keep_on {
+        log::debug!("Keeping the screen on is not supported on {}", std::env::consts::OS);
+    }
+}
+
+/// Locks the activity to the orientation with `Activity.setRequestedOrientation`.
+#[cfg(target_os = "android")]
+pub(crate) fn set_orientation(android_app: &AndroidApp, orientation: Orientation) {
+    // `ActivityInfo.SCREEN_ORIENTATION_*`
+    let requested = match orientation {
+        Orientation::Portrait => 7, // SENSOR_PORTRAIT
+        Orientation::Landscape => 6, // SENSOR_LANDSCAPE
+        Orientation::Sensor => 13, // FULL_USER, respects the rotation lock of the user
+    };
+    with_activity(android_app, "set orientation", |env, activity| {
+        env.call_method(activity, "setRequestedOrientation", "(I)V", &[JValue::Int(requested)])?;
+        Ok(())
+    });
+}
+
+/// Data url of the activity's intent, `Activity.getIntent().getData()`. New intents are only seen if the activity
+/// passes them to `setIntent` in `onNewIntent`.
+#[cfg(target_os = "android")]
+pub(crate) fn intent_url(android_app: &AndroidApp) -> Option<String> {
+    with_activity(android_app, "read intent", |env, activity| {
+        let intent = env.call_method(activity, "getIntent", "()Landroid/content/Intent;", &[])?.l()?;
+        if intent.is_null() {
+            return Ok(None);
+        }
+        let uri = env.call_method(&intent, "getData", "()Landroid/net/Uri;", &[])?.l()?;
+        if uri.is_null() {
+            return Ok(None);
+        }
+        let url = jni::objects::JString::from(env.call_method(&uri, "toString", "()Ljava/lang/String;", &[])?.l()?);
+        let url: String = env.get_string(&url)?.into();
+        Ok(Some(url))
+    })
+    .flatten()
+}
+
+/// Url passed on the command line, which is how desktops open apps registered for a url scheme.
+#[cfg(not(target_os = "android"))]
+pub(crate) fn command_line_url() -> Option<String> {
+    std::env::args().skip(1).find(|arg| arg.contains("://"))
+}
@@ -0,0 +1,169 @@
+//////////////////////////////////////////////////
+// Using
+
+use crate::gl;
+use crate::gl::types::*;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Shader
+
+// fullscreen triangle generated from the vertex id, no vertex buffer needed
+pub(crate) const FULLSCREEN_VS: &[u8] = b"#version 300 es
+out vec2 v_TexCoord;
+
+void main() {
+    vec2 pos = vec2(float((gl_VertexID << 1) & 2), float(gl_VertexID & 2));
+    v_TexCoord = pos;
+    gl_Position = vec4(pos * 2.0 - 1.0, 0.0, 1.0);
+}
+";
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Renders the scene offscreen and runs a chain of fullscreen passes over it, the last one drawing to the default framebuffer.
+///
+/// Pass fragment shaders receive `in vec2 v_TexCoord`, the previous pass result as `uniform sampler2DArray t_Source`
+/// and the untouched scene as `uniform sampler2DArray t_Scene` (both layer 0).
+/// Uniform buffers bound by the caller stay bound while the passes run.
+#[derive(Debug, Default)]
+pub struct PostProcess {
+    scene: GlFramebuffer,
+    targets: [GlFramebuffer; 2],
+    passes: Vec<PostPass>,
+    vao: GlVertexArrayObject,
+}
+
+/// Single fullscreen shader pass of a `PostProcess` chain.
+#[derive(Debug, Default)]
+pub struct PostPass {
+    name: String,
+    shader: GlShader,
+    enabled: bool,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl PostProcess {
+    /// Creates the scene target with `samples` and two single-sampled ping-pong targets, all using `internal_format`.
+    pub fn new(gl: &Gl, width: u32, height: u32, internal_format: GLenum, samples: u32) -> PostProcess {
+        PostProcess {
+            scene: GlFramebuffer::new(gl, width, height, internal_format, samples),
+            targets: [GlFramebuffer::new(gl, width, height, internal_format, 1), GlFramebuffer::new(gl, width, height, internal_format, 1)],
+            passes: Vec::new(),
+            vao: GlVertexArrayObject::new(gl),
+        }
+    }
+
+    /// Appends a pass running `fragment_shader`, passes run in the order they were added.
+    /// Fails with the info log if the shader does not compile, the pass is not added then.
+    pub fn add_pass(&mut self, gl: &Gl, name: &str, fragment_shader: &[u8]) -> Result<(), GlError> {
+        self.passes.push(PostPass {
+            name: name.to_string(),
+            shader: GlShader::try_new(gl, FULLSCREEN_VS, fragment_shader)?,
+            enabled: true,
+        });
+        Ok(())
+    }
+
+    pub fn pass(&mut self, name: &str) -> Option<&mut PostPass> {
+        self.passes.iter_mut().find(|pass| pass.name == name)
+    }
+
+    /// Recreates all targets, call from `resize_device`.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.scene.resize(width, height);
+        self.targets.iter_mut().for_each(|target| target.resize(width, height));
+    }
+
+    /// Offscreen target receiving the scene.
+    pub fn scene(&mut self) -> &mut GlFramebuffer {
+        &mut self.scene
+    }
+
+    /// Binds the scene target, everything drawn until `end` is post-processed.
+    pub fn begin(&mut self) {
+        self.scene.bind();
+    }
+
+    /// Runs all enabled passes and presents the result to the default framebuffer.
+    pub fn end(&mut self, gl: &Gl) {
+        self.scene.unbind();
+        self.scene.resolve();
+
+        let rect = self.scene.rect();
+        let passes: Vec<&mut PostPass> = self.passes.iter_mut().filter(|pass| pass.enabled).collect();
+        if passes.is_empty() {
+            GlFramebuffer::blit(gl, Some(&self.scene), rect, None, rect, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            return;
+        }
+
+        GlRenderState::default().apply(gl, None);
+        gl.set_viewport(rect);
+        self.vao.bind();
+        self.scene.texture().bind(1);
+        let last = passes.len() - 1;
+        for (i, pass) in passes.into_iter().enumerate() {
+            // pass i reads target (i + 1) % 2 and writes target i % 2, the first pass reads the scene
+            let [even, odd] = &mut self.targets;
+            let (source, target) = if i % 2 == 0 { (odd, even) } else { (even, odd) };
+            if i == 0 {
+                self.scene.texture().bind(0);
+            } else {
+                source.texture().bind(0);
+            }
+            if i == last {
+                target.unbind();
+            } else {
+                target.bind();
+            }
+            pass.shader.bind();
+            pass.shader.link_texture(0, "t_Source");
+            pass.shader.link_texture(1, "t_Scene");
+            pass.shader.draw_arrays(gl::TRIANGLES, 3);
+            pass.shader.unbind();
+        }
+        self.scene.texture().unbind();
+        self.vao.unbind();
+    }
+}
+
+impl PostPass {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Shader of the pass, e.g. to link additional uniform blocks.
+    pub fn shader(&mut self) -> &mut GlShader {
+        &mut self.shader
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Disabled passes are skipped, the next pass reads the previous result instead.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl GlResource for PostProcess {
+    fn release(&mut self) {
+        self.scene.release();
+        self.targets.iter_mut().for_each(|target| target.release());
+        self.passes.iter_mut().for_each(|pass| pass.shader.release());
+        self.vao.release();
+    }
+}
+impl Drop for PostProcess {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
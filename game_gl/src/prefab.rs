@@ -0,0 +1,200 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::file::{FileError, Files};
+
+//////////////////////////////////////////////////
+// Definition
+
+// folder of the prefab files, one `<name>.json` per prefab
+const PREFAB_DIR: &str = "prefabs";
+
+// key naming the prefab another prefab is based on
+const EXTENDS: &str = "extends";
+
+/// Entity templates loaded from `prefabs/<name>.json`, e.g. `prefabs/goblin.json`. A prefab is a JSON object of
+/// components and their values, and may extend another prefab by naming it in `"extends"`, overriding single
+/// fields of its components. Prefabs are instantiated into serde types, so content can be authored without
+/// recompiling.
+#[derive(Debug, Default)]
+pub struct Prefabs {
+    prefabs: HashMap<String, Map<String, Value>>,
+}
+
+#[derive(Debug)]
+pub enum PrefabError {
+    /// A prefab file could not be read or is no JSON object.
+    File(FileError),
+    /// No prefab with this name was loaded.
+    Unknown(String),
+    /// Prefabs extend each other in a cycle.
+    Cycle(String),
+    /// The prefab does not match the requested type.
+    Deserialize(String, String),
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Prefabs {
+    /// Loads all prefabs of the assets and mounts.
+    pub fn load(files: &Files) -> Result<Prefabs, PrefabError> {
+        let mut prefabs = Prefabs::default();
+        for path in files.list(PREFAB_DIR) {
+            let Some(name) = path.strip_prefix("prefabs/").and_then(|name| name.strip_suffix(".json")) else { continue };
+            let bytes = files.load_bytes(&path).map_err(PrefabError::File)?;
+            let prefab = serde_json::from_slice(&bytes).map_err(|e| PrefabError::File(FileError::Decode(path.clone(), e.to_string())))?;
+            prefabs.insert(name, prefab);
+        }
+        log::debug!("Loaded {} prefabs", prefabs.prefabs.len());
+        Ok(prefabs)
+    }
+
+    /// Adds or replaces a prefab, e.g. one created in an editor.
+    pub fn insert(&mut self, name: &str, prefab: Map<String, Value>) {
+        self.prefabs.insert(name.to_string(), prefab);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.prefabs.contains_key(name)
+    }
+
+    /// Sorted prefab names.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.prefabs.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    /// Components of a prefab merged with the prefabs it extends.
+    pub fn resolve(&self, name: &str) -> Result<Map<String, Value>, PrefabError> {
+        let mut chain = Vec::new();
+        let mut next = Some(name);
+        while let Some(name) = next {
+            if chain.iter().any(|(other, _)| *other == name) {
+                return Err(PrefabError::Cycle(name.to_string()));
+            }
+            let prefab = self.prefabs.get(name).ok_or_else(|| PrefabError::Unknown(name.to_string()))?;
+            next = prefab.get(EXTENDS).and_then(|base| base.as_str());
+            chain.push((name, prefab));
+        }
+        let mut components = Map::new();
+        for (_, prefab) in chain.into_iter().rev() {
+            for (key, value) in prefab.iter().filter(|(key, _)| key.as_str() != EXTENDS) {
+                merge(components.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+        Ok(components)
+    }
+
+    /// Deserializes a prefab, e.g. into a struct with one optional field per component.
+    pub fn instantiate<T: DeserializeOwned>(&self, name: &str) -> Result<T, PrefabError> {
+        serde_json::from_value(Value::Object(self.resolve(name)?)).map_err(|e| PrefabError::Deserialize(name.to_string(), e.to_string()))
+    }
+
+    /// Deserializes a single component of a prefab, `None` if the prefab doesn't have it.
+    pub fn component<T: DeserializeOwned>(&self, name: &str, component: &str) -> Result<Option<T>, PrefabError> {
+        match self.resolve(name)?.remove(component) {
+            Some(value) => serde_json::from_value(value).map(Some).map_err(|e| PrefabError::Deserialize(format!("{}.{}", name, component), e.to_string())),
+            None => Ok(None),
+        }
+    }
+}
+
+// objects are merged field by field, other values are replaced
+fn merge(target: &mut Value, value: &Value) {
+    match (target, value) {
+        (Value::Object(target), Value::Object(value)) => {
+            for (key, value) in value {
+                merge(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+        (target, value) => *target = value.clone(),
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl fmt::Display for PrefabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefabError::File(err) => write!(f, "Failed to load prefab: {}", err),
+            PrefabError::Unknown(name) => write!(f, "Unknown prefab {}", name),
+            PrefabError::Cycle(name) => write!(f, "Prefab {} extends itself through its base prefabs", name),
+            PrefabError::Deserialize(name, err) => write!(f, "Failed to instantiate prefab {}: {}", name, err),
+        }
+    }
+}
+
+impl std::error::Error for PrefabError {}
+
+//////////////////////////////////////////////////
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    fn prefabs(entries: &[(&str, Value)]) -> Prefabs {
+        let mut prefabs = Prefabs::default();
+        entries.iter().for_each(|(name, prefab)| prefabs.insert(name, prefab.as_object().unwrap().clone()));
+        prefabs
+    }
+
+    #[test]
+    fn merges_base_prefabs() {
+        let prefabs = prefabs(&[
+            ("creature", json!({ "health": { "max": 10, "regen": 1 }, "sprite": "creature.png", "tags": ["alive"] })),
+            ("goblin", json!({ "extends": "creature", "health": { "max": 20 }, "tags": ["enemy"] })),
+            ("goblin_chief", json!({ "extends": "goblin", "sprite": "chief.png", "loot": "crown" })),
+        ]);
+        let components = prefabs.resolve("goblin_chief").unwrap();
+        assert_eq!(
+            Value::Object(components),
+            json!({ "health": { "max": 20, "regen": 1 }, "sprite": "chief.png", "tags": ["enemy"], "loot": "crown" })
+        );
+    }
+
+    #[test]
+    fn instantiates_components() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Health {
+            max: u32,
+            regen: u32,
+        }
+        let prefabs = prefabs(&[("creature", json!({ "health": { "max": 10, "regen": 1 } })), ("goblin", json!({ "extends": "creature", "health": { "max": 20 } }))]);
+        assert_eq!(prefabs.component::<Health>("goblin", "health").unwrap(), Some(Health { max: 20, regen: 1 }));
+        assert_eq!(prefabs.component::<Health>("goblin", "sprite").unwrap(), None);
+        assert!(matches!(prefabs.component::<String>("goblin", "health"), Err(PrefabError::Deserialize(_, _))));
+    }
+
+    #[test]
+    fn reports_unknown_prefabs() {
+        let prefabs = prefabs(&[("goblin", json!({ "extends": "creature" }))]);
+        assert!(matches!(prefabs.resolve("goblin"), Err(PrefabError::Unknown(name)) if name == "creature"));
+        assert!(matches!(prefabs.resolve("orc"), Err(PrefabError::Unknown(name)) if name == "orc"));
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let prefabs = prefabs(&[
+            ("a", json!({ "extends": "b" })),
+            ("b", json!({ "extends": "c" })),
+            ("c", json!({ "extends": "a" })),
+            ("d", json!({ "extends": "d" })),
+            ("e", json!({ "extends": "a" })),
+        ]);
+        assert!(matches!(prefabs.resolve("a"), Err(PrefabError::Cycle(name)) if name == "a"));
+        assert!(matches!(prefabs.resolve("d"), Err(PrefabError::Cycle(name)) if name == "d"));
+        assert!(matches!(prefabs.resolve("e"), Err(PrefabError::Cycle(name)) if name == "a"));
+    }
+}
@@ -0,0 +1,155 @@
+//////////////////////////////////////////////////
+// Using
+
+use crate::gl;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+// number of frames in flight before results are read back
+const FRAME_LATENCY: usize = 3;
+
+/// Measures GPU time of named, nested scopes with timestamp queries.
+/// Results are resolved a few frames later to avoid stalling the pipeline.
+#[derive(Debug, Default)]
+pub struct GpuProfiler {
+    gl: Option<Gl>,
+    frames: Vec<GpuFrame>,
+    current: usize,
+    stack: Vec<usize>,
+    results: Vec<GpuScopeResult>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GpuScopeResult {
+    pub name: String,
+    pub depth: usize,
+    pub duration_ms: f32,
+}
+
+#[derive(Debug, Default)]
+struct GpuFrame {
+    scopes: Vec<GpuScope>,
+    used: usize,
+    pending: bool,
+}
+
+#[derive(Debug)]
+struct GpuScope {
+    name: String,
+    depth: usize,
+    begin: GlQuery,
+    end: GlQuery,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl GpuProfiler {
+    /// Creates a profiler. Without timer query support, all scopes are no-ops.
+    pub fn new(gl: &Gl) -> GpuProfiler {
+        if !GlQuery::is_timer_supported(gl) {
+            log::warn!("GPU timer queries not supported, profiling disabled");
+            return GpuProfiler::default();
+        }
+        GpuProfiler {
+            gl: Some(gl.clone()),
+            frames: (0..FRAME_LATENCY).map(|_| GpuFrame::default()).collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.gl.is_some()
+    }
+
+    /// Resolves the oldest frame in flight and starts recording a new frame.
+    pub fn begin_frame(&mut self) {
+        let Some(gl) = self.gl.as_ref() else { return };
+        let disjoint = GlQuery::is_disjoint(gl);
+        let frame = &mut self.frames[self.current];
+        if frame.pending {
+            let scopes = &frame.scopes[..frame.used];
+            if disjoint {
+                log::debug!("GPU timer disjoint, skipping profiler frame");
+            } else if let Some(results) = scopes.iter().map(GpuScope::resolve).collect::<Option<Vec<_>>>() {
+                self.results = results;
+            }
+        }
+        frame.pending = false;
+        frame.used = 0;
+        self.stack.clear();
+    }
+
+    pub fn end_frame(&mut self) {
+        if self.gl.is_none() {
+            return;
+        }
+        while !self.stack.is_empty() {
+            log::warn!("Unclosed GPU profiler scope at end of frame");
+            self.end_scope();
+        }
+        self.frames[self.current].pending = true;
+        self.current = (self.current + 1) % self.frames.len();
+    }
+
+    pub fn begin_scope(&mut self, name: &str) {
+        let Some(gl) = self.gl.as_ref() else { return };
+        let depth = self.stack.len();
+        let frame = &mut self.frames[self.current];
+        if frame.used == frame.scopes.len() {
+            frame.scopes.push(GpuScope {
+                name: String::new(),
+                depth: 0,
+                begin: GlQuery::new(gl, gl::TIMESTAMP_EXT),
+                end: GlQuery::new(gl, gl::TIMESTAMP_EXT),
+            });
+        }
+        let scope = &mut frame.scopes[frame.used];
+        scope.name.clear();
+        scope.name.push_str(name);
+        scope.depth = depth;
+        scope.begin.timestamp();
+        self.stack.push(frame.used);
+        frame.used += 1;
+    }
+
+    pub fn end_scope(&mut self) {
+        if let Some(index) = self.stack.pop() {
+            self.frames[self.current].scopes[index].end.timestamp();
+        }
+    }
+
+    /// Measures all GPU work submitted by `f`. Scopes can be nested by using the passed profiler.
+    pub fn scope<R>(&mut self, name: &str, f: impl FnOnce(&mut GpuProfiler) -> R) -> R {
+        self.begin_scope(name);
+        let result = f(self);
+        self.end_scope();
+        result
+    }
+
+    /// Scopes of the most recently resolved frame, in submission order.
+    pub fn results(&self) -> &[GpuScopeResult] {
+        &self.results
+    }
+
+    pub fn release(&mut self) {
+        self.frames.clear();
+        self.stack.clear();
+        self.gl = None;
+    }
+}
+
+impl GpuScope {
+    fn resolve(&self) -> Option<GpuScopeResult> {
+        let begin = self.begin.result()?;
+        let end = self.end.result()?;
+        Some(GpuScopeResult {
+            name: self.name.clone(),
+            depth: self.depth,
+            duration_ms: end.saturating_sub(begin) as f32 / 1_000_000.0,
+        })
+    }
+}
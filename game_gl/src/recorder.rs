@@ -0,0 +1,254 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Sender};
+use std::thread::JoinHandle;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+
+use crate::gl;
+use crate::gl::types::*;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// Looping animated gif.
+    Gif,
+    /// Numbered png files, `path` is extended with `_00000.png`.
+    PngSequence,
+    /// Raw NV12 frames appended to a single file, e.g. for `ffmpeg -f rawvideo -pix_fmt nv12`.
+    Nv12,
+}
+
+/// Records every nth frame of the default framebuffer. Pixels are read asynchronously into pixel buffers
+/// and encoded on a worker thread, so recording does not stall the render loop.
+#[derive(Debug, Default)]
+pub struct FrameRecorder {
+    gl: Option<Gl>,
+    pbos: [GLuint; 2],
+    pending: [Option<GlRect>; 2],
+    next: usize,
+    every_nth: u32,
+    frame: u32,
+    sender: Option<Sender<RgbaImage>>,
+    worker: Option<JoinHandle<std::io::Result<usize>>>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl FrameRecorder {
+    /// Starts recording to `path`. `fps` is the frame rate of the game loop, used for gif frame delays.
+    pub fn new(gl: &Gl, path: impl Into<PathBuf>, format: RecordingFormat, every_nth: u32, fps: u32) -> FrameRecorder {
+        let mut pbos = [0; 2];
+        unsafe {
+            gl.GenBuffers(2, pbos.as_mut_ptr());
+            check_error(gl, "Failed to create pixel buffers");
+        }
+        let every_nth = every_nth.max(1);
+        let delay = Delay::from_numer_denom_ms(1000 * every_nth, fps.max(1));
+        let path = path.into();
+        let (sender, receiver) = channel::<RgbaImage>();
+        let worker = std::thread::spawn(move || {
+            let mut encoder = FrameEncoder::new(path, format, delay)?;
+            receiver.iter().try_for_each(|frame| encoder.encode(frame))?;
+            encoder.finish()
+        });
+        log::info!("Started frame recording");
+        FrameRecorder {
+            gl: Some(gl.clone()),
+            pbos,
+            pending: [None; 2],
+            next: 0,
+            every_nth,
+            frame: 0,
+            sender: Some(sender),
+            worker: Some(worker),
+        }
+    }
+
+    /// Call once per frame after rendering, before the buffers are swapped. `rect` is usually `GlViewport::size`.
+    pub fn capture(&mut self, rect: GlRect) {
+        self.frame += 1;
+        if !self.frame.is_multiple_of(self.every_nth) {
+            return;
+        }
+        let gl = self.gl.clone().expect("Missing OpenGL Context!");
+
+        // the buffer written two captures ago is ready by now, collect it before reusing it
+        let index = self.next;
+        self.collect(&gl, index);
+        unsafe {
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl.BindBuffer(gl::PIXEL_PACK_BUFFER, self.pbos[index]);
+            gl.BufferData(gl::PIXEL_PACK_BUFFER, (rect.width * rect.height * 4) as GLsizeiptr, std::ptr::null(), gl::STREAM_READ);
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl.ReadPixels(rect.x, rect.y, rect.width, rect.height, gl::RGBA, gl::UNSIGNED_BYTE, std::ptr::null_mut());
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 4);
+            gl.BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            if !check_error(&gl, "Failed to read frame") {
+                self.pending[index] = Some(rect);
+            }
+        }
+        self.next = (index + 1) % self.pbos.len();
+    }
+
+    /// Stops recording, waits for the worker and returns the number of encoded frames.
+    pub fn finish(mut self) -> std::io::Result<usize> {
+        if let Some(gl) = self.gl.clone() {
+            let (next, count) = (self.next, self.pbos.len());
+            (0..count).for_each(|i| self.collect(&gl, (next + i) % count));
+        }
+        self.sender = None;
+        let result = self.worker.take().map(|worker| worker.join().unwrap_or_else(|_| Err(std::io::Error::other("Recording worker panicked")))).unwrap_or(Ok(0));
+        log::info!("Finished frame recording: {:?}", result);
+        result
+    }
+
+    // maps a filled pixel buffer and hands its content to the worker
+    fn collect(&mut self, gl: &Gl, index: usize) {
+        let Some(rect) = self.pending[index].take() else {
+            return;
+        };
+        let size = (rect.width * rect.height * 4) as usize;
+        let pixels = unsafe {
+            gl.BindBuffer(gl::PIXEL_PACK_BUFFER, self.pbos[index]);
+            let ptr = gl.MapBufferRange(gl::PIXEL_PACK_BUFFER, 0, size as GLsizeiptr, gl::MAP_READ_BIT) as *const u8;
+            let pixels = (!ptr.is_null()).then(|| std::slice::from_raw_parts(ptr, size).to_vec());
+            gl.UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+            gl.BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            check_error(gl, "Failed to map pixel buffer");
+            pixels
+        };
+        let image = pixels.and_then(|pixels| RgbaImage::from_raw(rect.width as u32, rect.height as u32, pixels));
+        if let (Some(mut image), Some(sender)) = (image, self.sender.as_ref()) {
+            image::imageops::flip_vertical_in_place(&mut image);
+            if sender.send(image).is_err() {
+                log::error!("Recording worker stopped, dropping frame");
+            }
+        }
+    }
+}
+
+// runs on the worker thread
+struct FrameEncoder {
+    path: PathBuf,
+    format: RecordingFormat,
+    gif: Option<GifEncoder<BufWriter<File>>>,
+    raw: Option<BufWriter<File>>,
+    delay: Delay,
+    size: Option<(u32, u32)>,
+    count: usize,
+}
+
+impl FrameEncoder {
+    fn new(path: PathBuf, format: RecordingFormat, delay: Delay) -> std::io::Result<FrameEncoder> {
+        let (gif, raw) = match format {
+            RecordingFormat::Gif => {
+                let mut encoder = GifEncoder::new(BufWriter::new(File::create(&path)?));
+                encoder.set_repeat(Repeat::Infinite).map_err(std::io::Error::other)?;
+                (Some(encoder), None)
+            }
+            RecordingFormat::Nv12 => (None, Some(BufWriter::new(File::create(&path)?))),
+            RecordingFormat::PngSequence => (None, None),
+        };
+        Ok(FrameEncoder {
+            path,
+            format,
+            gif,
+            raw,
+            delay,
+            size: None,
+            count: 0,
+        })
+    }
+
+    fn encode(&mut self, frame: RgbaImage) -> std::io::Result<()> {
+        // all frames of a recording share the size of the first one
+        let size = *self.size.get_or_insert(frame.dimensions());
+        if frame.dimensions() != size {
+            log::warn!("Skipping recorded frame with size {:?}, expected {:?}", frame.dimensions(), size);
+            return Ok(());
+        }
+        match self.format {
+            RecordingFormat::Gif => {
+                if let Some(gif) = self.gif.as_mut() {
+                    gif.encode_frame(Frame::from_parts(frame, 0, 0, self.delay)).map_err(std::io::Error::other)?;
+                }
+            }
+            RecordingFormat::PngSequence => {
+                let mut path = self.path.clone().into_os_string();
+                path.push(format!("_{:05}.png", self.count));
+                frame.save(PathBuf::from(path)).map_err(std::io::Error::other)?;
+            }
+            RecordingFormat::Nv12 => {
+                if let Some(raw) = self.raw.as_mut() {
+                    raw.write_all(&rgba_to_nv12(&frame))?;
+                }
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> std::io::Result<usize> {
+        // dropping the gif encoder writes the trailer
+        self.gif = None;
+        if let Some(raw) = self.raw.as_mut() {
+            raw.flush()?;
+        }
+        Ok(self.count)
+    }
+}
+
+// full resolution luma plane followed by interleaved chroma at half resolution, bt.601 limited range
+fn rgba_to_nv12(image: &RgbaImage) -> Vec<u8> {
+    let (width, height) = (image.width() & !1, image.height() & !1);
+    let mut data = Vec::with_capacity((width * height * 3 / 2) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let [r, g, b, _] = image.get_pixel(x, y).0.map(f32::from);
+            data.push((16.0 + 0.257 * r + 0.504 * g + 0.098 * b) as u8);
+        }
+    }
+    for y in (0..height).step_by(2) {
+        for x in (0..width).step_by(2) {
+            let [r, g, b] = [0, 1, 2].map(|c| [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)].iter().map(|&(px, py)| image.get_pixel(px, py).0[c] as f32).sum::<f32>() / 4.0);
+            data.push((128.0 - 0.148 * r - 0.291 * g + 0.439 * b) as u8);
+            data.push((128.0 + 0.439 * r - 0.368 * g - 0.071 * b) as u8);
+        }
+    }
+    data
+}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl GlResource for FrameRecorder {
+    fn release(&mut self) {
+        if let Some(gl) = self.gl.as_ref() {
+            unsafe {
+                gl.DeleteBuffers(self.pbos.len() as GLsizei, self.pbos.as_ptr());
+                check_error(gl, "Failed to delete pixel buffers");
+            }
+            log::debug!("Deleted pixel buffers {:?}", self.pbos);
+        }
+        self.gl = None;
+        self.pending = [None; 2];
+        // closing the channel lets the worker finish the file
+        self.sender = None;
+    }
+}
+impl Drop for FrameRecorder {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
@@ -0,0 +1,213 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+use image::RgbaImage;
+
+use crate::model::ModelMesh;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Copyable reference to a resource in a `ResourcePool`. Handles of removed resources stay invalid,
+/// even when their slot is reused.
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+/// Resources together with the function creating them, so they can be recreated after device loss.
+pub struct ResourcePool<T> {
+    entries: Vec<ResourceEntry<T>>,
+    free: Vec<u32>,
+}
+
+/// Textures, shaders and meshes owned by the `GameContext`. Everything is released after `destroy_device`
+/// and recreated before `create_device`, so games only upload their resources once.
+#[derive(Debug, Default)]
+pub struct GpuResources {
+    pub textures: ResourcePool<GlTexture>,
+    pub shaders: ResourcePool<GlShader>,
+    pub meshes: ResourcePool<ModelMesh>,
+}
+
+type CreateFn<T> = Box<dyn Fn(&Gl) -> T>;
+
+struct ResourceEntry<T> {
+    generation: u32,
+    create: Option<CreateFn<T>>,
+    value: Option<T>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl<T> ResourcePool<T> {
+    /// Creates a resource with `create` and keeps the function to recreate it on a new device.
+    pub fn insert(&mut self, gl: &Gl, create: impl Fn(&Gl) -> T + 'static) -> Handle<T> {
+        let value = Some(create(gl));
+        let create: Option<CreateFn<T>> = Some(Box::new(create));
+        let index = match self.free.pop() {
+            Some(index) => {
+                let entry = &mut self.entries[index as usize];
+                entry.create = create;
+                entry.value = value;
+                index
+            }
+            None => {
+                self.entries.push(ResourceEntry { generation: 0, create, value });
+                self.entries.len() as u32 - 1
+            }
+        };
+        Handle {
+            index,
+            generation: self.entries[index as usize].generation,
+            marker: PhantomData,
+        }
+    }
+
+    /// Swaps resource and create function of a valid handle, e.g. when its source file changed.
+    pub fn replace(&mut self, gl: &Gl, handle: Handle<T>, create: impl Fn(&Gl) -> T + 'static) -> bool {
+        if !self.contains(handle) {
+            return false;
+        }
+        let entry = &mut self.entries[handle.index as usize];
+        entry.value = Some(create(gl));
+        entry.create = Some(Box::new(create));
+        true
+    }
+
+    /// Resource of `handle`, `None` if it was removed or the device is lost.
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.entry(handle).and_then(|entry| entry.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.entries
+            .get_mut(handle.index as usize)
+            .filter(|entry| entry.generation == handle.generation && entry.create.is_some())
+            .and_then(|entry| entry.value.as_mut())
+    }
+
+    pub fn contains(&self, handle: Handle<T>) -> bool {
+        self.entry(handle).is_some()
+    }
+
+    /// Frees the resource, returns false for invalid handles.
+    pub fn remove(&mut self, handle: Handle<T>) -> bool {
+        if !self.contains(handle) {
+            return false;
+        }
+        let entry = &mut self.entries[handle.index as usize];
+        entry.generation = entry.generation.wrapping_add(1);
+        entry.create = None;
+        entry.value = None;
+        self.free.push(handle.index);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Recreates all resources on a new device.
+    pub fn restore(&mut self, gl: &Gl) {
+        self.entries.iter_mut().for_each(|entry| entry.value = entry.create.as_ref().map(|create| create(gl)));
+    }
+
+    /// Frees all resources but keeps their handles, called before the device is destroyed.
+    pub fn release(&mut self) {
+        self.entries.iter_mut().for_each(|entry| entry.value = None);
+    }
+
+    fn entry(&self, handle: Handle<T>) -> Option<&ResourceEntry<T>> {
+        self.entries.get(handle.index as usize).filter(|entry| entry.generation == handle.generation && entry.create.is_some())
+    }
+}
+
+impl GpuResources {
+    /// Texture with one layer per image, images are kept in memory for recreation.
+    pub fn load_texture(&mut self, gl: &Gl, images: Vec<RgbaImage>) -> Handle<GlTexture> {
+        self.textures.insert(gl, move |gl| GlTexture::new(gl, &images))
+    }
+
+    /// Fails with the info log if the sources do not compile, sources are kept in memory for recreation.
+    pub fn load_shader(&mut self, gl: &Gl, vert: &[u8], frag: &[u8]) -> Result<Handle<GlShader>, GlError> {
+        // the validated program is used right away, later device restores compile the same sources again
+        let program = Cell::new(Some(GlShader::try_new(gl, vert, frag)?));
+        let (vert, frag) = (vert.to_vec(), frag.to_vec());
+        Ok(self.shaders.insert(gl, move |gl| program.take().unwrap_or_else(|| GlShader::new(gl, &vert, &frag))))
+    }
+
+    pub fn texture(&mut self, handle: Handle<GlTexture>) -> Option<&mut GlTexture> {
+        self.textures.get_mut(handle)
+    }
+
+    pub fn shader(&mut self, handle: Handle<GlShader>) -> Option<&mut GlShader> {
+        self.shaders.get_mut(handle)
+    }
+
+    pub fn mesh(&mut self, handle: Handle<ModelMesh>) -> Option<&mut ModelMesh> {
+        self.meshes.get_mut(handle)
+    }
+
+    pub(crate) fn restore(&mut self, gl: &Gl) {
+        self.textures.restore(gl);
+        self.shaders.restore(gl);
+        self.meshes.restore(gl);
+        log::debug!("Restored {} textures, {} shaders and {} meshes", self.textures.len(), self.shaders.len(), self.meshes.len());
+    }
+
+    pub(crate) fn release(&mut self) {
+        self.textures.release();
+        self.shaders.release();
+        self.meshes.release();
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+// manual impls, derives would require `T` to implement the traits as well
+impl<T> Copy for Handle<T> {}
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.index, self.generation) == (other.index, other.generation)
+    }
+}
+impl<T> Eq for Handle<T> {}
+impl<T> std::hash::Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (self.index, self.generation).hash(state);
+    }
+}
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Handle").field("index", &self.index).field("generation", &self.generation).finish()
+    }
+}
+
+impl<T> Default for ResourcePool<T> {
+    fn default() -> Self {
+        ResourcePool { entries: Vec::new(), free: Vec::new() }
+    }
+}
+impl<T> std::fmt::Debug for ResourcePool<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResourcePool").field("len", &self.len()).finish()
+    }
+}
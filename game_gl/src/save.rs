@@ -0,0 +1,239 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Component, Path};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::file::{FileError, Files};
+
+//////////////////////////////////////////////////
+// Definition
+
+// folder of the slots in the user data directory
+const SAVE_DIR: &str = "saves";
+const SAVE_EXTENSION: &str = ".sav";
+const MAGIC: &[u8; 4] = b"GGSV";
+// magic, schema version and crc32 of the payload
+const HEADER_SIZE: usize = 12;
+
+type Migration = Box<dyn Fn(Value) -> Result<Value, String>>;
+
+/// Named save slots in the user data directory. Each slot stores serde data as JSON together with the schema
+/// version it was written with and a checksum. Slots of older versions are upgraded on load by the registered
+/// migrations, one version at a time.
+pub struct SaveGames {
+    files: Files,
+    version: u32,
+    migrations: BTreeMap<u32, Migration>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    /// The slot could not be read or written.
+    File(FileError),
+    /// The slot is damaged, e.g. by an interrupted copy or manual edits.
+    Corrupt(String),
+    /// The slot was written by a newer version of the game.
+    Version(u32),
+    /// No migration upgrades the slot from this version.
+    MissingMigration(u32),
+    /// A migration rejected the data.
+    Migration(u32, String),
+    /// The data could not be serialized or does not match the expected type.
+    Serialize(String),
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl SaveGames {
+    /// Saves written by this instance are tagged with `version`.
+    pub fn new(files: Files, version: u32) -> SaveGames {
+        SaveGames {
+            files,
+            version,
+            migrations: BTreeMap::new(),
+        }
+    }
+
+    /// Upgrades data of version `from` to `from + 1`.
+    pub fn with_migration(mut self, from: u32, migrate: impl Fn(Value) -> Result<Value, String> + 'static) -> Self {
+        self.migrations.insert(from, Box::new(migrate));
+        self
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn save<T: Serialize>(&self, slot: &str, data: &T) -> Result<(), SaveError> {
+        let bytes = self.encode(data)?;
+        self.files.save_bytes(&slot_path(slot)?, &bytes).map_err(SaveError::File)
+    }
+
+    /// Loads a slot, migrating it to the current version.
+    pub fn load<T: DeserializeOwned>(&self, slot: &str) -> Result<T, SaveError> {
+        let bytes = self.files.load_user_bytes(&slot_path(slot)?).map_err(SaveError::File)?;
+        self.decode(slot, &bytes)
+    }
+
+    fn encode<T: Serialize>(&self, data: &T) -> Result<Vec<u8>, SaveError> {
+        let payload = serde_json::to_vec(data).map_err(|e| SaveError::Serialize(e.to_string()))?;
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + payload.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, slot: &str, bytes: &[u8]) -> Result<T, SaveError> {
+        if bytes.len() < HEADER_SIZE || &bytes[0..4] != MAGIC {
+            return Err(SaveError::Corrupt(format!("{} is no save game", slot)));
+        }
+        let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        let checksum = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+        let payload = &bytes[HEADER_SIZE..];
+        if crc32fast::hash(payload) != checksum {
+            return Err(SaveError::Corrupt(format!("Checksum mismatch in {}", slot)));
+        }
+        if version > self.version {
+            return Err(SaveError::Version(version));
+        }
+        let mut data: Value = serde_json::from_slice(payload).map_err(|e| SaveError::Corrupt(e.to_string()))?;
+        for from in version..self.version {
+            let migrate = self.migrations.get(&from).ok_or(SaveError::MissingMigration(from))?;
+            data = migrate(data).map_err(|e| SaveError::Migration(from, e))?;
+            log::debug!("Migrated {} from version {} to {}", slot, from, from + 1);
+        }
+        serde_json::from_value(data).map_err(|e| SaveError::Serialize(e.to_string()))
+    }
+
+    pub fn exists(&self, slot: &str) -> bool {
+        let Ok(path) = slot_path(slot) else { return false };
+        self.files.user_data_dir().is_some_and(|dir| dir.join(path).is_file())
+    }
+
+    pub fn delete(&self, slot: &str) -> Result<(), SaveError> {
+        self.files.remove_user_file(&slot_path(slot)?).map_err(SaveError::File)
+    }
+
+    /// Names of all slots, sorted.
+    pub fn slots(&self) -> Vec<String> {
+        self.files
+            .list_user(SAVE_DIR)
+            .iter()
+            .filter_map(|path| path.strip_prefix(SAVE_DIR)?.strip_prefix('/')?.strip_suffix(SAVE_EXTENSION).map(|slot| slot.to_string()))
+            .collect()
+    }
+}
+
+// a slot is a single file name, it must not point into other folders
+fn slot_path(slot: &str) -> Result<String, SaveError> {
+    match Path::new(slot).components().collect::<Vec<_>>().as_slice() {
+        [Component::Normal(_)] if !slot.contains('/') => Ok(format!("{}/{}{}", SAVE_DIR, slot, SAVE_EXTENSION)),
+        _ => Err(SaveError::File(FileError::InvalidPath(slot.to_string()))),
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::File(err) => write!(f, "Failed to access save game: {}", err),
+            SaveError::Corrupt(err) => write!(f, "Corrupt save game: {}", err),
+            SaveError::Version(version) => write!(f, "Save game version {} is newer than supported", version),
+            SaveError::MissingMigration(version) => write!(f, "No migration from save game version {}", version),
+            SaveError::Migration(version, err) => write!(f, "Failed to migrate save game from version {}: {}", version, err),
+            SaveError::Serialize(err) => write!(f, "Failed to serialize save game: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl fmt::Debug for SaveGames {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SaveGames").field("version", &self.version).field("migrations", &self.migrations.keys().collect::<Vec<_>>()).finish()
+    }
+}
+
+//////////////////////////////////////////////////
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn save_games(version: u32) -> SaveGames {
+        SaveGames::new(Files::new(), version)
+            .with_migration(0, |mut data| {
+                data["lives"] = json!(3);
+                Ok(data)
+            })
+            .with_migration(1, |mut data| {
+                let level = data["level"].as_u64().ok_or("level is missing")?;
+                data["level"] = json!(format!("world-{}", level));
+                Ok(data)
+            })
+    }
+
+    #[test]
+    fn round_trips_current_version() {
+        let saves = save_games(2);
+        let data = json!({ "level": "world-4", "lives": 1 });
+        let bytes = saves.encode(&data).unwrap();
+        assert_eq!(saves.decode::<Value>("slot", &bytes).unwrap(), data);
+    }
+
+    #[test]
+    fn chains_migrations() {
+        let bytes = save_games(0).encode(&json!({ "level": 4 })).unwrap();
+        let data: Value = save_games(2).decode("slot", &bytes).unwrap();
+        assert_eq!(data, json!({ "level": "world-4", "lives": 3 }));
+        let data: Value = save_games(1).decode("slot", &bytes).unwrap();
+        assert_eq!(data, json!({ "level": 4, "lives": 3 }));
+    }
+
+    #[test]
+    fn reports_failed_and_missing_migrations() {
+        let bytes = save_games(1).encode(&json!({ "lives": 2 })).unwrap();
+        assert!(matches!(save_games(2).decode::<Value>("slot", &bytes), Err(SaveError::Migration(1, _))));
+        assert!(matches!(save_games(3).decode::<Value>("slot", &bytes), Err(SaveError::Migration(1, _))));
+        let bytes = save_games(2).encode(&json!({ "level": "world-4" })).unwrap();
+        assert!(matches!(save_games(3).decode::<Value>("slot", &bytes), Err(SaveError::MissingMigration(2))));
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let bytes = save_games(3).encode(&json!({})).unwrap();
+        assert!(matches!(save_games(2).decode::<Value>("slot", &bytes), Err(SaveError::Version(3))));
+    }
+
+    #[test]
+    fn rejects_checksum_mismatch() {
+        let saves = save_games(2);
+        let mut bytes = saves.encode(&json!({ "level": "world-4" })).unwrap();
+        let last = bytes.len() - 2;
+        bytes[last] ^= 1;
+        assert!(matches!(saves.decode::<Value>("slot", &bytes), Err(SaveError::Corrupt(_))));
+        assert!(matches!(saves.decode::<Value>("slot", &bytes[..HEADER_SIZE - 1]), Err(SaveError::Corrupt(_))));
+        assert!(matches!(saves.decode::<Value>("slot", b"not a save game"), Err(SaveError::Corrupt(_))));
+    }
+
+    #[test]
+    fn rejects_slots_outside_the_save_folder() {
+        assert_eq!(slot_path("slot-1").unwrap(), "saves/slot-1.sav");
+        for slot in ["", "..", "/slot", "../slot", "saves/slot", "slot/"] {
+            assert!(matches!(slot_path(slot), Err(SaveError::File(FileError::InvalidPath(_)))), "{}", slot);
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::any::Any;
+
+use crate::camera::Camera2D;
+use crate::input::{InputEvent, Location};
+use crate::opengl::GlTexture;
+use crate::resources::Handle;
+use crate::shape::ShapeRenderer;
+use crate::sprite::{Sprite, SpriteBatch};
+use crate::{GameContext, Gl};
+
+//////////////////////////////////////////////////
+// Definition
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CameraId(usize);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LayerId(usize);
+
+/// Handle of an entity, stays valid until the entity is despawned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EntityId(usize);
+
+/// Entity of a `Scene`, e.g. a player, a tile map or a particle effect.
+pub trait Drawable: Any {
+    /// Queues shapes and sprites in world coordinates of the layer's camera.
+    fn draw(&self, batch: &mut SceneBatch);
+
+    fn update(&mut self, _ctx: &mut GameContext, _elapsed_time: f32) {}
+
+    /// Pointer events come with their location in world coordinates of the layer's camera. Returning true stops the
+    /// event from reaching entities below and the `GameState`.
+    fn input(&mut self, _ctx: &mut GameContext, _event: &InputEvent, _world: Option<[f32; 2]>) -> bool {
+        false
+    }
+}
+
+/// Renderers a `Drawable` queues into, drawn with the camera of its layer.
+pub struct SceneBatch<'a> {
+    shapes: &'a mut ShapeRenderer,
+    sprites: Option<&'a mut SpriteBatch>,
+}
+
+/// Cameras, layers and entities of a game state. Layers are drawn in the order they were added, each through one
+/// camera, e.g. a world layer following the player below a HUD layer in screen pixels. Entities draw into the
+/// shapes and the sprites of their layer's texture. Input goes to the entities from the top layer down.
+#[derive(Default)]
+pub struct Scene {
+    cameras: Vec<(String, Camera2D)>,
+    layers: Vec<Layer>,
+    entities: Vec<Option<Entity>>,
+    cursor: Option<Location>,
+    shapes: Option<ShapeRenderer>,
+    sprites: Option<SpriteBatch>,
+}
+
+/// Builds a `Scene` from named cameras and layers.
+#[derive(Debug, Default)]
+pub struct SceneBuilder {
+    scene: Scene,
+}
+
+struct Layer {
+    name: String,
+    camera: CameraId,
+    texture: Option<Handle<GlTexture>>,
+    visible: bool,
+    // in draw order
+    entities: Vec<EntityId>,
+}
+
+struct Entity {
+    layer: LayerId,
+    drawable: Box<dyn Drawable>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl SceneBatch<'_> {
+    pub fn shapes(&mut self) -> &mut ShapeRenderer {
+        self.shapes
+    }
+
+    /// Sprites of the layer's texture, `None` on layers without texture.
+    pub fn sprites(&mut self) -> Option<&mut SpriteBatch> {
+        self.sprites.as_deref_mut()
+    }
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Default::default()
+    }
+
+    pub fn add_camera(&mut self, name: &str, camera: Camera2D) -> CameraId {
+        self.cameras.push((name.to_string(), camera));
+        CameraId(self.cameras.len() - 1)
+    }
+
+    pub fn camera(&self, id: CameraId) -> Option<&Camera2D> {
+        self.cameras.get(id.0).map(|(_, camera)| camera)
+    }
+
+    pub fn camera_mut(&mut self, id: CameraId) -> Option<&mut Camera2D> {
+        self.cameras.get_mut(id.0).map(|(_, camera)| camera)
+    }
+
+    pub fn find_camera(&self, name: &str) -> Option<CameraId> {
+        self.cameras.iter().position(|(other, _)| other == name).map(CameraId)
+    }
+
+    /// Adds a layer drawn above all others. Sprites of its entities are drawn with `texture`.
+    pub fn add_layer(&mut self, name: &str, camera: CameraId, texture: Option<Handle<GlTexture>>) -> LayerId {
+        self.layers.push(Layer {
+            name: name.to_string(),
+            camera,
+            texture,
+            visible: true,
+            entities: Vec::new(),
+        });
+        LayerId(self.layers.len() - 1)
+    }
+
+    pub fn find_layer(&self, name: &str) -> Option<LayerId> {
+        self.layers.iter().position(|layer| layer.name == name).map(LayerId)
+    }
+
+    /// Hidden layers are neither drawn nor receive input, their entities keep updating.
+    pub fn set_layer_visible(&mut self, id: LayerId, visible: bool) {
+        if let Some(layer) = self.layers.get_mut(id.0) {
+            layer.visible = visible;
+        }
+    }
+
+    pub fn set_layer_texture(&mut self, id: LayerId, texture: Option<Handle<GlTexture>>) {
+        if let Some(layer) = self.layers.get_mut(id.0) {
+            layer.texture = texture;
+        }
+    }
+
+    /// Adds an entity drawn above the others of its layer.
+    pub fn spawn(&mut self, layer: LayerId, drawable: impl Drawable) -> EntityId {
+        assert!(layer.0 < self.layers.len(), "Unknown layer");
+        let id = EntityId(self.entities.len());
+        self.entities.push(Some(Entity {
+            layer,
+            drawable: Box::new(drawable),
+        }));
+        self.layers[layer.0].entities.push(id);
+        id
+    }
+
+    /// Removes an entity, returns false if it was already despawned.
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        let Some(entity) = self.entities.get_mut(id.0).and_then(|entity| entity.take()) else { return false };
+        self.layers[entity.layer.0].entities.retain(|other| *other != id);
+        true
+    }
+
+    /// Entity of a known type, e.g. to move the player.
+    pub fn entity<T: Drawable>(&self, id: EntityId) -> Option<&T> {
+        let entity = self.entities.get(id.0)?.as_ref()?;
+        (entity.drawable.as_ref() as &dyn Any).downcast_ref()
+    }
+
+    pub fn entity_mut<T: Drawable>(&mut self, id: EntityId) -> Option<&mut T> {
+        let entity = self.entities.get_mut(id.0)?.as_mut()?;
+        (entity.drawable.as_mut() as &mut dyn Any).downcast_mut()
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.layers.iter().map(|layer| layer.entities.len()).sum()
+    }
+
+    /// Resizes all cameras, called with the logical size of the viewport.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.cameras.iter_mut().for_each(|(_, camera)| camera.resize(width, height));
+    }
+
+    /// Passes the events to the entities of the visible layers, top layer and topmost entity first, and returns
+    /// the events no entity consumed.
+    pub fn input(&mut self, ctx: &mut GameContext, input_events: &[InputEvent]) -> Vec<InputEvent> {
+        let mut remaining = Vec::with_capacity(input_events.len());
+        for event in input_events {
+            let location = match event {
+                InputEvent::Cursor(cursor) => {
+                    self.cursor = Some(cursor.location);
+                    Some(cursor.location)
+                }
+                InputEvent::Touch(touch) => Some(touch.location),
+                InputEvent::Mouse(_) => self.cursor,
+                InputEvent::Keyboard(_) => None,
+            };
+            let Scene { cameras, layers, entities, .. } = self;
+            let consumed = layers.iter().rev().filter(|layer| layer.visible).any(|layer| {
+                let world = location.zip(cameras.get(layer.camera.0)).map(|(location, (_, camera))| camera.screen_to_world(location));
+                layer.entities.iter().rev().any(|id| entities[id.0].as_mut().is_some_and(|entity| entity.drawable.input(ctx, event, world)))
+            });
+            if !consumed {
+                remaining.push(*event);
+            }
+        }
+        remaining
+    }
+
+    pub fn update(&mut self, ctx: &mut GameContext, elapsed_time: f32) {
+        self.entities.iter_mut().flatten().for_each(|entity| entity.drawable.update(ctx, elapsed_time));
+    }
+
+    /// Draws the visible layers, bottom first, over the current viewport.
+    pub fn render(&mut self, ctx: &mut GameContext, gl: &Gl) {
+        let Scene { cameras, layers, entities, shapes, sprites, .. } = self;
+        let shapes = shapes.get_or_insert_with(|| ShapeRenderer::new(gl));
+        let sprites = sprites.get_or_insert_with(|| SpriteBatch::new(gl));
+        for layer in layers.iter().filter(|layer| layer.visible) {
+            let Some((_, camera)) = cameras.get(layer.camera.0) else { continue };
+            let mut texture = layer.texture.and_then(|texture| ctx.resources().texture(texture));
+            let mut batch = SceneBatch {
+                shapes: &mut *shapes,
+                sprites: texture.is_some().then_some(&mut *sprites),
+            };
+            layer.entities.iter().filter_map(|id| entities[id.0].as_ref()).for_each(|entity| entity.drawable.draw(&mut batch));
+
+            let view_projection = camera.view_projection();
+            shapes.render(gl, &view_projection);
+            if let Some(texture) = texture.as_mut() {
+                sprites.render(gl, texture, &view_projection);
+            }
+        }
+    }
+
+    /// Releases the GPU resources, called on `destroy_device`. They are recreated by the next render.
+    pub fn release(&mut self) {
+        self.shapes = None;
+        self.sprites = None;
+    }
+}
+
+impl SceneBuilder {
+    pub fn new() -> SceneBuilder {
+        Default::default()
+    }
+
+    pub fn camera(mut self, name: &str, camera: Camera2D) -> Self {
+        self.scene.add_camera(name, camera);
+        self
+    }
+
+    /// Adds a layer drawn with the camera of this name. Panics if no such camera was added.
+    pub fn layer(mut self, name: &str, camera: &str, texture: Option<Handle<GlTexture>>) -> Self {
+        let camera = self.scene.find_camera(camera).unwrap_or_else(|| panic!("Unknown camera {}", camera));
+        self.scene.add_layer(name, camera, texture);
+        self
+    }
+
+    /// Adds an entity to the layer of this name. Panics if no such layer was added.
+    pub fn entity(mut self, layer: &str, drawable: impl Drawable) -> Self {
+        let layer = self.scene.find_layer(layer).unwrap_or_else(|| panic!("Unknown layer {}", layer));
+        self.scene.spawn(layer, drawable);
+        self
+    }
+
+    pub fn build(self) -> Scene {
+        self.scene
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl Drawable for Sprite {
+    fn draw(&self, batch: &mut SceneBatch) {
+        if let Some(sprites) = batch.sprites() {
+            sprites.draw(self);
+        }
+    }
+}
+
+impl std::fmt::Debug for Scene {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scene")
+            .field("cameras", &self.cameras.iter().map(|(name, _)| name).collect::<Vec<_>>())
+            .field("layers", &self.layers.iter().map(|layer| &layer.name).collect::<Vec<_>>())
+            .field("entities", &self.entity_count())
+            .finish()
+    }
+}
@@ -0,0 +1,194 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::fmt;
+
+use crate::GameContext;
+
+//////////////////////////////////////////////////
+// Definition
+
+type System<S> = Box<dyn FnMut(&mut S, &mut GameContext, f32)>;
+
+/// Named update steps of a game state, e.g. "input", "physics" and "animation", run in dependency order.
+/// Each system declares which systems it runs after, systems without dependencies between them keep the order
+/// they were added in.
+pub struct Schedule<S> {
+    systems: Vec<SystemEntry<S>>,
+    // indices into `systems`, `None` until sorted
+    order: Option<Vec<usize>>,
+}
+
+struct SystemEntry<S> {
+    name: String,
+    after: Vec<String>,
+    system: System<S>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// Two systems have the same name.
+    Duplicate(String),
+    /// A system runs after a system that was never added.
+    MissingDependency(String, String),
+    /// The systems depend on each other in a cycle.
+    Cycle(Vec<String>),
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl<S> Schedule<S> {
+    pub fn new() -> Schedule<S> {
+        Schedule { systems: Vec::new(), order: None }
+    }
+
+    /// Adds a system running after all systems named in `after`, which may be added later.
+    pub fn add(&mut self, name: &str, after: &[&str], system: impl FnMut(&mut S, &mut GameContext, f32) + 'static) -> &mut Self {
+        self.systems.push(SystemEntry {
+            name: name.to_string(),
+            after: after.iter().map(|name| name.to_string()).collect(),
+            system: Box::new(system),
+        });
+        self.order = None;
+        self
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.systems.len();
+        self.systems.retain(|entry| entry.name != name);
+        self.order = None;
+        self.systems.len() != len
+    }
+
+    /// Names of the systems in the order they run.
+    pub fn order(&mut self) -> Result<Vec<&str>, ScheduleError> {
+        self.update_order()?;
+        let systems = &self.systems;
+        Ok(self.order.iter().flatten().map(|&index| systems[index].name.as_str()).collect())
+    }
+
+    /// Runs all systems, sorting them first if systems were added or removed.
+    pub fn run(&mut self, state: &mut S, ctx: &mut GameContext, elapsed_time: f32) -> Result<(), ScheduleError> {
+        self.update_order()?;
+        for &index in self.order.iter().flatten() {
+            (self.systems[index].system)(state, ctx, elapsed_time);
+        }
+        Ok(())
+    }
+
+    fn update_order(&mut self) -> Result<(), ScheduleError> {
+        if self.order.is_none() {
+            self.order = Some(self.sort()?);
+        }
+        Ok(())
+    }
+
+    // topological sort preferring insertion order
+    fn sort(&self) -> Result<Vec<usize>, ScheduleError> {
+        let position = |name: &str| self.systems.iter().position(|other| other.name == name);
+        let mut dependencies = Vec::with_capacity(self.systems.len());
+        for (index, entry) in self.systems.iter().enumerate() {
+            if position(&entry.name) != Some(index) {
+                return Err(ScheduleError::Duplicate(entry.name.clone()));
+            }
+            let mut after = Vec::with_capacity(entry.after.len());
+            for name in entry.after.iter() {
+                after.push(position(name).ok_or_else(|| ScheduleError::MissingDependency(entry.name.clone(), name.clone()))?);
+            }
+            dependencies.push(after);
+        }
+
+        let mut order = Vec::with_capacity(self.systems.len());
+        let mut done = vec![false; self.systems.len()];
+        while order.len() < self.systems.len() {
+            let next = (0..self.systems.len()).find(|&index| !done[index] && dependencies[index].iter().all(|&dependency| done[dependency]));
+            let Some(next) = next else {
+                let cycle = (0..self.systems.len()).filter(|&index| !done[index]).map(|index| self.systems[index].name.clone()).collect();
+                return Err(ScheduleError::Cycle(cycle));
+            };
+            done[next] = true;
+            order.push(next);
+        }
+        Ok(order)
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl<S> Default for Schedule<S> {
+    fn default() -> Schedule<S> {
+        Schedule::new()
+    }
+}
+
+impl<S> fmt::Debug for Schedule<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Schedule").field("systems", &self.systems.iter().map(|entry| &entry.name).collect::<Vec<_>>()).finish()
+    }
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::Duplicate(name) => write!(f, "System {} was added twice", name),
+            ScheduleError::MissingDependency(name, dependency) => write!(f, "System {} runs after unknown system {}", name, dependency),
+            ScheduleError::Cycle(names) => write!(f, "Systems depend on each other in a cycle: {}", names.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+//////////////////////////////////////////////////
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(systems: &[(&str, &[&str])]) -> Schedule<()> {
+        let mut schedule = Schedule::new();
+        systems.iter().for_each(|(name, after)| {
+            schedule.add(name, after, |_, _, _| {});
+        });
+        schedule
+    }
+
+    #[test]
+    fn sorts_dependencies_first() {
+        let mut schedule = schedule(&[("render", &["physics"]), ("physics", &["input"]), ("input", &[])]);
+        assert_eq!(schedule.order(), Ok(vec!["input", "physics", "render"]));
+    }
+
+    #[test]
+    fn keeps_insertion_order_of_independent_systems() {
+        let mut schedule = schedule(&[("a", &[]), ("b", &[]), ("c", &["d"]), ("d", &[]), ("e", &[])]);
+        assert_eq!(schedule.order(), Ok(vec!["a", "b", "d", "c", "e"]));
+        schedule.remove("d");
+        schedule.add("d", &[], |_, _, _| {});
+        assert_eq!(schedule.order(), Ok(vec!["a", "b", "e", "d", "c"]));
+    }
+
+    #[test]
+    fn rejects_duplicates() {
+        let mut schedule = schedule(&[("a", &[]), ("b", &[]), ("a", &[])]);
+        assert_eq!(schedule.order(), Err(ScheduleError::Duplicate("a".to_string())));
+    }
+
+    #[test]
+    fn rejects_missing_dependencies() {
+        let mut schedule = schedule(&[("a", &[]), ("b", &["c"])]);
+        assert_eq!(schedule.order(), Err(ScheduleError::MissingDependency("b".to_string(), "c".to_string())));
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let mut schedule = schedule(&[("a", &[]), ("b", &["c"]), ("c", &["b"])]);
+        assert_eq!(schedule.order(), Err(ScheduleError::Cycle(vec!["b".to_string(), "c".to_string()])));
+        schedule.remove("c");
+        schedule.add("c", &[], |_, _, _| {});
+        assert_eq!(schedule.order(), Ok(vec!["a", "c", "b"]));
+    }
+}
@@ -0,0 +1,212 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::f32::consts::TAU;
+use std::mem::size_of;
+
+use crate::camera::Mat4;
+use crate::color::Color;
+use crate::gl;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Shader
+
+const VS: &[u8] = b"#version 300 es
+layout(location = 0) in vec2 a_Pos;
+layout(location = 1) in vec4 a_Color;
+
+layout(std140) uniform Transform {
+    mat4 u_ViewProjection;
+};
+
+out vec4 v_Color;
+
+void main() {
+    v_Color = a_Color;
+    gl_Position = u_ViewProjection * vec4(a_Pos, 0.0, 1.0);
+}
+";
+
+const FS: &[u8] = b"#version 300 es
+precision mediump float;
+
+in vec4 v_Color;
+
+layout(location = 0) out vec4 target0;
+
+void main() {
+    target0 = v_Color;
+}
+";
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Immediate mode renderer for colored 2D shapes, e.g. UI backgrounds, selection boxes or physics debug drawing.
+/// Shapes are collected as triangles and drawn in a single call by `render`.
+#[derive(Debug, Default)]
+pub struct ShapeRenderer {
+    shader: GlShader,
+    vao: GlVertexArrayObject,
+    vertices: GlVertexBuffer<ShapeVertex>,
+    transform: GlUniformBuffer<Mat4>,
+    triangles: Vec<ShapeVertex>,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct ShapeVertex {
+    position: [f32; 2],
+    color: Color,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl ShapeRenderer {
+    pub fn new(gl: &Gl) -> ShapeRenderer {
+        let mut renderer = ShapeRenderer {
+            shader: GlShader::new(gl, VS, FS),
+            vao: GlVertexArrayObject::new(gl),
+            vertices: GlVertexBuffer::new(gl, gl::DYNAMIC_DRAW, &[]),
+            transform: GlUniformBuffer::new(gl, gl::DYNAMIC_DRAW, &Mat4::default()),
+            triangles: Vec::new(),
+        };
+        let stride = size_of::<ShapeVertex>();
+        renderer.vao.bind();
+        renderer.vao.bind_attrib(&renderer.vertices, 0, 2, gl::FLOAT, gl::FALSE, 0, stride, 0);
+        renderer.vao.bind_attrib(&renderer.vertices, 1, 4, gl::FLOAT, gl::FALSE, 2 * size_of::<f32>(), stride, 0);
+        renderer.vao.unbind();
+        renderer
+    }
+
+    /// Filled triangle with one color per corner.
+    pub fn triangle(&mut self, points: [[f32; 2]; 3], colors: [impl Into<Color>; 3]) {
+        self.triangles.extend(points.iter().zip(colors).map(|(&position, color)| ShapeVertex { position, color: color.into() }));
+    }
+
+    /// Filled quad given in winding order with one color per corner, e.g. for gradients.
+    pub fn quad(&mut self, points: [[f32; 2]; 4], colors: [impl Into<Color>; 4]) {
+        let colors = colors.map(Into::<Color>::into);
+        self.triangle([points[0], points[1], points[2]], [colors[0], colors[1], colors[2]]);
+        self.triangle([points[2], points[3], points[0]], [colors[2], colors[3], colors[0]]);
+    }
+
+    pub fn line(&mut self, from: [f32; 2], to: [f32; 2], thickness: f32, color: impl Into<Color>) {
+        let color = color.into();
+        self.line_gradient(from, to, thickness, color, color);
+    }
+
+    /// Line fading from `from_color` to `to_color`.
+    pub fn line_gradient(&mut self, from: [f32; 2], to: [f32; 2], thickness: f32, from_color: impl Into<Color>, to_color: impl Into<Color>) {
+        let (from_color, to_color) = (from_color.into(), to_color.into());
+        let [dx, dy] = [to[0] - from[0], to[1] - from[1]];
+        let length = (dx * dx + dy * dy).sqrt();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let [nx, ny] = [-dy / length * thickness * 0.5, dx / length * thickness * 0.5];
+        self.quad(
+            [[from[0] + nx, from[1] + ny], [from[0] - nx, from[1] - ny], [to[0] - nx, to[1] - ny], [to[0] + nx, to[1] + ny]],
+            [from_color, from_color, to_color, to_color],
+        );
+    }
+
+    /// Connected line segments, `closed` also connects the last point to the first.
+    pub fn polyline(&mut self, points: &[[f32; 2]], thickness: f32, color: impl Into<Color>, closed: bool) {
+        let color = color.into();
+        points.windows(2).for_each(|segment| self.line(segment[0], segment[1], thickness, color));
+        if let (true, Some(&first), Some(&last)) = (closed && points.len() > 2, points.first(), points.last()) {
+            self.line(last, first, thickness, color);
+        }
+    }
+
+    pub fn rect(&mut self, min: [f32; 2], max: [f32; 2], color: impl Into<Color>) {
+        self.quad([min, [max[0], min[1]], max, [min[0], max[1]]], [color.into(); 4]);
+    }
+
+    /// Rectangle border drawn inside of `min` and `max`.
+    pub fn rect_outline(&mut self, min: [f32; 2], max: [f32; 2], thickness: f32, color: impl Into<Color>) {
+        let color = color.into();
+        let t = thickness.min((max[0] - min[0]) * 0.5).min((max[1] - min[1]) * 0.5);
+        self.rect(min, [max[0], min[1] + t], color);
+        self.rect([min[0], max[1] - t], max, color);
+        self.rect([min[0], min[1] + t], [min[0] + t, max[1] - t], color);
+        self.rect([max[0] - t, min[1] + t], [max[0], max[1] - t], color);
+    }
+
+    /// Filled convex polygon, triangulated as a fan around the first point.
+    pub fn polygon(&mut self, points: &[[f32; 2]], color: impl Into<Color>) {
+        let color = color.into();
+        if let Some((&first, rest)) = points.split_first() {
+            rest.windows(2).for_each(|edge| self.triangle([first, edge[0], edge[1]], [color; 3]));
+        }
+    }
+
+    pub fn circle(&mut self, center: [f32; 2], radius: f32, color: impl Into<Color>) {
+        let points = circle_points(center, radius);
+        self.polygon(&points, color);
+    }
+
+    pub fn circle_outline(&mut self, center: [f32; 2], radius: f32, thickness: f32, color: impl Into<Color>) {
+        let color = color.into();
+        let (outer, inner) = (circle_points(center, radius), circle_points(center, (radius - thickness).max(0.0)));
+        (0..outer.len()).for_each(|i| {
+            let j = (i + 1) % outer.len();
+            self.quad([outer[i], outer[j], inner[j], inner[i]], [color; 4]);
+        });
+    }
+
+    /// Draws and clears all collected shapes. `view_projection` maps shape coordinates to clip space, e.g. `Camera2D::view_projection`.
+    pub fn render(&mut self, gl: &Gl, view_projection: &Mat4) {
+        if self.triangles.is_empty() {
+            return;
+        }
+        self.vertices.update(&self.triangles);
+        self.transform.update(view_projection);
+
+        let state = GlRenderState::transparent();
+        state.apply(gl, None);
+        self.vao.bind();
+        self.transform.bind(0);
+        self.shader.bind();
+        self.shader.link_uniform(0, "Transform");
+        self.shader.draw_arrays(gl::TRIANGLES, self.triangles.len());
+        self.shader.unbind();
+        self.transform.unbind();
+        self.vao.unbind();
+        GlRenderState::default().apply(gl, Some(&state));
+
+        self.triangles.clear();
+    }
+}
+
+// segment count grows with the radius, so large circles stay round
+fn circle_points(center: [f32; 2], radius: f32) -> Vec<[f32; 2]> {
+    let segments = (radius.abs().sqrt() * 4.0).clamp(12.0, 128.0) as usize;
+    (0..segments)
+        .map(|i| {
+            let (s, c) = (i as f32 / segments as f32 * TAU).sin_cos();
+            [center[0] + c * radius, center[1] + s * radius]
+        })
+        .collect()
+}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl GlResource for ShapeRenderer {
+    fn release(&mut self) {
+        self.shader.release();
+        self.vao.release();
+        self.vertices.release();
+        self.transform.release();
+    }
+}
+impl Drop for ShapeRenderer {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
@@ -0,0 +1,276 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::mem::size_of;
+
+use crate::atlas::AtlasRegion;
+use crate::camera::Mat4;
+use crate::color::Color;
+use crate::gl;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Shader
+
+const VS: &[u8] = b"#version 300 es
+layout(location = 0) in vec2 a_Pos;
+layout(location = 1) in vec2 a_TexCoord;
+layout(location = 2) in vec4 a_Color;
+
+layout(std140) uniform Transform {
+    mat4 u_ViewProjection;
+};
+
+out vec2 v_TexCoord;
+out vec4 v_Color;
+
+void main() {
+    v_TexCoord = a_TexCoord;
+    v_Color = a_Color;
+    gl_Position = u_ViewProjection * vec4(a_Pos, 0.0, 1.0);
+}
+";
+
+const FS: &[u8] = b"#version 300 es
+precision mediump float;
+precision mediump sampler2DArray;
+
+in vec2 v_TexCoord;
+in vec4 v_Color;
+
+uniform sampler2DArray t_Sprite;
+
+layout(location = 0) out vec4 target0;
+
+void main() {
+    target0 = v_Color * texture(t_Sprite, vec3(v_TexCoord, 0.0));
+}
+";
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Collects textured quads sharing one texture and draws them with a single call.
+#[derive(Debug, Default)]
+pub struct SpriteBatch {
+    shader: GlShader,
+    vao: GlVertexArrayObject,
+    vertices: GlVertexBuffer<SpriteVertex>,
+    indices: GlIndexBuffer,
+    transform: GlUniformBuffer<Mat4>,
+    quads: Vec<SpriteVertex>,
+}
+
+/// Rotated, tinted rectangle showing a texture region.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sprite {
+    /// Center position.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    /// Counter-clockwise rotation in radians around the center.
+    pub rotation: f32,
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    pub color: Color,
+}
+
+/// Texture region split into a 3x3 grid: corners keep their size, edges stretch along one axis and the center along both.
+/// Used for buttons and panels that scale to any size.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NinePatch {
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    /// Size of the region in pixels.
+    pub size: [f32; 2],
+    /// Border widths in pixels: left, right, top, bottom.
+    pub borders: [f32; 4],
+    /// Scales the borders when drawn, e.g. for display density.
+    pub scale: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct SpriteVertex {
+    position: [f32; 2],
+    tex_coord: [f32; 2],
+    color: Color,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl SpriteBatch {
+    pub fn new(gl: &Gl) -> SpriteBatch {
+        let mut batch = SpriteBatch {
+            shader: GlShader::new(gl, VS, FS),
+            vao: GlVertexArrayObject::new(gl),
+            vertices: GlVertexBuffer::new(gl, gl::DYNAMIC_DRAW, &[]),
+            indices: GlIndexBuffer::new(gl, gl::DYNAMIC_DRAW, &[]),
+            transform: GlUniformBuffer::new(gl, gl::DYNAMIC_DRAW, &Mat4::default()),
+            quads: Vec::new(),
+        };
+        let stride = size_of::<SpriteVertex>();
+        batch.vao.bind();
+        batch.vao.bind_attrib(&batch.vertices, 0, 2, gl::FLOAT, gl::FALSE, 0, stride, 0);
+        batch.vao.bind_attrib(&batch.vertices, 1, 2, gl::FLOAT, gl::FALSE, 2 * size_of::<f32>(), stride, 0);
+        batch.vao.bind_attrib(&batch.vertices, 2, 4, gl::FLOAT, gl::FALSE, 4 * size_of::<f32>(), stride, 0);
+        batch.vao.unbind();
+        batch
+    }
+
+    pub fn draw(&mut self, sprite: &Sprite) {
+        let [hw, hh] = [sprite.size[0] * 0.5, sprite.size[1] * 0.5];
+        let (s, c) = sprite.rotation.sin_cos();
+        let corner = |x: f32, y: f32| [sprite.position[0] + x * c - y * s, sprite.position[1] + x * s + y * c];
+        let ([u0, v0], [u1, v1]) = (sprite.uv_min, sprite.uv_max);
+        self.draw_quad([corner(-hw, -hh), corner(hw, -hh), corner(hw, hh), corner(-hw, hh)], [[u0, v1], [u1, v1], [u1, v0], [u0, v0]], sprite.color);
+    }
+
+    /// Quad given in winding order with texture coordinates per corner.
+    pub fn draw_quad(&mut self, points: [[f32; 2]; 4], tex_coords: [[f32; 2]; 4], color: impl Into<Color>) {
+        let color = color.into();
+        self.quads.extend(points.iter().zip(tex_coords.iter()).map(|(&position, &tex_coord)| SpriteVertex { position, tex_coord, color }));
+    }
+
+    /// Draws `patch` stretched over the rectangle from `min` to `max`. Borders shrink if the rectangle is too small to fit them.
+    pub fn draw_nine_patch(&mut self, patch: &NinePatch, min: [f32; 2], max: [f32; 2], color: impl Into<Color>) {
+        let color = color.into();
+        let [left, right, top, bottom] = patch.borders.map(|border| border * patch.scale);
+        let fit_x = ((max[0] - min[0]) / (left + right)).min(1.0);
+        let fit_y = ((max[1] - min[1]) / (top + bottom)).min(1.0);
+        let ([u0, v0], [u1, v1]) = (patch.uv_min, patch.uv_max);
+        let [du, dv] = [(u1 - u0) / patch.size[0], (v1 - v0) / patch.size[1]];
+
+        // y points up, so the bottom row comes first and samples from the bottom of the region
+        let xs = [min[0], min[0] + left * fit_x, max[0] - right * fit_x, max[0]];
+        let ys = [min[1], min[1] + bottom * fit_y, max[1] - top * fit_y, max[1]];
+        let us = [u0, u0 + patch.borders[0] * du, u1 - patch.borders[1] * du, u1];
+        let vs = [v1, v1 - patch.borders[3] * dv, v0 + patch.borders[2] * dv, v0];
+        for row in 0..3 {
+            for column in 0..3 {
+                let (x0, x1, y0, y1) = (xs[column], xs[column + 1], ys[row], ys[row + 1]);
+                if x1 <= x0 || y1 <= y0 {
+                    continue;
+                }
+                let (tu0, tu1, tv0, tv1) = (us[column], us[column + 1], vs[row], vs[row + 1]);
+                self.draw_quad([[x0, y0], [x1, y0], [x1, y1], [x0, y1]], [[tu0, tv0], [tu1, tv0], [tu1, tv1], [tu0, tv1]], color);
+            }
+        }
+    }
+
+    pub fn sprite_count(&self) -> usize {
+        self.quads.len() / 4
+    }
+
+    /// Draws and clears all collected sprites with alpha blending. `view_projection` maps sprite coordinates to clip space.
+    pub fn render(&mut self, gl: &Gl, texture: &mut GlTexture, view_projection: &Mat4) {
+        if self.quads.is_empty() {
+            return;
+        }
+        let quad_count = self.quads.len() / 4;
+        if self.indices.count() < quad_count * 6 {
+            let indices: Vec<u32> = (0..quad_count as u32).flat_map(|i| [0, 1, 2, 2, 3, 0].map(|index| i * 4 + index)).collect();
+            self.indices.update(&indices);
+        }
+        self.vertices.update(&self.quads);
+        self.transform.update(view_projection);
+
+        let state = GlRenderState::transparent();
+        state.apply(gl, None);
+        self.vao.bind();
+        self.indices.bind();
+        texture.bind(0);
+        self.transform.bind(0);
+        self.shader.bind();
+        self.shader.link_texture(0, "t_Sprite");
+        self.shader.link_uniform(0, "Transform");
+        self.shader.draw_elements(gl::TRIANGLES, quad_count * 6);
+        self.shader.unbind();
+        self.transform.unbind();
+        texture.unbind();
+        self.indices.unbind();
+        self.vao.unbind();
+        GlRenderState::default().apply(gl, Some(&state));
+
+        self.quads.clear();
+    }
+}
+
+impl Sprite {
+    /// White, unrotated sprite showing the whole texture.
+    pub fn new(position: [f32; 2], size: [f32; 2]) -> Sprite {
+        Sprite {
+            position,
+            size,
+            rotation: 0.0,
+            uv_min: [0.0, 0.0],
+            uv_max: [1.0, 1.0],
+            color: Color::WHITE,
+        }
+    }
+
+    /// Shows an atlas region instead of the whole texture.
+    pub fn with_region(mut self, region: &AtlasRegion) -> Self {
+        self.uv_min = region.uv_min;
+        self.uv_max = region.uv_max;
+        self
+    }
+
+    pub fn with_color(mut self, color: impl Into<Color>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+}
+
+impl NinePatch {
+    /// Nine-patch covering a whole texture of `size` pixels.
+    pub fn new(size: [f32; 2], borders: [f32; 4]) -> NinePatch {
+        NinePatch {
+            uv_min: [0.0, 0.0],
+            uv_max: [1.0, 1.0],
+            size,
+            borders,
+            scale: 1.0,
+        }
+    }
+
+    /// Nine-patch covering an atlas region.
+    pub fn from_region(region: &AtlasRegion, borders: [f32; 4]) -> NinePatch {
+        NinePatch {
+            uv_min: region.uv_min,
+            uv_max: region.uv_max,
+            size: [region.width as f32, region.height as f32],
+            borders,
+            scale: 1.0,
+        }
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl GlResource for SpriteBatch {
+    fn release(&mut self) {
+        self.shader.release();
+        self.vao.release();
+        self.vertices.release();
+        self.indices.release();
+        self.transform.release();
+    }
+}
+impl Drop for SpriteBatch {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
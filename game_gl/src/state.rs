@@ -0,0 +1,431 @@
+//////////////////////////////////////////////////
+// Using
+
+use crate::input::InputEvent;
+use crate::opengl::{GlExt, GlFramebuffer};
+use crate::scene::Scene;
+use crate::transition::{TransitionEffect, TransitionRenderer};
+use crate::{GameContext, Gl};
+
+//////////////////////////////////////////////////
+// Definition
+
+// 60 ticks per second
+const DEFAULT_TIMESTEP: f32 = 1.0 / 60.0;
+
+// ticks per frame before time is dropped, so a slow frame can't cause ever slower frames
+const MAX_TICKS: u32 = 8;
+
+/// Screen of a game like a menu, a level or a pause overlay, managed by a `StateStack`.
+/// All callbacks are optional and mirror the ones of `GameLoop`.
+pub trait GameState {
+    /// Called when the state is pushed.
+    fn init(&mut self, _ctx: &mut GameContext) {}
+
+    /// Called when the state is popped.
+    fn cleanup(&mut self, _ctx: &mut GameContext) {}
+
+    /// The returned transition is applied after the state returned.
+    fn input(&mut self, _ctx: &mut GameContext, _input_events: &[InputEvent]) -> Transition {
+        Transition::None
+    }
+
+    /// Called at a fixed rate before `update`, e.g. for physics. `timestep` is constant, so the simulation is
+    /// deterministic. The returned transition is applied after the tick.
+    fn fixed_update(&mut self, _ctx: &mut GameContext, _timestep: f32) -> Transition {
+        Transition::None
+    }
+
+    /// Called once per frame. The returned transition is applied after the state returned.
+    fn update(&mut self, _ctx: &mut GameContext, _elapsed_time: f32) -> Transition {
+        Transition::None
+    }
+
+    fn render(&mut self, _ctx: &mut GameContext, _gl: &Gl) {}
+
+    /// Called for every state on the stack, and on push if the device already exists.
+    fn create_device(&mut self, _ctx: &mut GameContext, _gl: &Gl) {}
+
+    /// Called for every state on the stack, and on pop if the device exists.
+    fn destroy_device(&mut self, _ctx: &mut GameContext, _gl: &Gl) {}
+
+    fn resize_device(&mut self, _ctx: &mut GameContext, _gl: &Gl, _width: u32, _height: u32) {}
+
+    /// Scene of the state, updated and rendered by the stack before the state's own callbacks. Input events consumed
+    /// by its entities don't reach `input`.
+    fn scene(&mut self) -> Option<&mut Scene> {
+        None
+    }
+
+    /// Whether the state below keeps updating while this state is on top, false by default.
+    fn update_below(&self) -> bool {
+        false
+    }
+
+    /// Whether the state below is rendered before this state, e.g. for a pause menu over the frozen game.
+    /// False by default.
+    fn render_below(&self) -> bool {
+        false
+    }
+}
+
+/// Change of the state stack requested by the top state. States can't modify the stack they are called from,
+/// so they return a transition which the stack applies afterwards.
+#[derive(Default)]
+pub enum Transition {
+    /// Keeps the current state.
+    #[default]
+    None,
+    /// Pauses the current state and starts a new one on top.
+    Push(Box<dyn GameState>),
+    /// Ends the current state.
+    Pop,
+    /// Replaces the current state.
+    Switch(Box<dyn GameState>),
+    /// Pops all states and exits the game.
+    Quit,
+}
+
+/// Stack of game states, e.g. menu → game → pause. Input goes to the top state only. Update and render go to the
+/// top state and the states below it as long as `update_below` and `render_below` allow, bottom first. Device
+/// callbacks go to all states, so states below the top keep their GPU resources. A `GameLoop` owns the stack and
+/// forwards its callbacks to it. Transitions returned by the top state are applied after its callback.
+///
+/// With a `TransitionEffect` set, changes of the stack are animated: the old screen is captured before the change
+/// and blended with the new one, which keeps rendering offscreen until the animation ends. States must not bind
+/// the default framebuffer themselves while rendering, e.g. by unbinding their own framebuffers.
+pub struct StateStack {
+    states: Vec<Box<dyn GameState>>,
+    // device of pushed states, `None` while suspended
+    gl: Option<Gl>,
+    size: Option<(u32, u32)>,
+    timestep: f32,
+    // time not yet simulated by fixed updates
+    accumulator: f32,
+    effect: Option<TransitionEffect>,
+    duration: f32,
+    // created by the first animated change
+    transition: Option<TransitionRenderer>,
+    // seconds since the change, `None` without running animation
+    transition_time: Option<f32>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Transition {
+    pub fn push(state: impl GameState + 'static) -> Transition {
+        Transition::Push(Box::new(state))
+    }
+
+    pub fn switch(state: impl GameState + 'static) -> Transition {
+        Transition::Switch(Box::new(state))
+    }
+}
+
+impl StateStack {
+    pub fn new() -> StateStack {
+        Default::default()
+    }
+
+    /// Pauses the current state and starts a new one on top.
+    pub fn push(&mut self, ctx: &mut GameContext, state: impl GameState + 'static) {
+        self.begin_transition(ctx);
+        self.push_boxed(ctx, Box::new(state));
+    }
+
+    fn push_boxed(&mut self, ctx: &mut GameContext, mut state: Box<dyn GameState>) {
+        state.init(ctx);
+        if let Some(gl) = self.gl.as_ref() {
+            state.create_device(ctx, gl);
+            if let Some((width, height)) = self.size {
+                let (logical_width, logical_height) = ctx.viewport().logical_size();
+                if let Some(scene) = state.scene() {
+                    scene.resize(logical_width, logical_height);
+                }
+                state.resize_device(ctx, gl, width, height);
+            }
+        }
+        self.states.push(state);
+    }
+
+    /// Ends the current state and continues with the one below. Returns false if the stack was empty.
+    pub fn pop(&mut self, ctx: &mut GameContext) -> bool {
+        self.begin_transition(ctx);
+        self.pop_state(ctx)
+    }
+
+    fn pop_state(&mut self, ctx: &mut GameContext) -> bool {
+        let Some(mut state) = self.states.pop() else { return false };
+        if let Some(gl) = self.gl.as_ref() {
+            state.destroy_device(ctx, gl);
+        }
+        state.cleanup(ctx);
+        true
+    }
+
+    /// Replaces the current state, e.g. to switch from the menu to the game.
+    pub fn change(&mut self, ctx: &mut GameContext, state: impl GameState + 'static) {
+        self.begin_transition(ctx);
+        self.pop_state(ctx);
+        self.push_boxed(ctx, Box::new(state));
+    }
+
+    /// Applies a transition, also usable from the `GameLoop`.
+    pub fn apply(&mut self, ctx: &mut GameContext, transition: Transition) {
+        if matches!(transition, Transition::Push(_) | Transition::Pop | Transition::Switch(_)) {
+            self.begin_transition(ctx);
+        }
+        self.apply_now(ctx, transition);
+    }
+
+    fn apply_now(&mut self, ctx: &mut GameContext, transition: Transition) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(state) => self.push_boxed(ctx, state),
+            Transition::Pop => {
+                self.pop_state(ctx);
+            }
+            Transition::Switch(state) => {
+                self.pop_state(ctx);
+                self.push_boxed(ctx, state);
+            }
+            Transition::Quit => {
+                self.clear(ctx);
+                ctx.exit();
+            }
+        }
+    }
+
+    /// Pops all states, e.g. in `GameLoop::cleanup`.
+    pub fn clear(&mut self, ctx: &mut GameContext) {
+        while self.pop_state(ctx) {}
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn input(&mut self, ctx: &mut GameContext, input_events: &[InputEvent]) {
+        if let Some(state) = self.states.last_mut() {
+            let remaining = state.scene().map(|scene| scene.input(ctx, input_events));
+            let transition = state.input(ctx, remaining.as_deref().unwrap_or(input_events));
+            self.apply(ctx, transition);
+        }
+    }
+
+    /// Runs the due fixed updates, then the frame update.
+    pub fn update(&mut self, ctx: &mut GameContext, elapsed_time: f32) {
+        if let Some(time) = self.transition_time {
+            self.transition_time = Some(time + elapsed_time).filter(|time| *time < self.duration);
+        }
+        self.accumulator += elapsed_time;
+        let mut ticks = 0;
+        while self.accumulator >= self.timestep {
+            if ticks == MAX_TICKS {
+                self.accumulator %= self.timestep;
+                break;
+            }
+            for index in self.first(|state| state.update_below())..self.states.len() {
+                let transition = self.states[index].fixed_update(ctx, self.timestep);
+                if self.apply_from(ctx, index, transition) {
+                    break;
+                }
+            }
+            self.accumulator -= self.timestep;
+            ticks += 1;
+        }
+        for index in self.first(|state| state.update_below())..self.states.len() {
+            if let Some(scene) = self.states[index].scene() {
+                scene.update(ctx, elapsed_time);
+            }
+            let transition = self.states[index].update(ctx, elapsed_time);
+            if self.apply_from(ctx, index, transition) {
+                break;
+            }
+        }
+    }
+
+    /// Seconds between fixed updates, 1/60 by default.
+    pub fn set_fixed_timestep(&mut self, timestep: f32) {
+        self.timestep = timestep.max(f32::EPSILON);
+    }
+
+    pub fn fixed_timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    /// Progress towards the next fixed update in [0, 1), to interpolate rendering between two ticks.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.timestep
+    }
+
+    /// Animates changes of the stack with `effect` over `duration` seconds, `None` switches instantly.
+    /// A custom shader failing to compile is logged and falls back to switching instantly.
+    pub fn set_transition_effect(&mut self, effect: Option<TransitionEffect>, duration: f32) {
+        self.effect = effect;
+        self.duration = duration;
+    }
+
+    /// Whether a change of the stack is being animated.
+    pub fn is_transitioning(&self) -> bool {
+        self.transition_time.is_some()
+    }
+
+    pub fn render(&mut self, ctx: &mut GameContext, gl: &Gl) {
+        let (Some(time), Some(mut transition)) = (self.transition_time, self.transition.take()) else {
+            self.render_states(ctx, gl);
+            return;
+        };
+        transition.to().bind();
+        clear_target(ctx, transition.to());
+        self.render_states(ctx, gl);
+        transition.to().unbind();
+        // ease in and out
+        let t = (time / self.duration).clamp(0.0, 1.0);
+        transition.blend(gl, t * t * (3.0 - 2.0 * t), ctx.viewport().size());
+        gl.set_viewport(ctx.viewport().viewport());
+        self.transition = Some(transition);
+    }
+
+    // renders the current states into the old screen target, before the stack changes
+    fn begin_transition(&mut self, ctx: &mut GameContext) {
+        let (Some(effect), Some(gl), Some((width, height))) = (self.effect.as_ref(), self.gl.clone(), self.size) else { return };
+        if self.states.is_empty() || self.duration <= 0.0 {
+            return;
+        }
+        let mut transition = match self.transition.take() {
+            Some(transition) if transition.effect() == effect => transition,
+            _ => match TransitionRenderer::new(&gl, effect, width, height) {
+                Ok(transition) => transition,
+                Err(err) => {
+                    // switch instantly from now on instead of compiling the broken shader again
+                    log::error!("Failed to create transition, switching instantly. {}", err);
+                    self.effect = None;
+                    return;
+                }
+            },
+        };
+        transition.resize(width, height);
+        transition.from().bind();
+        clear_target(ctx, transition.from());
+        self.render_states(ctx, &gl);
+        transition.from().unbind();
+        gl.set_viewport(ctx.viewport().viewport());
+        self.transition = Some(transition);
+        self.transition_time = Some(0.0);
+    }
+
+    fn render_states(&mut self, ctx: &mut GameContext, gl: &Gl) {
+        for index in self.first(|state| state.render_below())..self.states.len() {
+            if let Some(scene) = self.states[index].scene() {
+                scene.render(ctx, gl);
+            }
+            self.states[index].render(ctx, gl);
+        }
+    }
+
+    // lowest state reached from the top while `below` allows
+    fn first(&self, below: impl Fn(&dyn GameState) -> bool) -> usize {
+        let mut first = self.states.len().saturating_sub(1);
+        while first > 0 && below(self.states[first].as_ref()) {
+            first -= 1;
+        }
+        first
+    }
+
+    // a transition of a state below the top first pops the states above it, so it applies to the state that
+    // returned it. Returns true if the stack changed.
+    fn apply_from(&mut self, ctx: &mut GameContext, index: usize, transition: Transition) -> bool {
+        if matches!(transition, Transition::None) {
+            return false;
+        }
+        if !matches!(transition, Transition::Quit) {
+            self.begin_transition(ctx);
+        }
+        while self.states.len() > index + 1 {
+            self.pop_state(ctx);
+        }
+        self.apply_now(ctx, transition);
+        true
+    }
+
+    pub fn create_device(&mut self, ctx: &mut GameContext, gl: &Gl) {
+        self.gl = Some(gl.clone());
+        self.states.iter_mut().for_each(|state| state.create_device(ctx, gl));
+    }
+
+    pub fn destroy_device(&mut self, ctx: &mut GameContext, gl: &Gl) {
+        self.states.iter_mut().rev().for_each(|state| {
+            state.destroy_device(ctx, gl);
+            if let Some(scene) = state.scene() {
+                scene.release();
+            }
+        });
+        self.transition = None;
+        self.transition_time = None;
+        self.gl = None;
+    }
+
+    pub fn resize_device(&mut self, ctx: &mut GameContext, gl: &Gl, width: u32, height: u32) {
+        self.size = Some((width, height));
+        if let Some(transition) = self.transition.as_mut() {
+            transition.resize(width, height);
+        }
+        // cameras work in logical pixels like the input
+        let (logical_width, logical_height) = ctx.viewport().logical_size();
+        self.states.iter_mut().for_each(|state| {
+            if let Some(scene) = state.scene() {
+                scene.resize(logical_width, logical_height);
+            }
+            state.resize_device(ctx, gl, width, height);
+        });
+    }
+}
+
+// clears a target the states render into, states may not clear the whole surface themselves
+fn clear_target(ctx: &GameContext, target: &mut GlFramebuffer) {
+    target.clear_colors(ctx.clear_color().to_array());
+    target.clear_depth_stencil(1.0, 0);
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl Default for StateStack {
+    fn default() -> StateStack {
+        StateStack {
+            states: Vec::new(),
+            gl: None,
+            size: None,
+            timestep: DEFAULT_TIMESTEP,
+            accumulator: 0.0,
+            effect: None,
+            duration: 0.0,
+            transition: None,
+            transition_time: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Transition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transition::None => write!(f, "None"),
+            Transition::Push(_) => write!(f, "Push"),
+            Transition::Pop => write!(f, "Pop"),
+            Transition::Switch(_) => write!(f, "Switch"),
+            Transition::Quit => write!(f, "Quit"),
+        }
+    }
+}
+
+impl std::fmt::Debug for StateStack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateStack").field("states", &self.states.len()).field("size", &self.size).field("timestep", &self.timestep).finish()
+    }
+}
@@ -0,0 +1,99 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::BTreeMap;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::file::{FileError, Files};
+
+//////////////////////////////////////////////////
+// Definition
+
+// file in the user data directory
+const STORE_FILE: &str = "store.json";
+
+/// Small persistent values like flags or high scores, stored as JSON in the user data directory.
+/// Changes are written by `save` and whenever the game is suspended.
+pub struct KvStore {
+    files: Files,
+    values: BTreeMap<String, Value>,
+    dirty: bool,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl KvStore {
+    /// Loads the stored values, starts empty if there are none or they can't be read.
+    pub fn load(files: Files) -> KvStore {
+        let values = match files.load_user_bytes(STORE_FILE) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                log::error!("Failed to parse {}, starting empty: {}", STORE_FILE, e);
+                BTreeMap::new()
+            }),
+            Err(FileError::NotFound(_)) => BTreeMap::new(),
+            Err(e) => {
+                log::error!("Failed to load {}, starting empty: {}", STORE_FILE, e);
+                BTreeMap::new()
+            }
+        };
+        KvStore { files, values, dirty: false }
+    }
+
+    /// `None` if the key is missing or holds another type.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.values.get(key).and_then(|value| T::deserialize(value).ok())
+    }
+
+    pub fn set<T: Serialize>(&mut self, key: &str, value: T) {
+        match serde_json::to_value(value) {
+            Ok(value) => {
+                self.dirty |= self.values.get(key) != Some(&value);
+                self.values.insert(key.to_string(), value);
+            }
+            Err(e) => log::error!("Failed to store {}: {}", key, e),
+        }
+    }
+
+    /// Boolean value, false if missing.
+    pub fn flag(&self, key: &str) -> bool {
+        self.get(key).unwrap_or(false)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        let removed = self.values.remove(key).is_some();
+        self.dirty |= removed;
+        removed
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(|key| key.as_str())
+    }
+
+    /// Writes pending changes.
+    pub fn save(&mut self) -> Result<(), FileError> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let bytes = serde_json::to_vec_pretty(&self.values).map_err(|e| FileError::Decode(STORE_FILE.to_string(), e.to_string()))?;
+        self.files.save_bytes(STORE_FILE, &bytes)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl std::fmt::Debug for KvStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KvStore").field("values", &self.values).field("dirty", &self.dirty).finish()
+    }
+}
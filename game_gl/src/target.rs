@@ -0,0 +1,133 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use crate::gl::types::*;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Shared render target, created by `GameContext::create_render_target`.
+pub type RenderTargetHandle = Rc<RefCell<RenderTarget>>;
+
+/// Offscreen color texture with depth-stencil that survives device loss. The framebuffer is released
+/// after `destroy_device` and recreated with the same size and format before `create_device`.
+/// The content is lost in between, so redraw it after the device was created.
+#[derive(Debug, Default)]
+pub struct RenderTarget {
+    width: u32,
+    height: u32,
+    internal_format: GLenum,
+    framebuffer: GlFramebuffer,
+}
+
+/// Keeps track of all render targets still referenced by the game.
+#[derive(Debug, Default)]
+pub(crate) struct RenderTargets {
+    targets: Vec<Weak<RefCell<RenderTarget>>>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl RenderTarget {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn internal_format(&self) -> GLenum {
+        self.internal_format
+    }
+
+    /// False while the device is lost.
+    pub fn is_valid(&self) -> bool {
+        self.framebuffer.width() != 0
+    }
+
+    /// Changes the size, kept when the target is recreated.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        if self.is_valid() {
+            self.framebuffer.resize(width, height);
+        }
+    }
+
+    /// Binds the target, draw with a viewport of `rect`.
+    pub fn bind(&mut self) {
+        self.framebuffer.bind();
+    }
+
+    pub fn unbind(&mut self) {
+        self.framebuffer.unbind();
+    }
+
+    pub fn rect(&self) -> GlRect {
+        self.framebuffer.rect()
+    }
+
+    /// Color result to sample from.
+    pub fn texture(&mut self) -> &mut GlTexture {
+        self.framebuffer.texture()
+    }
+
+    pub fn framebuffer(&mut self) -> &mut GlFramebuffer {
+        &mut self.framebuffer
+    }
+
+    fn create(&mut self, gl: &Gl) {
+        self.framebuffer = GlFramebuffer::new(gl, self.width, self.height, self.internal_format, 1);
+    }
+}
+
+impl RenderTargets {
+    pub fn create(&mut self, gl: &Gl, width: u32, height: u32, internal_format: GLenum) -> RenderTargetHandle {
+        let mut target = RenderTarget {
+            width,
+            height,
+            internal_format,
+            framebuffer: GlFramebuffer::default(),
+        };
+        target.create(gl);
+        let target = Rc::new(RefCell::new(target));
+        self.targets.retain(|target| target.strong_count() > 0);
+        self.targets.push(Rc::downgrade(&target));
+        target
+    }
+
+    /// Recreates all live targets on a new device.
+    pub fn restore(&mut self, gl: &Gl) {
+        self.targets.retain(|target| target.strong_count() > 0);
+        self.targets.iter().filter_map(Weak::upgrade).for_each(|target| target.borrow_mut().create(gl));
+        log::debug!("Restored {} render targets", self.targets.len());
+    }
+
+    /// Releases all live targets before the device is destroyed.
+    pub fn release(&mut self) {
+        self.targets.retain(|target| target.strong_count() > 0);
+        self.targets.iter().filter_map(Weak::upgrade).for_each(|target| target.borrow_mut().release());
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl GlResource for RenderTarget {
+    fn release(&mut self) {
+        // dropping the framebuffer deletes it, the default one marks the target as invalid
+        self.framebuffer = GlFramebuffer::default();
+    }
+}
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
@@ -0,0 +1,250 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use crate::events::{EventReader, Events};
+
+//////////////////////////////////////////////////
+// Definition
+
+type SendEvent = Box<dyn FnOnce(&mut Events)>;
+
+/// Handle of a spawned task.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TaskId(u64);
+
+/// Tasks spanning multiple frames, e.g. cutscenes or delayed spawns, written as async blocks instead of state
+/// machines. All tasks are polled once per frame after `GameLoop::update`. Tasks can't borrow the `GameContext`,
+/// they wait with their `TaskContext` and talk to the game through events or shared state.
+#[derive(Default)]
+pub struct Tasks {
+    tasks: Vec<Task>,
+    shared: Rc<RefCell<Shared>>,
+    next_id: u64,
+}
+
+/// Passed to spawned tasks to wait for time, frames and events.
+#[derive(Clone)]
+pub struct TaskContext {
+    shared: Rc<RefCell<Shared>>,
+}
+
+struct Task {
+    id: TaskId,
+    future: Pin<Box<dyn Future<Output = ()>>>,
+}
+
+#[derive(Default)]
+struct Shared {
+    // seconds since the first update
+    time: f64,
+    frame: u64,
+    waits: Vec<EventSubscription>,
+    next_wait: u64,
+    // events sent by tasks, sent into `Events` after polling
+    outbox: Vec<SendEvent>,
+}
+
+struct EventSubscription {
+    id: u64,
+    // false until the reader skipped the events sent before the wait
+    started: bool,
+    // returns true once the awaited event arrived
+    forward: Box<dyn FnMut(&Events) -> bool>,
+}
+
+// resolves with the first event of a type sent after the wait started
+struct EventWait<T> {
+    shared: Rc<RefCell<Shared>>,
+    id: Option<u64>,
+    event: Rc<RefCell<Option<T>>>,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl Tasks {
+    /// Starts a task, first polled by the next update. `task` receives the context to wait with, e.g.
+    /// `tasks.spawn(|task| async move { task.wait_seconds(2.0).await; task.send(ShowTitle) })`.
+    pub fn spawn<F, T>(&mut self, task: F) -> TaskId
+    where
+        F: FnOnce(TaskContext) -> T,
+        T: Future<Output = ()> + 'static,
+    {
+        let id = TaskId(self.next_id);
+        self.next_id += 1;
+        self.tasks.push(Task {
+            id,
+            future: Box::pin(task(self.context())),
+        });
+        id
+    }
+
+    /// Context for futures created outside `spawn`.
+    pub fn context(&self) -> TaskContext {
+        TaskContext { shared: self.shared.clone() }
+    }
+
+    /// Drops a task at its current await, returns false if it already finished.
+    pub fn cancel(&mut self, id: TaskId) -> bool {
+        let len = self.tasks.len();
+        self.tasks.retain(|task| task.id != id);
+        self.tasks.len() != len
+    }
+
+    pub fn cancel_all(&mut self) {
+        self.tasks.clear();
+    }
+
+    pub fn is_running(&self, id: TaskId) -> bool {
+        self.tasks.iter().any(|task| task.id == id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Advances the time and polls all tasks, called by the game loop after `update`.
+    pub(crate) fn update(&mut self, events: &mut Events, elapsed_time: f32) {
+        {
+            let mut shared = self.shared.borrow_mut();
+            shared.time += elapsed_time as f64;
+            shared.frame += 1;
+            shared.waits.retain_mut(|wait| !(wait.started && (wait.forward)(events)));
+        }
+
+        let mut context = Context::from_waker(Waker::noop());
+        self.tasks.retain_mut(|task| task.future.as_mut().poll(&mut context).is_pending());
+
+        let mut shared = self.shared.borrow_mut();
+        shared.outbox.drain(..).for_each(|send| send(events));
+        // waits started this frame only see events sent from now on
+        shared.waits.iter_mut().filter(|wait| !wait.started).for_each(|wait| {
+            (wait.forward)(events);
+            wait.started = true;
+        });
+    }
+}
+
+impl TaskContext {
+    /// Seconds since the tasks were first updated.
+    pub fn time(&self) -> f64 {
+        self.shared.borrow().time
+    }
+
+    /// Number of updates since the tasks were created.
+    pub fn frame(&self) -> u64 {
+        self.shared.borrow().frame
+    }
+
+    /// Resumes once `seconds` of game time passed.
+    pub fn wait_seconds(&self, seconds: f32) -> impl Future<Output = ()> {
+        let end = self.time() + seconds as f64;
+        let context = self.clone();
+        self.wait_until(move || context.time() >= end)
+    }
+
+    /// Resumes after `frames` updates.
+    pub fn wait_frames(&self, frames: u64) -> impl Future<Output = ()> {
+        let end = self.frame() + frames;
+        let context = self.clone();
+        self.wait_until(move || context.frame() >= end)
+    }
+
+    pub fn next_frame(&self) -> impl Future<Output = ()> {
+        self.wait_frames(1)
+    }
+
+    /// Resumes in the first update `condition` returns true, checked once per update.
+    pub fn wait_until(&self, mut condition: impl FnMut() -> bool) -> impl Future<Output = ()> {
+        // the first poll happens in the update after spawning, so the condition is only checked from there on
+        let polled = Cell::new(false);
+        std::future::poll_fn(move |_| if polled.replace(true) && condition() { Poll::Ready(()) } else { Poll::Pending })
+    }
+
+    /// Resumes with the first event of type `T` sent after waiting started, e.g. a `GuiEvent` of a dialog.
+    pub fn wait_for_event<T: Clone + 'static>(&self) -> impl Future<Output = T> {
+        EventWait {
+            shared: self.shared.clone(),
+            id: None,
+            event: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Sends an event into the game's `Events` after the tasks were polled.
+    pub fn send<T: 'static>(&self, event: T) {
+        self.shared.borrow_mut().outbox.push(Box::new(move |events: &mut Events| events.send(event)));
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl
+
+impl<T: Clone + 'static> Future for EventWait<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<T> {
+        let this = self.get_mut();
+        if let Some(event) = this.event.borrow_mut().take() {
+            this.id = None;
+            return Poll::Ready(event);
+        }
+        if this.id.is_none() {
+            let mut shared = this.shared.borrow_mut();
+            let id = shared.next_wait;
+            shared.next_wait += 1;
+            let event = this.event.clone();
+            let mut reader: Option<EventReader<T>> = None;
+            shared.waits.push(EventSubscription {
+                id,
+                started: false,
+                forward: Box::new(move |events: &Events| match reader.as_mut() {
+                    None => {
+                        reader = Some(events.reader());
+                        false
+                    }
+                    Some(reader) => match reader.read(events).next() {
+                        Some(received) => {
+                            *event.borrow_mut() = Some(received.clone());
+                            true
+                        }
+                        None => false,
+                    },
+                }),
+            });
+            this.id = Some(id);
+        }
+        Poll::Pending
+    }
+}
+
+// a cancelled task stops listening
+impl<T> Drop for EventWait<T> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.shared.borrow_mut().waits.retain(|wait| wait.id != id);
+        }
+    }
+}
+
+impl std::fmt::Debug for Tasks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tasks").field("tasks", &self.tasks.len()).finish()
+    }
+}
+
+impl std::fmt::Debug for TaskContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let shared = self.shared.borrow();
+        f.debug_struct("TaskContext").field("time", &shared.time).field("frame", &shared.frame).finish()
+    }
+}
@@ -0,0 +1,382 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use ab_glyph::{point, Font, FontVec, GlyphId, InvalidFont, PxScale, ScaleFont};
+use rustybuzz::{Face, UnicodeBuffer};
+
+use crate::color::Color;
+use crate::gl;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Shader
+
+const VS: &[u8] = b"#version 300 es
+layout(location = 0) in vec2 a_Pos;
+layout(location = 1) in vec2 a_TexCoord;
+layout(location = 2) in vec4 a_Color;
+
+layout(std140) uniform Screen {
+    vec4 u_Screen;
+};
+
+out vec2 v_TexCoord;
+out vec4 v_Color;
+
+void main() {
+    v_TexCoord = a_TexCoord;
+    v_Color = a_Color;
+    vec2 ndc = a_Pos / u_Screen.xy * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+}
+";
+
+const FS: &[u8] = b"#version 300 es
+precision mediump float;
+precision mediump sampler2DArray;
+
+in vec2 v_TexCoord;
+in vec4 v_Color;
+
+uniform sampler2DArray t_Glyphs;
+
+layout(location = 0) out vec4 target0;
+
+void main() {
+    target0 = vec4(v_Color.rgb, v_Color.a * texture(t_Glyphs, vec3(v_TexCoord, 0.0)).r);
+}
+";
+
+//////////////////////////////////////////////////
+// Definition
+
+const ATLAS_SIZE: u32 = 1024;
+const GLYPH_PADDING: u32 = 1;
+
+/// Draws text from a glyph atlas, rasterizing glyphs on first use.
+/// Text is queued by `draw_text` and drawn in a single batch by `render`. Text is shaped per font run,
+/// characters missing in the main font are taken from the first fallback font that has them.
+#[derive(Debug)]
+pub struct TextRenderer {
+    fonts: Vec<FontVec>,
+    atlas: GlyphAtlas,
+    batch: TextBatch,
+    runs: Vec<TextRun>,
+}
+
+#[derive(Debug)]
+struct TextRun {
+    text: String,
+    position: [f32; 2],
+    size: f32,
+    color: Color,
+}
+
+#[derive(Debug, Default)]
+struct GlyphAtlas {
+    texture: GlTexture,
+    glyphs: HashMap<(usize, GlyphId, u32), Option<AtlasGlyph>>,
+    cursor: [u32; 2],
+    shelf_height: u32,
+}
+
+// pixel bounds relative to the pen position on the baseline, and texture coordinates
+#[derive(Debug, Copy, Clone)]
+struct AtlasGlyph {
+    bounds: [f32; 4],
+    tex_coords: [f32; 4],
+}
+
+#[derive(Debug)]
+struct AtlasFull;
+
+#[derive(Debug, Default)]
+struct TextBatch {
+    shader: GlShader,
+    vao: GlVertexArrayObject,
+    vertices: GlVertexBuffer<TextVertex>,
+    indices: GlIndexBuffer,
+    screen: GlUniformBuffer<[f32; 4]>,
+    quads: Vec<TextVertex>,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct TextVertex {
+    position: [f32; 2],
+    tex_coord: [f32; 2],
+    color: Color,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl TextRenderer {
+    /// Creates a renderer for a TrueType or OpenType font, e.g. loaded with `Files::load_bytes`.
+    pub fn new(gl: &Gl, font: Vec<u8>) -> Result<TextRenderer, InvalidFont> {
+        Ok(TextRenderer {
+            fonts: vec![load_font(font)?],
+            atlas: GlyphAtlas::new(gl),
+            batch: TextBatch::new(gl),
+            runs: Vec::new(),
+        })
+    }
+
+    /// Adds a font for characters the previous fonts do not cover, e.g. CJK or emoji. Fallbacks are tried in the order they were added.
+    pub fn add_fallback_font(&mut self, font: Vec<u8>) -> Result<(), InvalidFont> {
+        self.fonts.push(load_font(font)?);
+        Ok(())
+    }
+
+    /// Queues text with its top left corner at `position` in pixels. `size` is the line height in pixels.
+    pub fn draw_text(&mut self, text: &str, position: [f32; 2], size: f32, color: impl Into<Color>) {
+        self.runs.push(TextRun {
+            text: text.to_string(),
+            position,
+            size,
+            color: color.into(),
+        });
+    }
+
+    /// Size of the text's bounding box in pixels, including all lines.
+    pub fn measure_text(&self, text: &str, size: f32) -> [f32; 2] {
+        layout(&self.fonts, text, [0.0, 0.0], size, |_, _, _| ())
+    }
+
+    /// Draws all queued text to the bound framebuffer with alpha blending, `screen_size` is the viewport size in pixels.
+    pub fn render(&mut self, gl: &Gl, screen_size: (u32, u32)) {
+        let runs = std::mem::take(&mut self.runs);
+        let (fonts, atlas, batch) = (&self.fonts, &mut self.atlas, &mut self.batch);
+        let state = GlRenderState::transparent();
+        state.apply(gl, None);
+        runs.iter().for_each(|run| {
+            let px_size = run.size.round().max(1.0);
+            layout(
+                fonts,
+                &run.text,
+                run.position,
+                px_size,
+                |font, id, pen| {
+                    let glyph = atlas.glyph(fonts, font, id, px_size).or_else(|AtlasFull| {
+                        // draw everything using the old atlas content before starting over
+                        batch.flush(&mut atlas.texture, screen_size);
+                        atlas.clear();
+                        atlas.glyph(fonts, font, id, px_size)
+                    });
+                    match glyph {
+                        Ok(Some(glyph)) => batch.push(&glyph, [pen[0].round(), pen[1].round()], run.color),
+                        Ok(None) => (),
+                        Err(AtlasFull) => log::warn!("Glyph {:?} at size {} does not fit into the glyph atlas", id, px_size),
+                    }
+                },
+            );
+        });
+        batch.flush(&mut atlas.texture, screen_size);
+        GlRenderState::default().apply(gl, Some(&state));
+    }
+}
+
+// checks the font can be shaped as well, ab_glyph and rustybuzz parse fonts separately
+fn load_font(font: Vec<u8>) -> Result<FontVec, InvalidFont> {
+    Face::from_slice(&font, 0).ok_or(InvalidFont)?;
+    FontVec::try_from_vec(font)
+}
+
+// shapes `text` and calls `glyph` with the font index, glyph and pen position on the baseline of each glyph. Returns the size of the laid out text.
+fn layout(fonts: &[FontVec], text: &str, position: [f32; 2], size: f32, mut glyph: impl FnMut(usize, GlyphId, [f32; 2])) -> [f32; 2] {
+    let scaled = fonts[0].as_scaled(PxScale::from(size));
+    let line_height = scaled.height() + scaled.line_gap();
+    let mut pen = [position[0], position[1] + scaled.ascent()];
+    let mut width = 0.0f32;
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            width = width.max(pen[0] - position[0]);
+            pen = [position[0], pen[1] + line_height];
+        }
+        for (font, run) in font_runs(fonts, line) {
+            let Some(face) = Face::from_slice(fonts[font].as_slice(), 0) else {
+                continue;
+            };
+            let scaled = fonts[font].as_scaled(PxScale::from(size));
+            let (h_scale, v_scale) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+            let mut buffer = UnicodeBuffer::new();
+            buffer.push_str(run);
+            buffer.guess_segment_properties();
+            let shaped = rustybuzz::shape(&face, &[], buffer);
+            // positions are in font units with y pointing up
+            for (info, offset) in shaped.glyph_infos().iter().zip(shaped.glyph_positions()) {
+                glyph(font, GlyphId(info.glyph_id as u16), [pen[0] + offset.x_offset as f32 * h_scale, pen[1] - offset.y_offset as f32 * v_scale]);
+                pen[0] += offset.x_advance as f32 * h_scale;
+            }
+        }
+    }
+    [width.max(pen[0] - position[0]), pen[1] - scaled.descent() - position[1]]
+}
+
+// splits a line into runs of one font each. Letters use the first font covering them, whitespace, punctuation
+// and combining marks stay with the current font if possible, so clusters are shaped together.
+fn font_runs<'a>(fonts: &[FontVec], line: &'a str) -> Vec<(usize, &'a str)> {
+    let mut runs = Vec::new();
+    let (mut start, mut current) = (0, 0);
+    for (i, c) in line.char_indices() {
+        let covers = |font: usize| fonts[font].glyph_id(c).0 != 0;
+        let font = if !c.is_alphanumeric() && covers(current) { current } else { (0..fonts.len()).find(|&font| covers(font)).unwrap_or(current) };
+        if font != current && i > start {
+            runs.push((current, &line[start..i]));
+            start = i;
+        }
+        current = font;
+    }
+    if start < line.len() {
+        runs.push((current, &line[start..]));
+    }
+    runs
+}
+
+impl GlyphAtlas {
+    fn new(gl: &Gl) -> GlyphAtlas {
+        GlyphAtlas {
+            texture: GlTexture::with_storage(gl, ATLAS_SIZE, ATLAS_SIZE, 1, gl::R8),
+            ..Default::default()
+        }
+    }
+
+    fn clear(&mut self) {
+        log::debug!("Glyph atlas full, evicting {} glyphs", self.glyphs.len());
+        self.glyphs.clear();
+        self.cursor = [0, 0];
+        self.shelf_height = 0;
+    }
+
+    // looks up a glyph, rasterizing it on first use. Glyphs without outline, e.g. spaces, are `None`.
+    fn glyph(&mut self, fonts: &[FontVec], font: usize, id: GlyphId, size: f32) -> Result<Option<AtlasGlyph>, AtlasFull> {
+        let key = (font, id, size as u32);
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return Ok(*glyph);
+        }
+        let Some(outline) = fonts[font].outline_glyph(id.with_scale_and_position(size, point(0.0, 0.0))) else {
+            self.glyphs.insert(key, None);
+            return Ok(None);
+        };
+        let bounds = outline.px_bounds();
+        let (width, height) = (bounds.width().ceil() as u32, bounds.height().ceil() as u32);
+        let (padded_width, padded_height) = (width + 2 * GLYPH_PADDING, height + 2 * GLYPH_PADDING);
+        let [x, y] = self.allocate(padded_width, padded_height)?;
+
+        // rasterize with a transparent border, so filtering does not bleed into neighbors
+        let mut pixels = vec![0u8; (padded_width * padded_height) as usize];
+        outline.draw(|px, py, coverage| {
+            if px < width && py < height {
+                pixels[((py + GLYPH_PADDING) * padded_width + px + GLYPH_PADDING) as usize] = (coverage * 255.0) as u8;
+            }
+        });
+        self.texture.update_region(0, GlRect::new(x as i32, y as i32, padded_width as i32, padded_height as i32), GlTextureFormat::R8, &pixels);
+
+        let (u, v) = ((x + GLYPH_PADDING) as f32 / ATLAS_SIZE as f32, (y + GLYPH_PADDING) as f32 / ATLAS_SIZE as f32);
+        let glyph = AtlasGlyph {
+            bounds: [bounds.min.x, bounds.min.y, bounds.min.x + width as f32, bounds.min.y + height as f32],
+            tex_coords: [u, v, u + width as f32 / ATLAS_SIZE as f32, v + height as f32 / ATLAS_SIZE as f32],
+        };
+        self.glyphs.insert(key, Some(glyph));
+        Ok(Some(glyph))
+    }
+
+    // shelf packing, glyphs of similar size share rows
+    fn allocate(&mut self, width: u32, height: u32) -> Result<[u32; 2], AtlasFull> {
+        if self.cursor[0] + width > ATLAS_SIZE {
+            self.cursor = [0, self.cursor[1] + self.shelf_height];
+            self.shelf_height = 0;
+        }
+        if width > ATLAS_SIZE || self.cursor[1] + height > ATLAS_SIZE {
+            return Err(AtlasFull);
+        }
+        let position = self.cursor;
+        self.cursor[0] += width;
+        self.shelf_height = self.shelf_height.max(height);
+        Ok(position)
+    }
+}
+
+impl TextBatch {
+    fn new(gl: &Gl) -> TextBatch {
+        let mut batch = TextBatch {
+            shader: GlShader::new(gl, VS, FS),
+            vao: GlVertexArrayObject::new(gl),
+            vertices: GlVertexBuffer::new(gl, gl::DYNAMIC_DRAW, &[]),
+            indices: GlIndexBuffer::new(gl, gl::DYNAMIC_DRAW, &[]),
+            screen: GlUniformBuffer::new(gl, gl::DYNAMIC_DRAW, &[0.0; 4]),
+            quads: Vec::new(),
+        };
+        let stride = size_of::<TextVertex>();
+        batch.vao.bind();
+        batch.vao.bind_attrib(&batch.vertices, 0, 2, gl::FLOAT, gl::FALSE, 0, stride, 0);
+        batch.vao.bind_attrib(&batch.vertices, 1, 2, gl::FLOAT, gl::FALSE, 2 * size_of::<f32>(), stride, 0);
+        batch.vao.bind_attrib(&batch.vertices, 2, 4, gl::FLOAT, gl::FALSE, 4 * size_of::<f32>(), stride, 0);
+        batch.vao.unbind();
+        batch
+    }
+
+    fn push(&mut self, glyph: &AtlasGlyph, pen: [f32; 2], color: Color) {
+        let [x0, y0, x1, y1] = glyph.bounds;
+        let [u0, v0, u1, v1] = glyph.tex_coords;
+        self.quads.extend_from_slice(&[
+            TextVertex { position: [pen[0] + x0, pen[1] + y0], tex_coord: [u0, v0], color },
+            TextVertex { position: [pen[0] + x1, pen[1] + y0], tex_coord: [u1, v0], color },
+            TextVertex { position: [pen[0] + x1, pen[1] + y1], tex_coord: [u1, v1], color },
+            TextVertex { position: [pen[0] + x0, pen[1] + y1], tex_coord: [u0, v1], color },
+        ]);
+    }
+
+    fn flush(&mut self, texture: &mut GlTexture, screen_size: (u32, u32)) {
+        if self.quads.is_empty() {
+            return;
+        }
+        let quad_count = self.quads.len() / 4;
+        if self.indices.count() < quad_count * 6 {
+            let indices: Vec<u32> = (0..quad_count as u32).flat_map(|i| [0, 1, 2, 2, 3, 0].map(|index| i * 4 + index)).collect();
+            self.indices.update(&indices);
+        }
+        self.vertices.update(&self.quads);
+        self.screen.update(&[screen_size.0 as f32, screen_size.1 as f32, 0.0, 0.0]);
+
+        self.vao.bind();
+        self.indices.bind();
+        texture.bind(0);
+        self.screen.bind(0);
+        self.shader.bind();
+        self.shader.link_texture(0, "t_Glyphs");
+        self.shader.link_uniform(0, "Screen");
+        self.shader.draw_elements(gl::TRIANGLES, quad_count * 6);
+        self.shader.unbind();
+        self.screen.unbind();
+        texture.unbind();
+        self.indices.unbind();
+        self.vao.unbind();
+
+        self.quads.clear();
+    }
+}
+
+//////////////////////////////////////////////////
+// Trait Impl GlResource
+
+impl GlResource for TextRenderer {
+    fn release(&mut self) {
+        self.atlas.texture.release();
+        self.atlas.glyphs.clear();
+        self.batch.shader.release();
+        self.batch.vao.release();
+        self.batch.vertices.release();
+        self.batch.indices.release();
+        self.batch.screen.release();
+    }
+}
+impl Drop for TextRenderer {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
@@ -0,0 +1,490 @@
+//////////////////////////////////////////////////
+// Using
+
+use std::fmt;
+use std::io::Read;
+
+use ddsfile::{D3DFormat, Dds, DxgiFormat};
+use ktx2::{Format, SupercompressionScheme};
+
+use crate::basis::{self, TranscodeTarget};
+use crate::file::FileError;
+use crate::gl;
+use crate::gl::types::*;
+use crate::opengl::*;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Definition
+
+#[derive(Debug)]
+pub enum TextureError {
+    /// The file could not be read.
+    File(FileError),
+    /// The file could not be parsed.
+    Parse(String),
+    /// The texture format is not supported by the loader or the running device.
+    Unsupported(String),
+    /// Supercompressed level data could not be inflated.
+    Decompress(String),
+    /// The texture could not be created.
+    Gl(GlError),
+}
+
+enum PixelFormat {
+    Uncompressed(GLenum, GLenum, GLenum),
+    Compressed(GLenum),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum DdsFormat {
+    Bc1,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc5,
+    Bc7,
+    Rgba8,
+    Bgra8,
+}
+
+//////////////////////////////////////////////////
+// KTX2
+
+/// Loads a KTX2 container into a texture array. Cubemap faces are loaded as consecutive layers.
+/// Zstandard supercompression is inflated on load, compressed formats must be supported by the device.
+/// Basis Universal content (ETC1S / UASTC) is transcoded to the best format of `compressed_formats()`, see `TranscodeTarget::select`.
+pub fn load_ktx2(gl: &Gl, bytes: &[u8]) -> Result<GlTexture, TextureError> {
+    let reader = ktx2::Reader::new(bytes).map_err(|err| TextureError::Parse(format!("{:?}", err)))?;
+    let header = reader.header();
+
+    if header.pixel_depth > 1 {
+        return Err(TextureError::Unsupported("3D textures".to_string()));
+    }
+    let layers = header.layer_count.max(1) * header.face_count.max(1);
+
+    if let Some((basis_format, srgb)) = basis::basis_format(&reader) {
+        let target = TranscodeTarget::select(&compressed_formats(gl));
+        log::debug!("Transcoding {:?} texture to {:?}", basis_format, target);
+        let levels = basis::transcode(&reader, basis_format, target)?;
+        let levels: Vec<&[u8]> = levels.iter().map(|level| level.as_slice()).collect();
+        let internal_format = target.internal_format(srgb);
+        let texture = match target {
+            TranscodeTarget::Rgba8 => GlTexture::try_create_levels(gl, header.pixel_width, header.pixel_height, layers, internal_format, Some((gl::RGBA, gl::UNSIGNED_BYTE)), &levels),
+            _ => GlTexture::try_from_compressed_levels(gl, header.pixel_width, header.pixel_height, layers, internal_format, &levels),
+        };
+        return texture.map_err(TextureError::Gl);
+    }
+
+    let format = header.format.ok_or_else(|| TextureError::Unsupported("KTX2 format undefined".to_string()))?;
+    let format = ktx2_format(format).ok_or_else(|| TextureError::Unsupported(format!("KTX2 format {:?}", format)))?;
+    if let PixelFormat::Compressed(internal_format) = format {
+        if !compressed_formats(gl).contains(&internal_format) {
+            return Err(TextureError::Unsupported(format!("Compressed format {:#x} not supported by device", internal_format)));
+        }
+    }
+
+    let levels = ktx2_levels(&reader)?;
+    let levels: Vec<&[u8]> = levels.iter().map(|level| level.as_slice()).collect();
+    let texture = match format {
+        PixelFormat::Uncompressed(format, internal_format, pixel_type) => {
+            GlTexture::try_create_levels(gl, header.pixel_width, header.pixel_height, layers, internal_format, Some((format, pixel_type)), &levels)
+        }
+        PixelFormat::Compressed(internal_format) => GlTexture::try_from_compressed_levels(gl, header.pixel_width, header.pixel_height, layers, internal_format, &levels),
+    };
+    texture.map_err(TextureError::Gl)
+}
+
+// level data with zstandard supercompression inflated
+pub(crate) fn ktx2_levels<Data: AsRef<[u8]>>(reader: &ktx2::Reader<Data>) -> Result<Vec<Vec<u8>>, TextureError> {
+    reader
+        .levels()
+        .map(|level| match reader.header().supercompression_scheme {
+            Some(SupercompressionScheme::Zstandard) => {
+                let mut data = Vec::with_capacity(level.uncompressed_byte_length as usize);
+                ruzstd::decoding::StreamingDecoder::new(level.data)
+                    .map_err(|err| TextureError::Decompress(err.to_string()))?
+                    .read_to_end(&mut data)
+                    .map_err(|err| TextureError::Decompress(err.to_string()))?;
+                Ok(data)
+            }
+            Some(scheme) => Err(TextureError::Unsupported(format!("Supercompression {:?}", scheme))),
+            None => Ok(level.data.to_vec()),
+        })
+        .collect()
+}
+
+fn ktx2_format(format: Format) -> Option<PixelFormat> {
+    use PixelFormat::*;
+    Some(match format {
+        Format::R8_UNORM => Uncompressed(gl::RED, gl::R8, gl::UNSIGNED_BYTE),
+        Format::R8G8_UNORM => Uncompressed(gl::RG, gl::RG8, gl::UNSIGNED_BYTE),
+        Format::R8G8B8_UNORM => Uncompressed(gl::RGB, gl::RGB8, gl::UNSIGNED_BYTE),
+        Format::R8G8B8_SRGB => Uncompressed(gl::RGB, gl::SRGB8, gl::UNSIGNED_BYTE),
+        Format::R8G8B8A8_UNORM => Uncompressed(gl::RGBA, gl::RGBA8, gl::UNSIGNED_BYTE),
+        Format::R8G8B8A8_SRGB => Uncompressed(gl::RGBA, gl::SRGB8_ALPHA8, gl::UNSIGNED_BYTE),
+        Format::R16_SFLOAT => Uncompressed(gl::RED, gl::R16F, gl::HALF_FLOAT),
+        Format::R16G16_SFLOAT => Uncompressed(gl::RG, gl::RG16F, gl::HALF_FLOAT),
+        Format::R16G16B16A16_SFLOAT => Uncompressed(gl::RGBA, gl::RGBA16F, gl::HALF_FLOAT),
+        Format::R32_SFLOAT => Uncompressed(gl::RED, gl::R32F, gl::FLOAT),
+        Format::R32G32B32A32_SFLOAT => Uncompressed(gl::RGBA, gl::RGBA32F, gl::FLOAT),
+        Format::ETC2_R8G8B8_UNORM_BLOCK => Compressed(gl::COMPRESSED_RGB8_ETC2),
+        Format::ETC2_R8G8B8_SRGB_BLOCK => Compressed(gl::COMPRESSED_SRGB8_ETC2),
+        Format::ETC2_R8G8B8A1_UNORM_BLOCK => Compressed(gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2),
+        Format::ETC2_R8G8B8A1_SRGB_BLOCK => Compressed(gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2),
+        Format::ETC2_R8G8B8A8_UNORM_BLOCK => Compressed(gl::COMPRESSED_RGBA8_ETC2_EAC),
+        Format::ETC2_R8G8B8A8_SRGB_BLOCK => Compressed(gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC),
+        Format::EAC_R11_UNORM_BLOCK => Compressed(gl::COMPRESSED_R11_EAC),
+        Format::EAC_R11G11_UNORM_BLOCK => Compressed(gl::COMPRESSED_RG11_EAC),
+        Format::ASTC_4x4_UNORM_BLOCK => Compressed(gl::COMPRESSED_RGBA_ASTC_4x4),
+        Format::ASTC_4x4_SRGB_BLOCK => Compressed(gl::COMPRESSED_SRGB8_ALPHA8_ASTC_4x4),
+        Format::ASTC_5x5_UNORM_BLOCK => Compressed(gl::COMPRESSED_RGBA_ASTC_5x5),
+        Format::ASTC_5x5_SRGB_BLOCK => Compressed(gl::COMPRESSED_SRGB8_ALPHA8_ASTC_5x5),
+        Format::ASTC_6x6_UNORM_BLOCK => Compressed(gl::COMPRESSED_RGBA_ASTC_6x6),
+        Format::ASTC_6x6_SRGB_BLOCK => Compressed(gl::COMPRESSED_SRGB8_ALPHA8_ASTC_6x6),
+        Format::ASTC_8x8_UNORM_BLOCK => Compressed(gl::COMPRESSED_RGBA_ASTC_8x8),
+        Format::ASTC_8x8_SRGB_BLOCK => Compressed(gl::COMPRESSED_SRGB8_ALPHA8_ASTC_8x8),
+        Format::BC1_RGBA_UNORM_BLOCK => Compressed(gl::COMPRESSED_RGBA_S3TC_DXT1_EXT),
+        Format::BC1_RGBA_SRGB_BLOCK => Compressed(gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT),
+        Format::BC2_UNORM_BLOCK => Compressed(gl::COMPRESSED_RGBA_S3TC_DXT3_EXT),
+        Format::BC2_SRGB_BLOCK => Compressed(gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT),
+        Format::BC3_UNORM_BLOCK => Compressed(gl::COMPRESSED_RGBA_S3TC_DXT5_EXT),
+        Format::BC3_SRGB_BLOCK => Compressed(gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT),
+        Format::BC4_UNORM_BLOCK => Compressed(gl::COMPRESSED_RED_RGTC1_EXT),
+        Format::BC5_UNORM_BLOCK => Compressed(gl::COMPRESSED_RED_GREEN_RGTC2_EXT),
+        Format::BC7_UNORM_BLOCK => Compressed(gl::COMPRESSED_RGBA_BPTC_UNORM_EXT),
+        Format::BC7_SRGB_BLOCK => Compressed(gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM_EXT),
+        _ => return None,
+    })
+}
+
+//////////////////////////////////////////////////
+// DDS
+
+/// Loads a DDS file with its mip chain into a texture array. Cubemap faces are loaded as consecutive layers.
+/// BCn data is uploaded directly where the device supports it, BC1 - BC5 fall back to decompression otherwise.
+pub fn load_dds(gl: &Gl, bytes: &[u8]) -> Result<GlTexture, TextureError> {
+    let dds = Dds::read(bytes).map_err(|err| TextureError::Parse(err.to_string()))?;
+    let (format, srgb) = dds_format(&dds).ok_or_else(|| TextureError::Unsupported(format!("DDS format {:?} / {:?}", dds.get_dxgi_format(), dds.get_d3d_format())))?;
+    if dds.get_depth() > 1 {
+        return Err(TextureError::Unsupported("3D textures".to_string()));
+    }
+
+    let (width, height) = (dds.get_width(), dds.get_height());
+    let cube = dds.header10.as_ref().map(|h| h.misc_flag.contains(ddsfile::MiscFlag::TEXTURECUBE)).unwrap_or(false);
+    let layers = dds.get_num_array_layers() * if cube { 6 } else { 1 };
+    let level_count = dds.get_num_mipmap_levels().max(1);
+
+    // dds stores all levels per layer, textures are uploaded per level with all layers
+    let level_sizes: Vec<usize> = (0..level_count as usize)
+        .map(|level| {
+            let (level_width, level_height) = level_dimensions(width, height, level);
+            dds_level_size(format, level_width, level_height)
+        })
+        .collect();
+    let layer_size: usize = level_sizes.iter().sum();
+    if dds.data.len() < layer_size * layers as usize {
+        return Err(TextureError::Parse("Truncated texture data".to_string()));
+    }
+    let layer_levels: Vec<Vec<&[u8]>> = dds
+        .data
+        .chunks_exact(layer_size)
+        .take(layers as usize)
+        .map(|data| {
+            let mut offset = 0;
+            level_sizes
+                .iter()
+                .map(|size| {
+                    offset += size;
+                    &data[offset - size..offset]
+                })
+                .collect()
+        })
+        .collect();
+
+    let compressed_format = match (format, srgb) {
+        (DdsFormat::Bc1, false) => Some(gl::COMPRESSED_RGBA_S3TC_DXT1_EXT),
+        (DdsFormat::Bc1, true) => Some(gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT),
+        (DdsFormat::Bc2, false) => Some(gl::COMPRESSED_RGBA_S3TC_DXT3_EXT),
+        (DdsFormat::Bc2, true) => Some(gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT),
+        (DdsFormat::Bc3, false) => Some(gl::COMPRESSED_RGBA_S3TC_DXT5_EXT),
+        (DdsFormat::Bc3, true) => Some(gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT),
+        (DdsFormat::Bc4, _) => Some(gl::COMPRESSED_RED_RGTC1_EXT),
+        (DdsFormat::Bc5, _) => Some(gl::COMPRESSED_RED_GREEN_RGTC2_EXT),
+        (DdsFormat::Bc7, false) => Some(gl::COMPRESSED_RGBA_BPTC_UNORM_EXT),
+        (DdsFormat::Bc7, true) => Some(gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM_EXT),
+        (DdsFormat::Rgba8 | DdsFormat::Bgra8, _) => None,
+    };
+    let internal_format = if srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 };
+
+    let levels: Vec<Vec<u8>> = match compressed_format {
+        Some(compressed_format) if compressed_formats(gl).contains(&compressed_format) => {
+            let levels = concat_levels(&layer_levels, width, height, level_count, |data, _, _| data.to_vec());
+            let levels: Vec<&[u8]> = levels.iter().map(|level| level.as_slice()).collect();
+            return GlTexture::try_from_compressed_levels(gl, width, height, layers, compressed_format, &levels).map_err(TextureError::Gl);
+        }
+        Some(_) if format == DdsFormat::Bc7 => return Err(TextureError::Unsupported("BC7 not supported by device".to_string())),
+        Some(_) => {
+            log::debug!("Compressed format {:?} not supported by device, decompressing", format);
+            concat_levels(&layer_levels, width, height, level_count, |data, level_width, level_height| {
+                decode_bc(format, level_width, level_height, data)
+            })
+        }
+        None if format == DdsFormat::Bgra8 => concat_levels(&layer_levels, width, height, level_count, |data, _, _| {
+            data.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect()
+        }),
+        None => concat_levels(&layer_levels, width, height, level_count, |data, _, _| data.to_vec()),
+    };
+    let levels: Vec<&[u8]> = levels.iter().map(|level| level.as_slice()).collect();
+    GlTexture::try_create_levels(gl, width, height, layers, internal_format, Some((gl::RGBA, gl::UNSIGNED_BYTE)), &levels).map_err(TextureError::Gl)
+}
+
+fn dds_format(dds: &Dds) -> Option<(DdsFormat, bool)> {
+    if let Some(format) = dds.get_dxgi_format() {
+        return Some(match format {
+            DxgiFormat::BC1_UNorm => (DdsFormat::Bc1, false),
+            DxgiFormat::BC1_UNorm_sRGB => (DdsFormat::Bc1, true),
+            DxgiFormat::BC2_UNorm => (DdsFormat::Bc2, false),
+            DxgiFormat::BC2_UNorm_sRGB => (DdsFormat::Bc2, true),
+            DxgiFormat::BC3_UNorm => (DdsFormat::Bc3, false),
+            DxgiFormat::BC3_UNorm_sRGB => (DdsFormat::Bc3, true),
+            DxgiFormat::BC4_UNorm => (DdsFormat::Bc4, false),
+            DxgiFormat::BC5_UNorm => (DdsFormat::Bc5, false),
+            DxgiFormat::BC7_UNorm => (DdsFormat::Bc7, false),
+            DxgiFormat::BC7_UNorm_sRGB => (DdsFormat::Bc7, true),
+            DxgiFormat::R8G8B8A8_UNorm => (DdsFormat::Rgba8, false),
+            DxgiFormat::R8G8B8A8_UNorm_sRGB => (DdsFormat::Rgba8, true),
+            DxgiFormat::B8G8R8A8_UNorm => (DdsFormat::Bgra8, false),
+            DxgiFormat::B8G8R8A8_UNorm_sRGB => (DdsFormat::Bgra8, true),
+            _ => return None,
+        });
+    }
+    dds.get_d3d_format().and_then(|format| match format {
+        D3DFormat::DXT1 => Some((DdsFormat::Bc1, false)),
+        D3DFormat::DXT3 => Some((DdsFormat::Bc2, false)),
+        D3DFormat::DXT5 => Some((DdsFormat::Bc3, false)),
+        D3DFormat::A8B8G8R8 => Some((DdsFormat::Rgba8, false)),
+        D3DFormat::A8R8G8B8 => Some((DdsFormat::Bgra8, false)),
+        _ => None,
+    })
+}
+
+fn dds_level_size(format: DdsFormat, width: u32, height: u32) -> usize {
+    let blocks = (width.div_ceil(4) * height.div_ceil(4)) as usize;
+    match format {
+        DdsFormat::Bc1 | DdsFormat::Bc4 => blocks * 8,
+        DdsFormat::Bc2 | DdsFormat::Bc3 | DdsFormat::Bc5 | DdsFormat::Bc7 => blocks * 16,
+        DdsFormat::Rgba8 | DdsFormat::Bgra8 => (width * height * 4) as usize,
+    }
+}
+
+// converts layer major data into level major data, mapping each image with `f(data, width, height)`
+fn concat_levels(layer_levels: &[Vec<&[u8]>], width: u32, height: u32, level_count: u32, f: impl Fn(&[u8], u32, u32) -> Vec<u8>) -> Vec<Vec<u8>> {
+    (0..level_count as usize)
+        .map(|level| {
+            let (width, height) = level_dimensions(width, height, level);
+            layer_levels.iter().flat_map(|levels| f(levels[level], width, height)).collect()
+        })
+        .collect()
+}
+
+fn level_dimensions(width: u32, height: u32, level: usize) -> (u32, u32) {
+    ((width >> level).max(1), (height >> level).max(1))
+}
+
+//////////////////////////////////////////////////
+// BCn decoding
+
+/// Decodes BC1 - BC5 blocks into tightly packed RGBA8 pixels.
+pub(crate) fn decode_bc(format: DdsFormat, width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+    let block_size = if matches!(format, DdsFormat::Bc1 | DdsFormat::Bc4) { 8 } else { 16 };
+    let blocks_x = width.div_ceil(4) as usize;
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    data.chunks_exact(block_size).enumerate().for_each(|(i, block)| {
+        let texels = match format {
+            DdsFormat::Bc1 => decode_bc1_color(block, true),
+            DdsFormat::Bc2 => {
+                let mut texels = decode_bc1_color(&block[8..], false);
+                texels.iter_mut().enumerate().for_each(|(j, texel)| texel[3] = ((block[j / 2] >> ((j % 2) * 4)) & 0x0F) * 17);
+                texels
+            }
+            DdsFormat::Bc3 => {
+                let mut texels = decode_bc1_color(&block[8..], false);
+                let alpha = decode_bc4_channel(&block[..8]);
+                texels.iter_mut().zip(alpha).for_each(|(texel, alpha)| texel[3] = alpha);
+                texels
+            }
+            DdsFormat::Bc4 => decode_bc4_channel(block).map(|r| [r, 0, 0, 255]),
+            DdsFormat::Bc5 => {
+                let red = decode_bc4_channel(&block[..8]);
+                let green = decode_bc4_channel(&block[8..]);
+                std::array::from_fn(|j| [red[j], green[j], 0, 255])
+            }
+            _ => unreachable!(),
+        };
+        let (bx, by) = ((i % blocks_x) * 4, (i / blocks_x) * 4);
+        texels.iter().enumerate().for_each(|(j, texel)| {
+            let (x, y) = (bx + j % 4, by + j / 4);
+            if x < width as usize && y < height as usize {
+                let offset = (y * width as usize + x) * 4;
+                pixels[offset..offset + 4].copy_from_slice(texel);
+            }
+        });
+    });
+    pixels
+}
+
+fn decode_bc1_color(block: &[u8], punchthrough: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (p0, p1) = (rgb565(c0), rgb565(c1));
+    let mix = |a: u8, b: u8, wa: u32, wb: u32| ((a as u32 * wa + b as u32 * wb) / (wa + wb)) as u8;
+    let palette: [[u8; 4]; 4] = if c0 > c1 || !punchthrough {
+        [
+            p0,
+            p1,
+            std::array::from_fn(|k| if k < 3 { mix(p0[k], p1[k], 2, 1) } else { 255 }),
+            std::array::from_fn(|k| if k < 3 { mix(p0[k], p1[k], 1, 2) } else { 255 }),
+        ]
+    } else {
+        [p0, p1, std::array::from_fn(|k| if k < 3 { mix(p0[k], p1[k], 1, 1) } else { 255 }), [0, 0, 0, 0]]
+    };
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|j| palette[((indices >> (j * 2)) & 0x3) as usize])
+}
+
+fn decode_bc4_channel(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let palette: [u8; 8] = std::array::from_fn(|i| {
+        let i = i as u32;
+        (match i {
+            0 => a0,
+            1 => a1,
+            _ if a0 > a1 => ((8 - i) * a0 + (i - 1) * a1) / 7,
+            6 => 0,
+            7 => 255,
+            _ => ((6 - i) * a0 + (i - 1) * a1) / 5,
+        }) as u8
+    });
+    let indices = block[2..8].iter().rev().fold(0u64, |bits, &byte| (bits << 8) | byte as u64);
+    std::array::from_fn(|j| palette[((indices >> (j * 3)) & 0x7) as usize])
+}
+
+fn rgb565(color: u16) -> [u8; 4] {
+    let r = ((color >> 11) & 0x1F) as u8;
+    let g = ((color >> 5) & 0x3F) as u8;
+    let b = (color & 0x1F) as u8;
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2), 255]
+}
+
+//////////////////////////////////////////////////
+// Compressed formats
+
+/// Compressed internal formats the device can upload.
+pub fn compressed_formats(gl: &Gl) -> Vec<GLenum> {
+    let mut count: GLint = 0;
+    unsafe {
+        gl.GetIntegerv(gl::NUM_COMPRESSED_TEXTURE_FORMATS, &mut count);
+        let mut formats = vec![0; count.max(0) as usize];
+        if count > 0 {
+            gl.GetIntegerv(gl::COMPRESSED_TEXTURE_FORMATS, formats.as_mut_ptr());
+        }
+        check_error(gl, "Failed to get compressed texture formats");
+        formats.into_iter().map(|format| format as GLenum).collect()
+    }
+}
+
+//////////////////////////////////////////////////
+// Traits
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::File(err) => write!(f, "Failed to load texture: {}", err),
+            TextureError::Parse(err) => write!(f, "Failed to parse texture: {}", err),
+            TextureError::Unsupported(err) => write!(f, "Unsupported texture: {}", err),
+            TextureError::Decompress(err) => write!(f, "Failed to decompress texture: {}", err),
+            TextureError::Gl(err) => write!(f, "Failed to create texture: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+//////////////////////////////////////////////////
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // red and blue endpoints, each row selects palette entries 0, 1, 2 and 3
+    const BC1_RED_BLUE: [u8; 8] = [0x00, 0xF8, 0x1F, 0x00, 0xE4, 0xE4, 0xE4, 0xE4];
+    // endpoints 255 and 0, the first two rows select palette entries 0 to 7
+    const BC4_RAMP: [u8; 8] = [255, 0, 0x88, 0xC6, 0xFA, 0, 0, 0];
+
+    fn pixel(pixels: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * width + x) * 4) as usize;
+        std::array::from_fn(|k| pixels[offset + k])
+    }
+
+    #[test]
+    fn decodes_bc1_palette() {
+        let pixels = decode_bc(DdsFormat::Bc1, 4, 4, &BC1_RED_BLUE);
+        assert_eq!(pixels.len(), 4 * 4 * 4);
+        let row: Vec<_> = (0..4).map(|x| pixel(&pixels, 4, x, 3)).collect();
+        assert_eq!(row, [[255, 0, 0, 255], [0, 0, 255, 255], [170, 0, 85, 255], [85, 0, 170, 255]]);
+    }
+
+    #[test]
+    fn decodes_bc1_punchthrough_alpha() {
+        // swapped endpoints select the three color palette with transparent black
+        let block = [0x1F, 0x00, 0x00, 0xF8, 0xE4, 0xE4, 0xE4, 0xE4];
+        let pixels = decode_bc(DdsFormat::Bc1, 4, 4, &block);
+        let row: Vec<_> = (0..4).map(|x| pixel(&pixels, 4, x, 0)).collect();
+        assert_eq!(row, [[0, 0, 255, 255], [255, 0, 0, 255], [127, 0, 127, 255], [0, 0, 0, 0]]);
+    }
+
+    #[test]
+    fn decodes_bc2_and_bc3_alpha() {
+        let mut bc2 = [0u8; 16];
+        bc2[0] = 0xF0;
+        bc2[8..].copy_from_slice(&BC1_RED_BLUE);
+        let pixels = decode_bc(DdsFormat::Bc2, 4, 4, &bc2);
+        assert_eq!(pixel(&pixels, 4, 0, 0), [255, 0, 0, 0]);
+        assert_eq!(pixel(&pixels, 4, 1, 0), [0, 0, 255, 255]);
+
+        let mut bc3 = [0u8; 16];
+        bc3[..8].copy_from_slice(&BC4_RAMP);
+        bc3[8..].copy_from_slice(&BC1_RED_BLUE);
+        let pixels = decode_bc(DdsFormat::Bc3, 4, 4, &bc3);
+        let alpha: Vec<_> = (0..4).map(|x| pixel(&pixels, 4, x, 0)[3]).collect();
+        assert_eq!(alpha, [255, 0, 218, 182]);
+    }
+
+    #[test]
+    fn decodes_bc4_and_bc5_channels() {
+        let pixels = decode_bc(DdsFormat::Bc4, 4, 4, &BC4_RAMP);
+        let red: Vec<_> = (0..8).map(|i| pixel(&pixels, 4, i % 4, i / 4)[0]).collect();
+        assert_eq!(red, [255, 0, 218, 182, 145, 109, 72, 36]);
+        assert_eq!(pixel(&pixels, 4, 0, 0), [255, 0, 0, 255]);
+
+        // six value palette with explicit 0 and 255 when the first endpoint is smaller
+        let block = [0, 255, 0x88, 0xC6, 0xFA, 0, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0];
+        let pixels = decode_bc(DdsFormat::Bc5, 4, 4, &block);
+        let red: Vec<_> = (0..8).map(|i| pixel(&pixels, 4, i % 4, i / 4)[0]).collect();
+        assert_eq!(red, [0, 255, 51, 102, 153, 204, 0, 255]);
+        assert_eq!(pixel(&pixels, 4, 0, 0), [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn crops_partial_blocks() {
+        // 6x2 pixels span two blocks horizontally, texels outside the image are dropped
+        let mut data = BC1_RED_BLUE.to_vec();
+        data.extend_from_slice(&[0x00, 0xF8, 0x00, 0xF8, 0, 0, 0, 0]);
+        let pixels = decode_bc(DdsFormat::Bc1, 6, 2, &data);
+        assert_eq!(pixels.len(), 6 * 2 * 4);
+        assert_eq!(pixel(&pixels, 6, 3, 1), [85, 0, 170, 255]);
+        assert_eq!(pixel(&pixels, 6, 5, 1), [255, 0, 0, 255]);
+    }
+}
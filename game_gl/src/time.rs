@@ -0,0 +1,24 @@
+//////////////////////////////////////////////////
+// Definition
+
+/// Frame timing, advanced by the game before `GameLoop::input`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct GameTime {
+    /// Seconds since the previous frame.
+    pub delta: f32,
+    /// Seconds since the game started, excluding time suspended.
+    pub total: f64,
+    /// Number of the current frame, the first frame is 1.
+    pub frame: u64,
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl GameTime {
+    pub(crate) fn advance(&mut self, delta: f32) {
+        self.frame += 1;
+        self.delta = delta;
+        self.total += delta as f64;
+    }
+}
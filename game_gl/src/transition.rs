@@ -0,0 +1,208 @@
+//////////////////////////////////////////////////
+// Using
+
+use crate::color::Color;
+use crate::gl;
+use crate::opengl::*;
+use crate::postprocess::FULLSCREEN_VS;
+use crate::Gl;
+
+//////////////////////////////////////////////////
+// Shader
+
+const FADE_FS: &[u8] = b"#version 300 es
+precision mediump float;
+precision mediump sampler2DArray;
+
+in vec2 v_TexCoord;
+
+uniform sampler2DArray t_From;
+uniform sampler2DArray t_To;
+
+layout(std140) uniform Transition {
+    vec4 u_Color;
+    vec4 u_Params;
+};
+
+layout(location = 0) out vec4 target0;
+
+void main() {
+    target0 = mix(texture(t_From, vec3(v_TexCoord, 0.0)), texture(t_To, vec3(v_TexCoord, 0.0)), u_Params.x);
+}
+";
+
+const FADE_THROUGH_FS: &[u8] = b"#version 300 es
+precision mediump float;
+precision mediump sampler2DArray;
+
+in vec2 v_TexCoord;
+
+uniform sampler2DArray t_From;
+uniform sampler2DArray t_To;
+
+layout(std140) uniform Transition {
+    vec4 u_Color;
+    vec4 u_Params;
+};
+
+layout(location = 0) out vec4 target0;
+
+void main() {
+    float progress = u_Params.x;
+    if (progress < 0.5) {
+        target0 = mix(texture(t_From, vec3(v_TexCoord, 0.0)), u_Color, progress * 2.0);
+    } else {
+        target0 = mix(u_Color, texture(t_To, vec3(v_TexCoord, 0.0)), progress * 2.0 - 1.0);
+    }
+}
+";
+
+const SLIDE_FS: &[u8] = b"#version 300 es
+precision mediump float;
+precision mediump sampler2DArray;
+
+in vec2 v_TexCoord;
+
+uniform sampler2DArray t_From;
+uniform sampler2DArray t_To;
+
+layout(std140) uniform Transition {
+    vec4 u_Color;
+    vec4 u_Params;
+};
+
+layout(location = 0) out vec4 target0;
+
+void main() {
+    vec2 direction = u_Params.yz;
+    vec2 from_uv = v_TexCoord + direction * u_Params.x;
+    vec2 to_uv = from_uv - direction;
+    bool incoming = all(greaterThanEqual(to_uv, vec2(0.0))) && all(lessThanEqual(to_uv, vec2(1.0)));
+    target0 = incoming ? texture(t_To, vec3(to_uv, 0.0)) : texture(t_From, vec3(from_uv, 0.0));
+}
+";
+
+//////////////////////////////////////////////////
+// Definition
+
+/// Animation between the screens of two game states, see `StateStack::set_transition_effect`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransitionEffect {
+    /// Crossfades from the old to the new screen.
+    Fade,
+    /// Fades the old screen out to a color and the new screen in.
+    FadeThrough(Color),
+    /// Moves the new screen in, pushing the old one out.
+    Slide(SlideDirection),
+    /// Fragment shader blending the screens. It receives `in vec2 v_TexCoord`, the old and new screen as
+    /// `uniform sampler2DArray t_From` and `t_To` (layer 0) and the uniform block
+    /// `Transition { vec4 u_Color; vec4 u_Params; }` with the eased progress in [0, 1] in `u_Params.x`.
+    Custom(Vec<u8>),
+}
+
+/// Direction the screens move in during a slide.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SlideDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Offscreen targets of the old and new screen and the shader blending them.
+#[derive(Debug)]
+pub(crate) struct TransitionRenderer {
+    effect: TransitionEffect,
+    from: GlFramebuffer,
+    to: GlFramebuffer,
+    shader: GlShader,
+    uniforms: GlUniformBuffer<TransitionUniforms>,
+    vao: GlVertexArrayObject,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct TransitionUniforms {
+    color: [f32; 4],
+    // progress, slide direction
+    params: [f32; 4],
+}
+
+//////////////////////////////////////////////////
+// Implementation
+
+impl TransitionRenderer {
+    /// Fails with the info log if the shader of a `TransitionEffect::Custom` does not compile.
+    pub fn new(gl: &Gl, effect: &TransitionEffect, width: u32, height: u32) -> Result<TransitionRenderer, GlError> {
+        let fragment_shader = match effect {
+            TransitionEffect::Fade => FADE_FS,
+            TransitionEffect::FadeThrough(_) => FADE_THROUGH_FS,
+            TransitionEffect::Slide(_) => SLIDE_FS,
+            TransitionEffect::Custom(fragment_shader) => fragment_shader.as_slice(),
+        };
+        Ok(TransitionRenderer {
+            effect: effect.clone(),
+            shader: GlShader::try_new(gl, FULLSCREEN_VS, fragment_shader)?,
+            from: GlFramebuffer::new(gl, width, height, gl::RGBA8, 1),
+            to: GlFramebuffer::new(gl, width, height, gl::RGBA8, 1),
+            uniforms: GlUniformBuffer::new(gl, gl::DYNAMIC_DRAW, &TransitionUniforms::default()),
+            vao: GlVertexArrayObject::new(gl),
+        })
+    }
+
+    pub fn effect(&self) -> &TransitionEffect {
+        &self.effect
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.from.resize(width, height);
+        self.to.resize(width, height);
+    }
+
+    /// Target of the old screen.
+    pub fn from(&mut self) -> &mut GlFramebuffer {
+        &mut self.from
+    }
+
+    /// Target of the new screen.
+    pub fn to(&mut self) -> &mut GlFramebuffer {
+        &mut self.to
+    }
+
+    /// Draws both screens blended at `progress` in [0, 1] to the default framebuffer covering `rect`.
+    pub fn blend(&mut self, gl: &Gl, progress: f32, rect: GlRect) {
+        let direction = match self.effect {
+            TransitionEffect::Slide(SlideDirection::Left) => [1.0, 0.0],
+            TransitionEffect::Slide(SlideDirection::Right) => [-1.0, 0.0],
+            // texture coordinates point up
+            TransitionEffect::Slide(SlideDirection::Up) => [0.0, -1.0],
+            TransitionEffect::Slide(SlideDirection::Down) => [0.0, 1.0],
+            _ => [0.0, 0.0],
+        };
+        let color = match self.effect {
+            TransitionEffect::FadeThrough(color) => color.to_array(),
+            _ => [0.0, 0.0, 0.0, 1.0],
+        };
+        self.uniforms.update(&TransitionUniforms {
+            color,
+            params: [progress, direction[0], direction[1], 0.0],
+        });
+
+        GlRenderState::default().apply(gl, None);
+        gl.set_viewport(rect);
+        self.vao.bind();
+        self.from.texture().bind(0);
+        self.to.texture().bind(1);
+        self.uniforms.bind(0);
+        self.shader.bind();
+        self.shader.link_texture(0, "t_From");
+        self.shader.link_texture(1, "t_To");
+        self.shader.link_uniform(0, "Transition");
+        self.shader.draw_arrays(gl::TRIANGLES, 3);
+        self.shader.unbind();
+        self.uniforms.unbind();
+        self.to.texture().unbind();
+        self.from.texture().unbind();
+        self.vao.unbind();
+    }
+}
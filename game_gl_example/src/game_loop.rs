@@ -51,7 +51,7 @@ pub struct ExampleGameLoop {
     vao: GlVertexArrayObject,
     vbo: GlVertexBuffer<[f32; 4]>,
     ibo: GlIndexBuffer,
-    ubo: GlUniformBuffer<(f32, f32, f32, f32)>,
+    ubo: GlUniformBuffer<Color>,
     texture: GlTexture,
     shader: GlShader,
     resolution: (GLsizei, GLsizei),
@@ -68,8 +68,11 @@ impl GameLoop for ExampleGameLoop {
         "Test Example"
     }
 
-    fn init(&mut self, _ctx: &mut GameContext) {
+    fn init(&mut self, ctx: &mut GameContext) {
         log::debug!("init");
+        // assets are compiled in, the example runs without its asset folder
+        ctx.files().mount_embedded("example", game_gl::embed_assets!["lena.png"]);
+        ctx.set_clear_color(Color::RED);
     }
 
     fn cleanup(&mut self, _ctx: &mut GameContext) {
@@ -91,22 +94,18 @@ impl GameLoop for ExampleGameLoop {
             InputEvent::Touch(event) => {
                 log::debug!("{:?}", event);
             }
-            InputEvent::Keyboard(KeyboardEvent { state, key }) => match (state, key) {
-                (KeyState::Released, Key::Escape) => {
+            InputEvent::Keyboard(KeyboardEvent { state, key }) => {
+                if let (KeyState::Released, Key::Escape) = (state, key) {
                     ctx.exit();
                 }
-                _ => {}
-            },
+            }
         });
     }
 
-    fn render(&mut self, _ctx: &mut GameContext, gl: &Gl) {
+    fn render(&mut self, ctx: &mut GameContext, gl: &Gl) {
         //log::debug!("render");
+        ctx.clear(gl, GlClearFlags::COLOR | GlClearFlags::DEPTH);
         unsafe {
-            gl.ClearColor(1.0, 0.0, 0.0, 1.0);
-            gl.ClearDepthf(1.0);
-            gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-
             self.vao.bind();
             self.ibo.bind();
 
@@ -148,8 +147,8 @@ impl GameLoop for ExampleGameLoop {
         self.ibo = GlIndexBuffer::new(gl, gl::STATIC_DRAW, &[0; 4]);
         self.ibo.update(&[0, 1, 2, 3]);
 
-        self.ubo = GlUniformBuffer::new(gl, gl::DYNAMIC_DRAW, &(0.0, 0.0, 0.0, 0.0));
-        self.ubo.update(&(0.5, 0.9, 0.9, 1.0));
+        self.ubo = GlUniformBuffer::new(gl, gl::DYNAMIC_DRAW, &Color::TRANSPARENT);
+        self.ubo.update(&Color::new(0.5, 0.9, 0.9, 1.0));
 
         let buffer = ctx.files().load_bytes("lena.png").unwrap();
         let image = image::load_from_memory(&buffer).unwrap().to_rgba8();